@@ -0,0 +1,102 @@
+//! Compares a process per Decapod call with the pooled stdio connection.
+//!
+//! Run with `cargo bench --bench decapod_transport [calls]`.  Both modes talk
+//! to the shell stand-in in `tests/fixtures`, so the numbers measure transport
+//! overhead rather than Decapod itself.
+
+use pincher::decapod::stdio::StdioTransport;
+use pincher::decapod::transport::{DecapodTransport, Invocation, ProcessTransport};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/decapod-stdio.sh"
+);
+
+async fn sequential(transport: &dyn DecapodTransport, calls: usize) -> Duration {
+    let started = Instant::now();
+    for _ in 0..calls {
+        let output = transport
+            .invoke(Invocation::new(&["todo", "list"]))
+            .await
+            .expect("fixture answers");
+        assert!(output.success());
+    }
+    started.elapsed()
+}
+
+async fn concurrent(transport: Arc<dyn DecapodTransport>, calls: usize) -> Duration {
+    let started = Instant::now();
+    let mut set = JoinSet::new();
+    for _ in 0..calls {
+        let transport = transport.clone();
+        set.spawn(async move { transport.invoke(Invocation::new(&["todo", "list"])).await });
+    }
+    while let Some(result) = set.join_next().await {
+        assert!(
+            result
+                .expect("task runs")
+                .expect("fixture answers")
+                .success()
+        );
+    }
+    started.elapsed()
+}
+
+fn report(mode: &str, shape: &str, calls: usize, elapsed: Duration) {
+    println!(
+        "{mode:<12} {shape:<11} {calls:>6} calls {:>10.2?} total {:>10.2?}/call",
+        elapsed,
+        elapsed / calls as u32
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    if cfg!(not(unix)) {
+        println!("decapod_transport benchmark needs a Unix shell; skipped");
+        return;
+    }
+    let calls = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(200usize);
+
+    let fixture = ProcessTransport::new(FIXTURE).with_project_root(env!("CARGO_MANIFEST_DIR"));
+    let process: Arc<dyn DecapodTransport> = Arc::new(fixture.clone());
+    let stdio = Arc::new(StdioTransport::new(fixture));
+    // Start the pool before timing so both modes are measured warm.
+    stdio.health_check().await;
+    let pooled: Arc<dyn DecapodTransport> = stdio.clone();
+
+    report(
+        "per-call",
+        "sequential",
+        calls,
+        sequential(process.as_ref(), calls).await,
+    );
+    report(
+        "stdio pool",
+        "sequential",
+        calls,
+        sequential(pooled.as_ref(), calls).await,
+    );
+    report(
+        "per-call",
+        "concurrent",
+        calls,
+        concurrent(process, calls).await,
+    );
+    report(
+        "stdio pool",
+        "concurrent",
+        calls,
+        concurrent(pooled, calls).await,
+    );
+
+    let stats = stdio.stats();
+    assert_eq!(stats.fallbacks, 0, "the stdio pool fell back: {stats:?}");
+    stdio.shutdown().await;
+}
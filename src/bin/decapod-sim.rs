@@ -0,0 +1,53 @@
+//! `decapod-sim`: an offline Decapod for local development and tests.
+//!
+//! Takes the same command line as `decapod` for the commands Pincher uses and
+//! answers them from a local state file; see [`pincher::decapod::sim`].
+
+use pincher::decapod::secret::{SecretSource, StdinSecret};
+use pincher::decapod::sim::Simulator;
+use pincher::decapod::stdio::STDIO_ARGS;
+use pincher::decapod::transport::{Invocation, SESSION_ENV};
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let simulator = match Simulator::from_env() {
+        Ok(simulator) => simulator,
+        Err(error) => {
+            eprintln!("decapod-sim: cannot read the working directory: {error}");
+            return ExitCode::from(70);
+        }
+    };
+    let mut invocation =
+        Invocation::new(&args).with_session(std::env::var(SESSION_ENV).ok().as_deref());
+    if args.iter().any(|arg| arg == "--password-stdin") {
+        match StdinSecret.read() {
+            Ok(password) => invocation.input = password,
+            Err(error) => {
+                eprintln!("decapod-sim: {error}");
+                return ExitCode::from(70);
+            }
+        }
+    }
+
+    if args == STDIO_ARGS {
+        let served = simulator.serve(io::stdin().lock(), io::stdout().lock(), &invocation.env);
+        return match served {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("decapod-sim: {error}");
+                ExitCode::from(70)
+            }
+        };
+    }
+
+    let output = simulator.run(&invocation);
+    let mut stdout = io::stdout().lock();
+    let _ = stdout.write_all(output.stdout.as_bytes());
+    let _ = stdout.flush();
+    if !output.stderr.is_empty() {
+        eprintln!("{}", output.stderr);
+    }
+    ExitCode::from(output.status.unwrap_or(70) as u8)
+}
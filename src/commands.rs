@@ -0,0 +1,659 @@
+//! Subcommands of the `pincher` binary.
+//!
+//! Every command reports through its exit status: 0 when the run is ready or
+//! the command succeeded, 1 for I/O and engine errors, 2 for usage errors, 3
+//! for a blocked run, and [`FailureCode::exit_code`] for a failed run.
+
+use pincher::daemon::http::{DEFAULT_PORT, HttpConfig};
+use pincher::governed_run::chain::verify_event_chain;
+use pincher::governed_run::schema::{ContractDocument, SchemaValidator};
+use pincher::governed_run::version::{upgrade_request, upgrade_snapshot};
+use pincher::{
+    EventSink, EventSinkError, FailureCode, GovernedRunEngine, InMemoryEventSink, RunEvent,
+    RunOutcome, RunScheduler, RunSnapshot, RunState, UnsupportedDecapodControlPlane,
+    UnsupportedProviderTurn,
+};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::net::SocketAddr;
+use std::num::NonZero;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+pub const SUCCESS: u8 = 0;
+pub const ERROR: u8 = 1;
+pub const USAGE: u8 = 2;
+pub const BLOCKED: u8 = 3;
+
+const HTTP_TOKEN_ENV: &str = "PINCHER_HTTP_TOKEN";
+
+pub const USAGE_TEXT: &str = "\
+usage: pincher <command> [options]
+
+commands:
+  run <request.json> [--events <log>] [--output <snapshot.json>]
+      execute a RunRequest and print the resulting snapshot
+  inspect <snapshot.json> [--json]
+      render a RunSnapshot or RunOutcome
+  events <log> [--run <id>] [--kind <prefix>] [--state <state>] [--json] [--verify]
+      pretty-print, filter, or verify a JSON-lines event log
+  validate-request <request.json>
+      check a RunRequest against the contract without running it
+  serve [--socket <path>] [--http <addr>] [--http-token-file <path>] [--max-runs <n>]
+      serve governed runs over JSON-RPC on a Unix socket, and optionally
+      over HTTP with Server-Sent Events (token also read from
+      PINCHER_HTTP_TOKEN); at most <n> runs execute at once (default: one
+      per CPU) and never two on the same workspace
+  decapod
+      start the interactive Decapod session
+
+Use `-` to read a request or snapshot from stdin.
+exit status: 0 ready, 1 error, 2 usage, 3 blocked, 10-19 failure code";
+
+/// Error that ends a command before it produced a result.
+pub struct Failure {
+    code: u8,
+    message: String,
+}
+
+impl Failure {
+    pub fn usage(message: impl Into<String>) -> Self {
+        Self {
+            code: USAGE,
+            message: message.into(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            code: ERROR,
+            message: message.into(),
+        }
+    }
+
+    fn invalid_request(message: impl Into<String>) -> Self {
+        Self {
+            code: FailureCode::InvalidRequest.exit_code(),
+            message: message.into(),
+        }
+    }
+
+    pub fn report(self) -> ExitCode {
+        eprintln!("pincher: {}", self.message);
+        if self.code == USAGE {
+            eprintln!("{USAGE_TEXT}");
+        }
+        ExitCode::from(self.code)
+    }
+}
+
+/// Positional arguments and `--flag [value]` options of one subcommand.
+pub struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, Option<String>)>,
+}
+
+impl Args {
+    /// Parses `args`; `valued` lists the options that take a value.
+    pub fn parse(args: &[String], valued: &[&str], flags: &[&str]) -> Result<Self, Failure> {
+        let mut parsed = Self {
+            positional: Vec::new(),
+            options: Vec::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if valued.contains(&arg.as_str()) {
+                let value = args
+                    .next()
+                    .ok_or_else(|| Failure::usage(format!("{arg} requires a value")))?;
+                parsed.options.push((arg.clone(), Some(value.clone())));
+            } else if flags.contains(&arg.as_str()) {
+                parsed.options.push((arg.clone(), None));
+            } else if arg.starts_with("--") {
+                return Err(Failure::usage(format!("unknown option {arg}")));
+            } else {
+                parsed.positional.push(arg.clone());
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(option, _)| option == name)
+    }
+
+    /// The single positional argument the command requires.
+    pub fn input(&self, what: &str) -> Result<&str, Failure> {
+        match self.positional.as_slice() {
+            [input] => Ok(input),
+            [] => Err(Failure::usage(format!("missing {what}"))),
+            [_, extra, ..] => Err(Failure::usage(format!("unexpected argument {extra}"))),
+        }
+    }
+}
+
+fn read_input(path: &str) -> Result<String, Failure> {
+    let mut text = String::new();
+    let result = if path == "-" {
+        std::io::stdin().read_to_string(&mut text).map(|_| ())
+    } else {
+        File::open(path).and_then(|mut file| file.read_to_string(&mut text).map(|_| ()))
+    };
+    result.map_err(|error| Failure::error(format!("cannot read {path}: {error}")))?;
+    Ok(text)
+}
+
+fn parse_json(path: &str, text: &str) -> Result<serde_json::Value, Failure> {
+    serde_json::from_str(text).map_err(|error| Failure::error(format!("{path}: {error}")))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, Failure> {
+    serde_json::to_string_pretty(value).map_err(|error| Failure::error(error.to_string()))
+}
+
+/// Exit status describing where a run ended.
+pub fn outcome_code(snapshot: &RunSnapshot) -> u8 {
+    match (&snapshot.failure, &snapshot.blocked) {
+        (Some(failure), _) => failure.code().exit_code(),
+        (None, Some(_)) => BLOCKED,
+        (None, None) if snapshot.state == RunState::Ready => SUCCESS,
+        (None, None) if snapshot.state == RunState::HandedOff => SUCCESS,
+        // A snapshot of a run that has not reached a terminal state.
+        (None, None) => ERROR,
+    }
+}
+
+/// Appends every event to a JSON-lines log.
+struct JsonLinesSink {
+    writer: BufWriter<File>,
+}
+
+impl EventSink for JsonLinesSink {
+    fn publish(&mut self, event: RunEvent) -> Result<(), EventSinkError> {
+        let write = serde_json::to_writer(&mut self.writer, &event)
+            .map_err(std::io::Error::from)
+            .and_then(|()| self.writer.write_all(b"\n"))
+            .and_then(|()| self.writer.flush());
+        write.map_err(|error| EventSinkError {
+            reason: error.to_string(),
+        })
+    }
+}
+
+/// `pincher run <request.json> [--events <log>] [--output <snapshot.json>]`
+///
+/// The concrete Decapod and provider adapters are not wired yet, so the
+/// configured ports are the fail-closed placeholders.
+pub fn run(args: &[String]) -> Result<u8, Failure> {
+    let args = Args::parse(args, &["--events", "--output"], &[])?;
+    let input = args.input("request file")?;
+    let request = upgrade_request(parse_json(input, &read_input(input)?)?)
+        .map_err(|error| Failure::invalid_request(format!("{input}: {error}")))?;
+
+    let control = UnsupportedDecapodControlPlane;
+    let provider = UnsupportedProviderTurn;
+    let outcome = match args.value("--events") {
+        Some(log) => {
+            let file = File::options()
+                .create(true)
+                .append(true)
+                .open(log)
+                .map_err(|error| Failure::error(format!("cannot open {log}: {error}")))?;
+            let sink = JsonLinesSink {
+                writer: BufWriter::new(file),
+            };
+            GovernedRunEngine::new(control, provider, sink).run(request)
+        }
+        None => {
+            GovernedRunEngine::new(control, provider, InMemoryEventSink::default()).run(request)
+        }
+    }
+    .map_err(|error| Failure::error(format!("run aborted: {error}")))?;
+
+    let snapshot = outcome.snapshot();
+    let encoded = to_json(snapshot)?;
+    match args.value("--output") {
+        Some(path) => std::fs::write(path, format!("{encoded}\n"))
+            .map_err(|error| Failure::error(format!("cannot write {path}: {error}")))?,
+        None => println!("{encoded}"),
+    }
+    eprintln!("{}", summary(snapshot));
+    Ok(outcome_code(snapshot))
+}
+
+/// `pincher inspect <snapshot.json> [--json]`
+pub fn inspect(args: &[String]) -> Result<u8, Failure> {
+    let args = Args::parse(args, &[], &["--json"])?;
+    let input = args.input("snapshot file")?;
+    let mut value = parse_json(input, &read_input(input)?)?;
+    // Accept a whole outcome as well as a bare snapshot.
+    if let Ok(outcome) = serde_json::from_value::<RunOutcome>(value.clone()) {
+        value = serde_json::to_value(outcome.snapshot())
+            .map_err(|error| Failure::error(error.to_string()))?;
+    }
+    let snapshot =
+        upgrade_snapshot(value).map_err(|error| Failure::error(format!("{input}: {error}")))?;
+
+    if args.flag("--json") {
+        println!("{}", to_json(&snapshot)?);
+    } else {
+        print!("{}", render_snapshot(&snapshot));
+    }
+    Ok(outcome_code(&snapshot))
+}
+
+/// `pincher events <log> [--run <id>] [--kind <prefix>] [--state <state>]
+/// [--json] [--verify]`
+pub fn events(args: &[String]) -> Result<u8, Failure> {
+    let args = Args::parse(
+        args,
+        &["--run", "--kind", "--state"],
+        &["--json", "--verify"],
+    )?;
+    let input = args.input("event log")?;
+    let events = parse_log(input, &read_input(input)?)?;
+    let state = args
+        .value("--state")
+        .map(|state| {
+            serde_json::from_value::<RunState>(serde_json::Value::String(state.to_string()))
+                .map_err(|_| Failure::usage(format!("unknown state {state}")))
+        })
+        .transpose()?;
+
+    if args.flag("--verify") {
+        return verify(&events, args.value("--run"));
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    for event in events.iter().filter(|event| {
+        args.value("--run")
+            .is_none_or(|run| event.run_id.as_str() == run)
+            && args
+                .value("--kind")
+                .is_none_or(|kind| event.kind.as_str().starts_with(kind))
+            && state.is_none_or(|state| event.state == Some(state))
+    }) {
+        let line = if args.flag("--json") {
+            serde_json::to_string(event).map_err(|error| Failure::error(error.to_string()))?
+        } else {
+            render_event(event)
+        };
+        if writeln!(stdout, "{line}").is_err() {
+            // The reader went away, for example `pincher events log | head`.
+            break;
+        }
+    }
+    Ok(SUCCESS)
+}
+
+/// Verifies the chain of every run in the log, in order of first appearance.
+fn verify(events: &[RunEvent], only: Option<&str>) -> Result<u8, Failure> {
+    let mut runs: Vec<(String, Vec<RunEvent>)> = Vec::new();
+    for event in events {
+        let run = event.run_id.as_str();
+        if only.is_some_and(|only| only != run) {
+            continue;
+        }
+        match runs.iter_mut().find(|(id, _)| id == run) {
+            Some((_, run_events)) => run_events.push(event.clone()),
+            None => runs.push((run.to_string(), vec![event.clone()])),
+        }
+    }
+
+    let mut code = SUCCESS;
+    for (run, run_events) in &runs {
+        match verify_event_chain(run_events, None) {
+            Ok(verification) => println!(
+                "{run}: ok, {} events, {} commitments",
+                verification.events, verification.commitments
+            ),
+            Err(violation) => {
+                println!("{run}: {violation:?}");
+                code = ERROR;
+            }
+        }
+    }
+    Ok(code)
+}
+
+/// `pincher validate-request <request.json>`
+pub fn validate_request(args: &[String]) -> Result<u8, Failure> {
+    let args = Args::parse(args, &[], &[])?;
+    let input = args.input("request file")?;
+    let value = parse_json(input, &read_input(input)?)?;
+
+    let validator = SchemaValidator::new(ContractDocument::RunRequest);
+    if let Err(violations) = validator.validate(&value) {
+        for violation in violations {
+            println!("{violation}");
+        }
+        return Ok(FailureCode::InvalidRequest.exit_code());
+    }
+    let request = serde_json::from_value::<pincher::RunRequest>(value)
+        .map_err(|error| Failure::invalid_request(format!("{input}: {error}")))?;
+    if let Err(failure) = request.validate() {
+        println!("{}", to_json(&failure)?);
+        return Ok(failure.code().exit_code());
+    }
+    println!(
+        "{input}: valid {} {} request",
+        request.contract.id, request.contract.version
+    );
+    Ok(SUCCESS)
+}
+
+fn parse_log(path: &str, text: &str) -> Result<Vec<RunEvent>, Failure> {
+    if text.trim_start().starts_with('[') {
+        return serde_json::from_str(text)
+            .map_err(|error| Failure::error(format!("{path}: {error}")));
+    }
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|error| Failure::error(format!("{path}:{}: {error}", index + 1)))
+        })
+        .collect()
+}
+
+fn name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        Ok(other) => other.to_string(),
+        Err(_) => "?".to_string(),
+    }
+}
+
+fn summary(snapshot: &RunSnapshot) -> String {
+    let mut summary = format!("{} {}", snapshot.request.run_id, name(&snapshot.state));
+    if let Some(failure) = &snapshot.failure {
+        summary.push_str(&format!(" ({})", name(&failure.code())));
+    }
+    summary
+}
+
+fn render_snapshot(snapshot: &RunSnapshot) -> String {
+    let request = &snapshot.request;
+    let mut lines = vec![
+        (
+            "run",
+            format!(
+                "{} ({} {})",
+                request.run_id, snapshot.contract.id, snapshot.contract.version
+            ),
+        ),
+        ("intent", request.intent_id.to_string()),
+        ("correlation", request.correlation_id.to_string()),
+        ("state", name(&snapshot.state)),
+    ];
+    if let Some(custody) = &snapshot.custody {
+        lines.push((
+            "custody",
+            format!(
+                "session={} task={} work_unit={} repository={} workspace={} receipt={}",
+                custody.session,
+                custody.task,
+                custody.work_unit,
+                custody.repository,
+                custody.workspace,
+                custody.receipt
+            ),
+        ));
+    }
+    if let Some(context) = &snapshot.context {
+        lines.push(("context", context.reference.to_string()));
+    }
+    if let Some(reference) = snapshot
+        .advisory
+        .as_ref()
+        .and_then(|advisory| advisory.reference.as_ref())
+    {
+        lines.push(("advisory", reference.to_string()));
+    }
+    if let Some(acknowledgement) = &snapshot.acknowledgement {
+        lines.push((
+            "acknowledged",
+            format!(
+                "{} by {}",
+                acknowledgement.advisory, acknowledgement.acknowledged_by
+            ),
+        ));
+    }
+    if let Some(approval) = &snapshot.approval {
+        lines.push(("approval", approval.reference.to_string()));
+    }
+    if let Some(progress) = &snapshot.approval_progress {
+        let mut approvals = format!(
+            "{} of {}",
+            progress.approvals(),
+            progress.quorum.approvals_needed
+        );
+        let outstanding = progress.outstanding_scopes();
+        if !outstanding.is_empty() {
+            approvals.push_str(&format!(", waiting on {}", outstanding.join(", ")));
+        }
+        lines.push(("approvals", approvals));
+    }
+    if let Some(blocked) = &snapshot.blocked {
+        lines.push(("blocked", blocked_text(blocked)));
+    }
+    if let Some(validation) = &snapshot.validation {
+        let verdict = if validation.passed {
+            "passed"
+        } else {
+            "rejected"
+        };
+        lines.push((
+            "validation",
+            format!("{} ({verdict})", validation.reference),
+        ));
+    }
+    if let Some(proof) = &snapshot.proof {
+        let verdict = if proof.backed { "backed" } else { "not backed" };
+        lines.push(("proof", format!("{} ({verdict})", proof.reference)));
+    }
+    if let Some(failure) = &snapshot.failure {
+        // Externally tagged: `{"Custody": {"reason": ..., "remediation": ...}}`.
+        let mut fields = match serde_json::to_value(failure) {
+            Ok(serde_json::Value::Object(variant)) => variant
+                .into_iter()
+                .next()
+                .and_then(|(_, fields)| match fields {
+                    serde_json::Value::Object(fields) => Some(fields),
+                    _ => None,
+                })
+                .unwrap_or_default(),
+            _ => serde_json::Map::new(),
+        };
+        let remediation = fields
+            .remove("remediation")
+            .and_then(|remediation| remediation.get("action").cloned())
+            .and_then(|action| action.as_str().map(ToString::to_string));
+        fields.retain(|_, value| !value.is_null());
+        lines.push((
+            "failure",
+            format!(
+                "{} {}",
+                name(&failure.code()),
+                serde_json::Value::Object(fields)
+            ),
+        ));
+        if let Some(remediation) = remediation {
+            lines.push(("remediation", remediation));
+        }
+    }
+    let transitions: Vec<String> = snapshot
+        .transitions
+        .iter()
+        .map(|transition| format!("{} -> {}", name(&transition.from), name(&transition.to)))
+        .collect();
+    if !transitions.is_empty() {
+        lines.push(("transitions", transitions.join(", ")));
+    }
+    let mut events = snapshot.event_count.to_string();
+    if let Some(head) = &snapshot.chain_head {
+        events.push_str(&format!(" (head {})", short(head)));
+    }
+    lines.push(("events", events));
+    if let Some(commitment) = &snapshot.commitment {
+        lines.push(("commitment", commitment.id.clone()));
+    }
+
+    lines
+        .into_iter()
+        .map(|(label, value)| format!("{label:<12} {value}\n"))
+        .collect()
+}
+
+fn blocked_text(blocked: &pincher::BlockedReason) -> String {
+    let (kind, remediation) = match blocked {
+        pincher::BlockedReason::Interlock { remediation, .. } => ("interlock", remediation),
+        pincher::BlockedReason::ApprovalPending { remediation, .. } => {
+            ("approval pending", remediation)
+        }
+        pincher::BlockedReason::ApprovalDenied { remediation, .. } => {
+            ("approval denied", remediation)
+        }
+        pincher::BlockedReason::AdvisoryUnacknowledged { remediation, .. } => {
+            ("advisory unacknowledged", remediation)
+        }
+    };
+    let text = format!("{kind} {}: {}", blocked.reference(), remediation.action);
+    match blocked.policy() {
+        Some(policy) => format!("{text} (policy {})", policy.name),
+        None => text,
+    }
+}
+
+fn render_event(event: &RunEvent) -> String {
+    let mut line = format!(
+        "{:>4} {} {:<28} {:<18}",
+        event.sequence,
+        event.occurred_at.format("%Y-%m-%dT%H:%M:%SZ"),
+        event.kind.as_str(),
+        event.state.map(|state| name(&state)).unwrap_or_default(),
+    );
+    let references = [
+        ("run", Some(event.run_id.to_string())),
+        ("failure", event.failure.as_ref().map(name)),
+        (
+            "advisory",
+            event.advisory_ref.as_ref().map(ToString::to_string),
+        ),
+        (
+            "interlock",
+            event.approval_ref.as_ref().map(ToString::to_string),
+        ),
+        (
+            "approval",
+            event
+                .approval_evidence_ref
+                .as_ref()
+                .map(ToString::to_string),
+        ),
+        (
+            "validation",
+            event.validation_ref.as_ref().map(ToString::to_string),
+        ),
+        ("proof", event.proof_ref.as_ref().map(ToString::to_string)),
+    ];
+    for (label, value) in references {
+        if let Some(value) = value {
+            line.push_str(&format!(" {label}={value}"));
+        }
+    }
+    if event.redactions > 0 {
+        line.push_str(&format!(" redacted={}", event.redactions));
+    }
+    line.trim_end().to_string()
+}
+
+fn short(digest: &str) -> &str {
+    digest.get(..12).unwrap_or(digest)
+}
+
+/// Default socket for `pincher serve`.
+pub fn default_socket() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("pincher.sock")
+}
+
+/// Checks that `path` names a socket location the daemon can bind.
+pub fn socket_arg(args: &Args) -> Result<PathBuf, Failure> {
+    let socket = args
+        .value("--socket")
+        .map(PathBuf::from)
+        .unwrap_or_else(default_socket);
+    if socket
+        .parent()
+        .is_some_and(|parent| !parent.as_os_str().is_empty() && !parent.exists())
+    {
+        return Err(Failure::error(format!(
+            "socket directory {} does not exist",
+            socket
+                .parent()
+                .map(Path::display)
+                .map(|d| d.to_string())
+                .unwrap_or_default()
+        )));
+    }
+    Ok(socket)
+}
+
+/// HTTP listener requested by `--http`.  The token comes from
+/// `--http-token-file` or `PINCHER_HTTP_TOKEN`, never from the command line.
+pub fn http_config(args: &Args) -> Result<Option<HttpConfig>, Failure> {
+    let Some(addr) = args.value("--http") else {
+        if args.value("--http-token-file").is_some() {
+            return Err(Failure::usage("--http-token-file requires --http"));
+        }
+        return Ok(None);
+    };
+    let addr = addr.parse::<SocketAddr>().map_err(|_| {
+        Failure::usage(format!(
+            "--http expects an address like 127.0.0.1:{DEFAULT_PORT}"
+        ))
+    })?;
+    let token = match args.value("--http-token-file") {
+        Some(path) => Some(
+            std::fs::read_to_string(path)
+                .map_err(|error| Failure::error(format!("cannot read {path}: {error}")))?
+                .trim()
+                .to_string(),
+        ),
+        None => std::env::var(HTTP_TOKEN_ENV).ok(),
+    };
+    let config = HttpConfig::new(addr);
+    Ok(Some(match token {
+        Some(token) => config.with_token(token),
+        None => config,
+    }))
+}
+
+/// Scheduler for `--max-runs`, one run per available CPU by default.
+pub fn scheduler(args: &Args) -> Result<RunScheduler, Failure> {
+    let max_runs = match args.value("--max-runs") {
+        Some(value) => value
+            .parse::<usize>()
+            .ok()
+            .filter(|max_runs| *max_runs > 0)
+            .ok_or_else(|| Failure::usage("--max-runs expects a positive number"))?,
+        None => std::thread::available_parallelism().map_or(1, NonZero::get),
+    };
+    Ok(RunScheduler::new(max_runs))
+}
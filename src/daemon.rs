@@ -0,0 +1,447 @@
+//! Long-lived host for governed runs.
+//!
+//! [`RunService`] owns every run submitted to one Pincher process.  Each run
+//! gets its own [`GovernedRunEngine`] on a blocking thread; its events are
+//! kept per run and fanned out to subscribers.  Transports such as
+//! [`jsonrpc`] and [`http`] only translate requests onto the service.
+
+pub mod http;
+pub mod jsonrpc;
+
+use crate::governed_run::projection::RunProjection;
+use crate::governed_run::{
+    ContractSupport, DecapodControlPlane, EventSink, EventSinkError, GovernedRunEngine,
+    ProviderTurn, RunCancellation, RunError, RunEvent, RunId, RunOutcome, RunRequest, RunScheduler,
+    RunSnapshot, RunState,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use thiserror::Error;
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+
+/// Live events buffered per subscriber before it has to catch up from the
+/// stored log.
+const LIVE_EVENT_CAPACITY: usize = 256;
+
+/// Finished runs kept by default before the oldest are evicted.
+pub const DEFAULT_RETENTION: usize = 1024;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ServiceError {
+    #[error("run {run_id} is unknown")]
+    RunNotFound { run_id: String },
+    #[error("run {run_id} already exists with a different idempotency key")]
+    RunConflict { run_id: String },
+    #[error("run {run_id} has not reached a terminal state")]
+    RunNotFinished { run_id: String },
+    #[error("run {run_id} has already finished")]
+    RunFinished { run_id: String },
+    #[error("run {run_id} cannot be handed off: {reason}")]
+    InvalidHandoff { run_id: String, reason: String },
+    #[error("the daemon is draining and accepts no new runs")]
+    Draining,
+}
+
+/// What a host sees of one run at a point in time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunStatus {
+    pub run_id: RunId,
+    /// State of the latest published event.
+    pub state: RunState,
+    pub event_count: u64,
+    pub cancel_requested: bool,
+    /// Present once the engine returned.
+    pub outcome: Option<RunOutcome>,
+    /// Engine error that ended the run without an outcome, such as an
+    /// illegal transition.
+    pub error: Option<String>,
+}
+
+impl RunStatus {
+    pub fn is_finished(&self) -> bool {
+        self.outcome.is_some() || self.error.is_some()
+    }
+}
+
+/// Events of one run: everything already published after the requested
+/// sequence, followed by live events.
+pub struct EventSubscription {
+    pub backlog: Vec<RunEvent>,
+    pub live: broadcast::Receiver<RunEvent>,
+    /// Becomes `true` once the engine has returned.  A run rejected before it
+    /// started, or ended by an engine error, publishes no terminal event, so
+    /// subscribers end once this is set and the stored log holds nothing
+    /// newer.
+    pub finished: watch::Receiver<bool>,
+}
+
+struct RunEntry {
+    request: RunRequest,
+    state: RunState,
+    events: Vec<RunEvent>,
+    live: broadcast::Sender<RunEvent>,
+    cancellation: RunCancellation,
+    outcome: Option<RunOutcome>,
+    error: Option<String>,
+    finished: watch::Sender<bool>,
+    handing_off: bool,
+}
+
+impl RunEntry {
+    fn status(&self) -> RunStatus {
+        RunStatus {
+            run_id: self.request.run_id.clone(),
+            state: self.state,
+            event_count: self.events.len() as u64,
+            cancel_requested: self.cancellation.is_cancelled(),
+            outcome: self.outcome.clone(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+struct Shared<C, P> {
+    control_plane: C,
+    provider: P,
+    scheduler: Option<RunScheduler>,
+    runs: Mutex<HashMap<RunId, RunEntry>>,
+    /// Finished runs, oldest first.
+    finished: Mutex<VecDeque<RunId>>,
+    retention: AtomicUsize,
+    draining: AtomicBool,
+    in_flight: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl<C, P> Shared<C, P> {
+    fn runs(&self) -> MutexGuard<'_, HashMap<RunId, RunEntry>> {
+        self.runs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn finish(&self, run_id: &RunId, result: Result<RunOutcome, RunError>) {
+        let mut runs = self.runs();
+        if let Some(entry) = runs.get_mut(run_id) {
+            match result {
+                Ok(outcome) => entry.outcome = Some(outcome),
+                Err(error) => entry.error = Some(error.to_string()),
+            }
+            entry.finished.send_replace(true);
+        }
+        let mut finished = self
+            .finished
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        finished.push_back(run_id.clone());
+        while finished.len() > self.retention.load(Ordering::SeqCst) {
+            if let Some(evicted) = finished.pop_front() {
+                runs.remove(&evicted);
+            }
+        }
+    }
+}
+
+/// Sink that records a run's events in the service and forwards them to
+/// subscribers.
+struct ServiceSink<C, P> {
+    shared: Arc<Shared<C, P>>,
+    run_id: RunId,
+}
+
+impl<C, P> EventSink for ServiceSink<C, P> {
+    fn publish(&mut self, event: RunEvent) -> Result<(), EventSinkError> {
+        let mut runs = self.shared.runs();
+        let entry = runs.get_mut(&self.run_id).ok_or_else(|| EventSinkError {
+            reason: format!("run {} is not registered", self.run_id),
+        })?;
+        if let Some(state) = event.state {
+            entry.state = state;
+        }
+        entry.events.push(event.clone());
+        // Subscribers that lag behind catch up from `events`.
+        let _ = entry.live.send(event);
+        Ok(())
+    }
+}
+
+pub struct RunService<C, P> {
+    shared: Arc<Shared<C, P>>,
+}
+
+impl<C, P> Clone for RunService<C, P> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<C, P> RunService<C, P>
+where
+    C: DecapodControlPlane + Clone + Send + Sync + 'static,
+    P: ProviderTurn + Clone + Send + Sync + 'static,
+{
+    /// Runs every submitted request at once, without admission control.
+    pub fn new(control_plane: C, provider: P) -> Self {
+        Self::build(control_plane, provider, None)
+    }
+
+    /// Admits submitted runs through `scheduler`, which bounds how many run
+    /// at once and keeps runs on the same workspace apart.
+    pub fn scheduled(control_plane: C, provider: P, scheduler: RunScheduler) -> Self {
+        Self::build(control_plane, provider, Some(scheduler))
+    }
+
+    fn build(control_plane: C, provider: P, scheduler: Option<RunScheduler>) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                control_plane,
+                provider,
+                scheduler,
+                runs: Mutex::new(HashMap::new()),
+                finished: Mutex::new(VecDeque::new()),
+                retention: AtomicUsize::new(DEFAULT_RETENTION),
+                draining: AtomicBool::new(false),
+                in_flight: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Keeps at most `finished_runs` finished runs, evicting the oldest.  An
+    /// evicted run is unknown to every call, and its run id can be submitted
+    /// again.
+    pub fn with_retention(self, finished_runs: usize) -> Self {
+        self.shared.retention.store(finished_runs, Ordering::SeqCst);
+        self
+    }
+
+    pub fn contract_support(&self) -> ContractSupport {
+        ContractSupport::current()
+    }
+
+    /// Starts a run on a blocking thread and returns immediately.
+    ///
+    /// Submitting the same run id with the same idempotency key again returns
+    /// the existing run instead of starting a second one.  Must be called
+    /// from within a Tokio runtime.
+    pub fn submit(&self, request: RunRequest) -> Result<RunStatus, ServiceError> {
+        if self.is_draining() {
+            return Err(ServiceError::Draining);
+        }
+        let run_id = request.run_id.clone();
+        let cancellation = RunCancellation::new();
+        let status = {
+            let mut runs = self.shared.runs();
+            if let Some(existing) = runs.get(&run_id) {
+                if existing.request.idempotency_key == request.idempotency_key {
+                    return Ok(existing.status());
+                }
+                return Err(ServiceError::RunConflict {
+                    run_id: run_id.to_string(),
+                });
+            }
+            let entry = RunEntry {
+                request: request.clone(),
+                state: RunState::Prepared,
+                events: Vec::new(),
+                live: broadcast::channel(LIVE_EVENT_CAPACITY).0,
+                cancellation: cancellation.clone(),
+                outcome: None,
+                error: None,
+                finished: watch::channel(false).0,
+                handing_off: false,
+            };
+            let status = entry.status();
+            runs.insert(run_id.clone(), entry);
+            status
+        };
+
+        let shared = Arc::clone(&self.shared);
+        let handle = tokio::task::spawn_blocking(move || {
+            let sink = ServiceSink {
+                shared: Arc::clone(&shared),
+                run_id: run_id.clone(),
+            };
+            let mut engine =
+                GovernedRunEngine::new(shared.control_plane.clone(), shared.provider.clone(), sink);
+            if let Some(scheduler) = &shared.scheduler {
+                engine = engine.with_scheduler(scheduler.clone());
+            }
+            let result = engine.run_cancellable(request, &cancellation);
+            shared.finish(&run_id, result);
+        });
+        let mut in_flight = self
+            .shared
+            .in_flight
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        in_flight.retain(|handle| !handle.is_finished());
+        in_flight.push(handle);
+        Ok(status)
+    }
+
+    pub fn get(&self, run_id: &RunId) -> Result<RunStatus, ServiceError> {
+        self.with_entry(run_id, |entry| Ok(entry.status()))
+    }
+
+    /// The run as the engine holds it: the outcome snapshot once the engine
+    /// returned, otherwise the snapshot folded from the events so far.
+    pub fn snapshot(&self, run_id: &RunId) -> Result<RunSnapshot, ServiceError> {
+        self.with_entry(run_id, |entry| {
+            if let Some(outcome) = &entry.outcome {
+                return Ok(outcome.snapshot().clone());
+            }
+            let mut projection = RunProjection::new(entry.request.clone());
+            for event in &entry.events {
+                projection.apply(event);
+            }
+            Ok(projection.snapshot().clone())
+        })
+    }
+
+    /// Requests cancellation; the run stops at its next checkpoint.
+    pub fn cancel(&self, run_id: &RunId) -> Result<RunStatus, ServiceError> {
+        self.with_entry(run_id, |entry| {
+            if entry.outcome.is_some() || entry.error.is_some() {
+                return Err(ServiceError::RunFinished {
+                    run_id: run_id.to_string(),
+                });
+            }
+            entry.cancellation.cancel();
+            Ok(entry.status())
+        })
+    }
+
+    /// Hands a finished run back to the host.
+    pub fn handoff(&self, run_id: &RunId) -> Result<RunStatus, ServiceError> {
+        let outcome = self.with_entry(run_id, |entry| {
+            let outcome = entry
+                .outcome
+                .clone()
+                .ok_or_else(|| ServiceError::RunNotFinished {
+                    run_id: run_id.to_string(),
+                })?;
+            if entry.handing_off {
+                return Err(ServiceError::InvalidHandoff {
+                    run_id: run_id.to_string(),
+                    reason: "a handoff is already in progress".to_string(),
+                });
+            }
+            entry.handing_off = true;
+            Ok(outcome)
+        })?;
+        let sink = ServiceSink {
+            shared: Arc::clone(&self.shared),
+            run_id: run_id.clone(),
+        };
+        let mut engine = GovernedRunEngine::new(
+            self.shared.control_plane.clone(),
+            self.shared.provider.clone(),
+            sink,
+        );
+        let result = engine.handoff(outcome);
+        self.with_entry(run_id, |entry| {
+            entry.handing_off = false;
+            entry.outcome = Some(result.map_err(|error| ServiceError::InvalidHandoff {
+                run_id: run_id.to_string(),
+                reason: error.to_string(),
+            })?);
+            Ok(entry.status())
+        })
+    }
+
+    /// Subscribes to the events of a run with a sequence after
+    /// `after_sequence`.
+    pub fn subscribe(
+        &self,
+        run_id: &RunId,
+        after_sequence: u64,
+    ) -> Result<EventSubscription, ServiceError> {
+        self.with_entry(run_id, |entry| {
+            Ok(EventSubscription {
+                backlog: events_after(&entry.events, after_sequence),
+                live: entry.live.subscribe(),
+                finished: entry.finished.subscribe(),
+            })
+        })
+    }
+
+    /// Stored events of a run with a sequence after `after_sequence`.
+    pub fn events_after(
+        &self,
+        run_id: &RunId,
+        after_sequence: u64,
+    ) -> Result<Vec<RunEvent>, ServiceError> {
+        self.with_entry(run_id, |entry| {
+            Ok(events_after(&entry.events, after_sequence))
+        })
+    }
+
+    /// Waits until the engine returns for `run_id`.
+    pub async fn wait(&self, run_id: &RunId) -> Result<RunStatus, ServiceError> {
+        let mut finished = self.with_entry(run_id, |entry| Ok(entry.finished.subscribe()))?;
+        // The sender lives as long as the entry; an evicted run is reported
+        // as unknown below.
+        let _ = finished.wait_for(|finished| *finished).await;
+        self.get(run_id)
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.shared.draining.load(Ordering::SeqCst)
+    }
+
+    /// Stops accepting runs and waits for every in-flight run to finish.
+    pub async fn drain(&self) {
+        self.shared.draining.store(true, Ordering::SeqCst);
+        loop {
+            let handles = std::mem::take(
+                &mut *self
+                    .shared
+                    .in_flight
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            );
+            if handles.is_empty() {
+                return;
+            }
+            for handle in handles {
+                if let Err(error) = handle.await {
+                    tracing::warn!("run task ended abnormally: {error}");
+                }
+            }
+        }
+    }
+
+    fn with_entry<T>(
+        &self,
+        run_id: &RunId,
+        f: impl FnOnce(&mut RunEntry) -> Result<T, ServiceError>,
+    ) -> Result<T, ServiceError> {
+        let mut runs = self.shared.runs();
+        let entry = runs
+            .get_mut(run_id)
+            .ok_or_else(|| ServiceError::RunNotFound {
+                run_id: run_id.to_string(),
+            })?;
+        f(entry)
+    }
+}
+
+fn events_after(events: &[RunEvent], after_sequence: u64) -> Vec<RunEvent> {
+    events
+        .iter()
+        .filter(|event| event.sequence > after_sequence)
+        .cloned()
+        .collect()
+}
+
+/// Whether `event` moved its run into a state that ends a subscription.
+fn is_terminal(event: &RunEvent) -> bool {
+    matches!(
+        event.state,
+        Some(RunState::Ready | RunState::Blocked | RunState::Failed | RunState::HandedOff)
+    ) && event.kind.as_str().starts_with("run.state.")
+}
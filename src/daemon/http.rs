@@ -0,0 +1,548 @@
+//! HTTP/1.1 transport for [`RunService`], for hosts such as web dashboards.
+//!
+//! Routes:
+//!
+//! - `POST /runs` takes a [`RunRequest`] body and answers `202 Accepted` with
+//!   the [`RunStatus`] and a `Location` of the run.
+//! - `GET /runs/{id}` returns the current [`RunSnapshot`].
+//! - `GET /runs/{id}/events` is a Server-Sent Events stream of
+//!   [`RunEvent`]s.  Every event carries its `sequence` as the SSE `id`, so a
+//!   client that reconnects with `Last-Event-ID: n` resumes after sequence
+//!   `n`.  The stream sends an `end` event after the first terminal state.
+//!
+//! The server binds to loopback by default.  With a token configured, every
+//! request needs `Authorization: Bearer <token>`; binding to any other
+//! address requires one.
+//!
+//! [`RunSnapshot`]: crate::governed_run::RunSnapshot
+
+use super::{RunService, ServiceError, is_terminal};
+use crate::governed_run::version::upgrade_request;
+use crate::governed_run::{DecapodControlPlane, ProviderTurn, RunEvent, RunId};
+use serde::Serialize;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+
+pub const DEFAULT_PORT: u16 = 7419;
+
+/// Largest request line plus headers accepted.
+const MAX_HEAD_BYTES: u64 = 16 * 1024;
+/// Largest request body accepted.
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+/// Interval of SSE comments that keep idle streams and proxies alive.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpConfig {
+    pub addr: SocketAddr,
+    pub token: Option<String>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            addr: SocketAddr::from((Ipv4Addr::LOCALHOST, DEFAULT_PORT)),
+            token: None,
+        }
+    }
+}
+
+impl HttpConfig {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self { addr, token: None }
+    }
+
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+}
+
+/// Bound HTTP listener, ready to serve.
+pub struct HttpServer {
+    listener: TcpListener,
+    token: Option<String>,
+}
+
+impl HttpServer {
+    /// Binds `config.addr`.  Fails closed when the address is not loopback
+    /// and no token is configured, or when the token is empty.
+    pub async fn bind(config: HttpConfig) -> std::io::Result<Self> {
+        if config.token.as_deref().is_some_and(str::is_empty) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the HTTP token must not be empty",
+            ));
+        }
+        if !config.addr.ip().is_loopback() && config.token.is_none() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "refusing to serve {} without a token; bind to loopback or configure one",
+                    config.addr
+                ),
+            ));
+        }
+        Ok(Self {
+            listener: TcpListener::bind(config.addr).await?,
+            token: config.token,
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves `service` until `shutdown` resolves, then stops accepting
+    /// connections and drains in-flight runs.
+    pub async fn serve<C, P>(
+        self,
+        service: RunService<C, P>,
+        shutdown: impl Future<Output = ()>,
+    ) -> std::io::Result<()>
+    where
+        C: DecapodControlPlane + Clone + Send + Sync + 'static,
+        P: ProviderTurn + Clone + Send + Sync + 'static,
+    {
+        tracing::info!("serving governed runs on http://{}", self.local_addr()?);
+        tokio::pin!(shutdown);
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                accepted = self.listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        let token = self.token.clone();
+                        tokio::spawn(serve_connection(service.clone(), stream, token));
+                    }
+                    Err(error) => tracing::warn!("failed to accept connection: {error}"),
+                },
+            }
+        }
+
+        drop(self.listener);
+        tracing::info!("draining in-flight runs");
+        service.drain().await;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn keep_alive(&self) -> bool {
+        !self
+            .header("connection")
+            .is_some_and(|value| value.eq_ignore_ascii_case("close"))
+    }
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, body: &impl Serialize) -> Self {
+        match serde_json::to_vec(body) {
+            Ok(body) => Self {
+                status,
+                headers: vec![("Content-Type", "application/json".to_string())],
+                body,
+            },
+            Err(error) => Self::error(500, "Internal", error.to_string()),
+        }
+    }
+
+    fn error(status: u16, code: &str, message: impl Into<String>) -> Self {
+        let body = serde_json::json!({ "error": { "code": code, "message": message.into() } });
+        Self {
+            status,
+            headers: vec![("Content-Type", "application/json".to_string())],
+            body: body.to_string().into_bytes(),
+        }
+    }
+
+    fn service(error: ServiceError) -> Self {
+        let (status, code) = match &error {
+            ServiceError::RunNotFound { .. } => (404, "RunNotFound"),
+            ServiceError::RunConflict { .. } => (409, "RunConflict"),
+            ServiceError::RunNotFinished { .. } => (409, "RunNotFinished"),
+            ServiceError::RunFinished { .. } => (409, "RunFinished"),
+            ServiceError::InvalidHandoff { .. } => (409, "InvalidHandoff"),
+            ServiceError::Draining => (503, "Draining"),
+        };
+        Self::error(status, code, error.to_string())
+    }
+
+    fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    fn encode(&self, keep_alive: bool) -> Vec<u8> {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, reason(self.status));
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        if !keep_alive {
+            head.push_str("Connection: close\r\n");
+        }
+        head.push_str("\r\n");
+        let mut encoded = head.into_bytes();
+        encoded.extend_from_slice(&self.body);
+        encoded
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        411 => "Length Required",
+        413 => "Content Too Large",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+/// Reads one request.  `Ok(None)` means the client closed the connection
+/// between requests; `Err` carries the response to send before closing.
+async fn read_request<R>(reader: &mut BufReader<R>) -> Result<Option<Request>, Response>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut head = reader.take(MAX_HEAD_BYTES);
+    let mut line = String::new();
+    match head.read_line(&mut line).await {
+        Ok(0) => return Ok(None),
+        Ok(_) => {}
+        Err(error) => return Err(Response::error(400, "BadRequest", error.to_string())),
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target), Some(_version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(Response::error(400, "BadRequest", "malformed request line"));
+    };
+    let mut request = Request {
+        method: method.to_string(),
+        path: target.split('?').next().unwrap_or_default().to_string(),
+        headers: Vec::new(),
+        body: Vec::new(),
+    };
+
+    loop {
+        line.clear();
+        match head.read_line(&mut line).await {
+            Ok(0) if head.limit() == 0 => {
+                return Err(Response::error(
+                    431,
+                    "HeadersTooLarge",
+                    "request head too large",
+                ));
+            }
+            Ok(0) => return Err(Response::error(400, "BadRequest", "truncated request head")),
+            Ok(_) => {}
+            Err(error) => return Err(Response::error(400, "BadRequest", error.to_string())),
+        }
+        let header = line.trim_end_matches(['\r', '\n']);
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(Response::error(400, "BadRequest", "malformed header"));
+        };
+        request
+            .headers
+            .push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    if request.header("transfer-encoding").is_some() {
+        return Err(Response::error(
+            411,
+            "LengthRequired",
+            "chunked request bodies are not supported; send Content-Length",
+        ));
+    }
+    let length = match request.header("content-length") {
+        Some(length) => length
+            .parse::<usize>()
+            .map_err(|_| Response::error(400, "BadRequest", "invalid Content-Length"))?,
+        None => 0,
+    };
+    if length > MAX_BODY_BYTES {
+        return Err(Response::error(
+            413,
+            "BodyTooLarge",
+            "request body too large",
+        ));
+    }
+    request.body = vec![0; length];
+    reader
+        .read_exact(&mut request.body)
+        .await
+        .map_err(|error| Response::error(400, "BadRequest", error.to_string()))?;
+    Ok(Some(request))
+}
+
+/// Compares tokens without an early exit on the first differing byte.
+fn token_matches(expected: &str, presented: &str) -> bool {
+    expected.len() == presented.len()
+        && expected
+            .bytes()
+            .zip(presented.bytes())
+            .fold(0u8, |difference, (left, right)| difference | (left ^ right))
+            == 0
+}
+
+fn authorized(request: &Request, token: Option<&str>) -> bool {
+    let Some(token) = token else {
+        return true;
+    };
+    request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|presented| token_matches(token, presented.trim()))
+}
+
+enum Route {
+    Respond(Response),
+    Events { run_id: RunId, after_sequence: u64 },
+}
+
+fn route<C, P>(service: &RunService<C, P>, request: &Request) -> Route
+where
+    C: DecapodControlPlane + Clone + Send + Sync + 'static,
+    P: ProviderTurn + Clone + Send + Sync + 'static,
+{
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    let respond = match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["runs"]) => submit(service, &request.body),
+        ("GET", ["runs", run_id]) => match run_id_from(run_id) {
+            Ok(run_id) => match service.snapshot(&run_id) {
+                Ok(snapshot) => Response::json(200, &snapshot),
+                Err(error) => Response::service(error),
+            },
+            Err(response) => response,
+        },
+        ("GET", ["runs", run_id, "events"]) => {
+            let run_id = match run_id_from(run_id) {
+                Ok(run_id) => run_id,
+                Err(response) => return Route::Respond(response),
+            };
+            let after_sequence = match request.header("last-event-id") {
+                Some(id) => match id.trim().parse::<u64>() {
+                    Ok(sequence) => sequence,
+                    Err(_) => {
+                        return Route::Respond(Response::error(
+                            400,
+                            "BadRequest",
+                            "Last-Event-ID must be an event sequence",
+                        ));
+                    }
+                },
+                None => 0,
+            };
+            if let Err(error) = service.get(&run_id) {
+                return Route::Respond(Response::service(error));
+            }
+            return Route::Events {
+                run_id,
+                after_sequence,
+            };
+        }
+        (_, ["runs"]) => {
+            Response::error(405, "MethodNotAllowed", "use POST").with_header("Allow", "POST")
+        }
+        (_, ["runs", _] | ["runs", _, "events"]) => {
+            Response::error(405, "MethodNotAllowed", "use GET").with_header("Allow", "GET")
+        }
+        _ => Response::error(404, "NotFound", format!("no route for {}", request.path)),
+    };
+    Route::Respond(respond)
+}
+
+fn run_id_from(segment: &str) -> Result<RunId, Response> {
+    RunId::new(segment).map_err(|error| Response::error(400, "BadRequest", error.to_string()))
+}
+
+fn submit<C, P>(service: &RunService<C, P>, body: &[u8]) -> Response
+where
+    C: DecapodControlPlane + Clone + Send + Sync + 'static,
+    P: ProviderTurn + Clone + Send + Sync + 'static,
+{
+    let request = match serde_json::from_slice(body)
+        .map_err(|error| error.to_string())
+        .and_then(|value| upgrade_request(value).map_err(|error| error.to_string()))
+    {
+        Ok(request) => request,
+        Err(error) => return Response::error(400, "InvalidRequest", error),
+    };
+    match service.submit(request) {
+        Ok(status) => {
+            let location = format!("/runs/{}", status.run_id);
+            Response::json(202, &status).with_header("Location", location)
+        }
+        Err(error) => Response::service(error),
+    }
+}
+
+async fn serve_connection<C, P>(service: RunService<C, P>, stream: TcpStream, token: Option<String>)
+where
+    C: DecapodControlPlane + Clone + Send + Sync + 'static,
+    P: ProviderTurn + Clone + Send + Sync + 'static,
+{
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    loop {
+        let request = match read_request(&mut reader).await {
+            Ok(Some(request)) => request,
+            Ok(None) => return,
+            Err(response) => {
+                let _ = writer.write_all(&response.encode(false)).await;
+                return;
+            }
+        };
+        let keep_alive = request.keep_alive();
+        let route = if authorized(&request, token.as_deref()) {
+            route(&service, &request)
+        } else {
+            Route::Respond(
+                Response::error(401, "Unauthorized", "missing or invalid bearer token")
+                    .with_header("WWW-Authenticate", "Bearer"),
+            )
+        };
+        match route {
+            Route::Respond(response) => {
+                if writer
+                    .write_all(&response.encode(keep_alive))
+                    .await
+                    .is_err()
+                    || !keep_alive
+                {
+                    return;
+                }
+            }
+            Route::Events {
+                run_id,
+                after_sequence,
+            } => {
+                stream_events(&service, &mut writer, run_id, after_sequence).await;
+                return;
+            }
+        }
+    }
+}
+
+fn sse_event(event: &RunEvent) -> Option<Vec<u8>> {
+    let data = serde_json::to_string(event).ok()?;
+    Some(format!("id: {}\nevent: run-event\ndata: {data}\n\n", event.sequence).into_bytes())
+}
+
+fn sse_end(last_sequence: u64) -> Vec<u8> {
+    format!("event: end\ndata: {{\"last_sequence\":{last_sequence}}}\n\n").into_bytes()
+}
+
+/// Writes the SSE stream of `run_id` until its first terminal state, or until
+/// the client goes away.
+async fn stream_events<C, P, W>(
+    service: &RunService<C, P>,
+    writer: &mut W,
+    run_id: RunId,
+    mut after_sequence: u64,
+) where
+    C: DecapodControlPlane + Clone + Send + Sync + 'static,
+    P: ProviderTurn + Clone + Send + Sync + 'static,
+    W: AsyncWriteExt + Unpin,
+{
+    let Ok(subscription) = service.subscribe(&run_id, after_sequence) else {
+        return;
+    };
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
+                Cache-Control: no-cache\r\nConnection: close\r\n\r\n";
+    if writer.write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+    let mut live = subscription.live;
+    let mut finished = subscription.finished;
+    let mut queued = subscription.backlog;
+    let mut keep_alive = tokio::time::interval(KEEP_ALIVE);
+    keep_alive.reset();
+    loop {
+        for event in queued.drain(..) {
+            if event.sequence <= after_sequence {
+                continue;
+            }
+            after_sequence = event.sequence;
+            let Some(frame) = sse_event(&event) else {
+                return;
+            };
+            if writer.write_all(&frame).await.is_err() {
+                return;
+            }
+            if is_terminal(&event) {
+                let _ = writer.write_all(&sse_end(after_sequence)).await;
+                return;
+            }
+        }
+        // A finished run publishes nothing newer than its stored log.
+        if *finished.borrow_and_update() {
+            match service.events_after(&run_id, after_sequence) {
+                Ok(events) if events.is_empty() => {
+                    let _ = writer.write_all(&sse_end(after_sequence)).await;
+                    return;
+                }
+                Ok(events) => {
+                    queued = events;
+                    continue;
+                }
+                Err(_) => return,
+            }
+        }
+        tokio::select! {
+            received = live.recv() => match received {
+                Ok(event) => queued.push(event),
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    match service.events_after(&run_id, after_sequence) {
+                        Ok(events) => queued = events,
+                        Err(_) => return,
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            changed = finished.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+            _ = keep_alive.tick() => {
+                if writer.write_all(b": keep-alive\n\n").await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
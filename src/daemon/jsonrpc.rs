@@ -0,0 +1,432 @@
+//! JSON-RPC 2.0 transport for [`RunService`].
+//!
+//! Each message is one line of JSON: a request object or a batch array.
+//! Methods:
+//!
+//! - `contract.support` returns the [`ContractSupport`] of the daemon.
+//! - `run.submit` takes `{ "request": RunRequest }` in any compatible
+//!   contract version, upgrades it like [`upgrade_request`], and returns a
+//!   [`RunStatus`].
+//! - `run.get`, `run.cancel`, and `run.handoff` take `{ "run_id": ... }` and
+//!   return a [`RunStatus`].
+//! - `events.subscribe` takes `{ "run_id": ..., "after_sequence": n }` and
+//!   returns `{ "subscription": id }`.  The connection then receives one
+//!   `events.event` notification per event with a sequence after `n`, and an
+//!   `events.closed` notification after the first terminal state event, or
+//!   once the engine has returned without publishing one.
+//!
+//! [`ContractSupport`]: crate::governed_run::ContractSupport
+//! [`upgrade_request`]: crate::governed_run::version::upgrade_request
+
+use super::{RunService, RunStatus, ServiceError, is_terminal};
+use crate::governed_run::version::upgrade_request;
+use crate::governed_run::{DecapodControlPlane, ProviderTurn, RunId};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{broadcast, mpsc};
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<ServiceError> for RpcError {
+    fn from(error: ServiceError) -> Self {
+        Self {
+            code: error_code(&error),
+            message: error.to_string(),
+            data: serde_json::to_value(&error).ok(),
+        }
+    }
+}
+
+/// Application error codes, in the range JSON-RPC reserves for servers.
+pub fn error_code(error: &ServiceError) -> i64 {
+    match error {
+        ServiceError::RunNotFound { .. } => -32001,
+        ServiceError::RunConflict { .. } => -32002,
+        ServiceError::RunNotFinished { .. } => -32003,
+        ServiceError::RunFinished { .. } => -32004,
+        ServiceError::InvalidHandoff { .. } => -32005,
+        ServiceError::Draining => -32006,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    method: String,
+    #[serde(default)]
+    params: Value,
+    id: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubmitParams {
+    request: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct RunParams {
+    run_id: RunId,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubscribeParams {
+    run_id: RunId,
+    #[serde(default)]
+    after_sequence: u64,
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    }
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    serde_json::json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params)
+        .map_err(|error| RpcError::new(INVALID_PARAMS, format!("invalid params: {error}")))
+}
+
+fn status(status: Result<RunStatus, ServiceError>) -> Result<Value, RpcError> {
+    let status = status?;
+    serde_json::to_value(status).map_err(|error| RpcError::new(INTERNAL_ERROR, error.to_string()))
+}
+
+/// Subscription accepted by `events.subscribe`, started once its response
+/// has been queued so that no notification overtakes it.
+struct PendingSubscription {
+    id: String,
+    run_id: RunId,
+    after_sequence: u64,
+}
+
+/// One client connection.  Outgoing messages are queued on `outgoing` and
+/// written by the transport.
+pub struct Connection<C, P> {
+    service: RunService<C, P>,
+    outgoing: mpsc::UnboundedSender<Value>,
+    subscriptions: u64,
+}
+
+impl<C, P> Connection<C, P>
+where
+    C: DecapodControlPlane + Clone + Send + Sync + 'static,
+    P: ProviderTurn + Clone + Send + Sync + 'static,
+{
+    pub fn new(service: RunService<C, P>, outgoing: mpsc::UnboundedSender<Value>) -> Self {
+        Self {
+            service,
+            outgoing,
+            subscriptions: 0,
+        }
+    }
+
+    /// Handles one line received from the client.
+    pub fn handle_line(&mut self, line: &str) {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(error) => {
+                let error = RpcError::new(PARSE_ERROR, format!("parse error: {error}"));
+                let _ = self.outgoing.send(response(Value::Null, Err(error)));
+                return;
+            }
+        };
+
+        let mut pending = Vec::new();
+        let reply = match message {
+            Value::Array(batch) if batch.is_empty() => Some(response(
+                Value::Null,
+                Err(RpcError::new(INVALID_REQUEST, "empty batch")),
+            )),
+            Value::Array(batch) => {
+                let replies: Vec<Value> = batch
+                    .into_iter()
+                    .filter_map(|message| self.handle_message(message, &mut pending))
+                    .collect();
+                (!replies.is_empty()).then_some(Value::Array(replies))
+            }
+            message => self.handle_message(message, &mut pending),
+        };
+        if let Some(reply) = reply {
+            let _ = self.outgoing.send(reply);
+        }
+        for subscription in pending {
+            self.start(subscription);
+        }
+    }
+
+    fn handle_message(
+        &mut self,
+        message: Value,
+        pending: &mut Vec<PendingSubscription>,
+    ) -> Option<Value> {
+        let id = message.get("id").cloned();
+        let request = match serde_json::from_value::<Request>(message) {
+            Ok(request) if request.jsonrpc == "2.0" => request,
+            _ => {
+                let error = RpcError::new(INVALID_REQUEST, "not a JSON-RPC 2.0 request");
+                return Some(response(id.unwrap_or(Value::Null), Err(error)));
+            }
+        };
+        let result = self.call(&request.method, request.params, pending);
+        // Requests without an id are notifications and get no response.
+        request.id.map(|id| response(id, result))
+    }
+
+    fn call(
+        &mut self,
+        method: &str,
+        raw: Value,
+        pending: &mut Vec<PendingSubscription>,
+    ) -> Result<Value, RpcError> {
+        match method {
+            "contract.support" => serde_json::to_value(self.service.contract_support())
+                .map_err(|error| RpcError::new(INTERNAL_ERROR, error.to_string())),
+            "run.submit" => {
+                let SubmitParams { request } = params(raw)?;
+                let request = upgrade_request(request).map_err(|error| {
+                    RpcError::new(INVALID_PARAMS, format!("invalid request: {error}"))
+                })?;
+                status(self.service.submit(request))
+            }
+            "run.get" => {
+                let RunParams { run_id } = params(raw)?;
+                status(self.service.get(&run_id))
+            }
+            "run.cancel" => {
+                let RunParams { run_id } = params(raw)?;
+                status(self.service.cancel(&run_id))
+            }
+            "run.handoff" => {
+                let RunParams { run_id } = params(raw)?;
+                status(self.service.handoff(&run_id))
+            }
+            "events.subscribe" => {
+                let SubscribeParams {
+                    run_id,
+                    after_sequence,
+                } = params(raw)?;
+                self.service.get(&run_id)?;
+                self.subscriptions += 1;
+                let id = format!("sub-{}", self.subscriptions);
+                pending.push(PendingSubscription {
+                    id: id.clone(),
+                    run_id,
+                    after_sequence,
+                });
+                Ok(serde_json::json!({ "subscription": id }))
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("method {method} is not supported"),
+            )),
+        }
+    }
+
+    fn start(&self, subscription: PendingSubscription) {
+        let service = self.service.clone();
+        let outgoing = self.outgoing.clone();
+        tokio::spawn(async move {
+            forward(service, outgoing, subscription).await;
+        });
+    }
+}
+
+async fn forward<C, P>(
+    service: RunService<C, P>,
+    outgoing: mpsc::UnboundedSender<Value>,
+    subscription: PendingSubscription,
+) where
+    C: DecapodControlPlane + Clone + Send + Sync + 'static,
+    P: ProviderTurn + Clone + Send + Sync + 'static,
+{
+    let PendingSubscription {
+        id,
+        run_id,
+        mut after_sequence,
+    } = subscription;
+    let Ok(subscription) = service.subscribe(&run_id, after_sequence) else {
+        return;
+    };
+    let mut live = subscription.live;
+    let mut finished = subscription.finished;
+    let mut queued = subscription.backlog;
+    let close = |last_sequence: u64| {
+        let params = serde_json::json!({ "subscription": id, "last_sequence": last_sequence });
+        let _ = outgoing.send(notification("events.closed", params));
+    };
+
+    loop {
+        for event in queued.drain(..) {
+            if event.sequence <= after_sequence {
+                continue;
+            }
+            after_sequence = event.sequence;
+            let terminal = is_terminal(&event);
+            let params = serde_json::json!({ "subscription": id, "event": event });
+            if outgoing.send(notification("events.event", params)).is_err() {
+                return;
+            }
+            if terminal {
+                close(after_sequence);
+                return;
+            }
+        }
+        if *finished.borrow_and_update() {
+            match service.events_after(&run_id, after_sequence) {
+                Ok(events) if events.is_empty() => {
+                    close(after_sequence);
+                    return;
+                }
+                Ok(events) => {
+                    queued = events;
+                    continue;
+                }
+                // Evicted since it finished; nothing more will follow.
+                Err(_) => {
+                    close(after_sequence);
+                    return;
+                }
+            }
+        }
+        tokio::select! {
+            received = live.recv() => match received {
+                Ok(event) => queued.push(event),
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    match service.events_after(&run_id, after_sequence) {
+                        Ok(events) => queued = events,
+                        Err(_) => return,
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            changed = finished.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Serves `service` on a Unix socket at `path` until `shutdown` resolves,
+/// then stops accepting connections and drains in-flight runs.
+///
+/// The socket is bound inside a fresh 0700 directory, restricted to mode
+/// 0600, and only then moved to `path`, so other users can never connect.
+/// A stale socket file is replaced; a socket another daemon still listens on
+/// is not.
+#[cfg(unix)]
+pub async fn serve_unix<C, P>(
+    service: RunService<C, P>,
+    path: &std::path::Path,
+    shutdown: impl Future<Output = ()>,
+) -> std::io::Result<()>
+where
+    C: DecapodControlPlane + Clone + Send + Sync + 'static,
+    P: ProviderTurn + Clone + Send + Sync + 'static,
+{
+    use std::os::unix::fs::PermissionsExt;
+
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                format!("a daemon is already listening on {}", path.display()),
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => std::path::Path::new("."),
+    };
+    let private = tempfile::Builder::new()
+        .prefix(".pincher-")
+        .permissions(std::fs::Permissions::from_mode(0o700))
+        .tempdir_in(parent)?;
+    let bound = private.path().join("sock");
+    let listener = tokio::net::UnixListener::bind(&bound)?;
+    std::fs::set_permissions(&bound, std::fs::Permissions::from_mode(0o600))?;
+    std::fs::rename(&bound, path)?;
+    drop(private);
+    tracing::info!("serving governed runs on {}", path.display());
+
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tokio::spawn(serve_connection(service.clone(), stream));
+                }
+                Err(error) => tracing::warn!("failed to accept connection: {error}"),
+            },
+        }
+    }
+
+    drop(listener);
+    tracing::info!("draining in-flight runs");
+    service.drain().await;
+    std::fs::remove_file(path)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn serve_connection<C, P>(service: RunService<C, P>, stream: tokio::net::UnixStream)
+where
+    C: DecapodControlPlane + Clone + Send + Sync + 'static,
+    P: ProviderTurn + Clone + Send + Sync + 'static,
+{
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let (reader, mut writer) = stream.into_split();
+    let (outgoing, mut queued) = mpsc::unbounded_channel::<Value>();
+    let writer_task = tokio::spawn(async move {
+        while let Some(message) = queued.recv().await {
+            let mut line = message.to_string();
+            line.push('\n');
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut connection = Connection::new(service, outgoing);
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if !line.trim().is_empty() {
+            connection.handle_line(&line);
+        }
+    }
+    // The writer finishes once open subscriptions have closed or the client
+    // has gone away.
+    drop(connection);
+    let _ = writer_task.await;
+}
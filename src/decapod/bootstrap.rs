@@ -0,0 +1,474 @@
+//! Builds a [`CustodyBinding`] from Decapod state.
+//!
+//! [`CustodyBootstrap`] performs the custody steps a host would otherwise run
+//! by hand: acquire a session, claim the task, initialise its work unit, and
+//! ensure a workspace.  The result is a [`RunRequest`] ready for the engine.
+//! When the plane runs Decapod in a [`ProjectRoot`], the root is verified
+//! before anything else and recorded in the binding next to the repository.
+//!
+//! Bootstrapping is idempotent.  A repeated idempotency key returns the request
+//! built the first time without touching Decapod, a task already owned by the
+//! session is not claimed again, and an open work unit for the same task and
+//! intent is reused.  When a later step fails, whatever this attempt claimed
+//! or created is undone before the error is returned.  The workspace is not:
+//! `workspace ensure` only reuses or creates the named workspace, and the next
+//! attempt ensures the same one, so it is left in place.
+
+use super::lifecycle::{DEFAULT_REFRESH_MARGIN, SessionManager};
+use super::project::ProjectRoot;
+use super::secret::Secret;
+use super::session::Session;
+use super::todo::{Task, TaskStatus, TodoManager};
+use super::transport::{DecapodTransport, default_transport};
+use super::workspace::{Workspace, WorkspaceManager};
+use super::workunit::{WorkUnit, WorkUnitManager, WorkUnitStatus};
+use crate::governed_run::{
+    ContractError, CorrelationId, CustodyBinding, IdempotencyKey, IntentId, RepositoryRef, RunId,
+    RunRequest, SessionRef, TaskRef, WorkUnitRef, WorkspaceRef,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Decapod operations custody needs; implemented by [`DecapodCustody`].
+pub trait CustodyPlane {
+    fn acquire_session(&self) -> impl Future<Output = anyhow::Result<Session>> + Send;
+    fn task(
+        &self,
+        session: &Session,
+        task_id: &str,
+    ) -> impl Future<Output = anyhow::Result<Task>> + Send;
+    fn claim_task(
+        &self,
+        session: &Session,
+        task_id: &str,
+    ) -> impl Future<Output = anyhow::Result<Task>> + Send;
+    fn release_task(
+        &self,
+        session: &Session,
+        task_id: &str,
+    ) -> impl Future<Output = anyhow::Result<Task>> + Send;
+    /// Work units recorded for `task_id`.
+    fn work_units(
+        &self,
+        session: &Session,
+        task_id: &str,
+    ) -> impl Future<Output = anyhow::Result<Vec<WorkUnit>>> + Send;
+    fn init_work_unit(
+        &self,
+        session: &Session,
+        task_id: &str,
+        intent_ref: &str,
+    ) -> impl Future<Output = anyhow::Result<WorkUnit>> + Send;
+    fn fail_work_unit(
+        &self,
+        session: &Session,
+        work_unit_id: &str,
+        reason: &str,
+    ) -> impl Future<Output = anyhow::Result<WorkUnit>> + Send;
+    /// Reuses the named workspace, or creates it.  Never undone.
+    fn ensure_workspace(
+        &self,
+        session: &Session,
+        name: Option<&str>,
+    ) -> impl Future<Output = anyhow::Result<Workspace>> + Send;
+
+    /// The verified project root Decapod runs in, if it runs in one.
+    fn project_root(&self) -> anyhow::Result<Option<ProjectRoot>> {
+        Ok(None)
+    }
+}
+
+/// [`CustodyPlane`] backed by the Decapod CLI.
+pub struct DecapodCustody {
+    password: Secret,
+    transport: Arc<dyn DecapodTransport>,
+    sessions: Option<Arc<SessionManager>>,
+}
+
+impl DecapodCustody {
+    pub fn new(password: impl Into<Secret>) -> Self {
+        Self {
+            password: password.into(),
+            transport: default_transport(),
+            sessions: None,
+        }
+    }
+
+    pub fn with_transport(mut self, transport: Arc<dyn DecapodTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Takes sessions from `sessions`, which persists and refreshes them,
+    /// instead of acquiring one with the password.
+    pub fn with_sessions(mut self, sessions: Arc<SessionManager>) -> Self {
+        self.sessions = Some(sessions);
+        self
+    }
+
+    fn todos(&self, session: &Session) -> TodoManager {
+        TodoManager::new()
+            .with_transport(self.transport.clone())
+            .with_session(session.token())
+    }
+
+    fn work_unit_manager(&self, session: &Session) -> WorkUnitManager {
+        WorkUnitManager::new()
+            .with_transport(self.transport.clone())
+            .with_session(session.token())
+    }
+
+    fn workspaces(&self, session: &Session) -> WorkspaceManager {
+        WorkspaceManager::new()
+            .with_transport(self.transport.clone())
+            .with_session(session.token())
+    }
+}
+
+impl CustodyPlane for DecapodCustody {
+    async fn acquire_session(&self) -> anyhow::Result<Session> {
+        if let Some(sessions) = &self.sessions {
+            return Ok(sessions.session().await?);
+        }
+        Session::acquire_with(self.transport.as_ref(), &self.password).await
+    }
+
+    async fn task(&self, session: &Session, task_id: &str) -> anyhow::Result<Task> {
+        self.todos(session).get(task_id).await
+    }
+
+    async fn claim_task(&self, session: &Session, task_id: &str) -> anyhow::Result<Task> {
+        self.todos(session).claim(task_id).await
+    }
+
+    async fn release_task(&self, session: &Session, task_id: &str) -> anyhow::Result<Task> {
+        self.todos(session).release(task_id).await
+    }
+
+    async fn work_units(&self, session: &Session, task_id: &str) -> anyhow::Result<Vec<WorkUnit>> {
+        self.work_unit_manager(session)
+            .list(Some(task_id), None)
+            .await
+    }
+
+    async fn init_work_unit(
+        &self,
+        session: &Session,
+        task_id: &str,
+        intent_ref: &str,
+    ) -> anyhow::Result<WorkUnit> {
+        self.work_unit_manager(session)
+            .init(task_id, intent_ref)
+            .await
+    }
+
+    async fn fail_work_unit(
+        &self,
+        session: &Session,
+        work_unit_id: &str,
+        reason: &str,
+    ) -> anyhow::Result<WorkUnit> {
+        self.work_unit_manager(session)
+            .fail(work_unit_id, reason)
+            .await
+    }
+
+    async fn ensure_workspace(
+        &self,
+        session: &Session,
+        name: Option<&str>,
+    ) -> anyhow::Result<Workspace> {
+        self.workspaces(session).ensure(name).await
+    }
+
+    fn project_root(&self) -> anyhow::Result<Option<ProjectRoot>> {
+        Ok(self
+            .transport
+            .project_root()
+            .map(ProjectRoot::verify)
+            .transpose()?)
+    }
+}
+
+/// What to bootstrap custody for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustodyRequest {
+    pub task_id: String,
+    pub intent_id: IntentId,
+    pub repository: RepositoryRef,
+    /// Workspace to ensure; Decapod picks one when `None`.
+    pub workspace: Option<String>,
+    pub idempotency_key: IdempotencyKey,
+}
+
+impl CustodyRequest {
+    /// The idempotency key defaults to one derived from the task and intent,
+    /// so the same task is never bootstrapped twice for one intent.
+    pub fn new(
+        task_id: impl Into<String>,
+        intent_id: IntentId,
+        repository: RepositoryRef,
+    ) -> Result<Self, ContractError> {
+        let task_id = task_id.into();
+        let idempotency_key = IdempotencyKey::new(format!("custody:{task_id}:{intent_id}"))?;
+        Ok(Self {
+            task_id,
+            intent_id,
+            repository,
+            workspace: None,
+            idempotency_key,
+        })
+    }
+
+    pub fn with_workspace(mut self, workspace: impl Into<String>) -> Self {
+        self.workspace = Some(workspace.into());
+        self
+    }
+
+    pub fn with_idempotency_key(mut self, idempotency_key: IdempotencyKey) -> Self {
+        self.idempotency_key = idempotency_key;
+        self
+    }
+}
+
+/// The custody step a bootstrap failed at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootstrapStep {
+    VerifyProjectRoot,
+    AcquireSession,
+    ClaimTask,
+    InitWorkUnit,
+    EnsureWorkspace,
+    BuildRequest,
+}
+
+impl fmt::Display for BootstrapStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::VerifyProjectRoot => "verify the project root",
+            Self::AcquireSession => "acquire a session",
+            Self::ClaimTask => "claim the task",
+            Self::InitWorkUnit => "initialise the work unit",
+            Self::EnsureWorkspace => "ensure the workspace",
+            Self::BuildRequest => "build the run request",
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("custody bootstrap failed to {step}: {source}")]
+pub struct BootstrapError {
+    pub step: BootstrapStep,
+    #[source]
+    pub source: anyhow::Error,
+    /// Undo steps that failed themselves.  Empty when everything this attempt
+    /// claimed or created was given back.
+    pub rollback_failures: Vec<String>,
+}
+
+impl BootstrapError {
+    fn new(step: BootstrapStep, source: impl Into<anyhow::Error>) -> Self {
+        Self {
+            step,
+            source: source.into(),
+            rollback_failures: Vec::new(),
+        }
+    }
+}
+
+/// Task claims and work units created by the attempt in progress.  The
+/// ensured workspace is reused across attempts and so is not tracked.
+#[derive(Default)]
+struct Acquired {
+    claimed_task: Option<String>,
+    created_work_unit: Option<String>,
+}
+
+pub struct CustodyBootstrap<P> {
+    plane: P,
+    session: Option<Session>,
+    /// Requests already bootstrapped, by idempotency key.
+    bootstrapped: HashMap<IdempotencyKey, RunRequest>,
+}
+
+impl<P: CustodyPlane> CustodyBootstrap<P> {
+    pub fn new(plane: P) -> Self {
+        Self {
+            plane,
+            session: None,
+            bootstrapped: HashMap::new(),
+        }
+    }
+
+    pub fn plane(&self) -> &P {
+        &self.plane
+    }
+
+    /// The session custody is currently bound to.
+    pub fn session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    /// Runs the custody steps for `request` and returns the run to start.
+    pub async fn bootstrap(
+        &mut self,
+        request: &CustodyRequest,
+    ) -> Result<RunRequest, BootstrapError> {
+        if let Some(existing) = self.bootstrapped.get(&request.idempotency_key) {
+            return Ok(existing.clone());
+        }
+        let root = self
+            .plane
+            .project_root()
+            .map_err(|error| BootstrapError::new(BootstrapStep::VerifyProjectRoot, error))?;
+        let session = self.current_session().await?;
+        let mut acquired = Acquired::default();
+        match self
+            .acquire(&session, request, root.as_ref(), &mut acquired)
+            .await
+        {
+            Ok(run) => {
+                self.bootstrapped
+                    .insert(request.idempotency_key.clone(), run.clone());
+                Ok(run)
+            }
+            Err(mut error) => {
+                error.rollback_failures = self.undo(&session, acquired, error.step).await;
+                Err(error)
+            }
+        }
+    }
+
+    /// Reuses the current session until it is about to expire.
+    async fn current_session(&mut self) -> Result<Session, BootstrapError> {
+        if let Some(session) = &self.session
+            && !session.expires_within(DEFAULT_REFRESH_MARGIN)
+        {
+            return Ok(session.clone());
+        }
+        let session = self
+            .plane
+            .acquire_session()
+            .await
+            .map_err(|error| BootstrapError::new(BootstrapStep::AcquireSession, error))?;
+        self.session = Some(session.clone());
+        Ok(session)
+    }
+
+    async fn acquire(
+        &self,
+        session: &Session,
+        request: &CustodyRequest,
+        root: Option<&ProjectRoot>,
+        acquired: &mut Acquired,
+    ) -> Result<RunRequest, BootstrapError> {
+        let claim = |error| BootstrapError::new(BootstrapStep::ClaimTask, error);
+        let task = self
+            .plane
+            .task(session, &request.task_id)
+            .await
+            .map_err(claim)?;
+        let owned = matches!(task.status, TaskStatus::Claimed | TaskStatus::InProgress)
+            && task.owner.as_deref() == Some(session.session_id());
+        if !owned {
+            self.plane
+                .claim_task(session, &request.task_id)
+                .await
+                .map_err(claim)?;
+            acquired.claimed_task = Some(request.task_id.clone());
+        }
+
+        let init = |error| BootstrapError::new(BootstrapStep::InitWorkUnit, error);
+        let intent_ref = request.intent_id.as_str();
+        let open = self
+            .plane
+            .work_units(session, &request.task_id)
+            .await
+            .map_err(init)?
+            .into_iter()
+            .find(|unit| {
+                unit.intent_ref == intent_ref
+                    && matches!(
+                        unit.status,
+                        WorkUnitStatus::Pending | WorkUnitStatus::Active
+                    )
+            });
+        let work_unit = match open {
+            Some(unit) => unit,
+            None => {
+                let unit = self
+                    .plane
+                    .init_work_unit(session, &request.task_id, intent_ref)
+                    .await
+                    .map_err(init)?;
+                acquired.created_work_unit = Some(unit.id.clone());
+                unit
+            }
+        };
+
+        let workspace = self
+            .plane
+            .ensure_workspace(session, request.workspace.as_deref())
+            .await
+            .map_err(|error| BootstrapError::new(BootstrapStep::EnsureWorkspace, error))?;
+
+        build_request(request, session, &work_unit, &workspace, root)
+            .map_err(|error| BootstrapError::new(BootstrapStep::BuildRequest, error))
+    }
+
+    /// Gives back what a failed attempt acquired, newest first.
+    async fn undo(
+        &self,
+        session: &Session,
+        acquired: Acquired,
+        step: BootstrapStep,
+    ) -> Vec<String> {
+        let mut failures = Vec::new();
+        if let Some(work_unit) = acquired.created_work_unit {
+            let reason = format!("custody bootstrap failed to {step}");
+            if let Err(error) = self
+                .plane
+                .fail_work_unit(session, &work_unit, &reason)
+                .await
+            {
+                tracing::warn!("failed to abandon work unit {work_unit}: {error}");
+                failures.push(format!("work unit {work_unit}: {error}"));
+            }
+        }
+        if let Some(task) = acquired.claimed_task
+            && let Err(error) = self.plane.release_task(session, &task).await
+        {
+            tracing::warn!("failed to release task {task}: {error}");
+            failures.push(format!("task {task}: {error}"));
+        }
+        failures
+    }
+}
+
+fn build_request(
+    request: &CustodyRequest,
+    session: &Session,
+    work_unit: &WorkUnit,
+    workspace: &Workspace,
+    root: Option<&ProjectRoot>,
+) -> Result<RunRequest, ContractError> {
+    let mut custody = CustodyBinding::complete(
+        SessionRef::new(session.session_id())?,
+        TaskRef::new(request.task_id.clone())?,
+        WorkUnitRef::new(work_unit.id.clone())?,
+        request.repository.clone(),
+        WorkspaceRef::new(workspace.name.clone())?,
+    );
+    if let Some(root) = root {
+        custody = custody.with_project_root(root.to_ref()?);
+    }
+    Ok(RunRequest::v1(
+        RunId::new(format!("run-{}", ulid::Ulid::new()))?,
+        request.intent_id.clone(),
+        CorrelationId::new(request.task_id.clone())?,
+        request.idempotency_key.clone(),
+        custody,
+    ))
+}
@@ -1,7 +1,9 @@
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::process::Stdio;
-use tokio::process::Command;
+use std::sync::Arc;
+use super::envelope;
+use super::transport::{default_transport, DecapodTransport, Invocation};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Capabilities {
@@ -17,6 +19,10 @@ pub struct CommandCapability {
     pub alias: Option<String>,
     pub description: String,
     pub subcommands: Vec<String>,
+    /// Optional flags accepted by each subcommand.  Older Decapod builds do
+    /// not report them.
+    #[serde(default)]
+    pub flags: HashMap<String, Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,17 +39,38 @@ pub struct RpcOperation {
     pub params: HashMap<String, serde_json::Value>,
 }
 
+impl Capabilities {
+    /// Whether Decapod reports that `command subcommand` accepts `flag`.
+    pub fn accepts_flag(&self, command: &str, subcommand: &str, flag: &str) -> bool {
+        self.commands
+            .iter()
+            .filter(|capability| capability.name == command)
+            .filter_map(|capability| capability.flags.get(subcommand))
+            .any(|flags| flags.iter().any(|offered| offered == flag))
+    }
+}
+
 pub struct CapabilitiesManager {
-    binary_path: String,
+    transport: Arc<dyn DecapodTransport>,
 }
 
 impl CapabilitiesManager {
     pub fn new() -> Self {
         Self {
-            binary_path: "decapod".to_string(),
+            transport: default_transport(),
         }
     }
 
+    pub fn with_transport(mut self, transport: Arc<dyn DecapodTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    async fn run_json<T: DeserializeOwned>(&self, args: &[&str], what: &str) -> anyhow::Result<T> {
+        let stdout = self.transport.invoke(Invocation::new(args)).await?.into_stdout(what)?;
+        Ok(envelope::decode(what, &stdout, self.transport.decode_mode())?)
+    }
+
     pub async fn discover(&self, format: Option<&str>) -> anyhow::Result<Capabilities> {
         let mut args = vec!["capabilities"];
         
@@ -52,27 +79,7 @@ impl CapabilitiesManager {
             args.push(f);
         }
 
-        let output = Command::new(&self.binary_path)
-            .args(&args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .await?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!(
-                "capabilities failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
-        }
-
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        
-        if let Ok(caps) = serde_json::from_str(&stdout) {
-            return Ok(caps);
-        }
-
-        Err(anyhow::anyhow!("failed to parse capabilities"))
+        self.run_json(&args, "capabilities").await
     }
 
     pub async fn discover_json(&self) -> anyhow::Result<Capabilities> {
@@ -86,27 +93,7 @@ impl CapabilitiesManager {
             args.push("--deterministic");
         }
 
-        let output = Command::new(&self.binary_path)
-            .args(&args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .await?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!(
-                "schema failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
-        }
-
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        
-        if let Ok(schema) = serde_json::from_str(&stdout) {
-            return Ok(schema);
-        }
-
-        Err(anyhow::anyhow!("failed to parse schema"))
+        self.run_json(&args, "schema").await
     }
 }
 
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use super::discovery::BinaryDiscovery;
+use super::transport::ProcessTransport;
 
 #[derive(Debug, Clone)]
 pub struct DecapodCli {
@@ -8,18 +10,36 @@ pub struct DecapodCli {
 }
 
 impl DecapodCli {
+    /// The binary [`BinaryDiscovery`] finds, run from the enclosing Decapod
+    /// project.
     pub fn new() -> anyhow::Result<Self> {
+        let discovery = BinaryDiscovery::new();
+        let binary = discovery.discover()?;
         Ok(Self {
-            binary_path: "decapod".to_string(),
-            project_root: None,
+            binary_path: binary.path.display().to_string(),
+            project_root: discovery.project_root().map(|root| root.display().to_string()),
         })
     }
 
+    pub fn with_binary(mut self, binary: impl Into<String>) -> Self {
+        self.binary_path = binary.into();
+        self
+    }
+
     pub fn with_project_root(mut self, root: impl Into<String>) -> Self {
         self.project_root = Some(root.into());
         self
     }
 
+    /// A transport running this binary from the project root.
+    pub fn transport(&self) -> ProcessTransport {
+        let transport = ProcessTransport::new(&self.binary_path);
+        match &self.project_root {
+            Some(root) => transport.with_project_root(root),
+            None => transport,
+        }
+    }
+
     pub fn validate(&self) -> CommandBuilder {
         CommandBuilder::new(self.binary_path.clone()).arg("validate")
     }
@@ -115,7 +135,7 @@ impl CommandBuilder {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DecapodResponse<T: Default> {
+pub struct DecapodResponse<T> {
     pub id: Option<String>,
     pub success: bool,
     #[serde(default)]
@@ -132,7 +152,6 @@ pub struct DecapodResponse<T: Default> {
     pub advisory: Option<Advisory>,
     #[serde(default)]
     pub attestation: Option<Attestation>,
-    #[serde(default)]
     pub data: Option<T>,
     #[serde(default)]
     pub error: Option<String>,
@@ -1,8 +1,9 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct StateCommitment {
     pub id: String,
     pub previous_commitment: Option<String>,
@@ -12,7 +13,7 @@ pub struct StateCommitment {
     pub agent_id: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct CommitmentEntry {
     pub entry_type: EntryType,
     pub key: String,
@@ -20,10 +21,13 @@ pub struct CommitmentEntry {
     pub metadata: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum EntryType {
     Intent,
+    Custody,
+    Context,
+    Advisory,
     Plan,
     Patch,
     Approval,
@@ -49,7 +53,7 @@ pub enum ProofVerification {
     LocallyIntegrityChecked,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ProofType {
     Validation,
@@ -72,6 +76,12 @@ impl StateCommitmentManager {
         }
     }
 
+    /// Continues an existing chain instead of starting a new one.
+    pub fn with_previous(mut self, commitment_id: impl Into<String>) -> Self {
+        self.current_commitment_id = Some(commitment_id.into());
+        self
+    }
+
     pub fn create_commitment(&mut self, entries: Vec<CommitmentEntry>) -> StateCommitment {
         let commitment_id = ulid::Ulid::new().to_string();
 
@@ -169,6 +179,26 @@ impl StateCommitmentManager {
         }
     }
 
+    /// Commits to an opaque reference, such as a Decapod evidence receipt,
+    /// without interpreting it.
+    pub fn add_reference(
+        &self,
+        entry_type: EntryType,
+        key: &str,
+        reference: &str,
+    ) -> CommitmentEntry {
+        CommitmentEntry {
+            entry_type,
+            key: key.to_string(),
+            value_hash: self.hash_value(reference),
+            metadata: {
+                let mut m = HashMap::new();
+                m.insert("reference".to_string(), serde_json::json!(reference));
+                m
+            },
+        }
+    }
+
     pub fn create_proof_surface(
         &self,
         commitment_id: &str,
@@ -0,0 +1,472 @@
+//! Finding the Decapod binary and checking that Pincher can work with it.
+//!
+//! [`BinaryDiscovery`] looks for Decapod in a fixed order and stops at the
+//! first place that names one:
+//!
+//! 1. an explicit path from configuration ([`BinaryDiscovery::with_binary`]);
+//! 2. the [`BIN_ENV`] environment variable;
+//! 3. a project-local binary at [`PROJECT_LOCAL_BINARY`] under the project root;
+//! 4. `decapod` on `PATH`.
+//!
+//! A binary named by configuration or the environment must exist: discovery
+//! reports it rather than quietly falling through to another Decapod.
+//!
+//! [`DecapodInstall::connect`] then asks the binary for its capabilities and
+//! checks them with a [`CompatibilityCheck`]: the version must fall in
+//! [`SUPPORTED_VERSIONS`] and every operation in [`REQUIRED_RPC_OPERATIONS`]
+//! must be offered.  Hosts connect once at startup and hand the verified
+//! transport to the managers, so an incompatible Decapod is refused before
+//! any run begins.
+
+use super::capabilities::{Capabilities, CapabilitiesManager};
+use super::rpc::{
+    AgentInitRequest, CapsuleQueryRequest, ContextResolveRequest, ContextScopeRequest, RpcRequest,
+    StoreQueryRequest, StoreUpsertRequest, TodoAddRequest, TodoClaimRequest, TodoListRequest,
+    ValidateRunRequest, WorkUnitInitRequest, WorkspaceEnsureRequest, WorkspaceStatusRequest,
+};
+use super::transport::{DecapodTransport, ProcessTransport};
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Environment variable naming the Decapod binary.
+pub const BIN_ENV: &str = "DECAPOD_BIN";
+
+/// The name looked up on `PATH`.
+pub const DEFAULT_BINARY: &str = "decapod";
+
+/// Where a project keeps its own Decapod, relative to the project root.
+pub const PROJECT_LOCAL_BINARY: &str = ".decapod/bin/decapod";
+
+/// Decapod releases this version of Pincher works with.
+pub const SUPPORTED_VERSIONS: VersionRange = VersionRange {
+    minimum: DecapodVersion::new(0, 1, 0),
+    below: DecapodVersion::new(1, 0, 0),
+};
+
+/// RPC operations Pincher sends; see [`super::rpc`].
+pub const REQUIRED_RPC_OPERATIONS: [&str; 13] = [
+    AgentInitRequest::OPERATION,
+    ContextResolveRequest::OPERATION,
+    ContextScopeRequest::OPERATION,
+    StoreUpsertRequest::OPERATION,
+    StoreQueryRequest::OPERATION,
+    ValidateRunRequest::OPERATION,
+    WorkspaceEnsureRequest::OPERATION,
+    WorkspaceStatusRequest::OPERATION,
+    TodoAddRequest::OPERATION,
+    TodoClaimRequest::OPERATION,
+    TodoListRequest::OPERATION,
+    WorkUnitInitRequest::OPERATION,
+    CapsuleQueryRequest::OPERATION,
+];
+
+/// Where a Decapod binary was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinarySource {
+    Config,
+    Environment,
+    ProjectLocal,
+    Path,
+}
+
+impl fmt::Display for BinarySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Config => "configuration",
+            Self::Environment => BIN_ENV,
+            Self::ProjectLocal => "the project",
+            Self::Path => "PATH",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecapodBinary {
+    pub path: PathBuf,
+    pub source: BinarySource,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum DiscoveryError {
+    #[error("{origin} names {}, which is not an executable file", path.display())]
+    Unusable { origin: BinarySource, path: PathBuf },
+    #[error("no decapod binary found; set {BIN_ENV} or put {DEFAULT_BINARY} on PATH")]
+    NotFound {
+        /// Every location that was tried, in order.
+        searched: Vec<PathBuf>,
+    },
+}
+
+/// Finds the Decapod binary; see the [module documentation](self).
+#[derive(Debug, Clone, Default)]
+pub struct BinaryDiscovery {
+    binary: Option<PathBuf>,
+    env_binary: Option<PathBuf>,
+    project_root: Option<PathBuf>,
+    search_path: Option<OsString>,
+}
+
+impl BinaryDiscovery {
+    /// Discovery from this process's environment and the enclosing Decapod
+    /// project, if any.
+    pub fn new() -> Self {
+        Self {
+            binary: None,
+            env_binary: std::env::var_os(BIN_ENV)
+                .filter(|value| !value.is_empty())
+                .map(PathBuf::from),
+            project_root: super::find_project_root(),
+            search_path: std::env::var_os("PATH"),
+        }
+    }
+
+    /// Uses `binary` from configuration ahead of everything else.  A bare
+    /// name is looked up on `PATH`.
+    pub fn with_binary(mut self, binary: impl Into<PathBuf>) -> Self {
+        self.binary = Some(binary.into());
+        self
+    }
+
+    /// Replaces the value read from [`BIN_ENV`].
+    pub fn with_env_binary(mut self, binary: Option<PathBuf>) -> Self {
+        self.env_binary = binary;
+        self
+    }
+
+    pub fn with_project_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.project_root = Some(root.into());
+        self
+    }
+
+    /// Replaces the `PATH` searched for bare names.
+    pub fn with_search_path(mut self, path: impl Into<OsString>) -> Self {
+        self.search_path = Some(path.into());
+        self
+    }
+
+    pub fn project_root(&self) -> Option<&Path> {
+        self.project_root.as_deref()
+    }
+
+    pub fn discover(&self) -> Result<DecapodBinary, DiscoveryError> {
+        let mut searched = Vec::new();
+        let named = [
+            (BinarySource::Config, &self.binary),
+            (BinarySource::Environment, &self.env_binary),
+        ];
+        for (source, binary) in named {
+            let Some(binary) = binary else { continue };
+            return self
+                .resolve(binary, &mut searched)
+                .map(|path| DecapodBinary { path, source })
+                .ok_or_else(|| DiscoveryError::Unusable {
+                    origin: source,
+                    path: binary.clone(),
+                });
+        }
+
+        if let Some(root) = &self.project_root {
+            let local = root.join(with_exe_suffix(PROJECT_LOCAL_BINARY));
+            searched.push(local.clone());
+            if is_executable(&local) {
+                return Ok(DecapodBinary {
+                    path: local,
+                    source: BinarySource::ProjectLocal,
+                });
+            }
+        }
+
+        self.search(Path::new(DEFAULT_BINARY), &mut searched)
+            .map(|path| DecapodBinary {
+                path,
+                source: BinarySource::Path,
+            })
+            .ok_or(DiscoveryError::NotFound { searched })
+    }
+
+    /// A transport running the discovered binary from the project root.
+    pub fn transport(&self) -> Result<ProcessTransport, DiscoveryError> {
+        let binary = self.discover()?;
+        Ok(self.transport_for(binary.path))
+    }
+
+    fn transport_for(&self, binary: PathBuf) -> ProcessTransport {
+        let transport = ProcessTransport::new(binary);
+        match &self.project_root {
+            Some(root) => transport.with_project_root(root),
+            None => transport,
+        }
+    }
+
+    /// `binary` itself when it is a path, otherwise its match on `PATH`.
+    fn resolve(&self, binary: &Path, searched: &mut Vec<PathBuf>) -> Option<PathBuf> {
+        if binary.components().count() > 1 {
+            searched.push(binary.to_path_buf());
+            return is_executable(binary).then(|| binary.to_path_buf());
+        }
+        self.search(binary, searched)
+    }
+
+    fn search(&self, name: &Path, searched: &mut Vec<PathBuf>) -> Option<PathBuf> {
+        let search_path = self.search_path.as_ref()?;
+        let name = with_exe_suffix(&name.to_string_lossy());
+        std::env::split_paths(search_path)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(|dir| dir.join(&name))
+            .find(|candidate| {
+                searched.push(candidate.clone());
+                is_executable(candidate)
+            })
+    }
+}
+
+fn with_exe_suffix(name: &str) -> String {
+    let suffix = std::env::consts::EXE_SUFFIX;
+    if suffix.is_empty() || name.ends_with(suffix) {
+        name.to_string()
+    } else {
+        format!("{name}{suffix}")
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = path.metadata() else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+/// A Decapod release number.  Pre-release and build suffixes are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DecapodVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl DecapodVersion {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl fmt::Display for DecapodVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for DecapodVersion {
+    type Err = CompatibilityError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let unreadable = || CompatibilityError::UnreadableVersion {
+            version: text.to_string(),
+        };
+        let trimmed = text.trim();
+        let release = trimmed
+            .strip_prefix('v')
+            .unwrap_or(trimmed)
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default();
+        let parts: Vec<u64> = release
+            .split('.')
+            .map(|part| part.parse().map_err(|_| unreadable()))
+            .collect::<Result<_, _>>()?;
+        match parts[..] {
+            [major] => Ok(Self::new(major, 0, 0)),
+            [major, minor] => Ok(Self::new(major, minor, 0)),
+            [major, minor, patch] => Ok(Self::new(major, minor, patch)),
+            _ => Err(unreadable()),
+        }
+    }
+}
+
+/// Versions from `minimum` up to but not including `below`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionRange {
+    pub minimum: DecapodVersion,
+    pub below: DecapodVersion,
+}
+
+impl VersionRange {
+    pub fn contains(&self, version: DecapodVersion) -> bool {
+        self.minimum <= version && version < self.below
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ">={}, <{}", self.minimum, self.below)
+    }
+}
+
+/// Why Pincher will not work with the Decapod it found.
+#[derive(Debug, Error)]
+pub enum CompatibilityError {
+    #[error(transparent)]
+    Discovery(#[from] DiscoveryError),
+    #[error("could not read decapod capabilities: {source}")]
+    Capabilities {
+        #[source]
+        source: anyhow::Error,
+    },
+    #[error("decapod reports an unreadable version {version:?}")]
+    UnreadableVersion { version: String },
+    #[error("decapod {version} is outside the supported range {supported}")]
+    UnsupportedVersion {
+        version: DecapodVersion,
+        supported: VersionRange,
+    },
+    #[error("decapod {version} lacks required RPC operations: {}", missing.join(", "))]
+    MissingOperations {
+        version: DecapodVersion,
+        missing: Vec<String>,
+    },
+}
+
+/// What a compatible Decapod must offer.
+#[derive(Debug, Clone)]
+pub struct CompatibilityCheck {
+    supported: VersionRange,
+    required_operations: Vec<String>,
+}
+
+impl CompatibilityCheck {
+    /// [`SUPPORTED_VERSIONS`] and [`REQUIRED_RPC_OPERATIONS`].
+    pub fn new() -> Self {
+        Self {
+            supported: SUPPORTED_VERSIONS,
+            required_operations: REQUIRED_RPC_OPERATIONS.map(String::from).to_vec(),
+        }
+    }
+
+    pub fn with_supported(mut self, supported: VersionRange) -> Self {
+        self.supported = supported;
+        self
+    }
+
+    pub fn with_required_operations<S: AsRef<str>>(mut self, operations: &[S]) -> Self {
+        self.required_operations = operations
+            .iter()
+            .map(|operation| operation.as_ref().to_string())
+            .collect();
+        self
+    }
+
+    pub fn supported(&self) -> VersionRange {
+        self.supported
+    }
+
+    /// Checks capabilities Decapod reported, returning its version.
+    pub fn check(&self, capabilities: &Capabilities) -> Result<DecapodVersion, CompatibilityError> {
+        let version: DecapodVersion = capabilities.version.parse()?;
+        if !self.supported.contains(version) {
+            return Err(CompatibilityError::UnsupportedVersion {
+                version,
+                supported: self.supported,
+            });
+        }
+        let missing: Vec<String> = self
+            .required_operations
+            .iter()
+            .filter(|required| {
+                !capabilities
+                    .rpc_operations
+                    .iter()
+                    .any(|offered| &offered.name == *required)
+            })
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            return Err(CompatibilityError::MissingOperations { version, missing });
+        }
+        Ok(version)
+    }
+
+    /// Asks Decapod for its capabilities and checks them.
+    pub async fn verify(
+        &self,
+        transport: Arc<dyn DecapodTransport>,
+    ) -> Result<(DecapodVersion, Capabilities), CompatibilityError> {
+        let capabilities = CapabilitiesManager::new()
+            .with_transport(transport)
+            .discover_json()
+            .await
+            .map_err(|source| CompatibilityError::Capabilities { source })?;
+        let version = self.check(&capabilities)?;
+        Ok((version, capabilities))
+    }
+}
+
+impl Default for CompatibilityCheck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Flag of `session acquire` that reads the password from stdin.
+pub const PASSWORD_STDIN_FLAG: &str = "--password-stdin";
+
+/// A discovered Decapod that passed its compatibility check.
+#[derive(Debug, Clone)]
+pub struct DecapodInstall {
+    pub binary: DecapodBinary,
+    pub version: DecapodVersion,
+    pub capabilities: Capabilities,
+    transport: ProcessTransport,
+}
+
+impl DecapodInstall {
+    /// Discovers Decapod and checks it; the startup step before any run.
+    pub async fn connect(
+        discovery: &BinaryDiscovery,
+        check: &CompatibilityCheck,
+    ) -> Result<Self, CompatibilityError> {
+        let binary = discovery.discover()?;
+        let transport = discovery.transport_for(binary.path.clone());
+        let (version, capabilities) = check.verify(Arc::new(transport.clone())).await?;
+        let transport = transport.with_password_stdin(capabilities.accepts_flag(
+            "session",
+            "acquire",
+            PASSWORD_STDIN_FLAG,
+        ));
+        tracing::info!(
+            "using decapod {version} from {} ({})",
+            binary.path.display(),
+            binary.source
+        );
+        Ok(Self {
+            binary,
+            version,
+            capabilities,
+            transport,
+        })
+    }
+
+    pub fn process(&self) -> &ProcessTransport {
+        &self.transport
+    }
+
+    /// A transport for the managers, running the verified binary.
+    pub fn transport(&self) -> Arc<dyn DecapodTransport> {
+        Arc::new(self.transport.clone())
+    }
+}
@@ -1,6 +1,8 @@
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::process::Stdio;
-use tokio::process::Command;
+use std::sync::Arc;
+use super::envelope;
+use super::transport::{default_transport, DecapodTransport, Invocation};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocFragment {
@@ -17,60 +19,36 @@ pub struct DocSearchResult {
 }
 
 pub struct DocsManager {
-    binary_path: String,
+    transport: Arc<dyn DecapodTransport>,
 }
 
 impl DocsManager {
     pub fn new() -> Self {
         Self {
-            binary_path: "decapod".to_string(),
+            transport: default_transport(),
         }
     }
 
-    pub async fn ingest(&self) -> anyhow::Result<IngestResult> {
-        let output = Command::new(&self.binary_path)
-            .args(["docs", "ingest"])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .await?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!(
-                "docs ingest failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
-        }
+    pub fn with_transport(mut self, transport: Arc<dyn DecapodTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        
-        if let Ok(result) = serde_json::from_str(&stdout) {
-            return Ok(result);
-        }
+    async fn run_command(&self, args: &[&str], what: &str) -> anyhow::Result<String> {
+        self.transport.invoke(Invocation::new(args)).await?.into_stdout(what)
+    }
 
-        Ok(IngestResult {
-            success: true,
-            documents_ingested: 0,
-            errors: Vec::new(),
-        })
+    async fn run_json<T: DeserializeOwned>(&self, args: &[&str], what: &str) -> anyhow::Result<T> {
+        let stdout = self.run_command(args, what).await?;
+        Ok(envelope::decode(what, &stdout, self.transport.decode_mode())?)
     }
 
-    pub async fn show(&self, path: &str) -> anyhow::Result<String> {
-        let output = Command::new(&self.binary_path)
-            .args(["docs", "show", path])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .await?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!(
-                "docs show failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
-        }
+    pub async fn ingest(&self) -> anyhow::Result<IngestResult> {
+        self.run_json(&["docs", "ingest"], "docs ingest").await
+    }
 
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    pub async fn show(&self, path: &str) -> anyhow::Result<String> {
+        self.run_command(&["docs", "show", path], "docs show").await
     }
 
     pub async fn search(&self, query: &str, op: Option<&str>, path: Option<&str>, tag: Option<&str>) -> anyhow::Result<DocSearchResult> {
@@ -89,31 +67,7 @@ impl DocsManager {
             args.push(tag);
         }
 
-        let output = Command::new(&self.binary_path)
-            .args(&args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .await?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!(
-                "docs search failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
-        }
-
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        
-        if let Ok(result) = serde_json::from_str(&stdout) {
-            return Ok(result);
-        }
-
-        Ok(DocSearchResult {
-            fragments: Vec::new(),
-            total_matches: 0,
-            query: query.to_string(),
-        })
+        self.run_json(&args, "docs search").await
     }
 
     pub async fn list(&self, path: Option<&str>) -> anyhow::Result<Vec<DocEntry>> {
@@ -123,27 +77,8 @@ impl DocsManager {
             args.push(path);
         }
 
-        let output = Command::new(&self.binary_path)
-            .args(&args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .await?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!(
-                "docs list failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
-        }
-
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        
-        if let Ok(result) = serde_json::from_str(&stdout) {
-            return Ok(result);
-        }
-
-        Ok(Vec::new())
+        let stdout = self.run_command(&args, "docs list").await?;
+        Ok(envelope::decode_list("docs list", &stdout, self.transport.decode_mode())?)
     }
 }
 
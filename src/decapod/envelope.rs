@@ -0,0 +1,222 @@
+//! Decoding what Decapod prints.
+//!
+//! Decapod answers every command with one JSON document on stdout.  In
+//! [`DecodeMode::Strict`] that is all the decoder accepts: the output must be
+//! exactly one document of the expected shape, and anything else is reported
+//! as a [`ParseError`] naming what was wrong.  Nothing is guessed — empty
+//! output is an error, not an empty result.
+//!
+//! [`DecodeMode::Lenient`] exists for older Decapod builds that mix log lines
+//! into stdout and print lists one document per line.  It skips lines that are
+//! not JSON, but still refuses output with no document, documents of the wrong
+//! shape, and documents that disagree with each other.
+
+use super::cli::DecapodResponse;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecodeMode {
+    /// Exactly one JSON document of the expected shape.
+    #[default]
+    Strict,
+    /// Legacy output: non-JSON lines are skipped and lists may be printed
+    /// one document per line.
+    Lenient,
+}
+
+/// Why Decapod output could not be decoded.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseError {
+    #[error("no output")]
+    Empty,
+    #[error("not JSON: {reason}")]
+    Syntax {
+        line: usize,
+        column: usize,
+        reason: String,
+    },
+    #[error("unexpected shape: {reason}")]
+    Shape { reason: String },
+    #[error("{count} copies of the same document")]
+    Duplicate { count: usize },
+    #[error("{count} conflicting documents")]
+    Conflicting { count: usize },
+    #[error("inconsistent envelope: {reason}")]
+    Inconsistent { reason: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("invalid {what} output: {kind}")]
+pub struct EnvelopeError {
+    /// The command whose output this was.
+    pub what: String,
+    pub kind: ParseError,
+}
+
+impl EnvelopeError {
+    fn new(what: &str, kind: ParseError) -> Self {
+        Self {
+            what: what.to_string(),
+            kind,
+        }
+    }
+}
+
+fn syntax(error: &serde_json::Error) -> ParseError {
+    ParseError::Syntax {
+        line: error.line(),
+        column: error.column(),
+        reason: error.to_string(),
+    }
+}
+
+fn shape(error: serde_json::Error) -> ParseError {
+    ParseError::Shape {
+        reason: error.to_string(),
+    }
+}
+
+/// Every JSON document in `output`.
+fn documents(output: &str, mode: DecodeMode) -> Result<Vec<Value>, ParseError> {
+    if output.trim().is_empty() {
+        return Err(ParseError::Empty);
+    }
+
+    let mut documents = Vec::new();
+    let mut stream = serde_json::Deserializer::from_str(output).into_iter::<Value>();
+    let error = loop {
+        match stream.next() {
+            None => return Ok(documents),
+            Some(Ok(document)) => documents.push(document),
+            Some(Err(error)) => break error,
+        }
+    };
+    if mode == DecodeMode::Strict {
+        return Err(syntax(&error));
+    }
+
+    let documents: Vec<Value> = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with('{') || line.starts_with('['))
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    if documents.is_empty() {
+        return Err(syntax(&error));
+    }
+    Ok(documents)
+}
+
+/// Drops repeated documents, keeping the first of each.
+fn distinct(documents: Vec<Value>) -> Vec<Value> {
+    let mut distinct: Vec<Value> = Vec::new();
+    for document in documents {
+        if !distinct.contains(&document) {
+            distinct.push(document);
+        }
+    }
+    distinct
+}
+
+/// The one document `documents` must hold in strict mode.
+fn single(mut documents: Vec<Value>) -> Result<Value, ParseError> {
+    match documents.len() {
+        1 => Ok(documents.remove(0)),
+        count if distinct(documents).len() == 1 => Err(ParseError::Duplicate { count }),
+        count => Err(ParseError::Conflicting { count }),
+    }
+}
+
+fn decode_one<T: DeserializeOwned>(output: &str, mode: DecodeMode) -> Result<T, ParseError> {
+    let documents = documents(output, mode)?;
+    if mode == DecodeMode::Strict {
+        return serde_json::from_value(single(documents)?).map_err(shape);
+    }
+
+    let mut decoded = Vec::new();
+    let mut mismatch = None;
+    for document in distinct(documents) {
+        match serde_json::from_value(document) {
+            Ok(value) => decoded.push(value),
+            Err(error) => mismatch = Some(error),
+        }
+    }
+    match decoded.len() {
+        0 => Err(shape(mismatch.expect("at least one document"))),
+        1 => Ok(decoded.remove(0)),
+        count => Err(ParseError::Conflicting { count }),
+    }
+}
+
+fn decode_many<T: DeserializeOwned>(output: &str, mode: DecodeMode) -> Result<Vec<T>, ParseError> {
+    let documents = documents(output, mode)?;
+    if mode == DecodeMode::Strict {
+        return serde_json::from_value(single(documents)?).map_err(shape);
+    }
+
+    // A single array, or one item per document.
+    if documents.len() == 1 && documents[0].is_array() {
+        return serde_json::from_value(documents.into_iter().next().expect("one document"))
+            .map_err(shape);
+    }
+    if documents.iter().any(Value::is_array) {
+        return Err(ParseError::Conflicting {
+            count: documents.len(),
+        });
+    }
+    documents
+        .into_iter()
+        .map(|document| serde_json::from_value(document).map_err(shape))
+        .collect()
+}
+
+/// Decodes the single `T` that `what` printed.
+pub fn decode<T: DeserializeOwned>(
+    what: &str,
+    output: &str,
+    mode: DecodeMode,
+) -> Result<T, EnvelopeError> {
+    decode_one(output, mode).map_err(|kind| EnvelopeError::new(what, kind))
+}
+
+/// Decodes the list of `T` that `what` printed.  Strict mode requires a JSON
+/// array; lenient mode also accepts one document per line.
+pub fn decode_list<T: DeserializeOwned>(
+    what: &str,
+    output: &str,
+    mode: DecodeMode,
+) -> Result<Vec<T>, EnvelopeError> {
+    decode_many(output, mode).map_err(|kind| EnvelopeError::new(what, kind))
+}
+
+/// Decodes an RPC envelope and checks that it agrees with itself: a
+/// successful envelope carries no error, and a failed one says why, either
+/// with an error or with the interlocks blocking it.
+pub fn decode_response<T: DeserializeOwned>(
+    what: &str,
+    output: &str,
+    mode: DecodeMode,
+) -> Result<DecapodResponse<T>, EnvelopeError> {
+    let response: DecapodResponse<T> = decode(what, output, mode)?;
+    let inconsistent = |reason: &str| {
+        Err(EnvelopeError::new(
+            what,
+            ParseError::Inconsistent {
+                reason: reason.to_string(),
+            },
+        ))
+    };
+    if response.success && response.error.is_some() {
+        return inconsistent("succeeded but carries an error");
+    }
+    if !response.success
+        && response.error.is_none()
+        && response.interlock.is_none()
+        && response.blocked_by.is_empty()
+    {
+        return inconsistent("failed without an error or interlock");
+    }
+    Ok(response)
+}
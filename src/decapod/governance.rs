@@ -1,4 +1,11 @@
+use crate::governed_run::{
+    AdvisoryEvidence, ApprovalInterlockRef, ApprovalProgress, ApprovalQuorum, ApprovalStatus,
+    DecapodPortError, GovernancePolicy, InterlockDecision, ProofEvidence, ProofEvidenceRef,
+    Remediation, ValidationEvidence, ValidationEvidenceRef,
+};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,9 +45,10 @@ pub struct Advisory {
     pub related_policies: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum AdvisoryPriority {
+    #[default]
     Info,
     Warning,
     Critical,
@@ -238,3 +246,191 @@ pub struct ApprovalRequirement {
     pub reason: String,
     pub approval_type: String,
 }
+
+// Bridges into the governed-run contract.  Decapod's legacy responses carry
+// no interlock identifiers, so an interlock is referenced by its policy name
+// and an advisory by its first related policy, its category, or, failing
+// both, a digest of its message.  Every advisory therefore has a reference
+// the host can acknowledge, and the same advisory always gets the same one.
+
+const OPERATION: &str = "governance response";
+
+fn incomplete(reason: impl Into<String>) -> DecapodPortError {
+    DecapodPortError::Incomplete {
+        operation: OPERATION.to_string(),
+        reason: reason.into(),
+    }
+}
+
+fn reference(value: &str) -> Result<ApprovalInterlockRef, DecapodPortError> {
+    ApprovalInterlockRef::new(value).map_err(|error| incomplete(error.to_string()))
+}
+
+impl Interlock {
+    /// The interlock's policy, with its approvers and escalation path.
+    pub fn policy(&self) -> GovernancePolicy {
+        GovernancePolicy {
+            name: self.policy.clone(),
+            required_approval: self.required_approval.clone(),
+            approver_scope: self.approver_scope.clone(),
+            escalation_path: self.escalation_path.clone(),
+        }
+    }
+
+    fn reference(&self) -> Result<ApprovalInterlockRef, DecapodPortError> {
+        reference(&self.policy)
+    }
+
+    /// One sign-off per approver scope; legacy responses report none yet.
+    fn progress(&self) -> Result<Option<ApprovalProgress>, DecapodPortError> {
+        if self.approver_scope.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(ApprovalProgress {
+            quorum: ApprovalQuorum {
+                reference: self.reference()?,
+                scopes: self.approver_scope.clone(),
+                approvals_needed: u32::try_from(self.approver_scope.len()).unwrap_or(u32::MAX),
+            },
+            scopes: Vec::new(),
+        }))
+    }
+
+    fn remediation(&self) -> Remediation {
+        let mut action = match &self.required_approval {
+            Some(approval) if self.approver_scope.is_empty() => {
+                format!("obtain {approval} approval under policy {}", self.policy)
+            }
+            Some(approval) => format!(
+                "obtain {approval} approval from {} under policy {}",
+                self.approver_scope.join(", "),
+                self.policy
+            ),
+            None => format!("resolve policy {}: {}", self.policy, self.reason),
+        };
+        if let Some(path) = &self.escalation_path {
+            action.push_str(&format!(", or escalate to {path}"));
+        }
+        Remediation::new(action)
+    }
+}
+
+impl Advisory {
+    /// Referenced as `advisory-{policy}-{digest}`, falling back to the
+    /// category and then to the digest of the message alone, so that two
+    /// advisories under one policy never share a reference and none matches
+    /// the policy's interlock.
+    fn evidence(&self) -> Result<AdvisoryEvidence, DecapodPortError> {
+        let digest = format!("{:x}", Sha256::digest(self.message.as_bytes()));
+        let digest = &digest[..16];
+        let reference = match self.related_policies.first().or(self.category.as_ref()) {
+            Some(origin) => reference(&format!("advisory-{origin}-{digest}"))?,
+            None => reference(&format!("advisory-{digest}"))?,
+        };
+        Ok(AdvisoryEvidence {
+            reference: Some(reference),
+            priority: self.priority,
+        })
+    }
+}
+
+impl Attestation {
+    fn reference(&self) -> Result<ValidationEvidenceRef, DecapodPortError> {
+        ValidationEvidenceRef::new(&self.proof_id).map_err(|error| incomplete(error.to_string()))
+    }
+
+    /// Passed and not yet expired.
+    fn holds(&self) -> bool {
+        self.is_valid() && !self.has_expired()
+    }
+}
+
+impl GovernanceResponse {
+    fn attestation(&self) -> Result<&Attestation, DecapodPortError> {
+        self.attestation
+            .as_ref()
+            .ok_or_else(|| incomplete("no attestation"))
+    }
+}
+
+impl TryFrom<&GovernanceResponse> for InterlockDecision {
+    type Error = DecapodPortError;
+
+    /// Follows [`GovernanceEngine::evaluate`]: a blocking interlock blocks,
+    /// and otherwise the most severe advisory is carried along.
+    fn try_from(response: &GovernanceResponse) -> Result<Self, Self::Error> {
+        let advisory = match GovernanceEngine::evaluate(response) {
+            GovernanceDecision::Blocked { .. } => {
+                let interlock = response
+                    .interlock
+                    .as_ref()
+                    .ok_or_else(|| incomplete("blocked without an interlock"))?;
+                return Ok(Self::Block {
+                    reference: interlock.reference()?,
+                    remediation: interlock.remediation(),
+                    policy: Some(interlock.policy()),
+                });
+            }
+            GovernanceDecision::CriticalAdvisory { advisories }
+            | GovernanceDecision::Warning { advisories } => advisories.first().cloned(),
+            GovernanceDecision::Proceed { .. } => response.advisories.first().cloned(),
+        };
+        Ok(Self::Allow {
+            advisory: advisory.as_ref().map(Advisory::evidence).transpose()?,
+        })
+    }
+}
+
+impl TryFrom<&GovernanceResponse> for ApprovalStatus {
+    type Error = DecapodPortError;
+
+    /// An interlock naming an approval is pending until Decapod stops
+    /// raising it; a blocking interlock without one cannot be approved.
+    fn try_from(response: &GovernanceResponse) -> Result<Self, Self::Error> {
+        let Some(interlock) = &response.interlock else {
+            return Ok(Self::NotRequired);
+        };
+        match (interlock.requires_approval(), interlock.is_blocking()) {
+            (true, _) => Ok(Self::Pending {
+                reference: interlock.reference()?,
+                remediation: interlock.remediation(),
+                policy: Some(interlock.policy()),
+                progress: interlock.progress()?,
+            }),
+            (false, true) => Ok(Self::Denied {
+                reference: interlock.reference()?,
+                remediation: interlock.remediation(),
+                policy: Some(interlock.policy()),
+            }),
+            (false, false) => Ok(Self::NotRequired),
+        }
+    }
+}
+
+impl TryFrom<&GovernanceResponse> for ValidationEvidence {
+    type Error = DecapodPortError;
+
+    /// The attestation; it passes only while valid and unexpired, and never
+    /// while an interlock blocks.
+    fn try_from(response: &GovernanceResponse) -> Result<Self, Self::Error> {
+        let attestation = response.attestation()?;
+        Ok(Self {
+            reference: attestation.reference()?,
+            passed: attestation.holds() && !response.is_blocked(),
+        })
+    }
+}
+
+impl TryFrom<&GovernanceResponse> for ProofEvidence {
+    type Error = DecapodPortError;
+
+    /// The attestation; it backs the run only while valid and unexpired.
+    fn try_from(response: &GovernanceResponse) -> Result<Self, Self::Error> {
+        let attestation = response.attestation()?;
+        Ok(Self {
+            reference: ProofEvidenceRef::new(&attestation.proof_id)
+                .map_err(|error| incomplete(error.to_string()))?,
+            backed: attestation.holds(),
+        })
+    }
+}
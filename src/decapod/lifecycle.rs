@@ -0,0 +1,251 @@
+//! Keeps one Decapod session alive for every manager.
+//!
+//! [`SessionManager`] hands out a session with time left on it.  It reuses
+//! the session in memory, then the one persisted at
+//! [`SessionConfig::persist_path`] once [`Session::validate_with`] confirms
+//! Decapod still honours it, and otherwise acquires a new one with the
+//! configured password and persists it.  A session is replaced once it is
+//! within the refresh margin of its expiry, or older than
+//! [`SessionConfig::ttl_secs`].
+//!
+//! The session file holds a live credential, so it is written with mode
+//! `0600` and a file readable by anyone else is not trusted.
+//!
+//! Managers built by [`SessionManager::todos`] and its siblings carry the
+//! token current when they were built.  A command refused because the
+//! session expired anyway fails with [`SessionExpired`], which a control
+//! plane turns into a custody failure with [`SessionExpired::to_port_error`].
+//!
+//! [`SessionExpired`]: super::session::SessionExpired
+//! [`SessionExpired::to_port_error`]: super::session::SessionExpired::to_port_error
+
+use super::rpc::RpcClient;
+use super::session::{Session, SessionConfig};
+use super::todo::TodoManager;
+use super::transport::{DecapodTransport, default_transport};
+use super::workspace::WorkspaceManager;
+use super::workunit::WorkUnitManager;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+/// How long before expiry a session is replaced.
+pub const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Error)]
+pub enum SessionError {
+    #[error("no Decapod session password is configured")]
+    NoPassword,
+    #[error("failed to acquire a Decapod session: {source}")]
+    Acquire {
+        #[source]
+        source: anyhow::Error,
+    },
+}
+
+/// Acquires, persists and refreshes the Decapod session; see the
+/// [module documentation](self).
+pub struct SessionManager {
+    config: SessionConfig,
+    transport: Arc<dyn DecapodTransport>,
+    refresh_margin: Duration,
+    current: Mutex<Option<Session>>,
+}
+
+impl SessionManager {
+    pub fn new(config: SessionConfig) -> Self {
+        Self {
+            config,
+            transport: default_transport(),
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            current: Mutex::new(None),
+        }
+    }
+
+    pub fn with_transport(mut self, transport: Arc<dyn DecapodTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub fn with_refresh_margin(mut self, margin: Duration) -> Self {
+        self.refresh_margin = margin;
+        self
+    }
+
+    pub fn config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    pub fn transport(&self) -> &Arc<dyn DecapodTransport> {
+        &self.transport
+    }
+
+    /// A session with more than the refresh margin left.
+    pub async fn session(&self) -> Result<Session, SessionError> {
+        let mut current = self.current.lock().await;
+        if let Some(session) = current.as_ref()
+            && !self.needs_refresh(session)
+        {
+            return Ok(session.clone());
+        }
+        if current.is_none()
+            && let Some(session) = self.load().await
+        {
+            *current = Some(session.clone());
+            return Ok(session);
+        }
+        let session = self.acquire().await?;
+        *current = Some(session.clone());
+        Ok(session)
+    }
+
+    /// Replaces the session whatever its expiry, for example after Decapod
+    /// refused it.
+    pub async fn refresh(&self) -> Result<Session, SessionError> {
+        let mut current = self.current.lock().await;
+        let session = self.acquire().await?;
+        *current = Some(session.clone());
+        Ok(session)
+    }
+
+    /// Drops the session and its persisted file.
+    pub async fn forget(&self) -> io::Result<()> {
+        self.current.lock().await.take();
+        match self
+            .config
+            .persist_path
+            .as_deref()
+            .map(std::fs::remove_file)
+        {
+            Some(Err(error)) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn todos(&self) -> Result<TodoManager, SessionError> {
+        let session = self.session().await?;
+        Ok(TodoManager::new()
+            .with_transport(self.transport.clone())
+            .with_session(session.token()))
+    }
+
+    pub async fn work_units(&self) -> Result<WorkUnitManager, SessionError> {
+        let session = self.session().await?;
+        Ok(WorkUnitManager::new()
+            .with_transport(self.transport.clone())
+            .with_session(session.token()))
+    }
+
+    pub async fn workspaces(&self) -> Result<WorkspaceManager, SessionError> {
+        let session = self.session().await?;
+        Ok(WorkspaceManager::new()
+            .with_transport(self.transport.clone())
+            .with_session(session.token()))
+    }
+
+    pub async fn rpc(&self) -> Result<RpcClient, SessionError> {
+        let session = self.session().await?;
+        Ok(RpcClient::new()
+            .with_transport(self.transport.clone())
+            .with_session(session.token()))
+    }
+
+    /// Whether `session` is within the refresh margin of its expiry or past
+    /// the configured TTL.
+    pub fn needs_refresh(&self, session: &Session) -> bool {
+        if session.expires_within(self.refresh_margin) {
+            return true;
+        }
+        let ttl = self.config.ttl_secs().map(Duration::from_secs);
+        match (ttl, session.created()) {
+            (Some(ttl), Some(created)) => created + ttl - self.refresh_margin < chrono::Utc::now(),
+            _ => false,
+        }
+    }
+
+    async fn acquire(&self) -> Result<Session, SessionError> {
+        let password = self.config.password().ok_or(SessionError::NoPassword)?;
+        let session = Session::acquire_with(self.transport.as_ref(), password)
+            .await
+            .map_err(|source| SessionError::Acquire { source })?;
+        if let Some(path) = &self.config.persist_path
+            && let Err(error) = persist(path, &session)
+        {
+            tracing::warn!("failed to persist session to {}: {error}", path.display());
+        }
+        Ok(session)
+    }
+
+    /// The persisted session, if it is private, fresh and still valid.
+    async fn load(&self) -> Option<Session> {
+        let path = self.config.persist_path.as_deref()?;
+        let text = match read_private(path) {
+            Ok(text) => text?,
+            Err(error) => {
+                tracing::warn!("ignoring session file {}: {error}", path.display());
+                return None;
+            }
+        };
+        let session: Session = match serde_json::from_str(&text) {
+            Ok(session) => session,
+            Err(error) => {
+                tracing::warn!("ignoring session file {}: {error}", path.display());
+                return None;
+            }
+        };
+        if self.needs_refresh(&session) {
+            return None;
+        }
+        match session.validate_with(self.transport.as_ref()).await {
+            Ok(true) => Some(session),
+            Ok(false) => None,
+            Err(error) => {
+                tracing::warn!("could not validate the persisted session: {error}");
+                None
+            }
+        }
+    }
+}
+
+/// Writes `session` to `path` atomically, readable only by its owner.
+fn persist(path: &Path, session: &Session) -> io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(dir)?;
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.as_file()
+            .set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(serde_json::to_string_pretty(session)?.as_bytes())?;
+    file.persist(path).map_err(|error| error.error)?;
+    Ok(())
+}
+
+/// The file at `path`, or `None` when there is none.  A file other users can
+/// read or write is an error.
+fn read_private(path: &Path) -> io::Result<Option<String>> {
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = metadata.permissions().mode() & 0o777;
+        if mode & 0o077 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("mode {mode:o} is not 600"),
+            ));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = metadata;
+    std::fs::read_to_string(path).map(Some)
+}
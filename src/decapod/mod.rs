@@ -1,14 +1,26 @@
 pub mod agent;
+pub mod bootstrap;
 pub mod broker;
 pub mod capabilities;
 pub mod cli;
 pub mod commitment;
 pub mod coordination;
+pub mod discovery;
 pub mod docs;
+pub mod envelope;
 pub mod governance;
+pub mod lifecycle;
+pub mod project;
+pub mod proof;
+pub mod queue;
+pub mod reconcile;
 pub mod rpc;
+pub mod secret;
 pub mod session;
+pub mod sim;
+pub mod stdio;
 pub mod todo;
+pub mod transport;
 pub mod validate;
 pub mod workspace;
 pub mod workunit;
@@ -17,11 +29,15 @@ pub use anyhow::Error as DecapodError;
 pub use anyhow::Result;
 
 pub use cli::DecapodCli;
+pub use discovery::{BinaryDiscovery, CompatibilityCheck, DecapodInstall};
+pub use lifecycle::SessionManager;
+pub use project::ProjectRoot;
 pub use session::Session;
 
 pub struct Decapod {
     cli: DecapodCli,
     session: Option<Session>,
+    install: Option<DecapodInstall>,
 }
 
 impl Decapod {
@@ -29,6 +45,21 @@ impl Decapod {
         Ok(Self {
             cli: DecapodCli::new()?,
             session: None,
+            install: None,
+        })
+    }
+
+    /// Discovers Decapod and refuses to start unless it is compatible.
+    pub async fn connect() -> Result<Self> {
+        let install = DecapodInstall::connect(&BinaryDiscovery::new(), &CompatibilityCheck::new()).await?;
+        let cli = DecapodCli {
+            binary_path: install.binary.path.display().to_string(),
+            project_root: install.process().project_root().map(|root| root.display().to_string()),
+        };
+        Ok(Self {
+            cli,
+            session: None,
+            install: Some(install),
         })
     }
 
@@ -49,17 +80,15 @@ impl Decapod {
     pub fn session(&self) -> Option<&Session> {
         self.session.as_ref()
     }
+
+    /// The checked install, when started with [`Decapod::connect`].
+    pub fn install(&self) -> Option<&DecapodInstall> {
+        self.install.as_ref()
+    }
 }
 
+/// The nearest Decapod project at or above the working directory; see
+/// [`ProjectRoot::discover`].
 pub fn find_project_root() -> Option<std::path::PathBuf> {
-    let mut path = std::env::current_dir().ok()?;
-    
-    loop {
-        if path.join(".decapod").exists() {
-            return Some(path);
-        }
-        if !path.pop() {
-            return None;
-        }
-    }
+    ProjectRoot::discover().ok().map(ProjectRoot::into_path)
 }
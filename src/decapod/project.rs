@@ -0,0 +1,107 @@
+//! The Decapod project a command runs against.
+//!
+//! Decapod keeps its state in a [`DECAPOD_DIR`] directory at the root of the
+//! repository it governs, and resolves every command against the directory
+//! it is started in.  A [`ProjectRoot`] is a directory that has been checked
+//! to hold that state; [`super::transport::ProcessTransport`] verifies its
+//! root before every command, so Pincher run from a subdirectory or another
+//! checkout cannot silently act on the wrong repository.
+//!
+//! The root is recorded next to the [`RepositoryRef`] in custody (see
+//! [`CustodyBinding::project_root`]) and the engine refuses a run whose
+//! recorded root Decapod does not confirm.
+//!
+//! [`RepositoryRef`]: crate::governed_run::RepositoryRef
+//! [`CustodyBinding::project_root`]: crate::governed_run::CustodyBinding::project_root
+
+use crate::governed_run::{ContractError, ProjectRootRef};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// The directory marking a Decapod project, relative to its root.
+pub const DECAPOD_DIR: &str = ".decapod";
+
+#[derive(Debug, Error)]
+pub enum ProjectRootError {
+    #[error("decapod project root {} is unreadable: {source}", path.display())]
+    Unreadable {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{} is not a Decapod project: it has no {DECAPOD_DIR} directory", path.display())]
+    NotAProject { path: PathBuf },
+    #[error("no Decapod project at or above {}", start.display())]
+    NotFound { start: PathBuf },
+}
+
+/// A canonical directory holding a [`DECAPOD_DIR`] directory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProjectRoot(PathBuf);
+
+impl ProjectRoot {
+    /// Checks that `path` is the root of a Decapod project.  Symlinks and
+    /// relative components are resolved, so two spellings of one directory
+    /// compare equal.
+    pub fn verify(path: impl AsRef<Path>) -> Result<Self, ProjectRootError> {
+        let path = path.as_ref();
+        let root = path
+            .canonicalize()
+            .map_err(|source| ProjectRootError::Unreadable {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        if !root.join(DECAPOD_DIR).is_dir() {
+            return Err(ProjectRootError::NotAProject { path: root });
+        }
+        Ok(Self(root))
+    }
+
+    /// The nearest Decapod project at or above `start`.
+    pub fn discover_from(start: impl AsRef<Path>) -> Result<Self, ProjectRootError> {
+        let start = start.as_ref();
+        start
+            .ancestors()
+            .find(|dir| dir.join(DECAPOD_DIR).is_dir())
+            .ok_or_else(|| ProjectRootError::NotFound {
+                start: start.to_path_buf(),
+            })
+            .and_then(Self::verify)
+    }
+
+    /// The nearest Decapod project at or above the working directory.
+    pub fn discover() -> Result<Self, ProjectRootError> {
+        let start = std::env::current_dir().map_err(|source| ProjectRootError::Unreadable {
+            path: PathBuf::from("."),
+            source,
+        })?;
+        Self::discover_from(start)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn into_path(self) -> PathBuf {
+        self.0
+    }
+
+    /// The root as custody records it.
+    pub fn to_ref(&self) -> Result<ProjectRootRef, ContractError> {
+        ProjectRootRef::new(self.0.to_string_lossy())
+    }
+}
+
+impl AsRef<Path> for ProjectRoot {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl fmt::Display for ProjectRoot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.display().fmt(f)
+    }
+}
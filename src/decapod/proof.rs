@@ -0,0 +1,192 @@
+//! Evidence carried by work-unit proofs.
+//!
+//! Each [`ProofType`] has an evidence schema: the map passed to
+//! [`WorkUnitManager::record_proof`](super::workunit::WorkUnitManager::record_proof)
+//! must deserialize into the matching type below.  Keys beyond the schema are
+//! kept, so hosts can attach extra context, but the required material cannot
+//! be left out.
+
+use super::commitment::ProofType;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Evidence for [`ProofType::Validation`]: a Decapod validation report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ValidationReportEvidence {
+    pub validation_ref: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passed: Option<bool>,
+}
+
+/// Evidence for [`ProofType::Test`]: the result of a test run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TestResultEvidence {
+    pub passed: u64,
+    pub failed: u64,
+    #[serde(default)]
+    pub skipped: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Where the full report is kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report_ref: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewVerdict {
+    Approved,
+    ChangesRequested,
+    Rejected,
+}
+
+/// Evidence for [`ProofType::Review`]: a reviewer's verdict.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ReviewEvidence {
+    pub reviewer: String,
+    pub verdict: ReviewVerdict,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_ref: Option<String>,
+}
+
+/// Evidence for [`ProofType::Audit`]: a reference into an audit trail.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AuditEvidence {
+    pub audit_ref: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auditor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub findings: Option<u64>,
+}
+
+/// Evidence for [`ProofType::External`]: a reference held by another system.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ExternalEvidence {
+    pub source: String,
+    pub reference: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EvidenceError {
+    #[error("unknown proof type {0}")]
+    UnknownProofType(String),
+    #[error("{proof_type} evidence does not match its schema: {reason}")]
+    Schema {
+        proof_type: ProofType,
+        reason: String,
+    },
+    #[error("{proof_type} evidence has an empty {field}")]
+    Empty {
+        proof_type: ProofType,
+        field: &'static str,
+    },
+}
+
+impl ProofType {
+    pub fn all() -> [Self; 5] {
+        [
+            Self::Validation,
+            Self::Test,
+            Self::Review,
+            Self::Audit,
+            Self::External,
+        ]
+    }
+
+    /// Name Decapod uses for this proof type.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Validation => "validation",
+            Self::Test => "test",
+            Self::Review => "review",
+            Self::Audit => "audit",
+            Self::External => "external",
+        }
+    }
+
+    /// JSON Schema of the evidence this proof type requires.
+    pub fn evidence_schema(self) -> Value {
+        let schema = match self {
+            Self::Validation => schemars::schema_for!(ValidationReportEvidence),
+            Self::Test => schemars::schema_for!(TestResultEvidence),
+            Self::Review => schemars::schema_for!(ReviewEvidence),
+            Self::Audit => schemars::schema_for!(AuditEvidence),
+            Self::External => schemars::schema_for!(ExternalEvidence),
+        };
+        schema.to_value()
+    }
+
+    /// Checks `evidence` against the schema of this proof type.
+    pub fn validate_evidence(self, evidence: &HashMap<String, Value>) -> Result<(), EvidenceError> {
+        let value = Value::Object(evidence.clone().into_iter().collect());
+        let schema = |error: serde_json::Error| EvidenceError::Schema {
+            proof_type: self,
+            reason: error.to_string(),
+        };
+        let empty = |field| EvidenceError::Empty {
+            proof_type: self,
+            field,
+        };
+        match self {
+            Self::Validation => {
+                let evidence: ValidationReportEvidence =
+                    serde_json::from_value(value).map_err(schema)?;
+                if evidence.validation_ref.trim().is_empty() {
+                    return Err(empty("validation_ref"));
+                }
+            }
+            Self::Test => {
+                serde_json::from_value::<TestResultEvidence>(value).map_err(schema)?;
+            }
+            Self::Review => {
+                let evidence: ReviewEvidence = serde_json::from_value(value).map_err(schema)?;
+                if evidence.reviewer.trim().is_empty() {
+                    return Err(empty("reviewer"));
+                }
+            }
+            Self::Audit => {
+                let evidence: AuditEvidence = serde_json::from_value(value).map_err(schema)?;
+                if evidence.audit_ref.trim().is_empty() {
+                    return Err(empty("audit_ref"));
+                }
+            }
+            Self::External => {
+                let evidence: ExternalEvidence = serde_json::from_value(value).map_err(schema)?;
+                if evidence.source.trim().is_empty() {
+                    return Err(empty("source"));
+                }
+                if evidence.reference.trim().is_empty() {
+                    return Err(empty("reference"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ProofType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ProofType {
+    type Err = EvidenceError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::all()
+            .into_iter()
+            .find(|proof_type| proof_type.as_str() == name)
+            .ok_or_else(|| EvidenceError::UnknownProofType(name.to_string()))
+    }
+}
+
+/// Converts typed evidence into the map `record_proof` takes.
+pub fn evidence_map(evidence: &impl Serialize) -> serde_json::Result<HashMap<String, Value>> {
+    serde_json::from_value(serde_json::to_value(evidence)?)
+}
@@ -0,0 +1,249 @@
+//! Turns pending Decapod tasks into governed runs.
+//!
+//! [`TaskQueue`] polls the pending tasks of a [`TaskBoard`], skips tasks whose
+//! `blocked_by` dependencies are not completed, and claims the most urgent
+//! remaining task.  A task whose dependencies Decapod does not report is
+//! never treated as unblocked; the poll skips it with a warning.  Each claimed
+//! task becomes a [`RunRequest`]; when the run ends Blocked or Failed the
+//! queue releases the claim so the task can be picked up again.  Ready runs keep their claim for whoever completes the
+//! task.
+
+use super::todo::{Task, TaskStatus, TodoManager};
+use crate::governed_run::{ContractError, RunId, RunOutcome, RunRequest, RunState};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Task operations the queue needs; implemented by [`TodoManager`].
+pub trait TaskBoard {
+    fn pending(&self) -> impl Future<Output = anyhow::Result<Vec<Task>>> + Send;
+    fn task(&self, task_id: &str) -> impl Future<Output = anyhow::Result<Task>> + Send;
+    fn claim(&self, task_id: &str) -> impl Future<Output = anyhow::Result<Task>> + Send;
+    fn release(&self, task_id: &str) -> impl Future<Output = anyhow::Result<Task>> + Send;
+}
+
+impl TaskBoard for TodoManager {
+    async fn pending(&self) -> anyhow::Result<Vec<Task>> {
+        self.list(Some("pending"), None, None).await
+    }
+
+    async fn task(&self, task_id: &str) -> anyhow::Result<Task> {
+        self.get(task_id).await
+    }
+
+    async fn claim(&self, task_id: &str) -> anyhow::Result<Task> {
+        TodoManager::claim(self, task_id).await
+    }
+
+    async fn release(&self, task_id: &str) -> anyhow::Result<Task> {
+        TodoManager::release(self, task_id).await
+    }
+}
+
+/// Urgency of a task; lower ranks are claimed first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TaskPriority(u32);
+
+impl TaskPriority {
+    /// Tasks without a priority rank with `medium`.
+    pub const DEFAULT: Self = Self(2);
+
+    /// Accepts `critical`, `high`, `medium` (or `normal`), `low`, `p0`-style
+    /// levels, and bare numbers.  Anything else ranks after `low`.
+    pub fn parse(priority: Option<&str>) -> Self {
+        let Some(priority) = priority.map(str::trim) else {
+            return Self::DEFAULT;
+        };
+        match priority.to_ascii_lowercase().as_str() {
+            "critical" | "urgent" => Self(0),
+            "high" => Self(1),
+            "medium" | "normal" | "" => Self::DEFAULT,
+            "low" => Self(3),
+            level => level
+                .strip_prefix('p')
+                .unwrap_or(level)
+                .parse()
+                .map(Self)
+                .unwrap_or(Self(u32::MAX)),
+        }
+    }
+}
+
+/// Orders tasks by priority, then age, then id.
+fn by_urgency(left: &Task, right: &Task) -> Ordering {
+    TaskPriority::parse(left.priority.as_deref())
+        .cmp(&TaskPriority::parse(right.priority.as_deref()))
+        .then_with(|| left.created_at.cmp(&right.created_at))
+        .then_with(|| left.id.cmp(&right.id))
+}
+
+/// A task this queue claimed, with the run that will carry it out.
+#[derive(Debug, Clone)]
+pub struct ClaimedTask {
+    pub task: Task,
+    pub request: RunRequest,
+}
+
+pub struct TaskQueue<B> {
+    board: B,
+    poll_interval: Duration,
+    /// Claimed tasks by the run carrying them out.
+    claimed: HashMap<RunId, String>,
+}
+
+impl<B: TaskBoard> TaskQueue<B> {
+    pub fn new(board: B) -> Self {
+        Self {
+            board,
+            poll_interval: Duration::from_secs(5),
+            claimed: HashMap::new(),
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn board(&self) -> &B {
+        &self.board
+    }
+
+    /// Tasks claimed by this queue whose runs have not been settled.
+    pub fn in_flight(&self) -> impl Iterator<Item = (&RunId, &str)> {
+        self.claimed
+            .iter()
+            .map(|(run_id, task_id)| (run_id, task_id.as_str()))
+    }
+
+    /// Claims the most urgent runnable task, if any, and builds its run with
+    /// `build`.
+    ///
+    /// A task another agent claims first is skipped.  When `build` fails the
+    /// claim is released again and the error returned.  A pending task that
+    /// does not report its dependencies is skipped.
+    pub async fn poll<F>(&mut self, mut build: F) -> anyhow::Result<Option<ClaimedTask>>
+    where
+        F: FnMut(&Task) -> Result<RunRequest, ContractError>,
+    {
+        let mut pending = self.board.pending().await?;
+        pending.retain(|task| task.status == TaskStatus::Pending);
+        pending.sort_by(by_urgency);
+
+        let mut completed: HashMap<String, bool> = HashMap::new();
+        for task in &pending {
+            if task.blocked_by.is_none() {
+                tracing::warn!(
+                    "Decapod did not report the dependencies of task {}; skipping it",
+                    task.id
+                );
+                continue;
+            }
+            if !self
+                .dependencies_completed(task, &pending, &mut completed)
+                .await
+            {
+                tracing::debug!("task {} waits for its dependencies", task.id);
+                continue;
+            }
+            let claimed = match self.board.claim(&task.id).await {
+                Ok(claimed) => claimed,
+                Err(error) => {
+                    tracing::debug!("task {} could not be claimed: {error}", task.id);
+                    continue;
+                }
+            };
+            let request = match build(&claimed) {
+                Ok(request) => request,
+                Err(error) => {
+                    if let Err(release) = self.board.release(&claimed.id).await {
+                        tracing::warn!("failed to release task {}: {release}", claimed.id);
+                    }
+                    return Err(anyhow::anyhow!(
+                        "cannot build a run for task {}: {error}",
+                        claimed.id
+                    ));
+                }
+            };
+            self.claimed
+                .insert(request.run_id.clone(), claimed.id.clone());
+            return Ok(Some(ClaimedTask {
+                task: claimed,
+                request,
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Polls until a task is claimed.
+    pub async fn next<F>(&mut self, mut build: F) -> anyhow::Result<ClaimedTask>
+    where
+        F: FnMut(&Task) -> Result<RunRequest, ContractError>,
+    {
+        loop {
+            if let Some(claimed) = self.poll(&mut build).await? {
+                return Ok(claimed);
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Records how the run for a claimed task ended.  Blocked and failed runs
+    /// give their task back; ready runs keep the claim.
+    ///
+    /// Returns the released task, or `None` when the claim is kept or the run
+    /// was not claimed by this queue.
+    pub async fn settle(&mut self, outcome: &RunOutcome) -> anyhow::Result<Option<Task>> {
+        let run_id = &outcome.snapshot().request.run_id;
+        let Some(task_id) = self.claimed.remove(run_id) else {
+            return Ok(None);
+        };
+        let state = match outcome {
+            RunOutcome::HandedOff { terminal_state, .. } => *terminal_state,
+            _ => outcome.snapshot().state,
+        };
+        match state {
+            RunState::Blocked | RunState::Failed => {
+                let released = self.board.release(&task_id).await.inspect_err(|_| {
+                    // Keep tracking the claim so settling can be retried.
+                    self.claimed.insert(run_id.clone(), task_id.clone());
+                })?;
+                Ok(Some(released))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Whether every task `task` is blocked by has been completed.  Lookups
+    /// that fail count as not completed.
+    async fn dependencies_completed(
+        &self,
+        task: &Task,
+        pending: &[Task],
+        completed: &mut HashMap<String, bool>,
+    ) -> bool {
+        for blocker in task.blocked_by.iter().flatten() {
+            if pending.iter().any(|pending| &pending.id == blocker) {
+                return false;
+            }
+            let done = match completed.get(blocker) {
+                Some(done) => *done,
+                None => {
+                    let done = match self.board.task(blocker).await {
+                        Ok(blocker) => blocker.status == TaskStatus::Completed,
+                        Err(error) => {
+                            tracing::warn!("cannot resolve dependency {blocker}: {error}");
+                            false
+                        }
+                    };
+                    completed.insert(blocker.clone(), done);
+                    done
+                }
+            };
+            if !done {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,440 @@
+//! Mirrors governed run outcomes back into Decapod.
+//!
+//! A run ends in Pincher, but the work unit and task it was bound to live in
+//! Decapod.  [`OutcomeReconciler`] applies the outcome there:
+//!
+//! - Ready records a validation proof, completes the work unit, then completes
+//!   the task.
+//! - Failed fails the work unit with the typed [`RunFailure`] and its
+//!   remediation.
+//! - Blocked records the interlock reference in the work unit's plan.
+//!
+//! Every call carries the run's idempotency key, and the reconciler remembers
+//! which steps succeeded per key.  Reconciling the same outcome again is a
+//! no-op, and after a partial failure it resumes with the step that failed.
+
+use super::commitment::ProofType;
+use super::todo::{Task, TaskStatus, TodoManager};
+use super::transport::DecapodTransport;
+use super::workunit::{Proof, WorkUnit, WorkUnitManager, WorkUnitStatus};
+use crate::governed_run::{
+    FailureCode, IdempotencyKey, Remediation, RunFailure, RunId, RunOutcome, RunSnapshot, RunState,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Work-unit and task operations the reconciler needs; implemented by
+/// [`DecapodLedger`].
+pub trait WorkLedger {
+    fn record_proof(
+        &self,
+        key: &IdempotencyKey,
+        work_unit_id: &str,
+        proof_type: ProofType,
+        criteria: &str,
+        evidence: HashMap<String, Value>,
+    ) -> impl Future<Output = anyhow::Result<Proof>> + Send;
+    fn complete_work_unit(
+        &self,
+        key: &IdempotencyKey,
+        work_unit_id: &str,
+    ) -> impl Future<Output = anyhow::Result<WorkUnit>> + Send;
+    fn complete_task(
+        &self,
+        key: &IdempotencyKey,
+        task_id: &str,
+        resolution: &str,
+    ) -> impl Future<Output = anyhow::Result<Task>> + Send;
+    fn fail_work_unit(
+        &self,
+        key: &IdempotencyKey,
+        work_unit_id: &str,
+        report: &FailureReport,
+    ) -> impl Future<Output = anyhow::Result<WorkUnit>> + Send;
+    fn block_work_unit(
+        &self,
+        key: &IdempotencyKey,
+        work_unit_id: &str,
+        interlock_ref: &str,
+    ) -> impl Future<Output = anyhow::Result<WorkUnit>> + Send;
+}
+
+/// [`WorkLedger`] backed by the Decapod CLI.  The Decapod CLI has no
+/// idempotency flag, so the key travels in the proof evidence, the task
+/// resolution, the failure report, and the plan of a blocked work unit.
+/// Every call reads Decapod's state first and skips the write when Decapod
+/// already shows it: a proof carrying the key, a completed task, or the
+/// target work-unit state.  A retry after a lost response does not repeat it.
+///
+/// Decapod has no block command; a block is recorded as the work unit's plan
+/// through `workunit update`, naming the interlock and the key.
+pub struct DecapodLedger {
+    work_units: WorkUnitManager,
+    todos: TodoManager,
+}
+
+impl DecapodLedger {
+    pub fn new(session_token: impl Into<String>) -> Self {
+        let session_token = session_token.into();
+        Self {
+            work_units: WorkUnitManager::new().with_session(session_token.clone()),
+            todos: TodoManager::new().with_session(session_token),
+        }
+    }
+
+    pub fn with_transport(mut self, transport: Arc<dyn DecapodTransport>) -> Self {
+        self.work_units = self.work_units.with_transport(transport.clone());
+        self.todos = self.todos.with_transport(transport);
+        self
+    }
+}
+
+impl WorkLedger for DecapodLedger {
+    async fn record_proof(
+        &self,
+        key: &IdempotencyKey,
+        work_unit_id: &str,
+        proof_type: ProofType,
+        criteria: &str,
+        mut evidence: HashMap<String, Value>,
+    ) -> anyhow::Result<Proof> {
+        evidence.insert("idempotency_key".to_string(), key.as_str().into());
+        let unit = self.work_units.get(work_unit_id).await?;
+        if let Some(proof) = unit.proofs.into_iter().find(|proof| {
+            proof.kind() == Some(proof_type)
+                && proof.evidence.get("idempotency_key") == evidence.get("idempotency_key")
+        }) {
+            return Ok(proof);
+        }
+        self.work_units
+            .record_proof(work_unit_id, proof_type, criteria, evidence)
+            .await
+    }
+
+    async fn complete_work_unit(
+        &self,
+        _key: &IdempotencyKey,
+        work_unit_id: &str,
+    ) -> anyhow::Result<WorkUnit> {
+        let unit = self.work_units.get(work_unit_id).await?;
+        if unit.status == WorkUnitStatus::Completed {
+            return Ok(unit);
+        }
+        self.work_units.complete(work_unit_id).await
+    }
+
+    async fn complete_task(
+        &self,
+        key: &IdempotencyKey,
+        task_id: &str,
+        resolution: &str,
+    ) -> anyhow::Result<Task> {
+        let task = self.todos.get(task_id).await?;
+        if task.status == TaskStatus::Completed {
+            return Ok(task);
+        }
+        let resolution = format!("{resolution} [{key}]");
+        self.todos.complete(task_id, Some(&resolution)).await
+    }
+
+    async fn fail_work_unit(
+        &self,
+        _key: &IdempotencyKey,
+        work_unit_id: &str,
+        report: &FailureReport,
+    ) -> anyhow::Result<WorkUnit> {
+        let unit = self.work_units.get(work_unit_id).await?;
+        if unit.status == WorkUnitStatus::Failed {
+            return Ok(unit);
+        }
+        let reason = serde_json::to_string(report)?;
+        self.work_units.fail(work_unit_id, &reason).await
+    }
+
+    async fn block_work_unit(
+        &self,
+        key: &IdempotencyKey,
+        work_unit_id: &str,
+        interlock_ref: &str,
+    ) -> anyhow::Result<WorkUnit> {
+        let plan = format!("blocked by interlock {interlock_ref} [{key}]");
+        let unit = self.work_units.get(work_unit_id).await?;
+        if unit.state.plan.as_deref() == Some(plan.as_str()) {
+            return Ok(unit);
+        }
+        self.work_units
+            .update_state(work_unit_id, None, Some(&plan))
+            .await
+    }
+}
+
+/// Why a run failed, as recorded on its work unit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailureReport {
+    pub run_id: RunId,
+    pub idempotency_key: IdempotencyKey,
+    pub code: FailureCode,
+    pub failure: RunFailure,
+    pub remediation: Option<Remediation>,
+}
+
+/// One Decapod call made while reconciling an outcome.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReconcileStep {
+    RecordProof,
+    CompleteWorkUnit,
+    CompleteTask,
+    FailWorkUnit,
+    BlockWorkUnit,
+}
+
+impl fmt::Display for ReconcileStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::RecordProof => "record the proof",
+            Self::CompleteWorkUnit => "complete the work unit",
+            Self::CompleteTask => "complete the task",
+            Self::FailWorkUnit => "fail the work unit",
+            Self::BlockWorkUnit => "block the work unit",
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ReconcileError {
+    /// The outcome lacks a reference or evidence its Decapod calls need.
+    #[error("run {run_id} cannot be reconciled: {reason}")]
+    Incomplete { run_id: RunId, reason: String },
+    #[error("failed to {step} for run {run_id}: {source}")]
+    Decapod {
+        run_id: RunId,
+        step: ReconcileStep,
+        #[source]
+        source: anyhow::Error,
+    },
+}
+
+pub struct OutcomeReconciler<L> {
+    ledger: L,
+    /// Steps already applied, by idempotency key.
+    applied: HashMap<IdempotencyKey, HashSet<ReconcileStep>>,
+}
+
+impl<L: WorkLedger> OutcomeReconciler<L> {
+    pub fn new(ledger: L) -> Self {
+        Self {
+            ledger,
+            applied: HashMap::new(),
+        }
+    }
+
+    pub fn ledger(&self) -> &L {
+        &self.ledger
+    }
+
+    /// Steps applied so far for `key`.
+    pub fn applied(&self, key: &IdempotencyKey) -> impl Iterator<Item = ReconcileStep> + '_ {
+        self.applied.get(key).into_iter().flatten().copied()
+    }
+
+    /// Applies `outcome` to Decapod and returns the steps performed by this
+    /// call.  Steps applied by an earlier call with the same idempotency key
+    /// are skipped.
+    pub async fn reconcile(
+        &mut self,
+        outcome: &RunOutcome,
+    ) -> Result<Vec<ReconcileStep>, ReconcileError> {
+        let snapshot = outcome.snapshot();
+        let state = match outcome {
+            RunOutcome::HandedOff { terminal_state, .. } => *terminal_state,
+            _ => snapshot.state,
+        };
+        match state {
+            RunState::Ready => self.ready(snapshot).await,
+            RunState::Failed => self.failed(snapshot).await,
+            RunState::Blocked => self.blocked(snapshot).await,
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    async fn ready(
+        &mut self,
+        snapshot: &RunSnapshot,
+    ) -> Result<Vec<ReconcileStep>, ReconcileError> {
+        let run_id = &snapshot.request.run_id;
+        let key = &snapshot.request.idempotency_key;
+        let work_unit = work_unit(snapshot)?;
+        let task = snapshot
+            .request
+            .custody
+            .task
+            .as_ref()
+            .ok_or_else(|| incomplete(run_id, "custody has no task"))?;
+        let (Some(validation), Some(proof)) = (&snapshot.validation, &snapshot.proof) else {
+            return Err(incomplete(run_id, "Ready without validation and proof"));
+        };
+
+        let mut performed = Vec::new();
+        if self.pending(key, ReconcileStep::RecordProof) {
+            // Validation evidence, plus the references that tie it to the run.
+            let evidence = HashMap::from([
+                (
+                    "validation_ref".to_string(),
+                    validation.reference.as_str().into(),
+                ),
+                ("passed".to_string(), validation.passed.into()),
+                ("proof_ref".to_string(), proof.reference.as_str().into()),
+                ("run_id".to_string(), run_id.as_str().into()),
+                ("chain_head".to_string(), snapshot.chain_head.clone().into()),
+            ]);
+            let criteria = format!("decapod validation {}", validation.reference);
+            let applied = self
+                .ledger
+                .record_proof(key, work_unit, ProofType::Validation, &criteria, evidence)
+                .await;
+            self.apply(
+                snapshot,
+                ReconcileStep::RecordProof,
+                applied,
+                &mut performed,
+            )?;
+        }
+        if self.pending(key, ReconcileStep::CompleteWorkUnit) {
+            let applied = self.ledger.complete_work_unit(key, work_unit).await;
+            self.apply(
+                snapshot,
+                ReconcileStep::CompleteWorkUnit,
+                applied,
+                &mut performed,
+            )?;
+        }
+        if self.pending(key, ReconcileStep::CompleteTask) {
+            let resolution = format!("run {run_id} ready with proof {}", proof.reference);
+            let applied = self
+                .ledger
+                .complete_task(key, task.as_str(), &resolution)
+                .await;
+            self.apply(
+                snapshot,
+                ReconcileStep::CompleteTask,
+                applied,
+                &mut performed,
+            )?;
+        }
+        Ok(performed)
+    }
+
+    async fn failed(
+        &mut self,
+        snapshot: &RunSnapshot,
+    ) -> Result<Vec<ReconcileStep>, ReconcileError> {
+        let run_id = &snapshot.request.run_id;
+        let key = &snapshot.request.idempotency_key;
+        let failure = snapshot
+            .failure
+            .as_ref()
+            .ok_or_else(|| incomplete(run_id, "Failed without a failure"))?;
+        // Requests rejected before custody have no work unit to fail.
+        let Some(work_unit) = snapshot.request.custody.work_unit.as_ref() else {
+            return Ok(Vec::new());
+        };
+
+        let mut performed = Vec::new();
+        if self.pending(key, ReconcileStep::FailWorkUnit) {
+            let report = FailureReport {
+                run_id: run_id.clone(),
+                idempotency_key: key.clone(),
+                code: failure.code(),
+                failure: failure.clone(),
+                remediation: failure.remediation().cloned(),
+            };
+            let applied = self
+                .ledger
+                .fail_work_unit(key, work_unit.as_str(), &report)
+                .await;
+            self.apply(
+                snapshot,
+                ReconcileStep::FailWorkUnit,
+                applied,
+                &mut performed,
+            )?;
+        }
+        Ok(performed)
+    }
+
+    async fn blocked(
+        &mut self,
+        snapshot: &RunSnapshot,
+    ) -> Result<Vec<ReconcileStep>, ReconcileError> {
+        let run_id = &snapshot.request.run_id;
+        let key = &snapshot.request.idempotency_key;
+        let work_unit = work_unit(snapshot)?;
+        let blocked = snapshot
+            .blocked
+            .as_ref()
+            .ok_or_else(|| incomplete(run_id, "Blocked without a reason"))?;
+
+        let mut performed = Vec::new();
+        if self.pending(key, ReconcileStep::BlockWorkUnit) {
+            let applied = self
+                .ledger
+                .block_work_unit(key, work_unit, blocked.reference().as_str())
+                .await;
+            self.apply(
+                snapshot,
+                ReconcileStep::BlockWorkUnit,
+                applied,
+                &mut performed,
+            )?;
+        }
+        Ok(performed)
+    }
+
+    fn pending(&self, key: &IdempotencyKey, step: ReconcileStep) -> bool {
+        !self
+            .applied
+            .get(key)
+            .is_some_and(|applied| applied.contains(&step))
+    }
+
+    fn apply<T>(
+        &mut self,
+        snapshot: &RunSnapshot,
+        step: ReconcileStep,
+        applied: anyhow::Result<T>,
+        performed: &mut Vec<ReconcileStep>,
+    ) -> Result<(), ReconcileError> {
+        applied.map_err(|source| ReconcileError::Decapod {
+            run_id: snapshot.request.run_id.clone(),
+            step,
+            source,
+        })?;
+        self.applied
+            .entry(snapshot.request.idempotency_key.clone())
+            .or_default()
+            .insert(step);
+        performed.push(step);
+        Ok(())
+    }
+}
+
+fn work_unit(snapshot: &RunSnapshot) -> Result<&str, ReconcileError> {
+    snapshot
+        .request
+        .custody
+        .work_unit
+        .as_ref()
+        .map(|work_unit| work_unit.as_str())
+        .ok_or_else(|| incomplete(&snapshot.request.run_id, "custody has no work unit"))
+}
+
+fn incomplete(run_id: &RunId, reason: &str) -> ReconcileError {
+    ReconcileError::Incomplete {
+        run_id: run_id.clone(),
+        reason: reason.to_string(),
+    }
+}
@@ -1,18 +1,27 @@
-use serde::Deserialize;
-use serde_json::{json, Value};
-use std::process::Stdio;
-use tokio::process::Command;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use crate::decapod::envelope;
+use crate::decapod::governance::{self, AdvisoryPriority, GovernanceResponse};
+use crate::decapod::transport::{default_transport, DecapodTransport, Invocation};
 use crate::decapod::cli::{DecapodResponse, Interlock, Advisory, Attestation, ContextCapsule};
+use crate::decapod::todo::Task;
+use crate::decapod::validate::ValidationResult;
+use crate::decapod::workspace::{Workspace, WorkspaceStatusResponse};
+use crate::decapod::workunit::WorkUnit;
 
 #[derive(Debug, Clone)]
 pub struct RpcClient {
     session_token: Option<String>,
+    transport: Arc<dyn DecapodTransport>,
 }
 
 impl RpcClient {
     pub fn new() -> Self {
         Self {
             session_token: None,
+            transport: default_transport(),
         }
     }
 
@@ -21,156 +30,119 @@ impl RpcClient {
         self
     }
 
+    pub fn with_transport(mut self, transport: Arc<dyn DecapodTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
     pub fn session_token(&self) -> Option<&str> {
         self.session_token.as_deref()
     }
 
-    pub async fn call<T: for<'de> Deserialize<'de> + Default>(
+    pub async fn call<T: DeserializeOwned>(
         &self,
         operation: &str,
         params: Option<Value>,
     ) -> anyhow::Result<DecapodResponse<T>> {
-        let mut cmd = Command::new("decapod");
-        cmd.arg("rpc")
-            .arg("--op")
-            .arg(operation);
-
-        if let Some(token) = &self.session_token {
-            cmd.env("DECAPOD_SESSION_PASSWORD", token);
-        }
+        let mut args = vec!["rpc".to_string(), "--op".to_string(), operation.to_string()];
 
         if let Some(p) = params {
-            let params_str = serde_json::to_string(&p)?;
-            cmd.arg("--params").arg(params_str);
+            args.push("--params".to_string());
+            args.push(serde_json::to_string(&p)?);
         }
 
-        let output = cmd
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .await?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!(
-                "RPC call failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
-        }
+        let invocation = Invocation::new(&args).with_session(self.session_token.as_deref());
+        let stdout = self.transport.invoke(invocation).await?.into_stdout("RPC call")?;
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        
-        if let Ok(response) = serde_json::from_str(&stdout) {
-            return Ok(response);
-        }
-
-        for line in stdout.lines() {
-            if let Ok(response) = serde_json::from_str(line) {
-                return Ok(response);
-            }
-        }
+        let what = format!("rpc {operation}");
+        Ok(envelope::decode_response(&what, &stdout, self.transport.decode_mode())?)
+    }
 
-        Err(anyhow::anyhow!("failed to parse RPC response"))
+    /// Sends a typed request and returns the response with its typed `data`.
+    pub async fn send<R: RpcRequest>(&self, request: &R) -> anyhow::Result<RpcResponse<R::Data>> {
+        let params = match serde_json::to_value(request)? {
+            Value::Null => None,
+            Value::Object(map) if map.is_empty() => None,
+            params => Some(params),
+        };
+        self.call(R::OPERATION, params).await.map(|r| r.into())
     }
 
-    pub async fn agent_init(&self, agent_id: &str) -> anyhow::Result<RpcResponse> {
-        self.call("agent.init", Some(json!({ "agent_id": agent_id })))
-            .await
-            .map(|r| r.into())
+    pub async fn agent_init(&self, agent_id: &str) -> anyhow::Result<RpcResponse<AgentInitData>> {
+        self.send(&AgentInitRequest { agent_id: agent_id.to_string() }).await
     }
 
-    pub async fn context_resolve(&self, scopes: Vec<&str>) -> anyhow::Result<RpcResponse> {
-        self.call("context.resolve", Some(json!({ "scopes": scopes })))
-            .await
-            .map(|r| r.into())
+    pub async fn context_resolve(&self, scopes: Vec<&str>) -> anyhow::Result<RpcResponse<ContextCapsule>> {
+        self.send(&ContextResolveRequest {
+            scopes: scopes.into_iter().map(String::from).collect(),
+        })
+        .await
     }
 
-    pub async fn context_scope(&self, query: &str, limit: Option<usize>) -> anyhow::Result<RpcResponse> {
-        let mut params = json!({ "query": query });
-        if let Some(l) = limit {
-            params["limit"] = json!(l);
-        }
-        self.call("context.scope", Some(params))
-            .await
-            .map(|r| r.into())
+    pub async fn context_scope(&self, query: &str, limit: Option<usize>) -> anyhow::Result<RpcResponse<ContextCapsule>> {
+        self.send(&ContextScopeRequest { query: query.to_string(), limit }).await
     }
 
-    pub async fn store_upsert(&self, entity_type: &str, key: &str, value: Value) -> anyhow::Result<RpcResponse> {
-        self.call("store.upsert", Some(json!({
-            "entity_type": entity_type,
-            "key": key,
-            "value": value
-        })))
+    pub async fn store_upsert(&self, entity_type: &str, key: &str, value: Value) -> anyhow::Result<RpcResponse<StoreEntry>> {
+        self.send(&StoreUpsertRequest {
+            entity_type: entity_type.to_string(),
+            key: key.to_string(),
+            value,
+        })
         .await
-        .map(|r| r.into())
     }
 
-    pub async fn store_query(&self, entity_type: &str, query: Value) -> anyhow::Result<RpcResponse> {
-        self.call("store.query", Some(json!({
-            "entity_type": entity_type,
-            "query": query
-        })))
+    pub async fn store_query(&self, entity_type: &str, query: Value) -> anyhow::Result<RpcResponse<Vec<StoreEntry>>> {
+        self.send(&StoreQueryRequest {
+            entity_type: entity_type.to_string(),
+            query,
+        })
         .await
-        .map(|r| r.into())
     }
 
-    pub async fn validate_run(&self) -> anyhow::Result<RpcResponse> {
-        self.call("validate.run", None)
-            .await
-            .map(|r| r.into())
+    pub async fn validate_run(&self) -> anyhow::Result<RpcResponse<ValidationResult>> {
+        self.send(&ValidateRunRequest).await
     }
 
-    pub async fn workspace_ensure(&self) -> anyhow::Result<RpcResponse> {
-        self.call("workspace.ensure", None)
-            .await
-            .map(|r| r.into())
+    pub async fn workspace_ensure(&self) -> anyhow::Result<RpcResponse<Workspace>> {
+        self.send(&WorkspaceEnsureRequest).await
     }
 
-    pub async fn workspace_status(&self) -> anyhow::Result<RpcResponse> {
-        self.call("workspace.status", None)
-            .await
-            .map(|r| r.into())
+    pub async fn workspace_status(&self) -> anyhow::Result<RpcResponse<WorkspaceStatusResponse>> {
+        self.send(&WorkspaceStatusRequest).await
     }
 
-    pub async fn todo_add(&self, content: &str, priority: Option<&str>) -> anyhow::Result<RpcResponse> {
-        let mut params = json!({ "content": content });
-        if let Some(p) = priority {
-            params["priority"] = json!(p);
-        }
-        self.call("todo.add", Some(params))
-            .await
-            .map(|r| r.into())
+    pub async fn todo_add(&self, content: &str, priority: Option<&str>) -> anyhow::Result<RpcResponse<Task>> {
+        self.send(&TodoAddRequest {
+            content: content.to_string(),
+            priority: priority.map(String::from),
+        })
+        .await
     }
 
-    pub async fn todo_claim(&self, task_id: &str) -> anyhow::Result<RpcResponse> {
-        self.call("todo.claim", Some(json!({ "task_id": task_id })))
-            .await
-            .map(|r| r.into())
+    pub async fn todo_claim(&self, task_id: &str) -> anyhow::Result<RpcResponse<Task>> {
+        self.send(&TodoClaimRequest { task_id: task_id.to_string() }).await
     }
 
-    pub async fn todo_list(&self, status: Option<&str>) -> anyhow::Result<RpcResponse> {
-        let params = status.map(|s| json!({ "status": s }));
-        self.call("todo.list", params)
-            .await
-            .map(|r| r.into())
+    pub async fn todo_list(&self, status: Option<&str>) -> anyhow::Result<RpcResponse<Vec<Task>>> {
+        self.send(&TodoListRequest { status: status.map(String::from) }).await
     }
 
-    pub async fn workunit_init(&self, task_id: &str, intent_ref: &str) -> anyhow::Result<RpcResponse> {
-        self.call("workunit.init", Some(json!({
-            "task_id": task_id,
-            "intent_ref": intent_ref
-        })))
+    pub async fn workunit_init(&self, task_id: &str, intent_ref: &str) -> anyhow::Result<RpcResponse<WorkUnit>> {
+        self.send(&WorkUnitInitRequest {
+            task_id: task_id.to_string(),
+            intent_ref: intent_ref.to_string(),
+        })
         .await
-        .map(|r| r.into())
     }
 
-    pub async fn capsule_query(&self, topic: &str, scope: &str, task_id: Option<&str>) -> anyhow::Result<RpcResponse> {
-        let mut params = json!({ "topic": topic, "scope": scope });
-        if let Some(t) = task_id {
-            params["task_id"] = json!(t);
-        }
-        self.call("capsule.query", Some(params))
-            .await
-            .map(|r| r.into())
+    pub async fn capsule_query(&self, topic: &str, scope: &str, task_id: Option<&str>) -> anyhow::Result<RpcResponse<ContextCapsule>> {
+        self.send(&CapsuleQueryRequest {
+            topic: topic.to_string(),
+            scope: scope.to_string(),
+            task_id: task_id.map(String::from),
+        })
+        .await
     }
 }
 
@@ -180,8 +152,168 @@ impl Default for RpcClient {
     }
 }
 
+/// A known Decapod RPC operation: its parameters, and the payload Decapod
+/// returns for it in `data`.
+pub trait RpcRequest: Serialize {
+    const OPERATION: &'static str;
+    type Data: DeserializeOwned;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentInitRequest {
+    pub agent_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentInitData {
+    pub agent_id: String,
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+impl RpcRequest for AgentInitRequest {
+    const OPERATION: &'static str = "agent.init";
+    type Data = AgentInitData;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextResolveRequest {
+    pub scopes: Vec<String>,
+}
+
+impl RpcRequest for ContextResolveRequest {
+    const OPERATION: &'static str = "context.resolve";
+    type Data = ContextCapsule;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextScopeRequest {
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+impl RpcRequest for ContextScopeRequest {
+    const OPERATION: &'static str = "context.scope";
+    type Data = ContextCapsule;
+}
+
+/// A record in Decapod's store.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoreEntry {
+    pub entity_type: String,
+    pub key: String,
+    pub value: Value,
+    #[serde(default)]
+    pub revision: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreUpsertRequest {
+    pub entity_type: String,
+    pub key: String,
+    pub value: Value,
+}
+
+impl RpcRequest for StoreUpsertRequest {
+    const OPERATION: &'static str = "store.upsert";
+    type Data = StoreEntry;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreQueryRequest {
+    pub entity_type: String,
+    pub query: Value,
+}
+
+impl RpcRequest for StoreQueryRequest {
+    const OPERATION: &'static str = "store.query";
+    type Data = Vec<StoreEntry>;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidateRunRequest;
+
+impl RpcRequest for ValidateRunRequest {
+    const OPERATION: &'static str = "validate.run";
+    type Data = ValidationResult;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceEnsureRequest;
+
+impl RpcRequest for WorkspaceEnsureRequest {
+    const OPERATION: &'static str = "workspace.ensure";
+    type Data = Workspace;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceStatusRequest;
+
+impl RpcRequest for WorkspaceStatusRequest {
+    const OPERATION: &'static str = "workspace.status";
+    type Data = WorkspaceStatusResponse;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TodoAddRequest {
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+}
+
+impl RpcRequest for TodoAddRequest {
+    const OPERATION: &'static str = "todo.add";
+    type Data = Task;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TodoClaimRequest {
+    pub task_id: String,
+}
+
+impl RpcRequest for TodoClaimRequest {
+    const OPERATION: &'static str = "todo.claim";
+    type Data = Task;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TodoListRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+impl RpcRequest for TodoListRequest {
+    const OPERATION: &'static str = "todo.list";
+    type Data = Vec<Task>;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkUnitInitRequest {
+    pub task_id: String,
+    pub intent_ref: String,
+}
+
+impl RpcRequest for WorkUnitInitRequest {
+    const OPERATION: &'static str = "workunit.init";
+    type Data = WorkUnit;
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CapsuleQueryRequest {
+    pub topic: String,
+    pub scope: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+}
+
+impl RpcRequest for CapsuleQueryRequest {
+    const OPERATION: &'static str = "capsule.query";
+    type Data = ContextCapsule;
+}
+
 #[derive(Debug, Clone)]
-pub struct RpcResponse {
+pub struct RpcResponse<T = Value> {
     pub success: bool,
     pub id: Option<String>,
     pub receipt: Option<crate::decapod::cli::Receipt>,
@@ -191,11 +323,12 @@ pub struct RpcResponse {
     pub interlock: Option<Interlock>,
     pub advisory: Option<Advisory>,
     pub attestation: Option<Attestation>,
+    pub data: Option<T>,
     pub error: Option<String>,
 }
 
-impl From<DecapodResponse<Value>> for RpcResponse {
-    fn from(r: DecapodResponse<Value>) -> Self {
+impl<T> From<DecapodResponse<T>> for RpcResponse<T> {
+    fn from(r: DecapodResponse<T>) -> Self {
         Self {
             success: r.success,
             id: r.id,
@@ -206,12 +339,36 @@ impl From<DecapodResponse<Value>> for RpcResponse {
             interlock: r.interlock,
             advisory: r.advisory,
             attestation: r.attestation,
+            data: r.data,
             error: r.error,
         }
     }
 }
 
-impl RpcResponse {
+impl<T> RpcResponse<T> {
+    pub fn data(&self) -> Option<&T> {
+        self.data.as_ref()
+    }
+
+    /// The payload of a successful response.  A failed or blocked response,
+    /// or one without a payload, is an error.
+    pub fn into_data(self) -> anyhow::Result<T> {
+        if !self.success {
+            if let Some(error) = self.error {
+                return Err(anyhow::anyhow!("RPC call failed: {}", error));
+            }
+            let policies: Vec<&str> = self
+                .interlock
+                .iter()
+                .chain(&self.blocked_by)
+                .map(|i| i.policy.as_str())
+                .collect();
+            return Err(anyhow::anyhow!("RPC call blocked by {}", policies.join(", ")));
+        }
+        self.data
+            .ok_or_else(|| anyhow::anyhow!("RPC call returned no data"))
+    }
+
     pub fn is_blocked(&self) -> bool {
         !self.blocked_by.is_empty() || self.interlock.as_ref().map(|i| i.blocking).unwrap_or(false)
     }
@@ -234,3 +391,61 @@ impl RpcResponse {
         self.attestation.as_ref().map(|a| a.passed).unwrap_or(false)
     }
 }
+
+/// The governance view of a response, so it converts into the governed-run
+/// decisions like any [`GovernanceResponse`].  An interlock listed only in
+/// `blocked_by` blocks, as in [`RpcResponse::is_blocked`]; an attestation on
+/// a failed response does not pass.  RPC interlocks name no approver scope
+/// or escalation path, and RPC advisories no category, so an advisory is
+/// referenced by the interlock's policy and a digest of its message, or by
+/// the digest alone.
+impl<T> From<&RpcResponse<T>> for GovernanceResponse {
+    fn from(response: &RpcResponse<T>) -> Self {
+        let interlock = match &response.interlock {
+            Some(interlock) if interlock.blocking => Some(interlock.clone()),
+            interlock => response
+                .blocked_by
+                .first()
+                .map(|blocking| Interlock { blocking: true, ..blocking.clone() })
+                .or_else(|| interlock.clone()),
+        };
+        let related_policies: Vec<String> =
+            interlock.iter().map(|interlock| interlock.policy.clone()).collect();
+        Self {
+            interlock: interlock.map(|interlock| governance::Interlock {
+                policy: interlock.policy,
+                reason: interlock.reason,
+                blocking: interlock.blocking,
+                required_approval: interlock.required_approval,
+                approver_scope: Vec::new(),
+                escalation_path: None,
+            }),
+            advisories: response
+                .advisory
+                .iter()
+                .map(|advisory| governance::Advisory {
+                    message: advisory.message.clone(),
+                    suggestions: advisory.suggestions.clone(),
+                    priority: match advisory.priority.to_ascii_lowercase().as_str() {
+                        "critical" => AdvisoryPriority::Critical,
+                        "warning" => AdvisoryPriority::Warning,
+                        _ => AdvisoryPriority::Info,
+                    },
+                    category: None,
+                    related_policies: related_policies.clone(),
+                })
+                .collect(),
+            attestation: response.attestation.as_ref().map(|attestation| governance::Attestation {
+                proof_id: attestation.proof_id.clone(),
+                criteria: attestation.criteria.clone(),
+                passed: attestation.passed && response.success,
+                evidence: attestation.evidence.clone(),
+                verified_by: None,
+                verified_at: None,
+                expiration: None,
+            }),
+            allowed_next_ops: response.allowed_next_ops.clone(),
+            blocked_by: response.blocked_by.iter().map(|interlock| interlock.reason.clone()).collect(),
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Where the Decapod session password comes from, and how it is held.
+//!
+//! A [`SecretSource`] yields the password as a [`Secret`], whose memory is
+//! zeroized when it is dropped and which never prints its value.  Pincher
+//! ships four sources:
+//!
+//! - [`EnvSecret`] reads an environment variable, [`PASSWORD_ENV`] by default;
+//! - [`FileSecret`] reads a file only its owner can read;
+//! - [`StdinSecret`] reads the first line of standard input;
+//! - [`FdSecret`] reads an inherited file descriptor, typically a pipe.
+//!
+//! The password reaches Decapod over the stdin of `session acquire` when the
+//! transport can pipe it; see [`super::session::Session::acquire_with`].
+
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use std::path::PathBuf;
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// Environment variable [`EnvSecret::default`] reads.
+pub const PASSWORD_ENV: &str = super::transport::SESSION_ENV;
+
+/// A string wiped from memory when dropped.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(Zeroizing<String>);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(Zeroizing::new(value.into()))
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(..)")
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("cannot read secret from {origin}: {source}")]
+    Unreadable {
+        origin: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("secret from {origin} is not UTF-8")]
+    NotUtf8 { origin: String },
+    #[error("secret file {} can be read by other users", path.display())]
+    Exposed { path: PathBuf },
+}
+
+pub trait SecretSource: fmt::Debug + Send + Sync {
+    /// The secret, or `None` when the source holds none.
+    fn read(&self) -> Result<Option<Secret>, SecretError>;
+}
+
+/// Reads an environment variable; empty counts as unset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvSecret {
+    var: String,
+}
+
+impl EnvSecret {
+    pub fn new(var: impl Into<String>) -> Self {
+        Self { var: var.into() }
+    }
+}
+
+impl Default for EnvSecret {
+    fn default() -> Self {
+        Self::new(PASSWORD_ENV)
+    }
+}
+
+impl SecretSource for EnvSecret {
+    fn read(&self) -> Result<Option<Secret>, SecretError> {
+        match std::env::var_os(&self.var) {
+            Some(value) if value.is_empty() => Ok(None),
+            Some(value) => value
+                .into_string()
+                .map(|value| Some(Secret::new(value)))
+                .map_err(|_| SecretError::NotUtf8 {
+                    origin: format!("${}", self.var),
+                }),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Reads a file, ignoring a trailing newline.  On Unix the file must not be
+/// readable or writable by group or others.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSecret {
+    path: PathBuf,
+}
+
+impl FileSecret {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl SecretSource for FileSecret {
+    fn read(&self) -> Result<Option<Secret>, SecretError> {
+        let origin = || self.path.display().to_string();
+        let file = std::fs::File::open(&self.path).map_err(|source| SecretError::Unreadable {
+            origin: origin(),
+            source,
+        })?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = file.metadata().map_err(|source| SecretError::Unreadable {
+                origin: origin(),
+                source,
+            })?;
+            if metadata.permissions().mode() & 0o077 != 0 {
+                return Err(SecretError::Exposed {
+                    path: self.path.clone(),
+                });
+            }
+        }
+        read_all(file, &origin())
+    }
+}
+
+/// Reads the first line of standard input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StdinSecret;
+
+impl SecretSource for StdinSecret {
+    fn read(&self) -> Result<Option<Secret>, SecretError> {
+        let mut line = Zeroizing::new(String::new());
+        io::stdin()
+            .lock()
+            .read_line(&mut line)
+            .map_err(|source| SecretError::Unreadable {
+                origin: "stdin".to_string(),
+                source,
+            })?;
+        Ok(trimmed(line))
+    }
+}
+
+/// Reads an inherited file descriptor to its end, such as a pipe the parent
+/// opened with `3<<<"$PASSWORD"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FdSecret {
+    fd: u32,
+}
+
+impl FdSecret {
+    pub fn new(fd: u32) -> Self {
+        Self { fd }
+    }
+}
+
+impl SecretSource for FdSecret {
+    fn read(&self) -> Result<Option<Secret>, SecretError> {
+        let origin = format!("file descriptor {}", self.fd);
+        // Opening the descriptor by path keeps this free of unsafe code.
+        let file = std::fs::File::open(format!("/dev/fd/{}", self.fd)).map_err(|source| {
+            SecretError::Unreadable {
+                origin: origin.clone(),
+                source,
+            }
+        })?;
+        read_all(file, &origin)
+    }
+}
+
+fn read_all(mut reader: impl Read, origin: &str) -> Result<Option<Secret>, SecretError> {
+    let mut bytes = Zeroizing::new(Vec::new());
+    reader
+        .read_to_end(&mut bytes)
+        .map_err(|source| SecretError::Unreadable {
+            origin: origin.to_string(),
+            source,
+        })?;
+    let text = std::str::from_utf8(&bytes).map_err(|_| SecretError::NotUtf8 {
+        origin: origin.to_string(),
+    })?;
+    Ok(trimmed(Zeroizing::new(text.to_string())))
+}
+
+/// `text` without its line ending, or `None` when nothing else is left.
+fn trimmed(mut text: Zeroizing<String>) -> Option<Secret> {
+    let len = text.trim_end_matches(['\r', '\n']).len();
+    text.truncate(len);
+    (!text.is_empty()).then(|| Secret(text))
+}
@@ -1,7 +1,12 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
-use std::process::Stdio;
-use tokio::process::Command;
+use std::time::Duration;
+use thiserror::Error;
+use crate::governed_run::{CustodyFailure, DecapodPortError, SessionRef};
+use super::discovery::PASSWORD_STDIN_FLAG;
+use super::envelope;
+use super::secret::{EnvSecret, Secret, SecretError, SecretSource};
+use super::transport::{default_transport, DecapodTransport, Invocation, SESSION_ENV};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
@@ -13,46 +18,37 @@ pub struct Session {
 
 impl Session {
     pub async fn acquire(password: &str) -> anyhow::Result<Self> {
-        let output = Command::new("decapod")
-            .args(["session", "acquire"])
-            .env("DECAPOD_SESSION_PASSWORD", password)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .await?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!(
-                "session acquire failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
-        }
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        
-        if let Ok(session) = serde_json::from_str::<Session>(&output_str) {
-            return Ok(session);
-        }
-
-        for line in output_str.lines() {
-            if let Ok(session) = serde_json::from_str::<Session>(line) {
-                return Ok(session);
-            }
-        }
+        Self::acquire_with(default_transport().as_ref(), &Secret::new(password)).await
+    }
 
-        Err(anyhow::anyhow!("failed to parse session response"))
+    /// Pipes the password when the transport accepts input, so it never
+    /// appears in the child's environment; otherwise sets [`SESSION_ENV`].
+    pub async fn acquire_with(
+        transport: &dyn DecapodTransport,
+        password: &Secret,
+    ) -> anyhow::Result<Self> {
+        let invocation = if transport.accepts_input() {
+            Invocation::new(&["session", "acquire", PASSWORD_STDIN_FLAG])
+                .with_input(password.clone())
+        } else {
+            Invocation::new(&["session", "acquire"]).with_env(SESSION_ENV, password.clone())
+        };
+        let output_str = transport
+            .invoke(invocation)
+            .await?
+            .into_stdout("session acquire")?;
+
+        Ok(envelope::decode("session acquire", &output_str, transport.decode_mode())?)
     }
 
     pub async fn validate(&self) -> anyhow::Result<bool> {
-        let output = Command::new("decapod")
-            .args(["session", "validate"])
-            .env("DECAPOD_SESSION_PASSWORD", &self.token)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .await?;
+        self.validate_with(default_transport().as_ref()).await
+    }
 
-        Ok(output.status.success())
+    pub async fn validate_with(&self, transport: &dyn DecapodTransport) -> anyhow::Result<bool> {
+        let invocation =
+            Invocation::new(&["session", "validate"]).with_env(SESSION_ENV, self.token.as_str());
+        Ok(transport.invoke(invocation).await?.success())
     }
 
     pub fn token(&self) -> &str {
@@ -64,18 +60,58 @@ impl Session {
     }
 
     pub fn is_expired(&self) -> bool {
-        if let Some(expires_at) = &self.expires_at
-            && let Ok(expires) = chrono::DateTime::parse_from_rfc3339(expires_at)
-        {
-            return expires < chrono::Utc::now();
+        self.expires_within(Duration::ZERO)
+    }
+
+    /// Whether the session expires in less than `margin`.  A session without
+    /// a readable expiry never does.
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        match self.expires() {
+            Some(expires) => expires - margin < chrono::Utc::now(),
+            None => false,
+        }
+    }
+
+    pub fn expires(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        parse_time(self.expires_at.as_deref()?)
+    }
+
+    pub fn created(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        parse_time(&self.created_at)
+    }
+}
+
+fn parse_time(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.with_timezone(&chrono::Utc))
+}
+
+/// Decapod refused a command because the session it carried has expired.
+///
+/// Manager errors carry this inside their [`anyhow::Error`]; find it with
+/// `error.downcast_ref::<SessionExpired>()`.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{command} failed: decapod session expired")]
+pub struct SessionExpired {
+    pub command: String,
+}
+
+impl SessionExpired {
+    /// How a control plane reports the expiry of `session` to the engine,
+    /// which fails the run on custody.
+    pub fn to_port_error(&self, session: SessionRef) -> DecapodPortError {
+        DecapodPortError::CustodyRejected {
+            reason: CustodyFailure::SessionExpired { session },
         }
-        false
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionConfig {
-    pub password: Option<String>,
+    /// Never serialized.
+    #[serde(skip)]
+    pub password: Option<Secret>,
     pub ttl_secs: Option<u64>,
     pub persist_path: Option<PathBuf>,
 }
@@ -83,7 +119,7 @@ pub struct SessionConfig {
 impl SessionConfig {
     pub fn new() -> Self {
         Self {
-            password: std::env::var("DECAPOD_SESSION_PASSWORD").ok(),
+            password: get_session_password(),
             ttl_secs: std::env::var("DECAPOD_SESSION_TTL_SECS")
                 .ok()
                 .and_then(|s| s.parse().ok()),
@@ -91,23 +127,48 @@ impl SessionConfig {
         }
     }
 
-    pub fn with_password(mut self, password: impl Into<String>) -> Self {
+    pub fn with_password(mut self, password: impl Into<Secret>) -> Self {
         self.password = Some(password.into());
         self
     }
 
+    /// Reads the password from `source`, keeping the current one when the
+    /// source holds none.
+    pub fn with_password_from(mut self, source: &dyn SecretSource) -> Result<Self, SecretError> {
+        if let Some(password) = source.read()? {
+            self.password = Some(password);
+        }
+        Ok(self)
+    }
+
     pub fn with_ttl(mut self, ttl_secs: u64) -> Self {
         self.ttl_secs = Some(ttl_secs);
         self
     }
 
-    pub fn password(&self) -> Option<&str> {
-        self.password.as_deref()
+    /// Persists the session at `path`; see [`super::lifecycle::SessionManager`].
+    pub fn with_persist_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.persist_path = Some(path.into());
+        self
+    }
+
+    /// Keeps the session in memory only.
+    pub fn without_persistence(mut self) -> Self {
+        self.persist_path = None;
+        self
+    }
+
+    pub fn password(&self) -> Option<&Secret> {
+        self.password.as_ref()
     }
 
     pub fn ttl_secs(&self) -> Option<u64> {
         self.ttl_secs
     }
+
+    pub fn persist_path(&self) -> Option<&std::path::Path> {
+        self.persist_path.as_deref()
+    }
 }
 
 impl Default for SessionConfig {
@@ -116,7 +177,10 @@ impl Default for SessionConfig {
     }
 }
 
-#[allow(clippy::disallowed_methods)]
-pub fn get_session_password() -> Option<String> {
-    std::env::var("DECAPOD_SESSION_PASSWORD").ok()
+/// The password in [`super::secret::PASSWORD_ENV`], if set.
+pub fn get_session_password() -> Option<Secret> {
+    EnvSecret::default().read().unwrap_or_else(|error| {
+        tracing::warn!("ignoring session password: {error}");
+        None
+    })
 }
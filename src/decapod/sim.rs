@@ -0,0 +1,1730 @@
+//! An offline stand-in for Decapod.
+//!
+//! [`Simulator`] answers the subset of the Decapod CLI and RPC surface that
+//! Pincher's managers use — `session`, `todo`, `workunit`, `workspace`,
+//! `validate`, `docs`, `capabilities`, `data schema` and `rpc` — from a JSON
+//! state file, so hosts and tests can run the whole custody loop without a
+//! Decapod install.  The `decapod-sim` binary wraps it with the same command
+//! line as `decapod`, including the `rpc --stdio` server used by
+//! [`super::stdio::StdioTransport`].
+//!
+//! The state file also holds a [`Scenario`]: interlocks to raise on chosen
+//! operations, the outcome of approval requests, validation failures to
+//! report, and session rules.  Scenarios are scripted either by writing the
+//! state with [`Simulator::set_scenario`] or with the `decapod-sim sim ...`
+//! commands:
+//!
+//! ```text
+//! decapod-sim sim interlock --op todo.claim --policy freeze --reason "release freeze"
+//! decapod-sim sim approval denied
+//! decapod-sim sim fail-validation --check fmt --message "run cargo fmt"
+//! ```
+//!
+//! The state lives in `.decapod/sim-state.json` under the working directory,
+//! or wherever [`STATE_ENV`] points.  Every command reads and rewrites it
+//! under a lock file, so concurrent commands see each other's changes.
+
+use super::capabilities::{
+    Capabilities, CommandCapability, EntitySchema, FieldSchema, RpcOperation, SchemaInfo,
+};
+use super::cli::{Advisory, ContextCapsule, ContextFragment, DecapodResponse, Interlock, Receipt};
+use super::discovery::PASSWORD_STDIN_FLAG;
+use super::docs::{DocEntry, DocFragment, DocSearchResult, IngestResult};
+use super::rpc::{AgentInitData, StoreEntry};
+use super::secret::Secret;
+use super::session::Session;
+use super::todo::{Task, TaskStatus};
+use super::transport::{
+    DecapodTransport, Invocation, InvokeFuture, SESSION_ENV, SESSION_EXIT_STATUS, TransportOutput,
+};
+use super::validate::{ValidationDetail, ValidationError, ValidationResult};
+use super::workspace::{Workspace, WorkspaceStatus, WorkspaceStatusResponse};
+use super::workunit::{Approval, Patch, Proof, WorkUnit, WorkUnitState, WorkUnitStatus};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Environment variable overriding where the simulator keeps its state.
+pub const STATE_ENV: &str = "DECAPOD_SIM_STATE";
+
+/// State file location relative to the project root.
+pub const DEFAULT_STATE: &str = ".decapod/sim-state.json";
+
+/// The Decapod version the simulator reports unless a scenario says otherwise.
+pub const SIM_VERSION: &str = "0.1.0";
+
+/// Default session lifetime.
+pub const DEFAULT_SESSION_TTL_SECS: i64 = 3600;
+
+/// How long a command waits for another to release the state file before
+/// treating its lock as stale.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+const RPC_OPERATIONS: [&str; 13] = [
+    "agent.init",
+    "capsule.query",
+    "context.resolve",
+    "context.scope",
+    "store.query",
+    "store.upsert",
+    "todo.add",
+    "todo.claim",
+    "todo.list",
+    "validate.run",
+    "workspace.ensure",
+    "workspace.status",
+    "workunit.init",
+];
+
+const COMMANDS: [(&str, &[&str]); 8] = [
+    ("session", &["acquire", "validate"]),
+    (
+        "todo",
+        &[
+            "add", "claim", "release", "complete", "list", "get", "handoff", "update", "archive",
+            "blocks",
+        ],
+    ),
+    (
+        "workunit",
+        &[
+            "init", "get", "list", "update", "patch", "approve", "proof", "complete", "fail",
+        ],
+    ),
+    (
+        "workspace",
+        &[
+            "ensure", "status", "list", "enter", "suspend", "archive", "delete", "path",
+        ],
+    ),
+    ("validate", &[]),
+    ("docs", &["ingest", "show", "search", "list"]),
+    ("capabilities", &[]),
+    ("rpc", &[]),
+];
+
+/// How the simulator answers `workunit approve`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalOutcome {
+    /// The approval is recorded on the work unit.
+    #[default]
+    Granted,
+    /// The request is refused and the command fails.
+    Denied,
+    /// The request is accepted but no approval is recorded yet.
+    Pending,
+}
+
+/// An interlock raised on every operation matching `operation`.
+///
+/// `operation` is an RPC operation name such as `todo.claim`; the CLI command
+/// `todo claim` matches it too.  `todo.*` matches a whole group and `*`
+/// matches everything.  An interlock naming a `required_approval` is lifted
+/// once a work unit holds an approval with that scope.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptedInterlock {
+    pub operation: String,
+    #[serde(flatten)]
+    pub interlock: Interlock,
+    /// How many more times the interlock fires; `None` for always.
+    #[serde(default)]
+    pub times: Option<u32>,
+}
+
+/// An advisory attached to RPC responses for matching operations.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptedAdvisory {
+    pub operation: String,
+    #[serde(flatten)]
+    pub advisory: Advisory,
+}
+
+/// A validation failure reported by `validate`, for one gate or all of them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptedFailure {
+    #[serde(default)]
+    pub gate: Option<String>,
+    #[serde(flatten)]
+    pub error: ValidationError,
+}
+
+/// What the simulator should do differently from a healthy Decapod.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    /// Password `session acquire` accepts; any password when unset.
+    pub password: Option<String>,
+    pub session_ttl_secs: Option<i64>,
+    /// Refuse governed commands without a live session token.
+    pub require_session: bool,
+    /// Neither advertise nor accept `session acquire --password-stdin`, like
+    /// a Decapod that predates it.
+    pub without_password_stdin: bool,
+    /// Version reported by `capabilities`.
+    pub version: Option<String>,
+    /// RPC operations to advertise and serve; all of them when unset.
+    pub rpc_operations: Option<Vec<String>>,
+    pub interlocks: Vec<ScriptedInterlock>,
+    pub advisories: Vec<ScriptedAdvisory>,
+    pub approval: ApprovalOutcome,
+    pub validation_failures: Vec<ScriptedFailure>,
+}
+
+impl Scenario {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    pub fn with_session_ttl(mut self, secs: i64) -> Self {
+        self.session_ttl_secs = Some(secs);
+        self
+    }
+
+    pub fn requiring_session(mut self) -> Self {
+        self.require_session = true;
+        self
+    }
+
+    pub fn without_password_stdin(mut self) -> Self {
+        self.without_password_stdin = true;
+        self
+    }
+
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    pub fn with_rpc_operations<S: AsRef<str>>(mut self, operations: &[S]) -> Self {
+        self.rpc_operations = Some(
+            operations
+                .iter()
+                .map(|operation| operation.as_ref().to_string())
+                .collect(),
+        );
+        self
+    }
+
+    pub fn with_interlock(mut self, operation: impl Into<String>, interlock: Interlock) -> Self {
+        self.interlocks.push(ScriptedInterlock {
+            operation: operation.into(),
+            interlock,
+            times: None,
+        });
+        self
+    }
+
+    /// Raises `interlock` on the next `times` matching operations only.
+    pub fn with_interlock_times(
+        mut self,
+        operation: impl Into<String>,
+        interlock: Interlock,
+        times: u32,
+    ) -> Self {
+        self.interlocks.push(ScriptedInterlock {
+            operation: operation.into(),
+            interlock,
+            times: Some(times),
+        });
+        self
+    }
+
+    pub fn with_advisory(mut self, operation: impl Into<String>, advisory: Advisory) -> Self {
+        self.advisories.push(ScriptedAdvisory {
+            operation: operation.into(),
+            advisory,
+        });
+        self
+    }
+
+    pub fn with_approval(mut self, outcome: ApprovalOutcome) -> Self {
+        self.approval = outcome;
+        self
+    }
+
+    /// Fails `validate` with `error`; for `gate` only when one is given.
+    pub fn with_validation_failure(mut self, gate: Option<&str>, error: ValidationError) -> Self {
+        self.validation_failures.push(ScriptedFailure {
+            gate: gate.map(String::from),
+            error,
+        });
+        self
+    }
+
+    fn version(&self) -> &str {
+        self.version.as_deref().unwrap_or(SIM_VERSION)
+    }
+
+    fn serves(&self, operation: &str) -> bool {
+        match &self.rpc_operations {
+            Some(operations) => operations.iter().any(|served| served == operation),
+            None => RPC_OPERATIONS.contains(&operation),
+        }
+    }
+}
+
+/// A document known to `docs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimDoc {
+    pub path: String,
+    pub title: Option<String>,
+    pub content: String,
+}
+
+impl SimDoc {
+    pub fn new(path: impl Into<String>, content: impl Into<String>) -> Self {
+        let content = content.into();
+        let title = content
+            .lines()
+            .find_map(|line| line.strip_prefix("# "))
+            .map(|title| title.trim().to_string());
+        Self {
+            path: path.into(),
+            title,
+            content,
+        }
+    }
+}
+
+/// Everything the simulator remembers between commands.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimState {
+    pub scenario: Scenario,
+    pub sessions: Vec<Session>,
+    pub tasks: Vec<Task>,
+    pub work_units: Vec<WorkUnit>,
+    pub workspaces: Vec<Workspace>,
+    pub current_workspace: Option<String>,
+    pub docs: Vec<SimDoc>,
+    pub store: Vec<StoreEntry>,
+    pub next_id: u64,
+}
+
+#[derive(Debug, Error)]
+pub enum SimError {
+    #[error("usage: {0}")]
+    Usage(String),
+    #[error("{0}")]
+    Rejected(String),
+    #[error("blocked by policy {}: {}", .0.policy, .0.reason)]
+    Blocked(Interlock),
+    #[error("session {0}")]
+    Session(String),
+    #[error("state file {}: {source}", path.display())]
+    State {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("state file {} is not valid: {source}", path.display())]
+    Corrupt {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+impl SimError {
+    /// The exit status `decapod-sim` reports for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Rejected(_) => 1,
+            Self::Usage(_) => 2,
+            Self::Blocked(_) => 3,
+            Self::Session(_) => SESSION_EXIT_STATUS,
+            Self::State { .. } | Self::Corrupt { .. } => 70,
+        }
+    }
+}
+
+fn rejected(message: impl Into<String>) -> SimError {
+    SimError::Rejected(message.into())
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).expect("simulator state serializes")
+}
+
+/// The name `value` serializes to, for matching status filters.
+fn name_of(value: &impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+fn operation_matches(pattern: &str, operation: &str) -> bool {
+    pattern == "*"
+        || pattern == operation
+        || pattern.strip_suffix(".*").is_some_and(|group| {
+            operation
+                .strip_prefix(group)
+                .is_some_and(|rest| rest.starts_with('.'))
+        })
+}
+
+/// Command-line arguments split into words and `--flag [value]` pairs.
+struct Args {
+    words: Vec<String>,
+    flags: Vec<(String, Option<String>)>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Self {
+        let mut words = Vec::new();
+        let mut flags = Vec::new();
+        let mut args = args.iter().peekable();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) => {
+                    let value = args.next_if(|next| !next.starts_with("--")).cloned();
+                    flags.push((name.to_string(), value));
+                }
+                None => words.push(arg.clone()),
+            }
+        }
+        Self { words, flags }
+    }
+
+    fn word(&self, index: usize) -> Option<&str> {
+        self.words.get(index).map(String::as_str)
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.flags.iter().any(|(flag, _)| flag == name)
+    }
+
+    fn flag(&self, name: &str) -> Option<&str> {
+        self.flags
+            .iter()
+            .rev()
+            .find(|(flag, _)| flag == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    fn flags(&self, name: &str) -> Vec<&str> {
+        self.flags
+            .iter()
+            .filter(|(flag, _)| flag == name)
+            .filter_map(|(_, value)| value.as_deref())
+            .collect()
+    }
+
+    fn require(&self, name: &str) -> Result<&str, SimError> {
+        self.flag(name)
+            .ok_or_else(|| SimError::Usage(format!("missing --{name}")))
+    }
+
+    fn require_word(&self, index: usize, what: &str) -> Result<&str, SimError> {
+        self.word(index)
+            .ok_or_else(|| SimError::Usage(format!("missing {what}")))
+    }
+}
+
+/// What a command printed and how it exited, before it reaches a transport.
+struct Printed {
+    status: i32,
+    stdout: String,
+}
+
+impl Printed {
+    fn text(stdout: impl Into<String>) -> Self {
+        Self {
+            status: 0,
+            stdout: stdout.into(),
+        }
+    }
+
+    fn json(value: &impl Serialize) -> Self {
+        Self::text(to_json(value))
+    }
+}
+
+impl SimState {
+    fn next(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{prefix}-{}", self.next_id)
+    }
+
+    fn live_session(&self, token: Option<&str>) -> Result<Option<&Session>, SimError> {
+        let Some(token) = token else {
+            return Ok(None);
+        };
+        match self.sessions.iter().find(|session| session.token == token) {
+            Some(session) if session.is_expired() => Err(SimError::Session("expired".into())),
+            session => Ok(session),
+        }
+    }
+
+    /// The session behind `token`, checked against the scenario's rules.
+    fn authorize(&self, token: Option<&str>) -> Result<Option<String>, SimError> {
+        let session = match self.live_session(token) {
+            Ok(session) => session,
+            Err(error) if self.scenario.require_session => return Err(error),
+            Err(_) => None,
+        };
+        if self.scenario.require_session && session.is_none() {
+            return Err(SimError::Session("required".into()));
+        }
+        Ok(session.map(|session| session.session_id.clone()))
+    }
+
+    fn approved(&self, scope: &str) -> bool {
+        self.work_units
+            .iter()
+            .flat_map(|unit| &unit.state.approvals)
+            .any(|approval| approval.scope.iter().any(|granted| granted == scope))
+    }
+
+    /// Fires the interlocks scripted for `operation`.  A blocking interlock
+    /// is an error; the others are returned as warnings.
+    fn gate(&mut self, operation: &str) -> Result<Vec<Interlock>, SimError> {
+        let lifted: Vec<bool> = self
+            .scenario
+            .interlocks
+            .iter()
+            .map(|scripted| {
+                scripted
+                    .interlock
+                    .required_approval
+                    .as_deref()
+                    .is_some_and(|scope| self.approved(scope))
+            })
+            .collect();
+
+        let mut warnings = Vec::new();
+        for (scripted, lifted) in self.scenario.interlocks.iter_mut().zip(lifted) {
+            if lifted
+                || scripted.times == Some(0)
+                || !operation_matches(&scripted.operation, operation)
+            {
+                continue;
+            }
+            if let Some(times) = &mut scripted.times {
+                *times -= 1;
+            }
+            if scripted.interlock.blocking {
+                return Err(SimError::Blocked(scripted.interlock.clone()));
+            }
+            warnings.push(scripted.interlock.clone());
+        }
+        Ok(warnings)
+    }
+
+    fn advisory(&self, operation: &str) -> Option<Advisory> {
+        self.scenario
+            .advisories
+            .iter()
+            .find(|scripted| operation_matches(&scripted.operation, operation))
+            .map(|scripted| scripted.advisory.clone())
+    }
+
+    fn acquire(&mut self, password: Option<&str>) -> Result<Session, SimError> {
+        let password =
+            password.ok_or_else(|| SimError::Session(format!("{SESSION_ENV} is not set")))?;
+        if self
+            .scenario
+            .password
+            .as_deref()
+            .is_some_and(|expected| expected != password)
+        {
+            return Err(SimError::Session("password rejected".into()));
+        }
+        let created = chrono::Utc::now();
+        let ttl = self
+            .scenario
+            .session_ttl_secs
+            .unwrap_or(DEFAULT_SESSION_TTL_SECS);
+        let session = Session {
+            token: format!("sim-token-{}", ulid::Ulid::new()),
+            session_id: self.next("session"),
+            expires_at: Some((created + chrono::Duration::seconds(ttl)).to_rfc3339()),
+            created_at: created.to_rfc3339(),
+        };
+        self.sessions.push(session.clone());
+        Ok(session)
+    }
+
+    fn task(&self, id: &str) -> Result<&Task, SimError> {
+        self.tasks
+            .iter()
+            .find(|task| task.id == id)
+            .ok_or_else(|| rejected(format!("no such task {id}")))
+    }
+
+    fn task_mut(&mut self, id: &str) -> Result<&mut Task, SimError> {
+        self.tasks
+            .iter_mut()
+            .find(|task| task.id == id)
+            .ok_or_else(|| rejected(format!("no such task {id}")))
+    }
+
+    fn add_task(&mut self, content: &str, priority: Option<&str>) -> Task {
+        let stamp = now();
+        let task = Task {
+            id: self.next("task"),
+            content: content.to_string(),
+            status: TaskStatus::Pending,
+            priority: priority.map(String::from),
+            owner: None,
+            created_at: stamp.clone(),
+            updated_at: stamp,
+            claimed_at: None,
+            completed_at: None,
+            blocked_by: Some(Vec::new()),
+        };
+        self.tasks.push(task.clone());
+        task
+    }
+
+    fn claim_task(&mut self, id: &str, owner: &str) -> Result<Task, SimError> {
+        let open: Vec<String> = self
+            .task(id)?
+            .blocked_by
+            .iter()
+            .flatten()
+            .filter(|blocker| {
+                self.task(blocker)
+                    .is_ok_and(|blocker| blocker.status != TaskStatus::Completed)
+            })
+            .cloned()
+            .collect();
+        if !open.is_empty() {
+            return Err(rejected(format!(
+                "task {id} is blocked by {}",
+                open.join(", ")
+            )));
+        }
+
+        let task = self.task_mut(id)?;
+        if let Some(current) = task.owner.as_deref().filter(|current| *current != owner) {
+            return Err(rejected(format!("task {id} is claimed by {current}")));
+        }
+        if matches!(task.status, TaskStatus::Completed | TaskStatus::Cancelled) {
+            return Err(rejected(format!("task {id} is {}", name_of(&task.status))));
+        }
+        let stamp = now();
+        task.status = TaskStatus::Claimed;
+        task.owner = Some(owner.to_string());
+        task.claimed_at = Some(stamp.clone());
+        task.updated_at = stamp;
+        Ok(task.clone())
+    }
+
+    fn list_tasks(&self, status: Option<&str>, owner: Option<&str>) -> Vec<Task> {
+        self.tasks
+            .iter()
+            .filter(|task| status.is_none_or(|status| name_of(&task.status) == status))
+            .filter(|task| owner.is_none_or(|owner| task.owner.as_deref() == Some(owner)))
+            .cloned()
+            .collect()
+    }
+
+    fn work_unit_mut(&mut self, id: &str) -> Result<&mut WorkUnit, SimError> {
+        self.work_units
+            .iter_mut()
+            .find(|unit| unit.id == id)
+            .ok_or_else(|| rejected(format!("no such workunit {id}")))
+    }
+
+    fn init_work_unit(&mut self, task_id: &str, intent_ref: &str) -> Result<WorkUnit, SimError> {
+        self.task(task_id)?;
+        let stamp = now();
+        let unit = WorkUnit {
+            id: self.next("workunit"),
+            task_id: task_id.to_string(),
+            intent_ref: intent_ref.to_string(),
+            status: WorkUnitStatus::Pending,
+            state: WorkUnitState {
+                intent: intent_ref.to_string(),
+                plan: None,
+                patches: Vec::new(),
+                approvals: Vec::new(),
+            },
+            acceptance_criteria: Vec::new(),
+            constraints: Vec::new(),
+            proofs: Vec::new(),
+            created_at: stamp.clone(),
+            updated_at: stamp,
+        };
+        self.work_units.push(unit.clone());
+        Ok(unit)
+    }
+
+    fn ensure_workspace(&mut self, root: &Path, name: Option<&str>) -> Result<Workspace, SimError> {
+        let name = name.unwrap_or("default");
+        if !self
+            .workspaces
+            .iter()
+            .any(|workspace| workspace.name == name)
+        {
+            let path = root.join(".decapod").join("workspaces").join(name);
+            fs::create_dir_all(&path).map_err(|source| SimError::State {
+                path: path.clone(),
+                source,
+            })?;
+            self.workspaces.push(Workspace {
+                name: name.to_string(),
+                branch: format!("pincher/{name}"),
+                path: path.display().to_string(),
+                status: WorkspaceStatus::Active,
+                created_at: Some(now()),
+            });
+        }
+        self.enter_workspace(name)
+    }
+
+    fn workspace_mut(&mut self, name: &str) -> Result<&mut Workspace, SimError> {
+        self.workspaces
+            .iter_mut()
+            .find(|workspace| workspace.name == name)
+            .ok_or_else(|| rejected(format!("no such workspace {name}")))
+    }
+
+    fn enter_workspace(&mut self, name: &str) -> Result<Workspace, SimError> {
+        let workspace = self.workspace_mut(name)?;
+        workspace.status = WorkspaceStatus::Active;
+        let workspace = workspace.clone();
+        self.current_workspace = Some(workspace.name.clone());
+        Ok(workspace)
+    }
+
+    fn leave_workspace(
+        &mut self,
+        name: &str,
+        status: WorkspaceStatus,
+    ) -> Result<Workspace, SimError> {
+        let workspace = self.workspace_mut(name)?;
+        workspace.status = status;
+        let workspace = workspace.clone();
+        if self.current_workspace.as_deref() == Some(name) {
+            self.current_workspace = None;
+        }
+        Ok(workspace)
+    }
+
+    fn workspace_status(&self) -> WorkspaceStatusResponse {
+        WorkspaceStatusResponse {
+            current: self.current_workspace.as_deref().and_then(|current| {
+                self.workspaces
+                    .iter()
+                    .find(|workspace| workspace.name == current)
+                    .cloned()
+            }),
+            available: self
+                .workspaces
+                .iter()
+                .filter(|workspace| workspace.status != WorkspaceStatus::Archived)
+                .cloned()
+                .collect(),
+            default_branch: "main".to_string(),
+        }
+    }
+
+    fn validation(&self, gate: Option<&str>) -> ValidationResult {
+        let errors: Vec<ValidationError> = self
+            .scenario
+            .validation_failures
+            .iter()
+            .filter(|failure| {
+                gate.is_none() || failure.gate.is_none() || failure.gate.as_deref() == gate
+            })
+            .map(|failure| failure.error.clone())
+            .collect();
+        let details = if errors.is_empty() {
+            vec![ValidationDetail {
+                check: "simulated".to_string(),
+                status: "passed".to_string(),
+                message: None,
+            }]
+        } else {
+            errors
+                .iter()
+                .map(|error| ValidationDetail {
+                    check: error.check.clone(),
+                    status: "failed".to_string(),
+                    message: Some(error.message.clone()),
+                })
+                .collect()
+        };
+        ValidationResult {
+            passed: errors.is_empty(),
+            gate: gate.unwrap_or("all").to_string(),
+            details,
+            errors,
+            warnings: Vec::new(),
+        }
+    }
+
+    fn doc_matches<'a>(&'a self, query: &'a str) -> impl Iterator<Item = (&'a SimDoc, usize)> + 'a {
+        let query = query.to_lowercase();
+        self.docs.iter().filter_map(move |doc| {
+            let hits = if query.is_empty() {
+                1
+            } else {
+                doc.content.to_lowercase().matches(query.as_str()).count()
+            };
+            (hits > 0).then_some((doc, hits))
+        })
+    }
+
+    fn capsule(&self, scope: &str, query: &str, limit: Option<usize>) -> ContextCapsule {
+        let fragments: Vec<ContextFragment> = self
+            .doc_matches(query)
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|(doc, hits)| ContextFragment {
+                path: doc.path.clone(),
+                content: doc.content.clone(),
+                relevance_score: hits as f64,
+            })
+            .collect();
+        let hash = format!("{:x}", Sha256::digest(to_json(&fragments).as_bytes()));
+        ContextCapsule {
+            scope: scope.to_string(),
+            query: query.to_string(),
+            fragments,
+            hash,
+        }
+    }
+
+    fn upsert(&mut self, entity_type: &str, key: &str, value: Value) -> StoreEntry {
+        let existing = self
+            .store
+            .iter_mut()
+            .find(|entry| entry.entity_type == entity_type && entry.key == key);
+        match existing {
+            Some(entry) => {
+                entry.value = value;
+                entry.revision = Some(entry.revision.unwrap_or(0) + 1);
+                entry.clone()
+            }
+            None => {
+                let entry = StoreEntry {
+                    entity_type: entity_type.to_string(),
+                    key: key.to_string(),
+                    value,
+                    revision: Some(1),
+                };
+                self.store.push(entry.clone());
+                entry
+            }
+        }
+    }
+}
+
+/// Answers Decapod commands from a state file.
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    root: PathBuf,
+    state_path: PathBuf,
+}
+
+impl Simulator {
+    /// A simulator for the project at `root`, keeping its state in
+    /// [`DEFAULT_STATE`] there.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        Self {
+            state_path: root.join(DEFAULT_STATE),
+            root,
+        }
+    }
+
+    /// A simulator for the working directory, honouring [`STATE_ENV`].
+    pub fn from_env() -> io::Result<Self> {
+        let simulator = Self::new(std::env::current_dir()?);
+        Ok(match std::env::var_os(STATE_ENV) {
+            Some(path) => simulator.with_state_path(path),
+            None => simulator,
+        })
+    }
+
+    pub fn with_state_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state_path = path.into();
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn state_path(&self) -> &Path {
+        &self.state_path
+    }
+
+    fn state_error(&self, source: io::Error) -> SimError {
+        SimError::State {
+            path: self.state_path.clone(),
+            source,
+        }
+    }
+
+    /// The current state; empty when no command has run yet.
+    pub fn load(&self) -> Result<SimState, SimError> {
+        match fs::read_to_string(&self.state_path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|source| SimError::Corrupt {
+                path: self.state_path.clone(),
+                source,
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(SimState::default()),
+            Err(error) => Err(self.state_error(error)),
+        }
+    }
+
+    pub fn save(&self, state: &SimState) -> Result<(), SimError> {
+        let _lock = self.lock()?;
+        self.write(state)
+    }
+
+    fn write(&self, state: &SimState) -> Result<(), SimError> {
+        let dir = self.state_dir();
+        let mut file = tempfile::NamedTempFile::new_in(&dir).map_err(|e| self.state_error(e))?;
+        file.write_all(to_json(state).as_bytes())
+            .map_err(|e| self.state_error(e))?;
+        file.persist(&self.state_path)
+            .map_err(|e| self.state_error(e.error))?;
+        Ok(())
+    }
+
+    fn state_dir(&self) -> PathBuf {
+        match self.state_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        }
+    }
+
+    /// Replaces the scenario, keeping everything else.
+    pub fn set_scenario(&self, scenario: Scenario) -> Result<(), SimError> {
+        self.transact(|state| {
+            state.scenario = scenario;
+            Ok(())
+        })
+    }
+
+    fn lock(&self) -> Result<StateLock, SimError> {
+        fs::create_dir_all(self.state_dir()).map_err(|e| self.state_error(e))?;
+        let mut path = self.state_path.clone().into_os_string();
+        path.push(".lock");
+        let path = PathBuf::from(path);
+
+        let started = Instant::now();
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(StateLock { path }),
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+                    if started.elapsed() > LOCK_TIMEOUT {
+                        // Left behind by a command that died holding it.
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+                    std::thread::sleep(Duration::from_millis(2));
+                }
+                Err(error) => return Err(self.state_error(error)),
+            }
+        }
+    }
+
+    /// Runs `change` on the state under the lock and writes the result back,
+    /// whether or not the change succeeded: a failed command may still have
+    /// used up a scripted interlock.
+    fn transact<T>(
+        &self,
+        change: impl FnOnce(&mut SimState) -> Result<T, SimError>,
+    ) -> Result<T, SimError> {
+        let _lock = self.lock()?;
+        let mut state = self.load()?;
+        let result = change(&mut state);
+        self.write(&state)?;
+        result
+    }
+
+    /// Runs one Decapod command.  The session credential is read from
+    /// [`SESSION_ENV`] in the invocation's environment, or from its input
+    /// when the command is given `--password-stdin`.
+    pub fn run(&self, invocation: &Invocation) -> TransportOutput {
+        let args = Args::parse(&invocation.args);
+        let credential = if args.has("password-stdin") {
+            invocation.input.as_ref().map(Secret::expose)
+        } else {
+            invocation
+                .env
+                .iter()
+                .rev()
+                .find(|(key, _)| key == SESSION_ENV)
+                .map(|(_, value)| value.expose())
+        };
+        let mut warnings = Vec::new();
+        let result = self.transact(|state| {
+            Command {
+                root: &self.root,
+                args: &args,
+                credential,
+                warnings: &mut warnings,
+            }
+            .dispatch(state)
+        });
+        let mut stderr: Vec<String> = warnings
+            .iter()
+            .map(|interlock| format!("warning: policy {}: {}", interlock.policy, interlock.reason))
+            .collect();
+        match result {
+            Ok(printed) => {
+                if printed.status != 0 {
+                    stderr.push(format!("{} failed", invocation.command()));
+                }
+                TransportOutput {
+                    status: Some(printed.status),
+                    stdout: printed.stdout,
+                    stderr: stderr.join("\n"),
+                }
+            }
+            Err(error) => {
+                stderr.push(error.to_string());
+                TransportOutput::failure(error.exit_code(), stderr.join("\n"))
+            }
+        }
+    }
+
+    /// Serves `rpc --stdio` frames from `input` until it closes.  `env` is
+    /// the server's own environment; each frame's environment is added on top.
+    pub fn serve(
+        &self,
+        input: impl BufRead,
+        mut output: impl Write,
+        env: &[(String, Secret)],
+    ) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<Frame>(&line) {
+                Ok(frame) if frame.ping => json!({"id": frame.id, "status": 0}),
+                Ok(frame) => {
+                    let mut invocation = Invocation::new(&frame.args);
+                    invocation.env = env.to_vec();
+                    invocation.env.extend(
+                        frame
+                            .env
+                            .into_iter()
+                            .map(|(key, value)| (key, Secret::from(value))),
+                    );
+                    let answer = self.run(&invocation);
+                    json!({
+                        "id": frame.id,
+                        "status": answer.status,
+                        "stdout": answer.stdout,
+                        "stderr": answer.stderr,
+                    })
+                }
+                Err(error) => {
+                    tracing::warn!("decapod-sim ignored an unreadable frame: {error}");
+                    continue;
+                }
+            };
+            writeln!(output, "{response}")?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl DecapodTransport for Simulator {
+    fn invoke(&self, invocation: Invocation) -> InvokeFuture<'_> {
+        let simulator = self.clone();
+        Box::pin(async move {
+            Ok(
+                tokio::task::spawn_blocking(move || simulator.run(&invocation))
+                    .await
+                    .unwrap_or_else(|_| TransportOutput::failure(70, "decapod-sim panicked")),
+            )
+        })
+    }
+
+    fn project_root(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+
+    /// Like [`DecapodInstall`](super::discovery::DecapodInstall), follows
+    /// what the scenario advertises.
+    fn accepts_input(&self) -> bool {
+        self.load()
+            .is_ok_and(|state| !state.scenario.without_password_stdin)
+    }
+}
+
+#[derive(Deserialize)]
+struct Frame {
+    id: u64,
+    #[serde(default)]
+    ping: bool,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    env: HashMap<String, String>,
+}
+
+/// Removes the state lock file when dropped.
+struct StateLock {
+    path: PathBuf,
+}
+
+impl Drop for StateLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// One command being answered.
+struct Command<'a> {
+    root: &'a Path,
+    args: &'a Args,
+    credential: Option<&'a str>,
+    warnings: &'a mut Vec<Interlock>,
+}
+
+impl Command<'_> {
+    fn dispatch(&mut self, state: &mut SimState) -> Result<Printed, SimError> {
+        let args = self.args;
+        let group = args.require_word(0, "a command")?;
+        let sub = args.word(1);
+        match (group, sub) {
+            ("session", Some("acquire")) => {
+                if args.has("password-stdin") && state.scenario.without_password_stdin {
+                    return Err(SimError::Usage(format!(
+                        "unexpected argument {PASSWORD_STDIN_FLAG}"
+                    )));
+                }
+                Ok(Printed::json(&state.acquire(self.credential)?))
+            }
+            ("session", Some("validate")) => match state.live_session(self.credential)? {
+                Some(session) => Ok(Printed::json(session)),
+                None => Err(SimError::Session("unknown".into())),
+            },
+            ("capabilities", _) => Ok(Printed::json(&capabilities(&state.scenario))),
+            ("data", Some("schema")) => Ok(Printed::json(&schema(&state.scenario))),
+            ("sim", Some(sub)) => self.sim(state, sub),
+            ("rpc", _) => self.rpc(state),
+            ("validate", _) => {
+                let gate = args.flag("gate");
+                self.governed(state, "validate.run")?;
+                let result = state.validation(gate);
+                Ok(Printed {
+                    status: if result.passed { 0 } else { 1 },
+                    stdout: to_json(&result),
+                })
+            }
+            ("todo" | "workunit" | "workspace" | "docs", Some(sub)) => {
+                let owner = self.governed(state, &format!("{group}.{sub}"))?;
+                match group {
+                    "todo" => self.todo(state, sub, owner),
+                    "workunit" => self.workunit(state, sub),
+                    "workspace" => self.workspace(state, sub),
+                    _ => self.docs(state, sub),
+                }
+            }
+            _ => Err(SimError::Usage(format!(
+                "unknown command {}",
+                args.words.join(" ")
+            ))),
+        }
+    }
+
+    /// Checks the session and interlocks for `operation`, returning the
+    /// session the command runs as.
+    fn governed(
+        &mut self,
+        state: &mut SimState,
+        operation: &str,
+    ) -> Result<Option<String>, SimError> {
+        let session = state.authorize(self.credential)?;
+        self.warnings.extend(state.gate(operation)?);
+        Ok(session)
+    }
+
+    fn todo(
+        &self,
+        state: &mut SimState,
+        sub: &str,
+        session: Option<String>,
+    ) -> Result<Printed, SimError> {
+        let args = self.args;
+        if sub == "add" {
+            let content = args.require_word(2, "task content")?;
+            return Ok(Printed::json(
+                &state.add_task(content, args.flag("priority")),
+            ));
+        }
+        if sub == "list" {
+            let mut tasks = state.list_tasks(args.flag("status"), args.flag("owner"));
+            if let Some(limit) = args.flag("limit").and_then(|limit| limit.parse().ok()) {
+                tasks.truncate(limit);
+            }
+            return Ok(Printed::json(&tasks));
+        }
+
+        let id = args.require("id")?;
+        if sub == "claim" {
+            let owner = session.as_deref().unwrap_or("anonymous");
+            return Ok(Printed::json(&state.claim_task(id, owner)?));
+        }
+        if sub == "blocks" {
+            let blockers = args.flags("blocked-by");
+            for blocker in &blockers {
+                state.task(blocker)?;
+            }
+            let task = state.task_mut(id)?;
+            let known = task.blocked_by.get_or_insert_with(Vec::new);
+            for blocker in blockers {
+                if !known.iter().any(|known| known == blocker) {
+                    known.push(blocker.to_string());
+                }
+            }
+            return Ok(Printed::json(&*task));
+        }
+
+        let task = state.task_mut(id)?;
+        let stamp = now();
+        match sub {
+            "get" => {}
+            "release" => {
+                task.status = TaskStatus::Pending;
+                task.owner = None;
+                task.claimed_at = None;
+            }
+            "complete" => {
+                task.status = TaskStatus::Completed;
+                task.completed_at = Some(stamp.clone());
+            }
+            "handoff" => {
+                task.owner = Some(args.require("to")?.to_string());
+                task.status = TaskStatus::Claimed;
+            }
+            "update" => {
+                if let Some(content) = args.flag("content") {
+                    task.content = content.to_string();
+                }
+                if let Some(priority) = args.flag("priority") {
+                    task.priority = Some(priority.to_string());
+                }
+            }
+            "archive" => task.status = TaskStatus::Cancelled,
+            _ => return Err(SimError::Usage(format!("unknown command todo {sub}"))),
+        }
+        if sub != "get" {
+            task.updated_at = stamp;
+        }
+        Ok(Printed::json(&*task))
+    }
+
+    fn workunit(&self, state: &mut SimState, sub: &str) -> Result<Printed, SimError> {
+        let args = self.args;
+        if sub == "init" {
+            let unit =
+                state.init_work_unit(args.require("task-id")?, args.require("intent-ref")?)?;
+            return Ok(Printed::json(&unit));
+        }
+        if sub == "list" {
+            let units: Vec<&WorkUnit> = state
+                .work_units
+                .iter()
+                .filter(|unit| args.flag("task-id").is_none_or(|task| unit.task_id == task))
+                .filter(|unit| {
+                    args.flag("status")
+                        .is_none_or(|status| name_of(&unit.status) == status)
+                })
+                .collect();
+            return Ok(Printed::json(&units));
+        }
+
+        let outcome = state.scenario.approval;
+        let id = args.require("id")?;
+        let patch_id = (sub == "patch").then(|| state.next("patch"));
+        let proof_id = (sub == "proof").then(|| state.next("proof"));
+        let unit = state.work_unit_mut(id)?;
+        let stamp = now();
+        match sub {
+            "get" => return Ok(Printed::json(&*unit)),
+            "update" => {
+                if let Some(intent) = args.flag("intent") {
+                    unit.state.intent = intent.to_string();
+                }
+                if let Some(plan) = args.flag("plan") {
+                    unit.state.plan = Some(plan.to_string());
+                }
+                if unit.status == WorkUnitStatus::Pending {
+                    unit.status = WorkUnitStatus::Active;
+                }
+            }
+            "patch" => unit.state.patches.push(Patch {
+                id: patch_id.unwrap_or_default(),
+                path: args.require("path")?.to_string(),
+                operation: args.require("op")?.to_string(),
+                content: args.flag("content").map(String::from),
+                status: "proposed".to_string(),
+            }),
+            "approve" => match outcome {
+                ApprovalOutcome::Granted => unit.state.approvals.push(Approval {
+                    approver: "decapod-sim".to_string(),
+                    approved_at: stamp.clone(),
+                    scope: args.flags("scope").into_iter().map(String::from).collect(),
+                }),
+                ApprovalOutcome::Denied => {
+                    return Err(rejected(format!("approval denied for workunit {id}")));
+                }
+                ApprovalOutcome::Pending => return Ok(Printed::json(&*unit)),
+            },
+            "proof" => {
+                let evidence: HashMap<String, Value> =
+                    serde_json::from_str(args.require("evidence")?).map_err(|error| {
+                        SimError::Usage(format!("--evidence must be a JSON object: {error}"))
+                    })?;
+                let passed = evidence
+                    .get("passed")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(true);
+                unit.proofs.push(Proof {
+                    id: proof_id.unwrap_or_default(),
+                    proof_type: args.require("type")?.to_string(),
+                    criteria: args.require("criteria")?.to_string(),
+                    evidence,
+                    passed,
+                    verified_at: Some(stamp.clone()),
+                });
+            }
+            "complete" => unit.status = WorkUnitStatus::Completed,
+            "fail" => {
+                args.require("reason")?;
+                unit.status = WorkUnitStatus::Failed;
+            }
+            _ => return Err(SimError::Usage(format!("unknown command workunit {sub}"))),
+        }
+        unit.updated_at = stamp;
+        Ok(Printed::json(&*unit))
+    }
+
+    fn workspace(&self, state: &mut SimState, sub: &str) -> Result<Printed, SimError> {
+        let args = self.args;
+        match sub {
+            "ensure" => Ok(Printed::json(
+                &state.ensure_workspace(self.root, args.flag("name"))?,
+            )),
+            "status" => Ok(Printed::json(&state.workspace_status())),
+            "list" => Ok(Printed::json(&state.workspaces)),
+            "enter" => Ok(Printed::json(
+                &state.enter_workspace(args.require("name")?)?,
+            )),
+            "suspend" => Ok(Printed::json(
+                &state.leave_workspace(args.require("name")?, WorkspaceStatus::Suspended)?,
+            )),
+            "archive" => Ok(Printed::json(
+                &state.leave_workspace(args.require("name")?, WorkspaceStatus::Archived)?,
+            )),
+            "delete" => {
+                let name = args.require("name")?;
+                let workspace = state.workspace_mut(name)?;
+                if workspace.status == WorkspaceStatus::Active && !args.has("force") {
+                    return Err(rejected(format!(
+                        "workspace {name} is active; pass --force to delete it"
+                    )));
+                }
+                state.workspaces.retain(|workspace| workspace.name != name);
+                if state.current_workspace.as_deref() == Some(name) {
+                    state.current_workspace = None;
+                }
+                Ok(Printed::json(&json!({"deleted": name})))
+            }
+            "path" => {
+                let name = args.require("name")?;
+                let workspace = state.workspace_mut(name)?;
+                Ok(Printed::text(workspace.path.clone()))
+            }
+            _ => Err(SimError::Usage(format!("unknown command workspace {sub}"))),
+        }
+    }
+
+    fn docs(&self, state: &mut SimState, sub: &str) -> Result<Printed, SimError> {
+        let args = self.args;
+        match sub {
+            "ingest" => {
+                let mut errors = Vec::new();
+                let mut found = Vec::new();
+                collect_markdown(self.root, self.root, false, &mut found, &mut errors);
+                collect_markdown(
+                    self.root,
+                    &self.root.join("docs"),
+                    true,
+                    &mut found,
+                    &mut errors,
+                );
+                let ingested = found.len();
+                for doc in found {
+                    state.docs.retain(|known| known.path != doc.path);
+                    state.docs.push(doc);
+                }
+                Ok(Printed::json(&IngestResult {
+                    success: errors.is_empty(),
+                    documents_ingested: ingested,
+                    errors,
+                }))
+            }
+            "show" => {
+                let path = args.require_word(2, "document path")?;
+                state
+                    .docs
+                    .iter()
+                    .find(|doc| doc.path == path)
+                    .map(|doc| Printed::text(doc.content.clone()))
+                    .ok_or_else(|| rejected(format!("no such document {path}")))
+            }
+            "search" => {
+                let query = args.require("query")?;
+                let prefix = args.flag("path").unwrap_or("");
+                let fragments: Vec<DocFragment> = state
+                    .doc_matches(query)
+                    .filter(|(doc, _)| doc.path.starts_with(prefix))
+                    .map(|(doc, hits)| DocFragment {
+                        path: doc.path.clone(),
+                        content: doc.content.clone(),
+                        relevance_score: hits as f64,
+                    })
+                    .collect();
+                Ok(Printed::json(&DocSearchResult {
+                    total_matches: fragments.len(),
+                    fragments,
+                    query: query.to_string(),
+                }))
+            }
+            "list" => {
+                let prefix = args.word(2).unwrap_or("");
+                let entries: Vec<DocEntry> = state
+                    .docs
+                    .iter()
+                    .filter(|doc| doc.path.starts_with(prefix))
+                    .map(|doc| DocEntry {
+                        path: doc.path.clone(),
+                        title: doc.title.clone(),
+                        modified: None,
+                    })
+                    .collect();
+                Ok(Printed::json(&entries))
+            }
+            _ => Err(SimError::Usage(format!("unknown command docs {sub}"))),
+        }
+    }
+
+    /// `decapod-sim sim ...`: inspects and scripts the simulator itself.
+    fn sim(&self, state: &mut SimState, sub: &str) -> Result<Printed, SimError> {
+        let args = self.args;
+        match sub {
+            "show" => return Ok(Printed::json(&*state)),
+            "reset" => *state = SimState::default(),
+            "scenario" => {
+                let Some(path) = args.word(2) else {
+                    return Ok(Printed::json(&state.scenario));
+                };
+                let text = fs::read_to_string(path).map_err(|source| SimError::State {
+                    path: PathBuf::from(path),
+                    source,
+                })?;
+                state.scenario =
+                    serde_json::from_str(&text).map_err(|source| SimError::Corrupt {
+                        path: PathBuf::from(path),
+                        source,
+                    })?;
+            }
+            "interlock" => {
+                let times = match args.flag("times") {
+                    Some(times) => Some(times.parse().map_err(|_| {
+                        SimError::Usage(format!("--times must be a number, not {times}"))
+                    })?),
+                    None => None,
+                };
+                state.scenario.interlocks.push(ScriptedInterlock {
+                    operation: args.require("op")?.to_string(),
+                    interlock: Interlock {
+                        policy: args.require("policy")?.to_string(),
+                        reason: args.require("reason")?.to_string(),
+                        blocking: !args.has("non-blocking"),
+                        required_approval: args.flag("approval").map(String::from),
+                    },
+                    times,
+                });
+            }
+            "approval" => {
+                let outcome = args.require_word(2, "granted, denied or pending")?;
+                state.scenario.approval = serde_json::from_value(Value::String(outcome.into()))
+                    .map_err(|_| SimError::Usage(format!("unknown approval outcome {outcome}")))?;
+            }
+            "fail-validation" => state.scenario.validation_failures.push(ScriptedFailure {
+                gate: args.flag("gate").map(String::from),
+                error: ValidationError {
+                    check: args.require("check")?.to_string(),
+                    message: args.require("message")?.to_string(),
+                    remediation: args.flag("remediation").map(String::from),
+                },
+            }),
+            "doc" => {
+                let path = args.require_word(2, "document path")?;
+                let doc = SimDoc::new(path, args.require("content")?);
+                state.docs.retain(|known| known.path != doc.path);
+                state.docs.push(doc);
+            }
+            "expire" => {
+                let past = (chrono::Utc::now() - chrono::Duration::seconds(1)).to_rfc3339();
+                for session in &mut state.sessions {
+                    session.expires_at = Some(past.clone());
+                }
+            }
+            _ => return Err(SimError::Usage(format!("unknown command sim {sub}"))),
+        }
+        Ok(Printed::json(&state.scenario))
+    }
+
+    /// `rpc --op <operation> [--params <json>]`.  Failures are reported in
+    /// the envelope, so the command itself succeeds.
+    fn rpc(&mut self, state: &mut SimState) -> Result<Printed, SimError> {
+        let operation = self.args.require("op")?.to_string();
+        let params: Value = match self.args.flag("params") {
+            Some(params) => serde_json::from_str(params)
+                .map_err(|error| SimError::Usage(format!("--params must be JSON: {error}")))?,
+            None => Value::Null,
+        };
+
+        let session = state.authorize(self.credential).ok().flatten();
+        let mut response = DecapodResponse::<Value> {
+            id: Some(state.next("rpc")),
+            success: false,
+            receipt: Some(Receipt {
+                timestamp: now(),
+                operation: operation.clone(),
+                session_id: session.clone(),
+            }),
+            context_capsule: None,
+            allowed_next_ops: Vec::new(),
+            blocked_by: Vec::new(),
+            interlock: None,
+            advisory: state.advisory(&operation),
+            attestation: None,
+            data: None,
+            error: None,
+        };
+
+        let answer = if state.scenario.serves(&operation) {
+            match self.governed(state, &operation) {
+                Ok(_) => rpc_operation(state, self.root, &operation, &params, session.as_deref()),
+                Err(error) => Err(error),
+            }
+        } else {
+            Err(rejected(format!("unknown operation {operation}")))
+        };
+        match answer {
+            Ok(data) => {
+                response.success = true;
+                response.data = Some(data);
+                response.interlock = self.warnings.first().cloned();
+            }
+            Err(SimError::Blocked(interlock)) => {
+                response.blocked_by = vec![interlock.clone()];
+                response.interlock = Some(interlock);
+            }
+            Err(error) => response.error = Some(error.to_string()),
+        }
+        Ok(Printed::json(&response))
+    }
+}
+
+fn param<'a>(params: &'a Value, name: &str) -> Result<&'a str, SimError> {
+    params[name]
+        .as_str()
+        .ok_or_else(|| rejected(format!("missing parameter {name}")))
+}
+
+fn rpc_operation(
+    state: &mut SimState,
+    root: &Path,
+    operation: &str,
+    params: &Value,
+    session: Option<&str>,
+) -> Result<Value, SimError> {
+    let data = match operation {
+        "agent.init" => json!(AgentInitData {
+            agent_id: param(params, "agent_id")?.to_string(),
+            session_id: session.map(String::from),
+        }),
+        "context.resolve" => {
+            let scopes: Vec<&str> = params["scopes"]
+                .as_array()
+                .map(|scopes| scopes.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            json!(state.capsule(&scopes.join(","), "", None))
+        }
+        "context.scope" => {
+            let limit = params["limit"].as_u64().map(|limit| limit as usize);
+            json!(state.capsule("repo", param(params, "query")?, limit))
+        }
+        "capsule.query" => {
+            json!(state.capsule(param(params, "scope")?, param(params, "topic")?, None))
+        }
+        "store.upsert" => json!(state.upsert(
+            param(params, "entity_type")?,
+            param(params, "key")?,
+            params["value"].clone()
+        )),
+        "store.query" => {
+            let entity_type = param(params, "entity_type")?;
+            let prefix = params["query"]["prefix"].as_str().unwrap_or("");
+            let entries: Vec<&StoreEntry> = state
+                .store
+                .iter()
+                .filter(|entry| entry.entity_type == entity_type && entry.key.starts_with(prefix))
+                .collect();
+            json!(entries)
+        }
+        "validate.run" => json!(state.validation(None)),
+        "workspace.ensure" => json!(state.ensure_workspace(root, None)?),
+        "workspace.status" => json!(state.workspace_status()),
+        "todo.add" => json!(state.add_task(param(params, "content")?, params["priority"].as_str())),
+        "todo.claim" => {
+            json!(state.claim_task(param(params, "task_id")?, session.unwrap_or("anonymous"))?)
+        }
+        "todo.list" => json!(state.list_tasks(params["status"].as_str(), None)),
+        "workunit.init" => {
+            json!(state.init_work_unit(param(params, "task_id")?, param(params, "intent_ref")?)?)
+        }
+        _ => return Err(rejected(format!("unknown operation {operation}"))),
+    };
+    Ok(data)
+}
+
+fn capabilities(scenario: &Scenario) -> Capabilities {
+    Capabilities {
+        version: scenario.version().to_string(),
+        commands: COMMANDS
+            .iter()
+            .map(|(name, subcommands)| CommandCapability {
+                name: name.to_string(),
+                alias: None,
+                description: format!("simulated decapod {name}"),
+                subcommands: subcommands.iter().map(|sub| sub.to_string()).collect(),
+                flags: match *name {
+                    "session" if !scenario.without_password_stdin => HashMap::from([(
+                        "acquire".to_string(),
+                        vec![PASSWORD_STDIN_FLAG.to_string()],
+                    )]),
+                    _ => HashMap::new(),
+                },
+            })
+            .collect(),
+        plugins: Vec::new(),
+        rpc_operations: RPC_OPERATIONS
+            .iter()
+            .filter(|operation| scenario.serves(operation))
+            .map(|operation| RpcOperation {
+                name: operation.to_string(),
+                description: None,
+                params: HashMap::new(),
+            })
+            .collect(),
+    }
+}
+
+fn schema(scenario: &Scenario) -> SchemaInfo {
+    let entity = |name: &str, fields: &[(&str, &str, bool)]| EntitySchema {
+        name: name.to_string(),
+        fields: fields
+            .iter()
+            .map(|(name, field_type, optional)| FieldSchema {
+                name: name.to_string(),
+                field_type: field_type.to_string(),
+                optional: *optional,
+            })
+            .collect(),
+    };
+    SchemaInfo {
+        entities: vec![
+            entity(
+                "task",
+                &[
+                    ("id", "string", false),
+                    ("content", "string", false),
+                    ("status", "string", false),
+                    ("owner", "string", true),
+                ],
+            ),
+            entity(
+                "workspace",
+                &[
+                    ("name", "string", false),
+                    ("branch", "string", false),
+                    ("status", "string", false),
+                ],
+            ),
+            entity(
+                "workunit",
+                &[
+                    ("id", "string", false),
+                    ("task_id", "string", false),
+                    ("status", "string", false),
+                ],
+            ),
+        ],
+        version: scenario.version().to_string(),
+    }
+}
+
+/// Markdown files in `dir`, recorded relative to `root`.
+fn collect_markdown(
+    root: &Path,
+    dir: &Path,
+    recurse: bool,
+    found: &mut Vec<SimDoc>,
+    errors: &mut Vec<String>,
+) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            if recurse {
+                collect_markdown(root, &path, true, found, errors);
+            }
+            continue;
+        }
+        if path.extension().is_none_or(|extension| extension != "md") {
+            continue;
+        }
+        let relative = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .display()
+            .to_string();
+        match fs::read_to_string(&path) {
+            Ok(content) => found.push(SimDoc::new(relative, content)),
+            Err(error) => errors.push(format!("{relative}: {error}")),
+        }
+    }
+}
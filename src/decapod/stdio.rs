@@ -0,0 +1,478 @@
+//! A long-lived Decapod connection over stdin/stdout.
+//!
+//! [`StdioTransport`] keeps a small pool of `decapod rpc --stdio` processes and
+//! sends each command to one of them as a frame instead of spawning a process
+//! per call.  Frames are single lines of JSON:
+//!
+//! ```text
+//! -> {"id":7,"args":["todo","list"],"env":{"DECAPOD_SESSION_PASSWORD":"..."}}
+//! <- {"id":7,"status":0,"stdout":"[...]","stderr":""}
+//! -> {"id":8,"ping":true,"args":[]}
+//! <- {"id":8,"status":0}
+//! ```
+//!
+//! Requests carry an ID, so many commands can be in flight on one connection
+//! and Decapod may answer them in any order.  A ping frame checks that a
+//! connection is healthy: every new connection must answer one, and so must a
+//! connection that has been idle for a while.  A connection whose process
+//! exits is replaced on the next command; commands in flight on it fail with
+//! [`TransportError::Disconnected`] rather than being sent again, since they
+//! may already have taken effect.  When no connection can be started — for
+//! example because this Decapod has no stdio mode — commands fall back to the
+//! per-call [`ProcessTransport`].
+
+use super::envelope::DecodeMode;
+use super::transport::{
+    DecapodTransport, Invocation, InvokeFuture, ProcessTransport, TransportError, TransportOutput,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::oneshot;
+
+/// Arguments that start Decapod's stdio server.
+pub const STDIO_ARGS: [&str; 2] = ["rpc", "--stdio"];
+
+#[derive(Serialize)]
+struct RequestFrame<'a> {
+    id: u64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    ping: bool,
+    args: &'a [String],
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<&'a str, &'a str>,
+}
+
+#[derive(Deserialize)]
+struct ResponseFrame {
+    id: u64,
+    #[serde(default)]
+    status: Option<i32>,
+    #[serde(default)]
+    stdout: String,
+    #[serde(default)]
+    stderr: String,
+}
+
+#[derive(Default)]
+struct Pending {
+    waiters: HashMap<u64, oneshot::Sender<ResponseFrame>>,
+    closed: bool,
+}
+
+/// One running stdio server.  Dropping it kills the process.
+struct Connection {
+    child: Mutex<Child>,
+    stdin: tokio::sync::Mutex<ChildStdin>,
+    pending: Arc<Mutex<Pending>>,
+    last_used: Mutex<Instant>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl Connection {
+    fn spawn(process: &ProcessTransport, args: &[String]) -> Result<Self, TransportError> {
+        let root = process.verified_project_root()?;
+        let mut command = Command::new(process.binary());
+        command
+            .args(args)
+            .envs(process.env().iter().cloned())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .current_dir(root.path());
+        let mut child = command.spawn().map_err(|source| TransportError::Spawn {
+            binary: process.binary().to_path_buf(),
+            source,
+        })?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let pending = Arc::new(Mutex::new(Pending::default()));
+
+        let routes = pending.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                match serde_json::from_str::<ResponseFrame>(&line) {
+                    Ok(frame) => {
+                        if let Some(waiter) = lock(&routes).waiters.remove(&frame.id) {
+                            let _ = waiter.send(frame);
+                        }
+                    }
+                    Err(error) => tracing::debug!("ignoring decapod stdio line {line:?}: {error}"),
+                }
+            }
+            // Dropping the waiters wakes every request still in flight.
+            let mut routes = lock(&routes);
+            routes.closed = true;
+            routes.waiters.clear();
+        });
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                tracing::debug!("decapod stdio stderr: {line}");
+            }
+        });
+
+        Ok(Self {
+            child: Mutex::new(child),
+            stdin: tokio::sync::Mutex::new(stdin),
+            pending,
+            last_used: Mutex::new(Instant::now()),
+        })
+    }
+
+    fn is_alive(&self) -> bool {
+        !lock(&self.pending).closed && matches!(lock(&self.child).try_wait(), Ok(None))
+    }
+
+    fn idle_for(&self) -> Duration {
+        lock(&self.last_used).elapsed()
+    }
+
+    async fn send(
+        &self,
+        id: u64,
+        ping: bool,
+        invocation: &Invocation,
+        timeout: Duration,
+    ) -> Result<TransportOutput, TransportError> {
+        let disconnected = || TransportError::Disconnected {
+            command: invocation.command(),
+        };
+        let (sender, receiver) = oneshot::channel();
+        {
+            let mut pending = lock(&self.pending);
+            if pending.closed {
+                return Err(disconnected());
+            }
+            pending.waiters.insert(id, sender);
+        }
+
+        let frame = RequestFrame {
+            id,
+            ping,
+            args: &invocation.args,
+            env: invocation
+                .env
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.expose()))
+                .collect(),
+        };
+        let mut line = serde_json::to_vec(&frame).expect("frames serialize");
+        line.push(b'\n');
+        let written = {
+            let mut stdin = self.stdin.lock().await;
+            match stdin.write_all(&line).await {
+                Ok(()) => stdin.flush().await,
+                Err(error) => Err(error),
+            }
+        };
+        if written.is_err() {
+            lock(&self.pending).waiters.remove(&id);
+            return Err(disconnected());
+        }
+        *lock(&self.last_used) = Instant::now();
+
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(frame)) => Ok(TransportOutput {
+                status: frame.status,
+                stdout: frame.stdout,
+                stderr: frame.stderr,
+            }),
+            Ok(Err(_)) => Err(disconnected()),
+            Err(_) => {
+                lock(&self.pending).waiters.remove(&id);
+                Err(TransportError::Timeout {
+                    command: invocation.command(),
+                    timeout,
+                })
+            }
+        }
+    }
+}
+
+enum Slot {
+    Empty,
+    Live(Arc<Connection>),
+    /// Starting a connection failed; do not try again before `retry_at`.
+    Down {
+        retry_at: Instant,
+    },
+}
+
+/// Counters describing what a [`StdioTransport`] has done.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StdioStats {
+    /// Stdio servers started, including replacements.
+    pub spawned: u64,
+    /// Commands answered over a stdio connection.
+    pub framed: u64,
+    /// Commands run through the per-call fallback.
+    pub fallbacks: u64,
+    /// Connections found dead and dropped.
+    pub disconnects: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    spawned: AtomicU64,
+    framed: AtomicU64,
+    fallbacks: AtomicU64,
+    disconnects: AtomicU64,
+}
+
+/// Runs Decapod commands over a pool of persistent stdio connections.
+pub struct StdioTransport {
+    process: ProcessTransport,
+    serve_args: Vec<String>,
+    health_timeout: Duration,
+    idle_check: Duration,
+    respawn_backoff: Duration,
+    fallback: bool,
+    slots: Vec<tokio::sync::Mutex<Slot>>,
+    next_slot: AtomicUsize,
+    next_id: AtomicU64,
+    counters: Counters,
+}
+
+impl StdioTransport {
+    pub const DEFAULT_POOL_SIZE: usize = 2;
+    pub const DEFAULT_HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+    pub const DEFAULT_IDLE_CHECK: Duration = Duration::from_secs(30);
+    pub const DEFAULT_RESPAWN_BACKOFF: Duration = Duration::from_secs(5);
+
+    /// Starts stdio servers with the binary, project root, environment, and
+    /// command timeout of `process`, which also serves as the fallback.
+    pub fn new(process: ProcessTransport) -> Self {
+        Self {
+            process,
+            serve_args: STDIO_ARGS.iter().map(|arg| arg.to_string()).collect(),
+            health_timeout: Self::DEFAULT_HEALTH_TIMEOUT,
+            idle_check: Self::DEFAULT_IDLE_CHECK,
+            respawn_backoff: Self::DEFAULT_RESPAWN_BACKOFF,
+            fallback: true,
+            slots: Vec::new(),
+            next_slot: AtomicUsize::new(0),
+            next_id: AtomicU64::new(1),
+            counters: Counters::default(),
+        }
+        .with_pool_size(Self::DEFAULT_POOL_SIZE)
+    }
+
+    /// Number of connections to keep; at least one.
+    pub fn with_pool_size(mut self, size: usize) -> Self {
+        self.slots = (0..size.max(1))
+            .map(|_| tokio::sync::Mutex::new(Slot::Empty))
+            .collect();
+        self
+    }
+
+    /// Arguments that start the stdio server, [`STDIO_ARGS`] by default.
+    pub fn with_serve_args<S: AsRef<str>>(mut self, args: &[S]) -> Self {
+        self.serve_args = args.iter().map(|arg| arg.as_ref().to_string()).collect();
+        self
+    }
+
+    /// How long a ping may take before the connection is replaced.
+    pub fn with_health_timeout(mut self, timeout: Duration) -> Self {
+        self.health_timeout = timeout;
+        self
+    }
+
+    /// Connections idle for longer than this are pinged before reuse.
+    pub fn with_idle_check(mut self, idle: Duration) -> Self {
+        self.idle_check = idle;
+        self
+    }
+
+    /// How long to wait before trying again after a server failed to start.
+    pub fn with_respawn_backoff(mut self, backoff: Duration) -> Self {
+        self.respawn_backoff = backoff;
+        self
+    }
+
+    /// Fails with [`TransportError::Unavailable`] instead of spawning a
+    /// process per call when no connection can be started.
+    pub fn without_fallback(mut self) -> Self {
+        self.fallback = false;
+        self
+    }
+
+    pub fn pool_size(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn stats(&self) -> StdioStats {
+        StdioStats {
+            spawned: self.counters.spawned.load(Ordering::Relaxed),
+            framed: self.counters.framed.load(Ordering::Relaxed),
+            fallbacks: self.counters.fallbacks.load(Ordering::Relaxed),
+            disconnects: self.counters.disconnects.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Pings every connection, replacing those that are dead or do not
+    /// answer, and returns how many are live afterwards.
+    pub async fn health_check(&self) -> usize {
+        let mut live = 0;
+        for slot in &self.slots {
+            let mut slot = slot.lock().await;
+            if let Slot::Live(connection) = &*slot
+                && !self.ping(connection).await
+            {
+                self.counters.disconnects.fetch_add(1, Ordering::Relaxed);
+                *slot = Slot::Empty;
+            }
+            if self.connect(&mut slot).await.is_some() {
+                live += 1;
+            }
+        }
+        live
+    }
+
+    /// Stops every connection; the next command starts new ones.
+    pub async fn shutdown(&self) {
+        for slot in &self.slots {
+            *slot.lock().await = Slot::Empty;
+        }
+    }
+
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    async fn ping(&self, connection: &Connection) -> bool {
+        let ping = Invocation::new::<&str>(&[]);
+        connection
+            .send(self.next_id(), true, &ping, self.health_timeout)
+            .await
+            .is_ok()
+    }
+
+    /// The live connection in `slot`, starting one if needed and allowed.
+    async fn connect(&self, slot: &mut Slot) -> Option<Arc<Connection>> {
+        match &*slot {
+            Slot::Live(connection) if connection.is_alive() => {
+                if connection.idle_for() < self.idle_check || self.ping(connection).await {
+                    return Some(connection.clone());
+                }
+                self.counters.disconnects.fetch_add(1, Ordering::Relaxed);
+            }
+            Slot::Live(_) => {
+                self.counters.disconnects.fetch_add(1, Ordering::Relaxed);
+            }
+            Slot::Down { retry_at } if Instant::now() < *retry_at => return None,
+            Slot::Down { .. } | Slot::Empty => {}
+        }
+
+        *slot = Slot::Empty;
+        let started = match Connection::spawn(&self.process, &self.serve_args) {
+            Ok(connection) if self.ping(&connection).await => Some(Arc::new(connection)),
+            Ok(_) => {
+                tracing::debug!("decapod stdio server did not answer its first ping");
+                None
+            }
+            Err(error) => {
+                tracing::debug!("decapod stdio server did not start: {error}");
+                None
+            }
+        };
+        match started {
+            Some(connection) => {
+                self.counters.spawned.fetch_add(1, Ordering::Relaxed);
+                *slot = Slot::Live(connection.clone());
+                Some(connection)
+            }
+            None => {
+                *slot = Slot::Down {
+                    retry_at: Instant::now() + self.respawn_backoff,
+                };
+                None
+            }
+        }
+    }
+
+    async fn run(&self, invocation: Invocation) -> Result<TransportOutput, TransportError> {
+        // A pooled connection outlives the check made when it was spawned.
+        self.process.verified_project_root()?;
+        if invocation.input.is_some() {
+            // Frames have no stdin of their own.
+            return self.process.invoke(invocation).await;
+        }
+        let index = self.next_slot.fetch_add(1, Ordering::Relaxed) % self.slots.len();
+        let connection = {
+            let mut slot = self.slots[index].lock().await;
+            self.connect(&mut slot).await
+        };
+
+        match connection {
+            Some(connection) => {
+                let result = connection
+                    .send(self.next_id(), false, &invocation, self.process.timeout())
+                    .await;
+                if result.is_ok() {
+                    self.counters.framed.fetch_add(1, Ordering::Relaxed);
+                }
+                result
+            }
+            None if self.fallback => {
+                self.counters.fallbacks.fetch_add(1, Ordering::Relaxed);
+                self.process.invoke(invocation).await
+            }
+            None => Err(TransportError::Unavailable {
+                command: invocation.command(),
+            }),
+        }
+    }
+}
+
+impl Default for StdioTransport {
+    fn default() -> Self {
+        Self::new(ProcessTransport::default())
+    }
+}
+
+impl fmt::Debug for StdioTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StdioTransport")
+            .field("process", &self.process)
+            .field("serve_args", &self.serve_args)
+            .field("pool_size", &self.slots.len())
+            .field("fallback", &self.fallback)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl DecapodTransport for StdioTransport {
+    fn invoke(&self, invocation: Invocation) -> InvokeFuture<'_> {
+        Box::pin(self.run(invocation))
+    }
+
+    fn decode_mode(&self) -> DecodeMode {
+        self.process.decode_mode()
+    }
+
+    fn project_root(&self) -> Option<&Path> {
+        self.process.project_root()
+    }
+
+    fn accepts_input(&self) -> bool {
+        self.process.accepts_input()
+    }
+}
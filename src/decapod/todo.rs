@@ -1,6 +1,8 @@
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::process::Stdio;
-use tokio::process::Command;
+use super::envelope;
+use super::transport::{default_transport, DecapodTransport, Invocation};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Task {
@@ -13,6 +15,10 @@ pub struct Task {
     pub updated_at: String,
     pub claimed_at: Option<String>,
     pub completed_at: Option<String>,
+    /// Tasks that must be completed first, as recorded by
+    /// [`TodoManager::blocks`].  `None` when Decapod did not report them.
+    #[serde(default)]
+    pub blocked_by: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
@@ -27,14 +33,14 @@ pub enum TaskStatus {
 }
 
 pub struct TodoManager {
-    binary_path: String,
+    transport: Arc<dyn DecapodTransport>,
     session_token: Option<String>,
 }
 
 impl TodoManager {
     pub fn new() -> Self {
         Self {
-            binary_path: "decapod".to_string(),
+            transport: default_transport(),
             session_token: None,
         }
     }
@@ -44,48 +50,27 @@ impl TodoManager {
         self
     }
 
-    async fn run_command(&self, args: &[&str]) -> anyhow::Result<String> {
-        let mut cmd = Command::new(&self.binary_path);
-        cmd.args(args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        if let Some(ref token) = self.session_token {
-            cmd.env("DECAPOD_SESSION_PASSWORD", token);
-        }
-
-        let output = cmd.output().await?;
+    pub fn with_transport(mut self, transport: Arc<dyn DecapodTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
 
-        if output.status.success() {
-            Ok(String::from_utf8_lossy(&output.stdout).to_string())
-        } else {
-            Err(anyhow::anyhow!(
-                "todo command failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ))
-        }
+    async fn run_command(&self, args: &[&str]) -> anyhow::Result<String> {
+        let invocation = Invocation::new(args).with_session(self.session_token.as_deref());
+        self.transport.invoke(invocation).await?.into_stdout("todo command")
     }
 
     async fn run_command_strings(&self, args: &[String]) -> anyhow::Result<String> {
-        let mut cmd = Command::new(&self.binary_path);
-        cmd.args(args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        if let Some(ref token) = self.session_token {
-            cmd.env("DECAPOD_SESSION_PASSWORD", token);
-        }
+        let invocation = Invocation::new(args).with_session(self.session_token.as_deref());
+        self.transport.invoke(invocation).await?.into_stdout("todo command")
+    }
 
-        let output = cmd.output().await?;
+    fn decode<T: DeserializeOwned>(&self, what: &str, output: &str) -> anyhow::Result<T> {
+        Ok(envelope::decode(what, output, self.transport.decode_mode())?)
+    }
 
-        if output.status.success() {
-            Ok(String::from_utf8_lossy(&output.stdout).to_string())
-        } else {
-            Err(anyhow::anyhow!(
-                "todo command failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ))
-        }
+    fn decode_list<T: DeserializeOwned>(&self, what: &str, output: &str) -> anyhow::Result<Vec<T>> {
+        Ok(envelope::decode_list(what, output, self.transport.decode_mode())?)
     }
 
     pub async fn add(&self, content: &str, priority: Option<&str>, tags: Option<Vec<&str>>) -> anyhow::Result<Task> {
@@ -105,45 +90,21 @@ impl TodoManager {
 
         let output = self.run_command(&args).await?;
         
-        if let Ok(task) = serde_json::from_str(&output) {
-            return Ok(task);
-        }
-
-        for line in output.lines() {
-            if let Ok(task) = serde_json::from_str(line) {
-                return Ok(task);
-            }
-        }
-
-        Err(anyhow::anyhow!("failed to parse task response"))
+        self.decode("todo add", &output)
     }
 
     pub async fn claim(&self, task_id: &str) -> anyhow::Result<Task> {
         let args = vec!["todo", "claim", "--id", task_id];
         let output = self.run_command(&args).await?;
         
-        if let Ok(task) = serde_json::from_str(&output) {
-            return Ok(task);
-        }
-
-        for line in output.lines() {
-            if let Ok(task) = serde_json::from_str(line) {
-                return Ok(task);
-            }
-        }
-
-        Err(anyhow::anyhow!("failed to claim task"))
+        self.decode("todo claim", &output)
     }
 
     pub async fn release(&self, task_id: &str) -> anyhow::Result<Task> {
         let args = vec!["todo", "release", "--id", task_id];
         let output = self.run_command(&args).await?;
         
-        if let Ok(task) = serde_json::from_str(&output) {
-            return Ok(task);
-        }
-
-        Err(anyhow::anyhow!("failed to release task"))
+        self.decode("todo release", &output)
     }
 
     pub async fn complete(&self, task_id: &str, resolution: Option<&str>) -> anyhow::Result<Task> {
@@ -156,11 +117,7 @@ impl TodoManager {
 
         let output = self.run_command(&args).await?;
         
-        if let Ok(task) = serde_json::from_str(&output) {
-            return Ok(task);
-        }
-
-        Err(anyhow::anyhow!("failed to complete task"))
+        self.decode("todo complete", &output)
     }
 
     pub async fn list(&self, status: Option<&str>, owner: Option<&str>, limit: Option<usize>) -> anyhow::Result<Vec<Task>> {
@@ -183,40 +140,21 @@ impl TodoManager {
 
         let output = self.run_command_strings(&args).await?;
         
-        if let Ok(tasks) = serde_json::from_str(&output) {
-            return Ok(tasks);
-        }
-
-        let mut tasks = Vec::new();
-        for line in output.lines() {
-            if let Ok(task) = serde_json::from_str(line) {
-                tasks.push(task);
-            }
-        }
-
-        Ok(tasks)
+        self.decode_list("todo list", &output)
     }
 
     pub async fn get(&self, task_id: &str) -> anyhow::Result<Task> {
         let args = vec!["todo", "get", "--id", task_id];
         let output = self.run_command(&args).await?;
         
-        if let Ok(task) = serde_json::from_str(&output) {
-            return Ok(task);
-        }
-
-        Err(anyhow::anyhow!("failed to get task"))
+        self.decode("todo get", &output)
     }
 
     pub async fn handoff(&self, task_id: &str, to_agent: &str) -> anyhow::Result<Task> {
         let args = vec!["todo", "handoff", "--id", task_id, "--to", to_agent];
         let output = self.run_command(&args).await?;
         
-        if let Ok(task) = serde_json::from_str(&output) {
-            return Ok(task);
-        }
-
-        Err(anyhow::anyhow!("failed to handoff task"))
+        self.decode("todo handoff", &output)
     }
 
     pub async fn update(&self, task_id: &str, content: Option<&str>, priority: Option<&str>) -> anyhow::Result<Task> {
@@ -234,11 +172,7 @@ impl TodoManager {
 
         let output = self.run_command(&args).await?;
         
-        if let Ok(task) = serde_json::from_str(&output) {
-            return Ok(task);
-        }
-
-        Err(anyhow::anyhow!("failed to update task"))
+        self.decode("todo update", &output)
     }
 
     pub async fn archive(&self, task_id: &str) -> anyhow::Result<()> {
@@ -257,11 +191,7 @@ impl TodoManager {
 
         let output = self.run_command(&args).await?;
         
-        if let Ok(task) = serde_json::from_str(&output) {
-            return Ok(task);
-        }
-
-        Err(anyhow::anyhow!("failed to set blocks"))
+        self.decode("todo blocks", &output)
     }
 }
 
@@ -0,0 +1,497 @@
+//! How the Decapod managers reach Decapod.
+//!
+//! Every manager sends its commands through a [`DecapodTransport`] instead of
+//! spawning the binary itself.  [`ProcessTransport`] runs the `decapod`
+//! binary and owns everything about the process: the binary path, the
+//! project-root working directory, extra environment, the timeout, and
+//! capture of stderr.  It refuses to run a command without a verified
+//! [`ProjectRoot`].  A secret given with [`Invocation::with_input`] is
+//! written to the command's stdin rather than its environment.
+//! [`ScriptedTransport`] answers from an in-memory script so managers can be
+//! tested without Decapod installed.
+
+use super::discovery::{BinaryDiscovery, DEFAULT_BINARY, DiscoveryError};
+use super::envelope::DecodeMode;
+use super::project::{ProjectRoot, ProjectRootError};
+use super::secret::Secret;
+use super::session::SessionExpired;
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Environment variable carrying the session credential to Decapod.
+pub const SESSION_ENV: &str = "DECAPOD_SESSION_PASSWORD";
+
+/// Exit status with which Decapod refuses a command's session.
+pub const SESSION_EXIT_STATUS: i32 = 4;
+
+/// Future returned by [`DecapodTransport::invoke`].
+pub type InvokeFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<TransportOutput, TransportError>> + Send + 'a>>;
+
+/// One Decapod command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    pub args: Vec<String>,
+    /// Environment for this command only, on top of the transport's own.
+    /// Values are credentials, so they are held as [`Secret`]s.
+    pub env: Vec<(String, Secret)>,
+    /// Written to the command's stdin, for transports that
+    /// [accept input](DecapodTransport::accepts_input).
+    pub input: Option<Secret>,
+}
+
+impl Invocation {
+    pub fn new<S: AsRef<str>>(args: &[S]) -> Self {
+        Self {
+            args: args.iter().map(|arg| arg.as_ref().to_string()).collect(),
+            env: Vec::new(),
+            input: None,
+        }
+    }
+
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<Secret>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Pipes `secret` to the command's stdin, which is then closed.
+    pub fn with_input(mut self, secret: Secret) -> Self {
+        self.input = Some(secret);
+        self
+    }
+
+    /// Passes the session credential, if any, in [`SESSION_ENV`].
+    pub fn with_session(self, token: Option<&str>) -> Self {
+        match token {
+            Some(token) => self.with_env(SESSION_ENV, token),
+            None => self,
+        }
+    }
+
+    /// The command line without its environment, for messages.
+    pub fn command(&self) -> String {
+        self.args.join(" ")
+    }
+
+    /// Whether the arguments start with `prefix`.
+    pub fn starts_with<S: AsRef<str>>(&self, prefix: &[S]) -> bool {
+        prefix.len() <= self.args.len()
+            && prefix
+                .iter()
+                .zip(&self.args)
+                .all(|(expected, arg)| expected.as_ref() == arg)
+    }
+}
+
+/// What a Decapod command printed and how it exited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransportOutput {
+    /// Exit code; `None` when the process was killed by a signal.
+    pub status: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl TransportOutput {
+    /// A successful command printing `stdout`.
+    pub fn ok(stdout: impl Into<String>) -> Self {
+        Self {
+            status: Some(0),
+            stdout: stdout.into(),
+            stderr: String::new(),
+        }
+    }
+
+    /// A successful command printing `value` as JSON.
+    pub fn json(value: &impl serde::Serialize) -> Self {
+        Self::ok(serde_json::to_string(value).unwrap_or_default())
+    }
+
+    /// A command that exited with `status` after printing `stderr`.
+    pub fn failure(status: i32, stderr: impl Into<String>) -> Self {
+        Self {
+            status: Some(status),
+            stdout: String::new(),
+            stderr: stderr.into(),
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.status == Some(0)
+    }
+
+    /// Whether Decapod refused the command because its session expired.
+    pub fn session_expired(&self) -> bool {
+        self.status == Some(SESSION_EXIT_STATUS) && self.stderr.contains("session expired")
+    }
+
+    /// Stdout of a successful command; otherwise an error naming `what` and
+    /// carrying stderr, or a [`SessionExpired`] when that is why it failed.
+    pub fn into_stdout(self, what: &str) -> anyhow::Result<String> {
+        if self.success() {
+            Ok(self.stdout)
+        } else if self.session_expired() {
+            Err(SessionExpired {
+                command: what.to_string(),
+            }
+            .into())
+        } else {
+            Err(anyhow::anyhow!("{what} failed: {}", self.stderr))
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TransportError {
+    #[error("failed to run {}: {source}", binary.display())]
+    Spawn {
+        binary: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("decapod {command} timed out after {timeout:?}")]
+    Timeout { command: String, timeout: Duration },
+    #[error("decapod connection closed during {command}")]
+    Disconnected { command: String },
+    #[error("no decapod connection available for {command}")]
+    Unavailable { command: String },
+    #[error("no scripted response for decapod {command}")]
+    Unscripted { command: String },
+    #[error("no Decapod project root is set for {}", binary.display())]
+    NoProjectRoot { binary: PathBuf },
+    #[error(transparent)]
+    ProjectRoot(#[from] ProjectRootError),
+}
+
+pub trait DecapodTransport: fmt::Debug + Send + Sync {
+    fn invoke(&self, invocation: Invocation) -> InvokeFuture<'_>;
+
+    /// How managers decode what this Decapod prints.
+    fn decode_mode(&self) -> DecodeMode {
+        DecodeMode::Strict
+    }
+
+    /// The project root commands run in, when they run in one.
+    fn project_root(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Whether commands receive [`Invocation::input`] on stdin.  Callers
+    /// fall back to the environment when they do not.
+    fn accepts_input(&self) -> bool {
+        false
+    }
+}
+
+/// The transport managers use unless given another: the `decapod` binary on
+/// `PATH`, run from the enclosing Decapod project.
+pub fn default_transport() -> Arc<dyn DecapodTransport> {
+    Arc::new(ProcessTransport::default())
+}
+
+/// Runs the Decapod binary once per command.
+#[derive(Debug, Clone)]
+pub struct ProcessTransport {
+    binary: PathBuf,
+    project_root: Option<PathBuf>,
+    env: Vec<(String, String)>,
+    timeout: Duration,
+    decode_mode: DecodeMode,
+    accepts_input: bool,
+}
+
+impl ProcessTransport {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+    /// Runs `binary`, giving up on a command after [`Self::DEFAULT_TIMEOUT`].
+    /// Commands are refused until a project root is set with
+    /// [`Self::with_project_root`].
+    pub fn new(binary: impl Into<PathBuf>) -> Self {
+        Self {
+            binary: binary.into(),
+            project_root: None,
+            env: Vec::new(),
+            timeout: Self::DEFAULT_TIMEOUT,
+            decode_mode: DecodeMode::Strict,
+            accepts_input: false,
+        }
+    }
+
+    /// Runs every command from `root`, which is verified as a Decapod
+    /// project before each command.
+    pub fn with_project_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.project_root = Some(root.into());
+        self
+    }
+
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Decodes output leniently, for Decapod builds that predate strict
+    /// envelopes.
+    pub fn with_decode_mode(mut self, mode: DecodeMode) -> Self {
+        self.decode_mode = mode;
+        self
+    }
+
+    /// Marks the binary as reading `--password-stdin`.  Off by default, so
+    /// the password goes in the environment of a Decapod that cannot read
+    /// it; [`DecapodInstall`](super::discovery::DecapodInstall) turns it on
+    /// when Decapod's capabilities report the flag.
+    pub fn with_password_stdin(mut self, accepted: bool) -> Self {
+        self.accepts_input = accepted;
+        self
+    }
+
+    pub fn binary(&self) -> &Path {
+        &self.binary
+    }
+
+    /// Environment added to every command.
+    pub fn env(&self) -> &[(String, String)] {
+        &self.env
+    }
+
+    pub fn project_root(&self) -> Option<&Path> {
+        self.project_root.as_deref()
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// The project root, checked to still be a Decapod project.
+    pub fn verified_project_root(&self) -> Result<ProjectRoot, TransportError> {
+        let root = self
+            .project_root
+            .as_ref()
+            .ok_or_else(|| TransportError::NoProjectRoot {
+                binary: self.binary.clone(),
+            })?;
+        Ok(ProjectRoot::verify(root)?)
+    }
+
+    async fn run(&self, invocation: Invocation) -> Result<TransportOutput, TransportError> {
+        let root = self.verified_project_root()?;
+        let mut command = Command::new(&self.binary);
+        command
+            .args(&invocation.args)
+            .envs(self.env.iter().cloned())
+            .envs(
+                invocation
+                    .env
+                    .iter()
+                    .map(|(key, value)| (key, value.expose())),
+            )
+            .stdin(match invocation.input {
+                Some(_) => Stdio::piped(),
+                None => Stdio::null(),
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // A command abandoned on timeout must not outlive us.
+            .kill_on_drop(true)
+            .current_dir(root.path());
+
+        let spawned = |source| TransportError::Spawn {
+            binary: self.binary.clone(),
+            source,
+        };
+        let output = tokio::time::timeout(self.timeout, async {
+            let mut child = command.spawn()?;
+            if let (Some(mut stdin), Some(input)) = (child.stdin.take(), &invocation.input) {
+                // A command that exits without reading its input is not an
+                // error here; its status says what happened.
+                match stdin.write_all(input.expose().as_bytes()).await {
+                    Err(error) if error.kind() != std::io::ErrorKind::BrokenPipe => {
+                        return Err(error);
+                    }
+                    _ => {}
+                }
+            }
+            child.wait_with_output().await
+        })
+        .await
+        .map_err(|_| TransportError::Timeout {
+            command: invocation.command(),
+            timeout: self.timeout,
+        })?
+        .map_err(spawned)?;
+
+        let output = TransportOutput {
+            status: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        };
+        if !output.stderr.trim().is_empty() {
+            tracing::debug!(
+                "decapod {} (status {:?}) stderr: {}",
+                invocation.command(),
+                output.status,
+                output.stderr.trim_end()
+            );
+        }
+        Ok(output)
+    }
+}
+
+impl Default for ProcessTransport {
+    /// The binary [`BinaryDiscovery`] finds, run from the enclosing Decapod
+    /// project.  When discovery fails the command is still attempted, so the
+    /// failure surfaces as a [`TransportError::Spawn`] naming the binary;
+    /// outside a project every command fails with
+    /// [`TransportError::NoProjectRoot`].
+    fn default() -> Self {
+        let discovery = BinaryDiscovery::new();
+        discovery.transport().unwrap_or_else(|error| {
+            tracing::debug!("decapod discovery failed: {error}");
+            let binary = match error {
+                DiscoveryError::Unusable { path, .. } => path,
+                DiscoveryError::NotFound { .. } => PathBuf::from(DEFAULT_BINARY),
+            };
+            let transport = Self::new(binary);
+            match discovery.project_root() {
+                Some(root) => transport.with_project_root(root),
+                None => transport,
+            }
+        })
+    }
+}
+
+impl DecapodTransport for ProcessTransport {
+    fn invoke(&self, invocation: Invocation) -> InvokeFuture<'_> {
+        Box::pin(self.run(invocation))
+    }
+
+    fn decode_mode(&self) -> DecodeMode {
+        self.decode_mode
+    }
+
+    fn project_root(&self) -> Option<&Path> {
+        self.project_root.as_deref()
+    }
+
+    fn accepts_input(&self) -> bool {
+        self.accepts_input
+    }
+}
+
+#[derive(Debug)]
+struct Reply {
+    prefix: Vec<String>,
+    output: TransportOutput,
+    once: bool,
+}
+
+#[derive(Debug, Default)]
+struct Script {
+    replies: VecDeque<Reply>,
+    invocations: Vec<Invocation>,
+    decode_mode: DecodeMode,
+}
+
+/// Answers commands from an in-memory script and records every invocation.
+///
+/// A reply matches commands whose arguments start with its prefix.  The first
+/// matching reply wins; replies added with [`Self::once`] are used up by the
+/// command they answer.  Commands nothing matches fail with
+/// [`TransportError::Unscripted`].
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedTransport {
+    script: Arc<Mutex<Script>>,
+}
+
+impl ScriptedTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers every command starting with `prefix`.
+    pub fn reply<S: AsRef<str>>(self, prefix: &[S], output: TransportOutput) -> Self {
+        self.push(prefix, output, false);
+        self
+    }
+
+    /// Answers the next command starting with `prefix`.
+    pub fn once<S: AsRef<str>>(self, prefix: &[S], output: TransportOutput) -> Self {
+        self.push(prefix, output, true);
+        self
+    }
+
+    /// Like the script, the mode is shared by every clone.
+    pub fn with_decode_mode(self, mode: DecodeMode) -> Self {
+        self.script().decode_mode = mode;
+        self
+    }
+
+    /// Every command received so far, oldest first.
+    pub fn invocations(&self) -> Vec<Invocation> {
+        self.script().invocations.clone()
+    }
+
+    /// Arguments of every command received so far, joined with spaces.
+    pub fn commands(&self) -> Vec<String> {
+        self.script()
+            .invocations
+            .iter()
+            .map(Invocation::command)
+            .collect()
+    }
+
+    fn push<S: AsRef<str>>(&self, prefix: &[S], output: TransportOutput, once: bool) {
+        self.script().replies.push_back(Reply {
+            prefix: prefix.iter().map(|arg| arg.as_ref().to_string()).collect(),
+            output,
+            once,
+        });
+    }
+
+    fn script(&self) -> std::sync::MutexGuard<'_, Script> {
+        self.script
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn answer(&self, invocation: Invocation) -> Result<TransportOutput, TransportError> {
+        let mut script = self.script();
+        let command = invocation.command();
+        let matched = script
+            .replies
+            .iter()
+            .position(|reply| invocation.starts_with(&reply.prefix));
+        script.invocations.push(invocation);
+        let index = matched.ok_or(TransportError::Unscripted { command })?;
+        if script.replies[index].once {
+            let reply = script.replies.remove(index).expect("matched reply");
+            Ok(reply.output)
+        } else {
+            Ok(script.replies[index].output.clone())
+        }
+    }
+}
+
+impl DecapodTransport for ScriptedTransport {
+    fn invoke(&self, invocation: Invocation) -> InvokeFuture<'_> {
+        let answer = self.answer(invocation);
+        Box::pin(async move { answer })
+    }
+
+    fn decode_mode(&self) -> DecodeMode {
+        self.script().decode_mode
+    }
+}
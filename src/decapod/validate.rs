@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
-use std::process::Stdio;
-use tokio::process::Command;
+use super::envelope::{self, DecodeMode, EnvelopeError, ParseError};
+use super::transport::{default_transport, DecapodTransport, Invocation};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ValidationResult {
@@ -34,11 +35,15 @@ pub struct ValidationWarning {
 #[derive(Debug, Clone)]
 pub struct Validator {
     strict: bool,
+    transport: Arc<dyn DecapodTransport>,
 }
 
 impl Validator {
     pub fn new() -> Self {
-        Self { strict: false }
+        Self {
+            strict: false,
+            transport: default_transport(),
+        }
     }
 
     pub fn strict(mut self) -> Self {
@@ -46,16 +51,20 @@ impl Validator {
         self
     }
 
+    pub fn with_transport(mut self, transport: Arc<dyn DecapodTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
     pub async fn run(&self) -> anyhow::Result<ValidationResult> {
-        let output = Command::new("decapod")
-            .args(["validate"])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .await?;
+        let output = self.transport.invoke(Invocation::new(&["validate"])).await?;
+        let passed = output.success();
+        if self.transport.decode_mode() == DecodeMode::Strict {
+            return Self::decode_report("validate", &output.stdout, passed);
+        }
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let stdout = output.stdout;
+        let stderr = output.stderr;
 
         if stdout.trim().is_empty() && stderr.trim().is_empty() {
             return Err(anyhow::anyhow!("validate produced no output"));
@@ -74,7 +83,7 @@ impl Validator {
         }
 
         Ok(ValidationResult {
-            passed: output.status.success(),
+            passed,
             gate: "unknown".to_string(),
             details: Self::parse_text_output(&output_str),
             errors: Vec::new(),
@@ -82,6 +91,21 @@ impl Validator {
         })
     }
 
+    /// A strict report must be JSON and agree with the exit status.
+    fn decode_report(what: &str, stdout: &str, exited_cleanly: bool) -> anyhow::Result<ValidationResult> {
+        let result: ValidationResult = envelope::decode(what, stdout, DecodeMode::Strict)?;
+        if result.passed && !exited_cleanly {
+            return Err(EnvelopeError {
+                what: what.to_string(),
+                kind: ParseError::Inconsistent {
+                    reason: "report passed but validate exited with an error".to_string(),
+                },
+            }
+            .into());
+        }
+        Ok(result)
+    }
+
     fn parse_text_output(output: &str) -> Vec<ValidationDetail> {
         output
             .lines()
@@ -95,22 +119,23 @@ impl Validator {
     }
 
     pub async fn run_gate(&self, gate: &str) -> anyhow::Result<ValidationResult> {
-        let output = Command::new("decapod")
-            .args(["validate", "--gate", gate])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
+        let output = self
+            .transport
+            .invoke(Invocation::new(&["validate", "--gate", gate]))
             .await?;
+        if self.transport.decode_mode() == DecodeMode::Strict {
+            let what = format!("validate --gate {gate}");
+            return Self::decode_report(&what, &output.stdout, output.success());
+        }
 
-        let output_str = String::from_utf8_lossy(&output.stdout)
-            .to_string();
+        let output_str = output.stdout.clone();
 
         if let Ok(result) = serde_json::from_str(&output_str) {
             return Ok(result);
         }
 
         Ok(ValidationResult {
-            passed: output.status.success(),
+            passed: output.success(),
             gate: gate.to_string(),
             details: Vec::new(),
             errors: Vec::new(),
@@ -1,6 +1,8 @@
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::process::Stdio;
-use tokio::process::Command;
+use super::envelope;
+use super::transport::{default_transport, DecapodTransport, Invocation};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Workspace {
@@ -20,14 +22,14 @@ pub enum WorkspaceStatus {
 }
 
 pub struct WorkspaceManager {
-    binary_path: String,
+    transport: Arc<dyn DecapodTransport>,
     session_token: Option<String>,
 }
 
 impl WorkspaceManager {
     pub fn new() -> Self {
         Self {
-            binary_path: "decapod".to_string(),
+            transport: default_transport(),
             session_token: None,
         }
     }
@@ -37,26 +39,22 @@ impl WorkspaceManager {
         self
     }
 
-    async fn run_command(&self, args: &[&str]) -> anyhow::Result<String> {
-        let mut cmd = Command::new(&self.binary_path);
-        cmd.args(args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
+    pub fn with_transport(mut self, transport: Arc<dyn DecapodTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
 
-        if let Some(ref token) = self.session_token {
-            cmd.env("DECAPOD_SESSION_PASSWORD", token);
-        }
+    async fn run_command(&self, args: &[&str]) -> anyhow::Result<String> {
+        let invocation = Invocation::new(args).with_session(self.session_token.as_deref());
+        self.transport.invoke(invocation).await?.into_stdout("workspace command")
+    }
 
-        let output = cmd.output().await?;
+    fn decode<T: DeserializeOwned>(&self, what: &str, output: &str) -> anyhow::Result<T> {
+        Ok(envelope::decode(what, output, self.transport.decode_mode())?)
+    }
 
-        if output.status.success() {
-            Ok(String::from_utf8_lossy(&output.stdout).to_string())
-        } else {
-            Err(anyhow::anyhow!(
-                "workspace command failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ))
-        }
+    fn decode_list<T: DeserializeOwned>(&self, what: &str, output: &str) -> anyhow::Result<Vec<T>> {
+        Ok(envelope::decode_list(what, output, self.transport.decode_mode())?)
     }
 
     pub async fn ensure(&self, name: Option<&str>) -> anyhow::Result<Workspace> {
@@ -69,55 +67,28 @@ impl WorkspaceManager {
 
         let output = self.run_command(&args).await?;
         
-        if let Ok(ws) = serde_json::from_str(&output) {
-            return Ok(ws);
-        }
-
-        Err(anyhow::anyhow!("failed to ensure workspace"))
+        self.decode("workspace ensure", &output)
     }
 
     pub async fn status(&self) -> anyhow::Result<WorkspaceStatusResponse> {
         let args = vec!["workspace", "status"];
         let output = self.run_command(&args).await?;
         
-        if let Ok(status) = serde_json::from_str(&output) {
-            return Ok(status);
-        }
-
-        Ok(WorkspaceStatusResponse {
-            current: None,
-            available: Vec::new(),
-            default_branch: "main".to_string(),
-        })
+        self.decode("workspace status", &output)
     }
 
     pub async fn list(&self) -> anyhow::Result<Vec<Workspace>> {
         let args = vec!["workspace", "list"];
         let output = self.run_command(&args).await?;
         
-        if let Ok(workspaces) = serde_json::from_str(&output) {
-            return Ok(workspaces);
-        }
-
-        let mut workspaces = Vec::new();
-        for line in output.lines() {
-            if let Ok(ws) = serde_json::from_str(line) {
-                workspaces.push(ws);
-            }
-        }
-
-        Ok(workspaces)
+        self.decode_list("workspace list", &output)
     }
 
     pub async fn enter(&self, name: &str) -> anyhow::Result<Workspace> {
         let args = vec!["workspace", "enter", "--name", name];
         let output = self.run_command(&args).await?;
         
-        if let Ok(ws) = serde_json::from_str(&output) {
-            return Ok(ws);
-        }
-
-        Err(anyhow::anyhow!("failed to enter workspace"))
+        self.decode("workspace enter", &output)
     }
 
     pub async fn suspend(&self, name: &str) -> anyhow::Result<()> {
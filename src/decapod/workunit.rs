@@ -1,7 +1,12 @@
+use super::commitment::ProofType;
+use super::proof::EvidenceError;
+use crate::governed_run::{ApprovalProgress, ApprovalQuorum, ScopeApproval};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::process::Stdio;
-use tokio::process::Command;
+use super::envelope;
+use super::transport::{default_transport, DecapodTransport, Invocation};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkUnit {
@@ -51,25 +56,67 @@ pub struct Approval {
     pub scope: Vec<String>,
 }
 
+/// Tallies recorded approvals against a quorum; a scope is satisfied once
+/// any approver has signed it.
+pub fn approval_progress(quorum: ApprovalQuorum, approvals: &[Approval]) -> ApprovalProgress {
+    let scopes = quorum
+        .scopes
+        .iter()
+        .map(|scope| {
+            let approvers: Vec<String> = approvals
+                .iter()
+                .filter(|approval| approval.scope.contains(scope))
+                .map(|approval| approval.approver.clone())
+                .collect();
+            ScopeApproval {
+                scope: scope.clone(),
+                satisfied: !approvers.is_empty(),
+                approvers,
+            }
+        })
+        .collect();
+    ApprovalProgress { quorum, scopes }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Proof {
     pub id: String,
     pub proof_type: String,
     pub criteria: String,
+    #[serde(default)]
     pub evidence: HashMap<String, serde_json::Value>,
     pub passed: bool,
     pub verified_at: Option<String>,
 }
 
+impl Proof {
+    /// The proof type, if it is one Pincher knows.
+    pub fn kind(&self) -> Option<ProofType> {
+        self.proof_type.parse().ok()
+    }
+
+    /// Checks the evidence against the schema of its proof type.
+    pub fn check_evidence(&self) -> Result<(), EvidenceError> {
+        let kind: ProofType = self.proof_type.parse()?;
+        kind.validate_evidence(&self.evidence)
+    }
+
+    /// Reads the evidence as one of the typed evidence records in
+    /// [`super::proof`].
+    pub fn evidence_as<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_value(serde_json::Value::Object(self.evidence.clone().into_iter().collect()))
+    }
+}
+
 pub struct WorkUnitManager {
-    binary_path: String,
+    transport: Arc<dyn DecapodTransport>,
     session_token: Option<String>,
 }
 
 impl WorkUnitManager {
     pub fn new() -> Self {
         Self {
-            binary_path: "decapod".to_string(),
+            transport: default_transport(),
             session_token: None,
         }
     }
@@ -79,48 +126,36 @@ impl WorkUnitManager {
         self
     }
 
-    async fn run_command(&self, args: &[&str]) -> anyhow::Result<String> {
-        let mut cmd = Command::new(&self.binary_path);
-        cmd.args(args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
+    pub fn with_transport(mut self, transport: Arc<dyn DecapodTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
 
-        if let Some(ref token) = self.session_token {
-            cmd.env("DECAPOD_SESSION_PASSWORD", token);
-        }
+    async fn run_command(&self, args: &[&str]) -> anyhow::Result<String> {
+        let invocation = Invocation::new(args).with_session(self.session_token.as_deref());
+        self.transport.invoke(invocation).await?.into_stdout("workunit command")
+    }
 
-        let output = cmd.output().await?;
+    fn decode<T: DeserializeOwned>(&self, what: &str, output: &str) -> anyhow::Result<T> {
+        Ok(envelope::decode(what, output, self.transport.decode_mode())?)
+    }
 
-        if output.status.success() {
-            Ok(String::from_utf8_lossy(&output.stdout).to_string())
-        } else {
-            Err(anyhow::anyhow!(
-                "workunit command failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ))
-        }
+    fn decode_list<T: DeserializeOwned>(&self, what: &str, output: &str) -> anyhow::Result<Vec<T>> {
+        Ok(envelope::decode_list(what, output, self.transport.decode_mode())?)
     }
 
     pub async fn init(&self, task_id: &str, intent_ref: &str) -> anyhow::Result<WorkUnit> {
         let args = vec!["workunit", "init", "--task-id", task_id, "--intent-ref", intent_ref];
         let output = self.run_command(&args).await?;
         
-        if let Ok(wu) = serde_json::from_str(&output) {
-            return Ok(wu);
-        }
-
-        Err(anyhow::anyhow!("failed to init workunit"))
+        self.decode("workunit init", &output)
     }
 
     pub async fn get(&self, workunit_id: &str) -> anyhow::Result<WorkUnit> {
         let args = vec!["workunit", "get", "--id", workunit_id];
         let output = self.run_command(&args).await?;
         
-        if let Ok(wu) = serde_json::from_str(&output) {
-            return Ok(wu);
-        }
-
-        Err(anyhow::anyhow!("failed to get workunit"))
+        self.decode("workunit get", &output)
     }
 
     pub async fn list(&self, task_id: Option<&str>, status: Option<&str>) -> anyhow::Result<Vec<WorkUnit>> {
@@ -138,18 +173,7 @@ impl WorkUnitManager {
 
         let output = self.run_command(&args).await?;
         
-        if let Ok(workunits) = serde_json::from_str(&output) {
-            return Ok(workunits);
-        }
-
-        let mut workunits = Vec::new();
-        for line in output.lines() {
-            if let Ok(wu) = serde_json::from_str(line) {
-                workunits.push(wu);
-            }
-        }
-
-        Ok(workunits)
+        self.decode_list("workunit list", &output)
     }
 
     pub async fn update_state(&self, workunit_id: &str, intent: Option<&str>, plan: Option<&str>) -> anyhow::Result<WorkUnit> {
@@ -167,11 +191,7 @@ impl WorkUnitManager {
 
         let output = self.run_command(&args).await?;
         
-        if let Ok(wu) = serde_json::from_str(&output) {
-            return Ok(wu);
-        }
-
-        Err(anyhow::anyhow!("failed to update workunit state"))
+        self.decode("workunit update", &output)
     }
 
     pub async fn add_patch(&self, workunit_id: &str, path: &str, operation: &str, content: Option<&str>) -> anyhow::Result<WorkUnit> {
@@ -184,11 +204,7 @@ impl WorkUnitManager {
 
         let output = self.run_command(&args).await?;
         
-        if let Ok(wu) = serde_json::from_str(&output) {
-            return Ok(wu);
-        }
-
-        Err(anyhow::anyhow!("failed to add patch"))
+        self.decode("workunit patch", &output)
     }
 
     pub async fn request_approval(&self, workunit_id: &str, scope: Vec<&str>) -> anyhow::Result<WorkUnit> {
@@ -201,45 +217,50 @@ impl WorkUnitManager {
 
         let output = self.run_command(&args).await?;
         
-        if let Ok(wu) = serde_json::from_str(&output) {
-            return Ok(wu);
-        }
-
-        Err(anyhow::anyhow!("failed to request approval"))
+        self.decode("workunit approve", &output)
     }
 
-    pub async fn record_proof(&self, workunit_id: &str, proof_type: &str, criteria: &str, _evidence: HashMap<String, serde_json::Value>) -> anyhow::Result<WorkUnit> {
-        let args = vec!["workunit", "proof", "--id", workunit_id, "--type", proof_type, "--criteria", criteria];
+    /// Records a proof with its evidence and returns the proof as Decapod
+    /// stored it.  Evidence must match the schema of `proof_type`, and the
+    /// stored proof must carry the same evidence back.
+    pub async fn record_proof(&self, workunit_id: &str, proof_type: ProofType, criteria: &str, evidence: HashMap<String, serde_json::Value>) -> anyhow::Result<Proof> {
+        proof_type.validate_evidence(&evidence)?;
+        let encoded = serde_json::to_string(&evidence)?;
+        let args = vec!["workunit", "proof", "--id", workunit_id, "--type", proof_type.as_str(), "--criteria", criteria, "--evidence", &encoded];
         
         let output = self.run_command(&args).await?;
         
-        if let Ok(wu) = serde_json::from_str(&output) {
-            return Ok(wu);
+        let wu: WorkUnit = self.decode("workunit proof", &output)?;
+
+        let proof = wu
+            .proofs
+            .into_iter()
+            .rev()
+            .find(|p| p.proof_type == proof_type.as_str() && p.criteria == criteria)
+            .ok_or_else(|| anyhow::anyhow!("workunit {workunit_id} does not list the recorded proof"))?;
+
+        if proof.evidence != evidence {
+            return Err(anyhow::anyhow!(
+                "workunit {workunit_id} stored different evidence for proof {}",
+                proof.id
+            ));
         }
 
-        Err(anyhow::anyhow!("failed to record proof"))
+        Ok(proof)
     }
 
     pub async fn complete(&self, workunit_id: &str) -> anyhow::Result<WorkUnit> {
         let args = vec!["workunit", "complete", "--id", workunit_id];
         let output = self.run_command(&args).await?;
         
-        if let Ok(wu) = serde_json::from_str(&output) {
-            return Ok(wu);
-        }
-
-        Err(anyhow::anyhow!("failed to complete workunit"))
+        self.decode("workunit complete", &output)
     }
 
     pub async fn fail(&self, workunit_id: &str, reason: &str) -> anyhow::Result<WorkUnit> {
         let args = vec!["workunit", "fail", "--id", workunit_id, "--reason", reason];
         let output = self.run_command(&args).await?;
         
-        if let Ok(wu) = serde_json::from_str(&output) {
-            return Ok(wu);
-        }
-
-        Err(anyhow::anyhow!("failed to mark workunit as failed"))
+        self.decode("workunit fail", &output)
     }
 }
 
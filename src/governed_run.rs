@@ -4,17 +4,36 @@
 //! implementation.  It is the narrow boundary between a host such as Amnion,
 //! the Pincher loop, and the Decapod control plane.
 
+use crate::decapod::commitment::StateCommitment;
+pub use crate::decapod::governance::AdvisoryPriority;
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use thiserror::Error;
 
+pub mod chain;
+pub mod projection;
+pub mod redaction;
+pub mod scheduler;
+pub mod schema;
+pub mod version;
+
+pub use chain::EventChainLink;
+pub use redaction::{RedactionPolicy, Redactor};
+pub use scheduler::{RunScheduler, WorkspaceLease};
+pub use version::{ContractSupport, ContractVersion};
+
 /// Stable identifier for the first host contract.
 pub const GOVERNED_RUN_CONTRACT_ID: &str = "pincher.governed-run";
 /// Version of [`GOVERNED_RUN_CONTRACT_ID`].
-pub const GOVERNED_RUN_CONTRACT_VERSION: &str = "1.0.0";
+pub const GOVERNED_RUN_CONTRACT_VERSION: &str = "1.1.0";
+/// Newest accepted version of each supported major; see [`version`].
+pub const SUPPORTED_CONTRACT_VERSIONS: &[&str] = &[GOVERNED_RUN_CONTRACT_VERSION];
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct ContractIdentity {
     pub id: String,
     pub version: String,
@@ -27,23 +46,38 @@ impl ContractIdentity {
             version: GOVERNED_RUN_CONTRACT_VERSION.to_string(),
         }
     }
-
-    fn is_supported(&self) -> bool {
-        self.id == GOVERNED_RUN_CONTRACT_ID && self.version == GOVERNED_RUN_CONTRACT_VERSION
-    }
 }
 
 #[derive(Debug, Error, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ContractError {
     #[error("{field} reference must not be empty")]
     EmptyReference { field: &'static str },
+    #[error("contract version {version} is not MAJOR.MINOR.PATCH")]
+    InvalidVersion { version: String },
+    #[error("unknown contract {id}")]
+    UnknownContract { id: String },
+    #[error("contract version {version} is not compatible with {supported:?}")]
+    UnsupportedVersion {
+        version: String,
+        supported: Vec<String>,
+    },
+    #[error("no offered contract version {offered:?} is compatible with {supported:?}")]
+    NoCommonVersion {
+        offered: Vec<String>,
+        supported: Vec<String>,
+    },
+    #[error("{document} is malformed: {reason}")]
+    Malformed {
+        document: &'static str,
+        reason: String,
+    },
 }
 
 macro_rules! public_reference {
     ($name:ident, $field:literal) => {
-        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
         #[serde(transparent)]
-        pub struct $name(String);
+        pub struct $name(#[schemars(length(min = 1))] String);
 
         impl $name {
             pub fn new(value: impl Into<String>) -> Result<Self, ContractError> {
@@ -73,6 +107,7 @@ public_reference!(SessionRef, "session");
 public_reference!(TaskRef, "task");
 public_reference!(WorkUnitRef, "work_unit");
 public_reference!(RepositoryRef, "repository");
+public_reference!(ProjectRootRef, "project_root");
 public_reference!(WorkspaceRef, "workspace");
 public_reference!(CustodyReceiptRef, "custody_receipt");
 public_reference!(ContextEvidenceRef, "context_evidence");
@@ -85,13 +120,17 @@ public_reference!(CorrelationId, "correlation_id");
 public_reference!(IdempotencyKey, "idempotency_key");
 public_reference!(EventId, "event_id");
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema, Default)]
 pub struct CustodyBinding {
     pub session: Option<SessionRef>,
     pub task: Option<TaskRef>,
     pub work_unit: Option<WorkUnitRef>,
     pub repository: Option<RepositoryRef>,
     pub workspace: Option<WorkspaceRef>,
+    /// Decapod project root the repository was bound in.  When set, Decapod
+    /// must report the same root or the run is refused.
+    #[serde(default)]
+    pub project_root: Option<ProjectRootRef>,
 }
 
 impl CustodyBinding {
@@ -108,9 +147,27 @@ impl CustodyBinding {
             work_unit: Some(work_unit),
             repository: Some(repository),
             workspace: Some(workspace),
+            project_root: None,
         }
     }
 
+    pub fn with_project_root(mut self, root: ProjectRootRef) -> Self {
+        self.project_root = Some(root);
+        self
+    }
+
+    /// Why `evidence` does not describe the repository this binding names,
+    /// if it does not.
+    fn repository_mismatch(&self, evidence: &CustodyEvidence) -> Option<CustodyFailure> {
+        let repository_differs = self.repository.as_ref() != Some(&evidence.repository);
+        let root_differs =
+            self.project_root.is_some() && self.project_root != evidence.project_root;
+        (repository_differs || root_differs).then(|| CustodyFailure::RepositoryMismatch {
+            repository: evidence.repository.clone(),
+            project_root: evidence.project_root.clone(),
+        })
+    }
+
     fn missing_fields(&self) -> Vec<CustodyField> {
         let mut missing = Vec::new();
         if self.session.is_none() {
@@ -132,7 +189,7 @@ impl CustodyBinding {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum CustodyField {
     Session,
@@ -142,7 +199,7 @@ pub enum CustodyField {
     Workspace,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct RunRequest {
     pub contract: ContractIdentity,
     pub run_id: RunId,
@@ -150,6 +207,10 @@ pub struct RunRequest {
     pub correlation_id: CorrelationId,
     pub idempotency_key: IdempotencyKey,
     pub custody: CustodyBinding,
+    /// Critical advisories the host has acknowledged; see
+    /// [`BlockedReason::AdvisoryUnacknowledged`].
+    #[serde(default)]
+    pub acknowledgements: Vec<AdvisoryAcknowledgement>,
 }
 
 impl RunRequest {
@@ -167,26 +228,44 @@ impl RunRequest {
             correlation_id,
             idempotency_key,
             custody,
+            acknowledgements: Vec::new(),
         }
     }
 
+    /// Resubmits the run with `acknowledgement` of a critical advisory.
+    pub fn with_acknowledgement(mut self, acknowledgement: AdvisoryAcknowledgement) -> Self {
+        self.acknowledgements.push(acknowledgement);
+        self
+    }
+
+    /// The host's acknowledgement of `advisory`, if any.
+    pub fn acknowledgement(
+        &self,
+        advisory: &ApprovalInterlockRef,
+    ) -> Option<&AdvisoryAcknowledgement> {
+        self.acknowledgements
+            .iter()
+            .find(|acknowledgement| &acknowledgement.advisory == advisory)
+    }
+
     pub fn validate(&self) -> Result<(), RunFailure> {
-        if !self.contract.is_supported() {
+        if !self.contract.is_compatible() {
             return Err(RunFailure::InvalidRequest {
                 reason: InvalidRequestReason::UnsupportedContract {
                     id: self.contract.id.clone(),
                     version: self.contract.version.clone(),
                 },
-                remediation: Some(Remediation::new(
-                    "use pincher.governed-run contract version 1.0.0",
-                )),
+                remediation: Some(Remediation::new(format!(
+                    "use a {GOVERNED_RUN_CONTRACT_ID} contract version compatible with {}",
+                    SUPPORTED_CONTRACT_VERSIONS.join(", ")
+                ))),
             });
         }
         Ok(())
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct CustodyEvidence {
     pub session: SessionRef,
     pub task: TaskRef,
@@ -195,34 +274,66 @@ pub struct CustodyEvidence {
     pub workspace: WorkspaceRef,
     pub receipt: CustodyReceiptRef,
     pub workspace_allowed: bool,
+    /// Project root Decapod resolved the repository in.
+    #[serde(default)]
+    pub project_root: Option<ProjectRootRef>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct ContextEvidence {
     pub reference: ContextEvidenceRef,
     pub resolved: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct ValidationEvidence {
     pub reference: ValidationEvidenceRef,
     pub passed: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct ProofEvidence {
     pub reference: ProofEvidenceRef,
     pub backed: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct ApprovalEvidence {
     pub reference: ApprovalEvidenceRef,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct AdvisoryEvidence {
     pub reference: Option<ApprovalInterlockRef>,
+    /// A critical advisory holds the run until the host acknowledges it.
+    #[serde(default)]
+    pub priority: AdvisoryPriority,
+}
+
+impl AdvisoryEvidence {
+    pub fn is_critical(&self) -> bool {
+        self.priority == AdvisoryPriority::Critical
+    }
+}
+
+/// The host's acknowledgement of a critical advisory, supplied on the
+/// resubmitted [`RunRequest`] and recorded in the snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AdvisoryAcknowledgement {
+    pub advisory: ApprovalInterlockRef,
+    /// Who acknowledged the advisory, as the host names them.
+    pub acknowledged_by: String,
+    pub acknowledged_at: DateTime<Utc>,
+}
+
+impl AdvisoryAcknowledgement {
+    pub fn new(advisory: ApprovalInterlockRef, acknowledged_by: impl Into<String>) -> Self {
+        Self {
+            advisory,
+            acknowledged_by: acknowledged_by.into(),
+            acknowledged_at: Utc::now(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -233,6 +344,8 @@ pub enum InterlockDecision {
     Block {
         reference: ApprovalInterlockRef,
         remediation: Remediation,
+        #[serde(default)]
+        policy: Option<GovernancePolicy>,
     },
 }
 
@@ -242,17 +355,131 @@ pub enum ApprovalStatus {
     NotRequired,
     Granted {
         evidence: ApprovalEvidence,
+        /// The policy granted, kept if the grant falls short of its quorum.
+        #[serde(default)]
+        policy: Option<GovernancePolicy>,
+        /// Decapod's tally when the approval needed several sign-offs.
+        #[serde(default)]
+        progress: Option<ApprovalProgress>,
     },
     Pending {
         reference: ApprovalInterlockRef,
         remediation: Remediation,
+        #[serde(default)]
+        policy: Option<GovernancePolicy>,
+        /// Sign-offs Decapod has counted so far.
+        #[serde(default)]
+        progress: Option<ApprovalProgress>,
     },
     Denied {
         reference: ApprovalInterlockRef,
         remediation: Remediation,
+        #[serde(default)]
+        policy: Option<GovernancePolicy>,
     },
 }
 
+/// Sign-offs an approval needs: one from each scope, and `approvals_needed`
+/// distinct approvers in total.  An approver who signs for two scopes counts
+/// once, and one who signs only for scopes outside the quorum not at all.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ApprovalQuorum {
+    /// The interlock being approved.
+    pub reference: ApprovalInterlockRef,
+    pub scopes: Vec<String>,
+    pub approvals_needed: u32,
+}
+
+/// Who has signed off for one scope, and whether Decapod counts the scope
+/// as satisfied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ScopeApproval {
+    pub scope: String,
+    pub approvers: Vec<String>,
+    pub satisfied: bool,
+}
+
+/// Decapod's tally of the sign-offs an [`ApprovalQuorum`] asks for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ApprovalProgress {
+    pub quorum: ApprovalQuorum,
+    pub scopes: Vec<ScopeApproval>,
+}
+
+impl ApprovalProgress {
+    /// Distinct approvers across the quorum's scopes.
+    pub fn approvals(&self) -> u32 {
+        let mut approvers: Vec<&str> = self
+            .scopes
+            .iter()
+            .filter(|scope| self.quorum.scopes.contains(&scope.scope))
+            .flat_map(|scope| scope.approvers.iter().map(String::as_str))
+            .collect();
+        approvers.sort_unstable();
+        approvers.dedup();
+        u32::try_from(approvers.len()).unwrap_or(u32::MAX)
+    }
+
+    /// Required scopes Decapod does not report satisfied.
+    pub fn outstanding_scopes(&self) -> Vec<&str> {
+        self.quorum
+            .scopes
+            .iter()
+            .filter(|required| {
+                !self
+                    .scopes
+                    .iter()
+                    .any(|scope| &scope.scope == *required && scope.satisfied)
+            })
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Every required scope is satisfied and the quorum is reached.
+    pub fn is_satisfied(&self) -> bool {
+        self.outstanding_scopes().is_empty() && self.approvals() >= self.quorum.approvals_needed
+    }
+
+    fn remediation(&self) -> Remediation {
+        let outstanding = self.outstanding_scopes();
+        let mut action = format!(
+            "collect {} of {} approvals",
+            self.approvals(),
+            self.quorum.approvals_needed
+        );
+        if !outstanding.is_empty() {
+            action.push_str(&format!(", including {}", outstanding.join(", ")));
+        }
+        Remediation::new(action)
+    }
+}
+
+/// The Decapod policy behind an interlock or approval, as Decapod named it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct GovernancePolicy {
+    pub name: String,
+    /// Kind of approval the policy asks for, when it can be approved.
+    #[serde(default)]
+    pub required_approval: Option<String>,
+    /// Scopes whose approvers may sign off.
+    #[serde(default)]
+    pub approver_scope: Vec<String>,
+    /// Where the decision goes when the approvers do not answer.
+    #[serde(default)]
+    pub escalation_path: Option<String>,
+}
+
+impl GovernancePolicy {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            required_approval: None,
+            approver_scope: Vec::new(),
+            escalation_path: None,
+        }
+    }
+}
+
 /// The only Decapod operations the core engine requires for one run.
 ///
 /// Implementations must return authoritative references from Decapod.  They
@@ -295,7 +522,7 @@ pub trait DecapodControlPlane {
     ) -> Result<ProofEvidence, DecapodPortError>;
 }
 
-#[derive(Debug, Error, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Error, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum DecapodPortError {
     #[error("Decapod operation is unsupported: {operation}")]
     Unsupported {
@@ -394,7 +621,20 @@ pub trait ProviderTurn {
     fn infer(&self, request: GovernedInferenceRequest) -> Result<ProviderProposal, ProviderError>;
 }
 
-#[derive(Debug, Error, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// Explicit provider placeholder.  It fails closed until a real provider
+/// adapter is configured.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UnsupportedProviderTurn;
+
+impl ProviderTurn for UnsupportedProviderTurn {
+    fn infer(&self, _request: GovernedInferenceRequest) -> Result<ProviderProposal, ProviderError> {
+        Err(ProviderError::Unavailable {
+            reason: "no provider adapter is configured".to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum ProviderError {
     #[error("provider is unavailable: {reason}")]
     Unavailable { reason: String },
@@ -402,7 +642,7 @@ pub enum ProviderError {
     Rejected { reason: String },
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(transparent)]
 pub struct EventKind(String);
 
@@ -424,13 +664,15 @@ impl EventKind {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct EventCustody {
     pub session: SessionRef,
     pub task: TaskRef,
     pub work_unit: WorkUnitRef,
     pub repository: RepositoryRef,
     pub workspace: WorkspaceRef,
+    #[serde(default)]
+    pub project_root: Option<ProjectRootRef>,
 }
 
 impl From<&CustodyEvidence> for EventCustody {
@@ -441,11 +683,12 @@ impl From<&CustodyEvidence> for EventCustody {
             work_unit: evidence.work_unit.clone(),
             repository: evidence.repository.clone(),
             workspace: evidence.workspace.clone(),
+            project_root: evidence.project_root.clone(),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct RunEvent {
     pub contract: ContractIdentity,
     pub event_id: EventId,
@@ -464,6 +707,12 @@ pub struct RunEvent {
     pub proof_ref: Option<ProofEvidenceRef>,
     pub failure: Option<FailureCode>,
     pub payload: serde_json::Value,
+    /// Number of values the [`Redactor`] replaced before publication.
+    #[serde(default)]
+    pub redactions: u32,
+    /// Link to the previous event of the run; see [`chain`].
+    #[serde(default)]
+    pub chain: Option<EventChainLink>,
 }
 
 pub trait EventSink {
@@ -494,7 +743,7 @@ impl EventSink for InMemoryEventSink {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum RunState {
     Prepared,
@@ -530,13 +779,13 @@ impl RunState {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct StateTransition {
     pub from: RunState,
     pub to: RunState,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct Remediation {
     pub action: String,
 }
@@ -549,34 +798,50 @@ impl Remediation {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum CustodyFailure {
-    Missing { fields: Vec<CustodyField> },
-    WorkspaceNotAllowed { workspace: WorkspaceRef },
-    Rejected { reason: String },
+    Missing {
+        fields: Vec<CustodyField>,
+    },
+    WorkspaceNotAllowed {
+        workspace: WorkspaceRef,
+    },
+    Rejected {
+        reason: String,
+    },
+    /// The Decapod session expired while the run held it.
+    SessionExpired {
+        session: SessionRef,
+    },
+    /// Decapod resolved custody for a different repository or project root
+    /// than the binding recorded; the fields are what Decapod reported.
+    RepositoryMismatch {
+        repository: RepositoryRef,
+        project_root: Option<ProjectRootRef>,
+    },
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum InvalidRequestReason {
     UnsupportedContract { id: String, version: String },
     MissingCustody { fields: Vec<CustodyField> },
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum ValidationFailure {
     DecapodRejected,
     EvidenceMissing,
     ControlPlane { source: DecapodPortError },
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum ProofFailure {
     DecapodRejected,
     EvidenceMissing,
     ControlPlane { source: DecapodPortError },
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum FailureCode {
     InvalidRequest,
     Custody,
@@ -587,9 +852,30 @@ pub enum FailureCode {
     ControlPlane,
     EventSink,
     IllegalTransition,
+    Cancelled,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+impl FailureCode {
+    /// Process exit status used by the `pincher` command line.  Failures use
+    /// 10 and up so they never collide with success (0), general errors (1),
+    /// usage errors (2), or a blocked run (3).
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::InvalidRequest => 10,
+            Self::Custody => 11,
+            Self::Context => 12,
+            Self::Provider => 13,
+            Self::Validation => 14,
+            Self::Proof => 15,
+            Self::ControlPlane => 16,
+            Self::EventSink => 17,
+            Self::IllegalTransition => 18,
+            Self::Cancelled => 19,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum RunFailure {
     InvalidRequest {
         reason: InvalidRequestReason,
@@ -618,10 +904,12 @@ pub enum RunFailure {
         evidence: Option<ProofEvidenceRef>,
         remediation: Option<Remediation>,
     },
+    /// The host cancelled the run before it reached a terminal state.
+    Cancelled { remediation: Option<Remediation> },
 }
 
 impl RunFailure {
-    fn code(&self) -> FailureCode {
+    pub fn code(&self) -> FailureCode {
         match self {
             Self::InvalidRequest { .. } => FailureCode::InvalidRequest,
             Self::Custody { .. } => FailureCode::Custody,
@@ -629,27 +917,92 @@ impl RunFailure {
             Self::Provider { .. } => FailureCode::Provider,
             Self::Validation { .. } => FailureCode::Validation,
             Self::Proof { .. } => FailureCode::Proof,
+            Self::Cancelled { .. } => FailureCode::Cancelled,
         }
     }
+
+    pub fn remediation(&self) -> Option<&Remediation> {
+        match self {
+            Self::InvalidRequest { remediation, .. }
+            | Self::Custody { remediation, .. }
+            | Self::Context { remediation, .. }
+            | Self::Provider { remediation, .. }
+            | Self::Validation { remediation, .. }
+            | Self::Proof { remediation, .. }
+            | Self::Cancelled { remediation } => remediation.as_ref(),
+        }
+    }
+
+    /// Custody Decapod reports lost, such as a session that expired mid-run,
+    /// fails the run on custody whichever call reported it.
+    fn custody_lost(error: &DecapodPortError, receipt: &CustodyReceiptRef) -> Option<Self> {
+        let DecapodPortError::CustodyRejected { reason } = error else {
+            return None;
+        };
+        Some(Self::Custody {
+            reason: reason.clone(),
+            receipt: Some(receipt.clone()),
+            remediation: Some(custody_remediation(reason)),
+        })
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+fn custody_remediation(reason: &CustodyFailure) -> Remediation {
+    Remediation::new(match reason {
+        CustodyFailure::SessionExpired { .. } => "acquire a fresh Decapod session and rerun",
+        _ => "resolve the Decapod custody rejection before retrying",
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum BlockedReason {
     Interlock {
         reference: ApprovalInterlockRef,
         remediation: Remediation,
+        #[serde(default)]
+        policy: Option<GovernancePolicy>,
     },
     ApprovalPending {
         reference: ApprovalInterlockRef,
         remediation: Remediation,
+        #[serde(default)]
+        policy: Option<GovernancePolicy>,
     },
     ApprovalDenied {
         reference: ApprovalInterlockRef,
         remediation: Remediation,
+        #[serde(default)]
+        policy: Option<GovernancePolicy>,
+    },
+    /// Decapod raised a critical advisory the request does not acknowledge.
+    AdvisoryUnacknowledged {
+        reference: ApprovalInterlockRef,
+        remediation: Remediation,
     },
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+impl BlockedReason {
+    pub fn reference(&self) -> &ApprovalInterlockRef {
+        match self {
+            Self::Interlock { reference, .. }
+            | Self::ApprovalPending { reference, .. }
+            | Self::ApprovalDenied { reference, .. }
+            | Self::AdvisoryUnacknowledged { reference, .. } => reference,
+        }
+    }
+
+    /// The policy Decapod blocked the run under, when it named one.
+    pub fn policy(&self) -> Option<&GovernancePolicy> {
+        match self {
+            Self::Interlock { policy, .. }
+            | Self::ApprovalPending { policy, .. }
+            | Self::ApprovalDenied { policy, .. } => policy.as_ref(),
+            Self::AdvisoryUnacknowledged { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct RunSnapshot {
     pub contract: ContractIdentity,
     pub request: RunRequest,
@@ -657,16 +1010,54 @@ pub struct RunSnapshot {
     pub custody: Option<CustodyEvidence>,
     pub context: Option<ContextEvidence>,
     pub advisory: Option<AdvisoryEvidence>,
+    /// The host's acknowledgement of a critical advisory.
+    #[serde(default)]
+    pub acknowledgement: Option<AdvisoryAcknowledgement>,
     pub approval: Option<ApprovalEvidence>,
+    /// Decapod's latest tally of a multi-scope approval.
+    #[serde(default)]
+    pub approval_progress: Option<ApprovalProgress>,
     pub validation: Option<ValidationEvidence>,
     pub proof: Option<ProofEvidence>,
     pub blocked: Option<BlockedReason>,
     pub failure: Option<RunFailure>,
     pub transitions: Vec<StateTransition>,
     pub event_count: u64,
+    /// Digest of the last published event.
+    #[serde(default)]
+    pub chain_head: Option<String>,
+    /// Local commitment produced when the run reached its latest terminal
+    /// state.
+    #[serde(default)]
+    pub commitment: Option<StateCommitment>,
+}
+
+impl RunSnapshot {
+    fn prepared(request: RunRequest) -> Self {
+        let request = request.upgraded();
+        Self {
+            contract: request.contract.clone(),
+            request,
+            state: RunState::Prepared,
+            custody: None,
+            context: None,
+            advisory: None,
+            acknowledgement: None,
+            approval: None,
+            approval_progress: None,
+            validation: None,
+            proof: None,
+            blocked: None,
+            failure: None,
+            transitions: Vec::new(),
+            event_count: 0,
+            chain_head: None,
+            commitment: None,
+        }
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum RunOutcome {
     Ready(RunSnapshot),
     Blocked(RunSnapshot),
@@ -726,11 +1117,34 @@ pub enum RunError {
     EventSink(#[from] EventSinkError),
 }
 
+/// Cooperative cancellation for one run.  The engine checks it before every
+/// Decapod and provider call, so a cancelled run claims no further custody,
+/// approval, validation, or proof; a call already in progress is not
+/// interrupted.
+#[derive(Debug, Clone, Default)]
+pub struct RunCancellation(Arc<AtomicBool>);
+
+impl RunCancellation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
 pub struct GovernedRunEngine<C, P, S> {
     control_plane: C,
     provider: P,
     event_sink: S,
     source: String,
+    redactor: Redactor,
+    scheduler: Option<RunScheduler>,
 }
 
 impl<C, P, S> GovernedRunEngine<C, P, S>
@@ -745,16 +1159,55 @@ where
             provider,
             event_sink,
             source: "pincher.governed-run".to_string(),
+            redactor: Redactor::default(),
+            scheduler: None,
         }
     }
 
+    /// Replaces the default redaction policy applied to every event before
+    /// it reaches the sink.
+    pub fn with_redaction_policy(mut self, policy: RedactionPolicy) -> Self {
+        self.redactor = Redactor::new(policy);
+        self
+    }
+
+    /// Makes every run wait for admission by `scheduler` once its custody
+    /// binding is complete, and hold the lease on its workspace until the
+    /// engine returns.  While waiting the run publishes
+    /// `run.activity.queued` events with its position, then
+    /// `run.activity.dequeued`.
+    pub fn with_scheduler(mut self, scheduler: RunScheduler) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// Contract versions this engine accepts and produces.
+    pub fn contract_support(&self) -> ContractSupport {
+        ContractSupport::current()
+    }
+
     pub fn run(&mut self, request: RunRequest) -> Result<RunOutcome, RunError> {
+        self.run_cancellable(request, &RunCancellation::new())
+    }
+
+    /// Runs like [`GovernedRunEngine::run`] but stops with
+    /// [`RunFailure::Cancelled`] at the next checkpoint after `cancellation`
+    /// fires.
+    pub fn run_cancellable(
+        &mut self,
+        request: RunRequest,
+        cancellation: &RunCancellation,
+    ) -> Result<RunOutcome, RunError> {
         if let Err(failure) = request.validate() {
             return self.failed_without_started_run(request, failure);
         }
 
-        let mut session = RunSession::new(request, &self.source, &mut self.event_sink);
+        let mut session =
+            RunSession::new(request, &self.source, &self.redactor, &mut self.event_sink);
         session.emit_state(RunState::Prepared)?;
+        if cancellation.is_cancelled() {
+            return session.finish_cancelled();
+        }
 
         let missing = session.snapshot.request.custody.missing_fields();
         if !missing.is_empty() {
@@ -766,24 +1219,75 @@ where
             });
         }
 
+        // A complete custody binding always names its workspace.
+        let workspace = session.snapshot.request.custody.workspace.clone();
+        let _lease = match (&self.scheduler, workspace) {
+            (Some(scheduler), Some(workspace)) => {
+                let run_id = session.snapshot.request.run_id.clone();
+                let mut published = Ok(());
+                let lease = scheduler.acquire(&run_id, &workspace, cancellation, |queued| {
+                    if published.is_ok() {
+                        published = session.emit_activity(
+                            EventKind::activity("queued"),
+                            serde_json::json!({
+                                "position": queued.position,
+                                "running": queued.running,
+                                "max_concurrent": queued.max_concurrent,
+                            }),
+                        );
+                    }
+                });
+                published?;
+                let Some(lease) = lease else {
+                    return session.finish_cancelled();
+                };
+                session.emit_activity(
+                    EventKind::activity("dequeued"),
+                    serde_json::json!({ "workspace": workspace }),
+                )?;
+                Some(lease)
+            }
+            _ => None,
+        };
+
+        if cancellation.is_cancelled() {
+            return session.finish_cancelled();
+        }
         let custody = match self
             .control_plane
             .validate_custody(&session.snapshot.request.custody)
         {
             Ok(custody) => custody,
             Err(error) => {
-                return session.finish_failure(RunFailure::Custody {
-                    reason: CustodyFailure::Rejected {
+                let reason = match error {
+                    DecapodPortError::CustodyRejected { reason } => reason,
+                    error => CustodyFailure::Rejected {
                         reason: error.to_string(),
                     },
+                };
+                return session.finish_failure(RunFailure::Custody {
+                    remediation: Some(custody_remediation(&reason)),
+                    reason,
                     receipt: None,
-                    remediation: Some(Remediation::new(
-                        "resolve the Decapod custody rejection before retrying",
-                    )),
                 });
             }
         };
 
+        if let Some(reason) = session
+            .snapshot
+            .request
+            .custody
+            .repository_mismatch(&custody)
+        {
+            return session.finish_failure(RunFailure::Custody {
+                reason,
+                receipt: Some(custody.receipt.clone()),
+                remediation: Some(Remediation::new(
+                    "run Pincher from the Decapod project root recorded in custody",
+                )),
+            });
+        }
+
         if !custody.workspace_allowed {
             return session.finish_failure(RunFailure::Custody {
                 reason: CustodyFailure::WorkspaceNotAllowed {
@@ -796,6 +1300,9 @@ where
             });
         }
 
+        if cancellation.is_cancelled() {
+            return session.finish_cancelled();
+        }
         let context = match self
             .control_plane
             .resolve_context(&custody, &session.snapshot.request.intent_id)
@@ -810,6 +1317,9 @@ where
                 });
             }
             Err(error) => {
+                if let Some(failure) = RunFailure::custody_lost(&error, &custody.receipt) {
+                    return session.finish_failure(failure);
+                }
                 return session.finish_failure(RunFailure::Context {
                     reason: error.to_string(),
                     remediation: Some(Remediation::new(
@@ -822,11 +1332,20 @@ where
         session.snapshot.custody = Some(custody.clone());
         session.snapshot.context = Some(context.clone());
         session.transition(RunState::ContextResolved)?;
-        session.emit_state(RunState::ContextResolved)?;
+        session.emit_state_with_payload(
+            RunState::ContextResolved,
+            serde_json::json!({ "custody": custody, "context": context }),
+        )?;
+        if cancellation.is_cancelled() {
+            return session.finish_cancelled();
+        }
 
         let interlocks = match self.control_plane.evaluate_interlocks(&custody, &context) {
             Ok(decision) => decision,
             Err(error) => {
+                if let Some(failure) = RunFailure::custody_lost(&error, &custody.receipt) {
+                    return session.finish_failure(failure);
+                }
                 return session.finish_failure(RunFailure::Context {
                     reason: error.to_string(),
                     remediation: Some(Remediation::new(
@@ -839,24 +1358,71 @@ where
         if let InterlockDecision::Block {
             reference,
             remediation,
+            policy,
         } = &interlocks
         {
             return session.finish_blocked(BlockedReason::Interlock {
                 reference: reference.clone(),
                 remediation: remediation.clone(),
+                policy: policy.clone(),
             });
         }
 
-        if let InterlockDecision::Allow { advisory } = interlocks {
-            session.snapshot.advisory = advisory;
-            if session.snapshot.advisory.is_some() {
-                session.emit_activity(EventKind::activity("advisory"), serde_json::Value::Null)?;
+        if let InterlockDecision::Allow {
+            advisory: Some(advisory),
+        } = interlocks
+        {
+            session.snapshot.advisory = Some(advisory.clone());
+            session.emit_activity(
+                EventKind::activity("advisory"),
+                serde_json::json!({ "advisory": advisory }),
+            )?;
+
+            if advisory.is_critical() {
+                let Some(reference) = advisory.reference.clone() else {
+                    return session.finish_failure(RunFailure::Context {
+                        reason: "Decapod raised a critical advisory without a reference"
+                            .to_string(),
+                        remediation: Some(Remediation::new(
+                            "obtain an acknowledgeable advisory reference from Decapod",
+                        )),
+                    });
+                };
+                let Some(acknowledgement) = session
+                    .snapshot
+                    .request
+                    .acknowledgement(&reference)
+                    .cloned()
+                else {
+                    return session.finish_blocked(BlockedReason::AdvisoryUnacknowledged {
+                        reference,
+                        remediation: Remediation::new(
+                            "acknowledge the critical Decapod advisory and resubmit the run",
+                        ),
+                    });
+                };
+                session.transition(RunState::AwaitingApproval)?;
+                session.emit_state_with_payload(
+                    RunState::AwaitingApproval,
+                    serde_json::json!({ "advisory": advisory }),
+                )?;
+                session.snapshot.acknowledgement = Some(acknowledgement.clone());
+                session.emit_activity(
+                    EventKind::activity("advisory_acknowledged"),
+                    serde_json::json!({ "acknowledgement": acknowledgement }),
+                )?;
             }
         }
 
+        if cancellation.is_cancelled() {
+            return session.finish_cancelled();
+        }
         let approval = match self.control_plane.approval_status(&custody, &context) {
             Ok(status) => status,
             Err(error) => {
+                if let Some(failure) = RunFailure::custody_lost(&error, &custody.receipt) {
+                    return session.finish_failure(failure);
+                }
                 return session.finish_failure(RunFailure::Context {
                     reason: error.to_string(),
                     remediation: Some(Remediation::new(
@@ -868,29 +1434,60 @@ where
 
         match approval {
             ApprovalStatus::NotRequired => {}
-            ApprovalStatus::Granted { evidence } => {
+            ApprovalStatus::Granted {
+                evidence,
+                progress: None,
+                ..
+            } => {
+                session.snapshot.approval = Some(evidence);
+            }
+            ApprovalStatus::Granted {
+                evidence,
+                policy,
+                progress: Some(progress),
+            } => {
+                session.approval_progress(&progress)?;
+                // A grant continues the run only once every scope is in.
+                if !progress.is_satisfied() {
+                    return session.finish_blocked(BlockedReason::ApprovalPending {
+                        reference: progress.quorum.reference.clone(),
+                        remediation: progress.remediation(),
+                        policy,
+                    });
+                }
                 session.snapshot.approval = Some(evidence);
             }
             ApprovalStatus::Pending {
                 reference,
                 remediation,
+                policy,
+                progress,
             } => {
+                if let Some(progress) = &progress {
+                    session.approval_progress(progress)?;
+                }
                 return session.finish_blocked(BlockedReason::ApprovalPending {
                     reference,
                     remediation,
+                    policy,
                 });
             }
             ApprovalStatus::Denied {
                 reference,
                 remediation,
+                policy,
             } => {
                 return session.finish_blocked(BlockedReason::ApprovalDenied {
                     reference,
                     remediation,
+                    policy,
                 });
             }
         }
 
+        if cancellation.is_cancelled() {
+            return session.finish_cancelled();
+        }
         session.transition(RunState::Executing)?;
         session.emit_state(RunState::Executing)?;
 
@@ -918,12 +1515,18 @@ where
             EventKind::activity("proposal_received"),
             serde_json::json!({ "proposal_ref": proposal.reference }),
         )?;
+        if cancellation.is_cancelled() {
+            return session.finish_cancelled();
+        }
         session.transition(RunState::Verifying)?;
         session.emit_state(RunState::Verifying)?;
 
         let validation = match self.control_plane.validate(&custody, &context, &proposal) {
             Ok(validation) => validation,
             Err(error) => {
+                if let Some(failure) = RunFailure::custody_lost(&error, &custody.receipt) {
+                    return session.finish_failure(failure);
+                }
                 return session.finish_failure(RunFailure::Validation {
                     reason: ValidationFailure::ControlPlane { source: error },
                     evidence: None,
@@ -944,9 +1547,15 @@ where
             });
         }
 
+        if cancellation.is_cancelled() {
+            return session.finish_cancelled();
+        }
         let proof = match self.control_plane.obtain_proof(&custody, &validation) {
             Ok(proof) => proof,
             Err(error) => {
+                if let Some(failure) = RunFailure::custody_lost(&error, &custody.receipt) {
+                    return session.finish_failure(failure);
+                }
                 return session.finish_failure(RunFailure::Proof {
                     reason: ProofFailure::ControlPlane { source: error },
                     evidence: None,
@@ -968,50 +1577,23 @@ where
         }
 
         session.transition(RunState::Ready)?;
-        session.emit_state(RunState::Ready)?;
+        session.emit_terminal(RunState::Ready, serde_json::Map::new(), None)?;
         Ok(RunOutcome::Ready(session.snapshot))
     }
 
     pub fn handoff(&mut self, outcome: RunOutcome) -> Result<RunOutcome, RunError> {
         let mut outcome = outcome.handoff()?;
-        let snapshot = outcome.snapshot().clone();
-        let event = RunEvent {
-            contract: snapshot.contract.clone(),
-            event_id: EventId::new(format!("{}-handoff", snapshot.request.run_id)).map_err(
-                |_| RunError::IllegalTransition {
-                    from: RunState::HandedOff,
-                    to: RunState::HandedOff,
-                },
-            )?,
-            run_id: snapshot.request.run_id.clone(),
-            correlation_id: snapshot.request.correlation_id.clone(),
-            sequence: snapshot.event_count + 1,
-            occurred_at: Utc::now(),
-            source: self.source.clone(),
-            kind: EventKind::state("handed_off"),
-            state: Some(RunState::HandedOff),
-            custody: snapshot.custody.as_ref().map(EventCustody::from),
-            advisory_ref: snapshot
-                .advisory
-                .as_ref()
-                .and_then(|evidence| evidence.reference.clone()),
-            approval_ref: snapshot.blocked.as_ref().map(|reason| match reason {
-                BlockedReason::Interlock { reference, .. }
-                | BlockedReason::ApprovalPending { reference, .. }
-                | BlockedReason::ApprovalDenied { reference, .. } => reference.clone(),
-            }),
-            approval_evidence_ref: snapshot
-                .approval
-                .as_ref()
-                .map(|evidence| evidence.reference.clone()),
-            validation_ref: snapshot.validation.as_ref().map(|e| e.reference.clone()),
-            proof_ref: snapshot.proof.as_ref().map(|e| e.reference.clone()),
-            failure: snapshot.failure.as_ref().map(RunFailure::code),
-            payload: serde_json::Value::Null,
-        };
-        self.event_sink.publish(event)?;
         if let RunOutcome::HandedOff { snapshot, .. } = &mut outcome {
-            snapshot.event_count += 1;
+            let mut session = RunSession {
+                sequence: snapshot.event_count,
+                snapshot: snapshot.clone(),
+                source: &self.source,
+                redactor: &self.redactor,
+                sink: &mut self.event_sink,
+            };
+            let failure = snapshot.failure.as_ref().map(RunFailure::code);
+            session.emit_terminal(RunState::HandedOff, serde_json::Map::new(), failure)?;
+            *snapshot = session.snapshot;
         }
         Ok(outcome)
     }
@@ -1028,7 +1610,9 @@ where
             custody: None,
             context: None,
             advisory: None,
+            acknowledgement: None,
             approval: None,
+            approval_progress: None,
             validation: None,
             proof: None,
             blocked: None,
@@ -1038,6 +1622,12 @@ where
                 to: RunState::Failed,
             }],
             event_count: 0,
+            chain_head: None,
+            commitment: None,
+        };
+        let snapshot = RunSnapshot {
+            commitment: Some(chain::commit(&snapshot, &self.source)),
+            ..snapshot
         };
         Ok(RunOutcome::Failed(snapshot))
     }
@@ -1047,29 +1637,22 @@ struct RunSession<'a> {
     snapshot: RunSnapshot,
     sequence: u64,
     source: &'a str,
+    redactor: &'a Redactor,
     sink: &'a mut dyn EventSink,
 }
 
 impl<'a> RunSession<'a> {
-    fn new(request: RunRequest, source: &'a str, sink: &'a mut dyn EventSink) -> Self {
+    fn new(
+        request: RunRequest,
+        source: &'a str,
+        redactor: &'a Redactor,
+        sink: &'a mut dyn EventSink,
+    ) -> Self {
         Self {
-            snapshot: RunSnapshot {
-                contract: request.contract.clone(),
-                request,
-                state: RunState::Prepared,
-                custody: None,
-                context: None,
-                advisory: None,
-                approval: None,
-                validation: None,
-                proof: None,
-                blocked: None,
-                failure: None,
-                transitions: Vec::new(),
-                event_count: 0,
-            },
+            snapshot: RunSnapshot::prepared(request),
             sequence: 0,
             source,
+            redactor,
             sink,
         }
     }
@@ -1090,10 +1673,20 @@ impl<'a> RunSession<'a> {
     }
 
     fn emit_state(&mut self, state: RunState) -> Result<(), RunError> {
+        self.emit_state_with_payload(state, serde_json::Value::Null)
+    }
+
+    /// State payloads carry the evidence a projection needs to rebuild the
+    /// snapshot; they contain references only, never resolved content.
+    fn emit_state_with_payload(
+        &mut self,
+        state: RunState,
+        payload: serde_json::Value,
+    ) -> Result<(), RunError> {
         self.emit(
             EventKind::state(&format!("{state:?}").to_lowercase()),
             Some(state),
-            serde_json::Value::Null,
+            payload,
             None,
         )
     }
@@ -1115,7 +1708,7 @@ impl<'a> RunSession<'a> {
     ) -> Result<(), RunError> {
         self.sequence += 1;
         self.snapshot.event_count = self.sequence;
-        self.sink.publish(RunEvent {
+        let mut event = RunEvent {
             contract: self.snapshot.contract.clone(),
             event_id: EventId::new(format!(
                 "{}-{}",
@@ -1138,11 +1731,11 @@ impl<'a> RunSession<'a> {
                 .advisory
                 .as_ref()
                 .and_then(|evidence| evidence.reference.clone()),
-            approval_ref: self.snapshot.blocked.as_ref().map(|reason| match reason {
-                BlockedReason::Interlock { reference, .. }
-                | BlockedReason::ApprovalPending { reference, .. }
-                | BlockedReason::ApprovalDenied { reference, .. } => reference.clone(),
-            }),
+            approval_ref: self
+                .snapshot
+                .blocked
+                .as_ref()
+                .map(|reason| reason.reference().clone()),
             approval_evidence_ref: self
                 .snapshot
                 .approval
@@ -1156,28 +1749,99 @@ impl<'a> RunSession<'a> {
             proof_ref: self.snapshot.proof.as_ref().map(|e| e.reference.clone()),
             failure,
             payload,
-        })?;
+            redactions: 0,
+            chain: None,
+        };
+        self.redactor.redact_event(&mut event);
+        let digest = chain::seal(&mut event, self.snapshot.chain_head.take());
+        self.snapshot.chain_head = Some(digest);
+        self.sink.publish(event)?;
         Ok(())
     }
 
+    /// Emits the event for a terminal state together with the commitment over
+    /// the run's evidence and the chain head that precedes it.  Validation and
+    /// proof evidence travel in full, so a projection reads whether they
+    /// passed rather than guessing from the state.
+    fn emit_terminal(
+        &mut self,
+        state: RunState,
+        mut payload: serde_json::Map<String, serde_json::Value>,
+        failure: Option<FailureCode>,
+    ) -> Result<(), RunError> {
+        if let Some(validation) = &self.snapshot.validation {
+            payload.insert(
+                "validation".to_string(),
+                serde_json::to_value(validation).unwrap_or_default(),
+            );
+        }
+        if let Some(proof) = &self.snapshot.proof {
+            payload.insert(
+                "proof".to_string(),
+                serde_json::to_value(proof).unwrap_or_default(),
+            );
+        }
+        let commitment = chain::commit(&self.snapshot, self.source);
+        payload.insert(
+            "commitment".to_string(),
+            serde_json::to_value(&commitment).unwrap_or_default(),
+        );
+        self.snapshot.commitment = Some(commitment);
+        let kind = match state {
+            RunState::HandedOff => EventKind::state("handed_off"),
+            _ => EventKind::state(&format!("{state:?}").to_lowercase()),
+        };
+        self.emit(kind, Some(state), payload.into(), failure)
+    }
+
+    /// Records Decapod's tally and publishes it as
+    /// `run.activity.approval_progress`.
+    fn approval_progress(&mut self, progress: &ApprovalProgress) -> Result<(), RunError> {
+        self.snapshot.approval_progress = Some(progress.clone());
+        self.emit_activity(
+            EventKind::activity("approval_progress"),
+            serde_json::json!({
+                "progress": progress,
+                "approvals": progress.approvals(),
+                "outstanding_scopes": progress.outstanding_scopes(),
+            }),
+        )
+    }
+
     fn finish_failure(mut self, failure: RunFailure) -> Result<RunOutcome, RunError> {
         self.snapshot.failure = Some(failure.clone());
         self.transition(RunState::Failed)?;
-        self.emit(
-            EventKind::state("failed"),
-            Some(RunState::Failed),
-            serde_json::Value::Null,
-            Some(failure.code()),
-        )?;
+        let mut payload = serde_json::Map::new();
+        payload.insert(
+            "failure".to_string(),
+            serde_json::to_value(&failure).unwrap_or_default(),
+        );
+        self.emit_terminal(RunState::Failed, payload, Some(failure.code()))?;
         Ok(RunOutcome::Failed(self.snapshot))
     }
 
+    fn finish_cancelled(self) -> Result<RunOutcome, RunError> {
+        self.finish_failure(RunFailure::Cancelled {
+            remediation: Some(Remediation::new(
+                "resubmit the run with a new run id if the work is still wanted",
+            )),
+        })
+    }
+
     fn finish_blocked(mut self, reason: BlockedReason) -> Result<RunOutcome, RunError> {
-        self.snapshot.blocked = Some(reason.clone());
-        self.transition(RunState::AwaitingApproval)?;
-        self.emit_state(RunState::AwaitingApproval)?;
+        let mut payload = serde_json::Map::new();
+        payload.insert(
+            "blocked".to_string(),
+            serde_json::to_value(&reason).unwrap_or_default(),
+        );
+        self.snapshot.blocked = Some(reason);
+        // A run paused on an acknowledged advisory is already waiting.
+        if self.snapshot.state != RunState::AwaitingApproval {
+            self.transition(RunState::AwaitingApproval)?;
+            self.emit_state_with_payload(RunState::AwaitingApproval, payload.clone().into())?;
+        }
         self.transition(RunState::Blocked)?;
-        self.emit_state(RunState::Blocked)?;
+        self.emit_terminal(RunState::Blocked, payload, None)?;
         Ok(RunOutcome::Blocked(self.snapshot))
     }
 }
@@ -1289,6 +1953,7 @@ mod tests {
                 workspace: binding.workspace.clone().unwrap(),
                 receipt: reference("custody-1"),
                 workspace_allowed: true,
+                project_root: binding.project_root.clone(),
             })
         }
 
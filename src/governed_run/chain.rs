@@ -0,0 +1,371 @@
+//! Tamper-evident linkage for published run events.
+//!
+//! Every event the engine publishes carries an [`EventChainLink`] whose digest
+//! covers the whole event, including the digest of the event before it.  Each
+//! terminal state additionally carries a [`StateCommitment`] over the run's
+//! evidence references and the chain head that preceded it.  The commitment is
+//! local integrity only; it cannot create Decapod validation or proof.
+
+use super::projection::RunProjection;
+use super::{RunEvent, RunFailure, RunId, RunSnapshot};
+use crate::decapod::commitment::{
+    CommitmentEntry, EntryType, StateCommitment, StateCommitmentManager,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct EventChainLink {
+    /// Digest of the previous event in the run, absent for the first event.
+    pub previous: Option<String>,
+    /// SHA-256 over the event serialized with an empty `digest`.
+    pub digest: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChainViolation {
+    /// The event carries no chain link.
+    Unlinked { sequence: u64 },
+    /// Events were removed or reordered before this one.
+    SequenceGap { expected: u64, found: u64 },
+    /// The event does not link to the digest of the event before it.
+    BrokenLink {
+        sequence: u64,
+        expected: Option<String>,
+        found: Option<String>,
+    },
+    /// The event content no longer matches its digest.
+    Altered { sequence: u64 },
+    /// A terminal commitment does not cover the chain head or evidence it
+    /// claims to.
+    CommitmentMismatch { sequence: u64 },
+    /// The log ends at a different head than the snapshot recorded.
+    HeadMismatch {
+        expected: Option<String>,
+        found: Option<String>,
+    },
+}
+
+/// Result of a successful verification of a stored log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainVerification {
+    pub head: Option<String>,
+    pub events: u64,
+    pub commitments: usize,
+}
+
+/// Computes the digest an event must carry given its current `previous` link.
+pub fn event_digest(event: &RunEvent) -> String {
+    let mut unsealed = event.clone();
+    unsealed.chain = Some(EventChainLink {
+        previous: event.chain.as_ref().and_then(|link| link.previous.clone()),
+        digest: String::new(),
+    });
+    let encoded = serde_json::to_vec(&unsealed).unwrap_or_default();
+    format!("{:x}", Sha256::digest(&encoded))
+}
+
+pub(super) fn seal(event: &mut RunEvent, previous: Option<String>) -> String {
+    event.chain = Some(EventChainLink {
+        previous,
+        digest: String::new(),
+    });
+    let digest = event_digest(event);
+    if let Some(link) = &mut event.chain {
+        link.digest = digest.clone();
+    }
+    digest
+}
+
+/// Builds the terminal commitment for a snapshot.  Successive terminal states
+/// of one run (for example `Ready` and then `HandedOff`) form their own chain.
+pub(super) fn commit(snapshot: &RunSnapshot, agent_id: &str) -> StateCommitment {
+    let mut manager = StateCommitmentManager::new(agent_id);
+    if let Some(previous) = &snapshot.commitment {
+        manager = manager.with_previous(previous.id.clone());
+    }
+    let entries = commitment_entries(&manager, snapshot);
+    manager.create_commitment(entries)
+}
+
+fn commitment_entries(
+    manager: &StateCommitmentManager,
+    snapshot: &RunSnapshot,
+) -> Vec<CommitmentEntry> {
+    let run = &snapshot.request.run_id;
+    let mut entries = vec![manager.add_reference(
+        EntryType::Intent,
+        &format!("intent:{run}"),
+        snapshot.request.intent_id.as_str(),
+    )];
+    if let Some(custody) = &snapshot.custody {
+        entries.push(manager.add_reference(
+            EntryType::Custody,
+            &format!("custody:{run}"),
+            custody.receipt.as_str(),
+        ));
+    }
+    if let Some(context) = &snapshot.context {
+        entries.push(manager.add_reference(
+            EntryType::Context,
+            &format!("context:{run}"),
+            context.reference.as_str(),
+        ));
+    }
+    if let Some(reference) = snapshot
+        .advisory
+        .as_ref()
+        .and_then(|advisory| advisory.reference.as_ref())
+    {
+        entries.push(manager.add_reference(
+            EntryType::Advisory,
+            &format!("advisory:{run}"),
+            reference.as_str(),
+        ));
+    }
+    if let Some(acknowledgement) = &snapshot.acknowledgement {
+        entries.push(manager.add_reference(
+            EntryType::Advisory,
+            &format!("acknowledgement:{run}"),
+            &format!(
+                "{} by {} at {}",
+                acknowledgement.advisory,
+                acknowledgement.acknowledged_by,
+                acknowledgement.acknowledged_at.to_rfc3339()
+            ),
+        ));
+    }
+    if let Some(approval) = &snapshot.approval {
+        entries.push(manager.add_reference(
+            EntryType::Approval,
+            &format!("approval:{run}"),
+            approval.reference.as_str(),
+        ));
+    }
+    if let Some(progress) = &snapshot.approval_progress {
+        let signoffs: Vec<String> = progress
+            .scopes
+            .iter()
+            .map(|scope| format!("{}={}", scope.scope, scope.approvers.join("+")))
+            .collect();
+        entries.push(manager.add_reference(
+            EntryType::Approval,
+            &format!("approvals:{run}"),
+            &signoffs.join(","),
+        ));
+    }
+    if let Some(blocked) = &snapshot.blocked {
+        entries.push(manager.add_reference(
+            EntryType::Approval,
+            &format!("interlock:{run}"),
+            blocked.reference().as_str(),
+        ));
+    }
+    if let Some(validation) = &snapshot.validation {
+        entries.push(manager.add_reference(
+            EntryType::Validation,
+            &format!("validation:{run}"),
+            validation.reference.as_str(),
+        ));
+    }
+    if let Some(proof) = &snapshot.proof {
+        entries.push(manager.add_reference(
+            EntryType::Proof,
+            &format!("proof:{run}"),
+            proof.reference.as_str(),
+        ));
+    }
+    entries.push(events_entry(manager, run, snapshot));
+    entries
+}
+
+fn events_entry(
+    manager: &StateCommitmentManager,
+    run: &RunId,
+    snapshot: &RunSnapshot,
+) -> CommitmentEntry {
+    let mut entry = manager.add_reference(
+        EntryType::Event,
+        &format!("events:{run}"),
+        snapshot.chain_head.as_deref().unwrap_or_default(),
+    );
+    entry.metadata.insert(
+        "sequence".to_string(),
+        serde_json::json!(snapshot.event_count),
+    );
+    entry
+        .metadata
+        .insert("state".to_string(), serde_json::json!(snapshot.state));
+    if let Some(failure) = snapshot.failure.as_ref().map(RunFailure::code) {
+        entry
+            .metadata
+            .insert("failure".to_string(), serde_json::json!(failure));
+    }
+    entry
+}
+
+/// Verifies a stored log of one run's events.
+///
+/// Removed, reordered, or altered events break either the sequence, the
+/// digest, or the link to the previous digest.  Truncation of the tail is only
+/// detectable against a known head, such as [`RunSnapshot::chain_head`].
+/// Commitment entries are checked against the references they record; use
+/// [`verify_run`] to check them against the run's evidence.
+pub fn verify_event_chain(
+    events: &[RunEvent],
+    expected_head: Option<&str>,
+) -> Result<ChainVerification, ChainViolation> {
+    let mut previous: Option<String> = None;
+    let mut previous_commitment: Option<String> = None;
+    let mut commitments = 0;
+    for (offset, event) in events.iter().enumerate() {
+        let expected = offset as u64 + 1;
+        if event.sequence != expected {
+            return Err(ChainViolation::SequenceGap {
+                expected,
+                found: event.sequence,
+            });
+        }
+        let Some(link) = &event.chain else {
+            return Err(ChainViolation::Unlinked {
+                sequence: event.sequence,
+            });
+        };
+        if link.previous != previous {
+            return Err(ChainViolation::BrokenLink {
+                sequence: event.sequence,
+                expected: previous,
+                found: link.previous.clone(),
+            });
+        }
+        if event_digest(event) != link.digest {
+            return Err(ChainViolation::Altered {
+                sequence: event.sequence,
+            });
+        }
+        if let Some(commitment) = event_commitment(event) {
+            if !commitment_covers(&commitment, link.previous.as_deref(), &previous_commitment) {
+                return Err(ChainViolation::CommitmentMismatch {
+                    sequence: event.sequence,
+                });
+            }
+            previous_commitment = Some(commitment.id.clone());
+            commitments += 1;
+        }
+        previous = Some(link.digest.clone());
+    }
+
+    if let Some(expected) = expected_head
+        && previous.as_deref() != Some(expected)
+    {
+        return Err(ChainViolation::HeadMismatch {
+            expected: Some(expected.to_string()),
+            found: previous,
+        });
+    }
+
+    Ok(ChainVerification {
+        head: previous,
+        events: events.len() as u64,
+        commitments,
+    })
+}
+
+/// Verifies a stored log against the snapshot the engine returned for it.
+///
+/// In addition to [`verify_event_chain`], every commitment in the log, and
+/// the snapshot's own, must commit to exactly the evidence the run holds at
+/// that point.
+pub fn verify_run(
+    events: &[RunEvent],
+    snapshot: &RunSnapshot,
+) -> Result<ChainVerification, ChainViolation> {
+    let verification = verify_event_chain(events, snapshot.chain_head.as_deref())?;
+    let mut projection = RunProjection::new(snapshot.request.clone());
+    for event in events {
+        projection.apply(event);
+        if let Some(commitment) = event_commitment(event)
+            && !commitment_matches(&commitment, projection.snapshot())
+        {
+            return Err(ChainViolation::CommitmentMismatch {
+                sequence: event.sequence,
+            });
+        }
+    }
+    if let Some(commitment) = &snapshot.commitment
+        && !commitment_matches(commitment, snapshot)
+    {
+        return Err(ChainViolation::CommitmentMismatch {
+            sequence: snapshot.event_count,
+        });
+    }
+    Ok(verification)
+}
+
+fn event_commitment(event: &RunEvent) -> Option<StateCommitment> {
+    event
+        .payload
+        .get("commitment")
+        .cloned()
+        .and_then(|value| serde_json::from_value(value).ok())
+}
+
+/// Recomputes the evidence entries from `snapshot` and compares them with the
+/// commitment's; the event entry is checked against the chain instead.
+fn commitment_matches(commitment: &StateCommitment, snapshot: &RunSnapshot) -> bool {
+    let manager = StateCommitmentManager::new(&commitment.agent_id);
+    let evidence = |entries: &[CommitmentEntry]| -> Vec<(EntryType, String, String)> {
+        entries
+            .iter()
+            .filter(|entry| entry.entry_type != EntryType::Event)
+            .map(|entry| {
+                (
+                    entry.entry_type.clone(),
+                    entry.key.clone(),
+                    entry.value_hash.clone(),
+                )
+            })
+            .collect()
+    };
+    evidence(&commitment.commitments) == evidence(&commitment_entries(&manager, snapshot))
+}
+
+fn commitment_covers(
+    commitment: &StateCommitment,
+    chain_head: Option<&str>,
+    previous_commitment: &Option<String>,
+) -> bool {
+    if previous_commitment.is_some() && commitment.previous_commitment != *previous_commitment {
+        return false;
+    }
+    let mut manager = StateCommitmentManager::new(&commitment.agent_id);
+    if let Some(previous) = &commitment.previous_commitment {
+        manager = manager.with_previous(previous.clone());
+    }
+    if !manager.verify_chain(commitment) {
+        return false;
+    }
+    // Each entry must hash the reference it records.
+    let recorded = commitment.commitments.iter().all(|entry| {
+        let reference = entry
+            .metadata
+            .get("reference")
+            .and_then(|reference| reference.as_str())
+            .unwrap_or_default();
+        manager
+            .add_reference(entry.entry_type.clone(), &entry.key, reference)
+            .value_hash
+            == entry.value_hash
+    });
+    if !recorded {
+        return false;
+    }
+    let expected_head = manager
+        .add_reference(EntryType::Event, "", chain_head.unwrap_or_default())
+        .value_hash;
+    commitment
+        .commitments
+        .iter()
+        .any(|entry| entry.entry_type == EntryType::Event && entry.value_hash == expected_head)
+}
@@ -0,0 +1,453 @@
+//! Host-side projection of a governed-run event stream.
+//!
+//! A host that only receives [`RunEvent`]s can fold them back into the
+//! [`RunSnapshot`] the engine held when it published them.  The projection
+//! never trusts the stream: sequence gaps, illegal transitions, and evidence
+//! references that do not line up are reported as typed discrepancies rather
+//! than silently repaired.
+
+use super::{
+    ApprovalEvidence, ApprovalEvidenceRef, ApprovalInterlockRef, BlockedReason, ContextEvidence,
+    CustodyEvidence, EventCustody, FailureCode, ProofEvidence, ProofEvidenceRef, RunEvent,
+    RunFailure, RunRequest, RunSnapshot, RunState, StateTransition, ValidationEvidence,
+    ValidationEvidenceRef, chain,
+};
+use serde::{Deserialize, Serialize};
+
+const STATE_PREFIX: &str = "run.state.";
+const ACTIVITY_PREFIX: &str = "run.activity.";
+const ADVISORY_KIND: &str = "run.activity.advisory";
+const ACKNOWLEDGED_KIND: &str = "run.activity.advisory_acknowledged";
+const APPROVAL_PROGRESS_KIND: &str = "run.activity.approval_progress";
+
+/// Evidence carried on every event whose value the projection cross-checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvidenceField {
+    Custody,
+    Advisory,
+    Acknowledgement,
+    Approval,
+    ApprovalEvidence,
+    ApprovalProgress,
+    Validation,
+    Proof,
+    Failure,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProjectionDiscrepancy {
+    /// The stream contained no events for the run.
+    EmptyStream,
+    /// An event arrived out of order, twice, or after a missing event.
+    SequenceGap { expected: u64, found: u64 },
+    /// The event belongs to another run, correlation, or contract.
+    ForeignEvent { sequence: u64 },
+    /// The stream does not begin with the `Prepared` state.
+    MissingPrepared { found: Option<RunState> },
+    IllegalTransition {
+        sequence: u64,
+        from: RunState,
+        to: RunState,
+    },
+    /// An activity event reports a state other than the projected state, or a
+    /// state event carries no state.
+    StateMismatch {
+        sequence: u64,
+        expected: Option<RunState>,
+        found: Option<RunState>,
+    },
+    /// A state event lacks the payload needed to rebuild its evidence.
+    MissingPayload { sequence: u64, field: EvidenceField },
+    /// An evidence reference differs from the one the projection expects.
+    EvidenceMismatch {
+        sequence: u64,
+        field: EvidenceField,
+        expected: Option<String>,
+        found: Option<String>,
+    },
+    /// The event does not link to the digest of the event before it, or its
+    /// content no longer matches its own digest.
+    BrokenChain { sequence: u64 },
+    /// The stream stops before a terminal state.
+    Incomplete { state: RunState },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectionReport {
+    pub snapshot: RunSnapshot,
+    pub discrepancies: Vec<ProjectionDiscrepancy>,
+}
+
+impl ProjectionReport {
+    /// True when the stream reached a terminal state without discrepancies.
+    pub fn is_consistent(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        !self.discrepancies.iter().any(|discrepancy| {
+            matches!(
+                discrepancy,
+                ProjectionDiscrepancy::EmptyStream
+                    | ProjectionDiscrepancy::SequenceGap { .. }
+                    | ProjectionDiscrepancy::Incomplete { .. }
+            )
+        })
+    }
+}
+
+/// Incremental fold from ordered [`RunEvent`]s to a [`RunSnapshot`].
+#[derive(Debug, Clone)]
+pub struct RunProjection {
+    snapshot: RunSnapshot,
+    started: bool,
+    discrepancies: Vec<ProjectionDiscrepancy>,
+}
+
+impl RunProjection {
+    pub fn new(request: RunRequest) -> Self {
+        Self {
+            snapshot: RunSnapshot::prepared(request),
+            started: false,
+            discrepancies: Vec::new(),
+        }
+    }
+
+    pub fn snapshot(&self) -> &RunSnapshot {
+        &self.snapshot
+    }
+
+    pub fn discrepancies(&self) -> &[ProjectionDiscrepancy] {
+        &self.discrepancies
+    }
+
+    pub fn apply(&mut self, event: &RunEvent) {
+        let sequence = event.sequence;
+        let expected = self.snapshot.event_count + 1;
+        if sequence != expected {
+            self.discrepancies.push(ProjectionDiscrepancy::SequenceGap {
+                expected,
+                found: sequence,
+            });
+        }
+        self.snapshot.event_count = sequence.max(self.snapshot.event_count);
+
+        let request = &self.snapshot.request;
+        if event.run_id != request.run_id
+            || event.correlation_id != request.correlation_id
+            || event.contract != self.snapshot.contract
+        {
+            self.discrepancies
+                .push(ProjectionDiscrepancy::ForeignEvent { sequence });
+            return;
+        }
+
+        self.follow_chain(event);
+
+        let kind = event.kind.as_str();
+        if kind.starts_with(STATE_PREFIX) {
+            self.apply_state(event);
+        } else if kind.starts_with(ACTIVITY_PREFIX) {
+            self.apply_activity(event);
+        } else {
+            // Unknown kinds are preserved by hosts but carry no semantics.
+            return;
+        }
+        self.check_evidence(event);
+    }
+
+    pub fn finish(mut self) -> ProjectionReport {
+        if !self.started {
+            if self.snapshot.event_count == 0 {
+                self.discrepancies.push(ProjectionDiscrepancy::EmptyStream);
+            } else {
+                self.discrepancies
+                    .push(ProjectionDiscrepancy::MissingPrepared { found: None });
+            }
+        }
+        if self.started
+            && !matches!(
+                self.snapshot.state,
+                RunState::Ready | RunState::Blocked | RunState::Failed | RunState::HandedOff
+            )
+        {
+            self.discrepancies.push(ProjectionDiscrepancy::Incomplete {
+                state: self.snapshot.state,
+            });
+        }
+
+        ProjectionReport {
+            snapshot: self.snapshot,
+            discrepancies: self.discrepancies,
+        }
+    }
+
+    fn apply_state(&mut self, event: &RunEvent) {
+        let sequence = event.sequence;
+        let Some(next) = event.state else {
+            self.discrepancies
+                .push(ProjectionDiscrepancy::StateMismatch {
+                    sequence,
+                    expected: None,
+                    found: None,
+                });
+            return;
+        };
+
+        if !self.started {
+            self.started = true;
+            if next != RunState::Prepared {
+                self.discrepancies
+                    .push(ProjectionDiscrepancy::MissingPrepared { found: Some(next) });
+                self.transition(next);
+            }
+        } else {
+            if !self.snapshot.state.can_transition_to(next) {
+                self.discrepancies
+                    .push(ProjectionDiscrepancy::IllegalTransition {
+                        sequence,
+                        from: self.snapshot.state,
+                        to: next,
+                    });
+            }
+            self.transition(next);
+        }
+
+        match next {
+            RunState::ContextResolved => {
+                match payload_field::<CustodyEvidence>(event, "custody") {
+                    Some(custody) => self.snapshot.custody = Some(custody),
+                    None => self.missing(sequence, EvidenceField::Custody),
+                }
+                if let Some(context) = payload_field::<ContextEvidence>(event, "context") {
+                    self.snapshot.context = Some(context);
+                }
+            }
+            RunState::AwaitingApproval | RunState::Blocked => {
+                match payload_field::<BlockedReason>(event, "blocked") {
+                    Some(reason) => self.snapshot.blocked = Some(reason),
+                    None if next == RunState::Blocked && self.snapshot.blocked.is_none() => {
+                        self.missing(sequence, EvidenceField::Approval)
+                    }
+                    None => {}
+                }
+            }
+            RunState::Executing => {
+                if let Some(reference) = &event.approval_evidence_ref {
+                    self.snapshot.approval = Some(ApprovalEvidence {
+                        reference: reference.clone(),
+                    });
+                }
+            }
+            RunState::Ready | RunState::Failed => {
+                match payload_field::<ValidationEvidence>(event, "validation") {
+                    Some(validation) => self.snapshot.validation = Some(validation),
+                    None if event.validation_ref.is_some() || next == RunState::Ready => {
+                        self.missing(sequence, EvidenceField::Validation)
+                    }
+                    None => {}
+                }
+                match payload_field::<ProofEvidence>(event, "proof") {
+                    Some(proof) => self.snapshot.proof = Some(proof),
+                    None if event.proof_ref.is_some() || next == RunState::Ready => {
+                        self.missing(sequence, EvidenceField::Proof)
+                    }
+                    None => {}
+                }
+                if next == RunState::Failed {
+                    match payload_field::<RunFailure>(event, "failure") {
+                        Some(failure) => self.snapshot.failure = Some(failure),
+                        None => self.missing(sequence, EvidenceField::Failure),
+                    }
+                }
+            }
+            RunState::Prepared | RunState::Verifying | RunState::HandedOff => {}
+        }
+    }
+
+    fn follow_chain(&mut self, event: &RunEvent) {
+        let linked = event.chain.as_ref().is_some_and(|link| {
+            link.previous == self.snapshot.chain_head && chain::event_digest(event) == link.digest
+        });
+        if !linked {
+            self.discrepancies.push(ProjectionDiscrepancy::BrokenChain {
+                sequence: event.sequence,
+            });
+        }
+        self.snapshot.chain_head = event.chain.as_ref().map(|link| link.digest.clone());
+        if let Some(commitment) = payload_field(event, "commitment") {
+            self.snapshot.commitment = Some(commitment);
+        }
+    }
+
+    fn apply_activity(&mut self, event: &RunEvent) {
+        if event.state != Some(self.snapshot.state) {
+            self.discrepancies
+                .push(ProjectionDiscrepancy::StateMismatch {
+                    sequence: event.sequence,
+                    expected: Some(self.snapshot.state),
+                    found: event.state,
+                });
+        }
+        match event.kind.as_str() {
+            ADVISORY_KIND => match payload_field(event, "advisory") {
+                Some(advisory) => self.snapshot.advisory = Some(advisory),
+                None => self.missing(event.sequence, EvidenceField::Advisory),
+            },
+            ACKNOWLEDGED_KIND => match payload_field(event, "acknowledgement") {
+                Some(acknowledgement) => self.snapshot.acknowledgement = Some(acknowledgement),
+                None => self.missing(event.sequence, EvidenceField::Acknowledgement),
+            },
+            APPROVAL_PROGRESS_KIND => match payload_field(event, "progress") {
+                Some(progress) => self.snapshot.approval_progress = Some(progress),
+                None => self.missing(event.sequence, EvidenceField::ApprovalProgress),
+            },
+            _ => {}
+        }
+    }
+
+    /// Compares every evidence reference on the event with the references the
+    /// engine would have attached given the projected snapshot.
+    fn check_evidence(&mut self, event: &RunEvent) {
+        let snapshot = &self.snapshot;
+        let sequence = event.sequence;
+        let mut mismatches = Vec::new();
+
+        let expected_custody = snapshot.custody.as_ref().map(EventCustody::from);
+        if event.custody != expected_custody {
+            mismatches.push((
+                EvidenceField::Custody,
+                expected_custody.map(|custody| custody.summary()),
+                event.custody.as_ref().map(EventCustody::summary),
+            ));
+        }
+        compare(
+            &mut mismatches,
+            EvidenceField::Advisory,
+            snapshot
+                .advisory
+                .as_ref()
+                .and_then(|advisory| advisory.reference.as_ref()),
+            event.advisory_ref.as_ref(),
+        );
+        compare::<ApprovalInterlockRef>(
+            &mut mismatches,
+            EvidenceField::Approval,
+            snapshot.blocked.as_ref().map(BlockedReason::reference),
+            event.approval_ref.as_ref(),
+        );
+        compare::<ApprovalEvidenceRef>(
+            &mut mismatches,
+            EvidenceField::ApprovalEvidence,
+            snapshot
+                .approval
+                .as_ref()
+                .map(|approval| &approval.reference),
+            event.approval_evidence_ref.as_ref(),
+        );
+        compare::<ValidationEvidenceRef>(
+            &mut mismatches,
+            EvidenceField::Validation,
+            snapshot
+                .validation
+                .as_ref()
+                .map(|validation| &validation.reference),
+            event.validation_ref.as_ref(),
+        );
+        compare::<ProofEvidenceRef>(
+            &mut mismatches,
+            EvidenceField::Proof,
+            snapshot.proof.as_ref().map(|proof| &proof.reference),
+            event.proof_ref.as_ref(),
+        );
+
+        let expected_failure = match event.state {
+            Some(RunState::Failed) if event.kind.as_str().starts_with(STATE_PREFIX) => {
+                snapshot.failure.as_ref().map(RunFailure::code)
+            }
+            Some(RunState::HandedOff) => snapshot.failure.as_ref().map(RunFailure::code),
+            _ => None,
+        };
+        if event.failure != expected_failure {
+            mismatches.push((
+                EvidenceField::Failure,
+                expected_failure.map(failure_name),
+                event.failure.clone().map(failure_name),
+            ));
+        }
+
+        self.discrepancies
+            .extend(mismatches.into_iter().map(|(field, expected, found)| {
+                ProjectionDiscrepancy::EvidenceMismatch {
+                    sequence,
+                    field,
+                    expected,
+                    found,
+                }
+            }));
+    }
+
+    fn transition(&mut self, next: RunState) {
+        if next != self.snapshot.state {
+            self.snapshot.transitions.push(StateTransition {
+                from: self.snapshot.state,
+                to: next,
+            });
+        }
+        self.snapshot.state = next;
+    }
+
+    fn missing(&mut self, sequence: u64, field: EvidenceField) {
+        self.discrepancies
+            .push(ProjectionDiscrepancy::MissingPayload { sequence, field });
+    }
+}
+
+/// Folds a complete stream in one call.
+pub fn project<'a>(
+    request: RunRequest,
+    events: impl IntoIterator<Item = &'a RunEvent>,
+) -> ProjectionReport {
+    let mut projection = RunProjection::new(request);
+    for event in events {
+        projection.apply(event);
+    }
+    projection.finish()
+}
+
+impl EventCustody {
+    fn summary(&self) -> String {
+        format!(
+            "{}/{}/{}/{}/{}",
+            self.session, self.task, self.work_unit, self.repository, self.workspace
+        )
+    }
+}
+
+fn compare<T: PartialEq + std::fmt::Display>(
+    mismatches: &mut Vec<(EvidenceField, Option<String>, Option<String>)>,
+    field: EvidenceField,
+    expected: Option<&T>,
+    found: Option<&T>,
+) {
+    if expected != found {
+        mismatches.push((
+            field,
+            expected.map(ToString::to_string),
+            found.map(ToString::to_string),
+        ));
+    }
+}
+
+fn failure_name(code: FailureCode) -> String {
+    format!("{code:?}")
+}
+
+fn payload_field<T: for<'de> Deserialize<'de>>(event: &RunEvent, field: &str) -> Option<T> {
+    event
+        .payload
+        .get(field)
+        .cloned()
+        .and_then(|value| serde_json::from_value(value).ok())
+}
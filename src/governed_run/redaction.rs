@@ -0,0 +1,312 @@
+//! Redaction applied to every event before it reaches an [`EventSink`].
+//!
+//! Event payloads are free-form JSON and some of their strings (provider and
+//! control-plane failure reasons, for example) originate outside Pincher.  The
+//! [`Redactor`] replaces denied keys, known credential shapes, and
+//! high-entropy tokens with a digest marker so hosts can still correlate
+//! repeated values without ever receiving them.
+//!
+//! [`EventSink`]: super::EventSink
+
+use super::{EventKind, RunEvent};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+const MARKER_PREFIX: &str = "[redacted:sha256:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialPattern {
+    /// The token following an `Authorization: Bearer` style prefix.
+    BearerToken,
+    /// `AKIA`/`ASIA` access key identifiers.
+    AwsAccessKey,
+    /// `ghp_`, `gho_`, `ghu_`, `ghs_`, `ghr_`, and `github_pat_` tokens.
+    GitHubToken,
+    /// `xoxb-`, `xoxp-`, `xoxa-`, `xoxr-`, and `xoxs-` tokens.
+    SlackToken,
+    /// `sk-`, `sk_live_`, `sk_test_`, and `rk_live_` provider keys.
+    SecretKey,
+    /// Three-segment JSON Web Tokens.
+    Jwt,
+    /// PEM private key blocks.
+    PrivateKeyBlock,
+}
+
+impl CredentialPattern {
+    pub fn all() -> Vec<Self> {
+        vec![
+            Self::BearerToken,
+            Self::AwsAccessKey,
+            Self::GitHubToken,
+            Self::SlackToken,
+            Self::SecretKey,
+            Self::Jwt,
+            Self::PrivateKeyBlock,
+        ]
+    }
+
+    fn matches_token(self, token: &str) -> bool {
+        match self {
+            Self::BearerToken | Self::PrivateKeyBlock => false,
+            Self::AwsAccessKey => {
+                token.len() == 20
+                    && (token.starts_with("AKIA") || token.starts_with("ASIA"))
+                    && token
+                        .chars()
+                        .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+            }
+            Self::GitHubToken => {
+                token.len() >= 20
+                    && ["ghp_", "gho_", "ghu_", "ghs_", "ghr_", "github_pat_"]
+                        .iter()
+                        .any(|prefix| token.starts_with(prefix))
+            }
+            Self::SlackToken => ["xoxb-", "xoxp-", "xoxa-", "xoxr-", "xoxs-"]
+                .iter()
+                .any(|prefix| token.starts_with(prefix)),
+            Self::SecretKey => {
+                token.len() >= 20
+                    && ["sk-", "sk_live_", "sk_test_", "rk_live_"]
+                        .iter()
+                        .any(|prefix| token.starts_with(prefix))
+            }
+            Self::Jwt => {
+                let segments: Vec<&str> = token.split('.').collect();
+                token.starts_with("eyJ")
+                    && token.len() >= 30
+                    && segments.len() == 3
+                    && segments.iter().all(|segment| {
+                        !segment.is_empty()
+                            && segment
+                                .chars()
+                                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                    })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedactionPolicy {
+    /// Object keys whose values are always redacted.  Matching ignores case,
+    /// `_`, and `-`, and applies to any key containing an entry.
+    pub denied_keys: Vec<String>,
+    pub credential_patterns: Vec<CredentialPattern>,
+    /// Tokens at least this long are checked for entropy.
+    pub min_entropy_length: usize,
+    /// Shannon entropy, in bits per character, at or above which a token
+    /// containing both letters and digits is treated as a secret.
+    pub entropy_threshold: f64,
+}
+
+impl Default for RedactionPolicy {
+    fn default() -> Self {
+        Self {
+            denied_keys: [
+                "password",
+                "passwd",
+                "secret",
+                "token",
+                "apikey",
+                "privatekey",
+                "authorization",
+                "bearer",
+                "credential",
+                "cookie",
+                "prompt",
+                "resolvedcontext",
+            ]
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+            credential_patterns: CredentialPattern::all(),
+            min_entropy_length: 32,
+            entropy_threshold: 4.3,
+        }
+    }
+}
+
+impl RedactionPolicy {
+    pub fn with_denied_key(mut self, key: impl Into<String>) -> Self {
+        self.denied_keys.push(key.into());
+        self
+    }
+
+    fn denies(&self, key: &str) -> bool {
+        let key = normalize_key(key);
+        self.denied_keys
+            .iter()
+            .any(|denied| key.contains(&normalize_key(denied)))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    policy: RedactionPolicy,
+}
+
+impl Redactor {
+    pub fn new(policy: RedactionPolicy) -> Self {
+        Self { policy }
+    }
+
+    pub fn policy(&self) -> &RedactionPolicy {
+        &self.policy
+    }
+
+    /// Redacts the event in place and records the number of replacements in
+    /// [`RunEvent::redactions`].
+    pub fn redact_event(&self, event: &mut RunEvent) -> u32 {
+        let mut count = self.redact_value(&mut event.payload);
+
+        let (kind, replaced) = self.redact_text(event.kind.as_str());
+        if replaced > 0 {
+            event.kind = EventKind::new(kind);
+            count += replaced;
+        }
+        let (source, replaced) = self.redact_text(&event.source);
+        if replaced > 0 {
+            event.source = source;
+            count += replaced;
+        }
+
+        event.redactions += count;
+        count
+    }
+
+    pub fn redact_value(&self, value: &mut serde_json::Value) -> u32 {
+        match value {
+            serde_json::Value::Object(map) => {
+                let mut count = 0;
+                for (key, value) in map.iter_mut() {
+                    if self.policy.denies(key) && !value.is_null() && !is_marker(value) {
+                        *value = serde_json::Value::String(marker(&value.to_string()));
+                        count += 1;
+                    } else {
+                        count += self.redact_value(value);
+                    }
+                }
+                count
+            }
+            serde_json::Value::Array(values) => values
+                .iter_mut()
+                .map(|value| self.redact_value(value))
+                .sum(),
+            serde_json::Value::String(text) => {
+                let (redacted, count) = self.redact_text(text);
+                if count > 0 {
+                    *text = redacted;
+                }
+                count
+            }
+            _ => 0,
+        }
+    }
+
+    /// Returns the text with every secret token replaced and the number of
+    /// replacements.
+    pub fn redact_text(&self, text: &str) -> (String, u32) {
+        if self
+            .policy
+            .credential_patterns
+            .contains(&CredentialPattern::PrivateKeyBlock)
+            && text.contains("-----BEGIN")
+            && text.contains("PRIVATE KEY-----")
+        {
+            return (marker(text), 1);
+        }
+
+        let bearer = self
+            .policy
+            .credential_patterns
+            .contains(&CredentialPattern::BearerToken);
+        let mut output = String::with_capacity(text.len());
+        let mut count = 0;
+        let mut cursor = 0;
+        let mut after_bearer = false;
+        for (start, end) in tokens(text) {
+            let token = &text[start..end];
+            let secret = (bearer && after_bearer)
+                || self
+                    .policy
+                    .credential_patterns
+                    .iter()
+                    .any(|pattern| pattern.matches_token(token))
+                || self.is_high_entropy(token);
+            after_bearer = token.eq_ignore_ascii_case("bearer");
+            if secret && !token.starts_with(MARKER_PREFIX) {
+                output.push_str(&text[cursor..start]);
+                output.push_str(&marker(token));
+                cursor = end;
+                count += 1;
+            }
+        }
+        output.push_str(&text[cursor..]);
+        (output, count)
+    }
+
+    fn is_high_entropy(&self, token: &str) -> bool {
+        token.len() >= self.policy.min_entropy_length
+            && token.chars().any(|c| c.is_ascii_alphabetic())
+            && token.chars().any(|c| c.is_ascii_digit())
+            && shannon_entropy(token) >= self.policy.entropy_threshold
+    }
+}
+
+fn tokens(text: &str) -> Vec<(usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (index, c) in text.char_indices() {
+        let separator = c.is_whitespace()
+            || matches!(
+                c,
+                '"' | '\'' | ',' | ';' | '=' | '(' | ')' | '{' | '}' | '<' | '>'
+            );
+        match (separator, start) {
+            (true, Some(begin)) => {
+                tokens.push((begin, index));
+                start = None;
+            }
+            (false, None) => start = Some(index),
+            _ => {}
+        }
+    }
+    if let Some(begin) = start {
+        tokens.push((begin, text.len()));
+    }
+    tokens
+}
+
+fn shannon_entropy(token: &str) -> f64 {
+    let mut counts = std::collections::HashMap::new();
+    for c in token.chars() {
+        *counts.entry(c).or_insert(0usize) += 1;
+    }
+    let length = token.chars().count() as f64;
+    counts
+        .values()
+        .map(|&count| {
+            let probability = count as f64 / length;
+            -probability * probability.log2()
+        })
+        .sum()
+}
+
+fn normalize_key(key: &str) -> String {
+    key.chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn marker(value: &str) -> String {
+    let digest = format!("{:x}", Sha256::digest(value.as_bytes()));
+    format!("{MARKER_PREFIX}{}]", &digest[..16])
+}
+
+fn is_marker(value: &serde_json::Value) -> bool {
+    value
+        .as_str()
+        .is_some_and(|text| text.starts_with(MARKER_PREFIX))
+}
@@ -0,0 +1,202 @@
+//! Admission of concurrent runs.
+//!
+//! A [`RunScheduler`] is shared by every engine that should compete for the
+//! same capacity.  It admits at most `max_concurrent` runs at once and never
+//! admits two runs bound to the same [`WorkspaceRef`]: an admitted run holds a
+//! [`WorkspaceLease`] until its engine returns.  Waiting runs are admitted in
+//! arrival order, except that a run whose workspace is free may pass runs
+//! still waiting for a busy workspace.  A run never passes an earlier run for
+//! the same workspace.
+
+use super::{RunCancellation, RunId, WorkspaceRef};
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// How often a waiting run checks its cancellation.
+const CANCELLATION_POLL: Duration = Duration::from_millis(25);
+
+#[derive(Debug)]
+struct Waiter {
+    ticket: u64,
+    workspace: WorkspaceRef,
+}
+
+#[derive(Debug, Default)]
+struct Queue {
+    running: usize,
+    leased: HashSet<WorkspaceRef>,
+    waiting: VecDeque<Waiter>,
+    next_ticket: u64,
+}
+
+impl Queue {
+    /// Whether the waiter at `index` may be admitted now.
+    fn admissible(&self, index: usize, max_concurrent: usize) -> bool {
+        let workspace = &self.waiting[index].workspace;
+        self.running < max_concurrent
+            && !self.leased.contains(workspace)
+            && !self
+                .waiting
+                .iter()
+                .take(index)
+                .any(|earlier| &earlier.workspace == workspace)
+    }
+
+    fn index_of(&self, ticket: u64) -> Option<usize> {
+        self.waiting
+            .iter()
+            .position(|waiter| waiter.ticket == ticket)
+    }
+}
+
+#[derive(Debug)]
+struct Shared {
+    max_concurrent: usize,
+    queue: Mutex<Queue>,
+    changed: Condvar,
+}
+
+impl Shared {
+    fn queue(&self) -> MutexGuard<'_, Queue> {
+        self.queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Where a run stands while it waits for admission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueuePosition {
+    /// 1-based position among waiting runs.
+    pub position: usize,
+    pub running: usize,
+    pub max_concurrent: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct RunScheduler {
+    shared: Arc<Shared>,
+}
+
+impl RunScheduler {
+    /// Panics when `max_concurrent` is zero, which would admit nothing.
+    pub fn new(max_concurrent: usize) -> Self {
+        assert!(
+            max_concurrent > 0,
+            "a scheduler must admit at least one run"
+        );
+        Self {
+            shared: Arc::new(Shared {
+                max_concurrent,
+                queue: Mutex::new(Queue::default()),
+                changed: Condvar::new(),
+            }),
+        }
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.shared.max_concurrent
+    }
+
+    /// Runs currently holding a lease.
+    pub fn running(&self) -> usize {
+        self.shared.queue().running
+    }
+
+    /// Runs waiting for admission.
+    pub fn waiting(&self) -> usize {
+        self.shared.queue().waiting.len()
+    }
+
+    pub fn is_leased(&self, workspace: &WorkspaceRef) -> bool {
+        self.shared.queue().leased.contains(workspace)
+    }
+
+    /// Blocks until `run_id` may hold `workspace`, reporting every change of
+    /// its queue position to `on_position`, including the first.
+    ///
+    /// Returns `None` without a lease when `cancellation` fires first.
+    pub fn acquire(
+        &self,
+        run_id: &RunId,
+        workspace: &WorkspaceRef,
+        cancellation: &RunCancellation,
+        mut on_position: impl FnMut(QueuePosition),
+    ) -> Option<WorkspaceLease> {
+        let max_concurrent = self.shared.max_concurrent;
+        let mut queue = self.shared.queue();
+        let ticket = queue.next_ticket;
+        queue.next_ticket += 1;
+        queue.waiting.push_back(Waiter {
+            ticket,
+            workspace: workspace.clone(),
+        });
+        tracing::debug!("run {run_id} queued for workspace {workspace}");
+
+        let mut reported = None;
+        loop {
+            let index = queue.index_of(ticket)?;
+            if cancellation.is_cancelled() {
+                queue.waiting.remove(index);
+                drop(queue);
+                // Runs behind this one may move up.
+                self.shared.changed.notify_all();
+                return None;
+            }
+            let position = QueuePosition {
+                position: index + 1,
+                running: queue.running,
+                max_concurrent,
+            };
+            if reported != Some(position.position) {
+                reported = Some(position.position);
+                // Publishing may be slow; nobody should wait on it.
+                drop(queue);
+                on_position(position);
+                queue = self.shared.queue();
+                continue;
+            }
+            if queue.admissible(index, max_concurrent) {
+                queue.waiting.remove(index);
+                queue.running += 1;
+                queue.leased.insert(workspace.clone());
+                drop(queue);
+                self.shared.changed.notify_all();
+                return Some(WorkspaceLease {
+                    shared: Arc::clone(&self.shared),
+                    workspace: workspace.clone(),
+                });
+            }
+            queue = self
+                .shared
+                .changed
+                .wait_timeout(queue, CANCELLATION_POLL)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+    }
+}
+
+/// Exclusive hold on a workspace and one concurrency slot; released on drop.
+#[derive(Debug)]
+pub struct WorkspaceLease {
+    shared: Arc<Shared>,
+    workspace: WorkspaceRef,
+}
+
+impl WorkspaceLease {
+    pub fn workspace(&self) -> &WorkspaceRef {
+        &self.workspace
+    }
+}
+
+impl Drop for WorkspaceLease {
+    fn drop(&mut self) {
+        let mut queue = self.shared.queue();
+        queue.running -= 1;
+        queue.leased.remove(&self.workspace);
+        drop(queue);
+        self.shared.changed.notify_all();
+    }
+}
@@ -0,0 +1,469 @@
+//! JSON Schemas for the host-facing documents of the governed-run contract.
+//!
+//! Schemas are generated from the Rust types, so they cannot drift from what
+//! the engine serializes.  The published copies live under
+//! `schemas/<contract id>/<contract version>/` and are checked against the
+//! generator by the contract tests.  [`SchemaValidator`] lets a host check a
+//! payload before submitting it; it implements the subset of JSON Schema
+//! 2020-12 that the generated schemas use.
+
+use super::{
+    ContractIdentity, GOVERNED_RUN_CONTRACT_ID, GOVERNED_RUN_CONTRACT_VERSION, RunEvent,
+    RunOutcome, RunRequest, RunSnapshot,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractDocument {
+    RunRequest,
+    RunEvent,
+    RunSnapshot,
+    RunOutcome,
+}
+
+impl ContractDocument {
+    pub fn all() -> [Self; 4] {
+        [
+            Self::RunRequest,
+            Self::RunEvent,
+            Self::RunSnapshot,
+            Self::RunOutcome,
+        ]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::RunRequest => "run-request",
+            Self::RunEvent => "run-event",
+            Self::RunSnapshot => "run-snapshot",
+            Self::RunOutcome => "run-outcome",
+        }
+    }
+
+    pub fn file_name(self) -> String {
+        format!("{}.schema.json", self.name())
+    }
+
+    /// `$id` of the schema for this document in the given contract version.
+    pub fn schema_id(self, contract: &ContractIdentity) -> String {
+        format!("urn:{}:{}:{}", contract.id, contract.version, self.name())
+    }
+
+    /// Path of the published schema relative to the schema root.
+    pub fn published_path(self, contract: &ContractIdentity) -> PathBuf {
+        Path::new(&contract.id)
+            .join(&contract.version)
+            .join(self.file_name())
+    }
+
+    /// Generates the schema for the contract version this crate implements.
+    pub fn schema(self) -> Value {
+        let schema = match self {
+            Self::RunRequest => schemars::schema_for!(RunRequest),
+            Self::RunEvent => schemars::schema_for!(RunEvent),
+            Self::RunSnapshot => schemars::schema_for!(RunSnapshot),
+            Self::RunOutcome => schemars::schema_for!(RunOutcome),
+        };
+        let mut schema = schema.to_value();
+        pin_contract_id(&mut schema);
+
+        let contract = ContractIdentity::v1();
+        if let Value::Object(object) = &mut schema {
+            object.insert("$id".to_string(), Value::String(self.schema_id(&contract)));
+            object.insert(
+                "x-pincher-contract".to_string(),
+                serde_json::to_value(&contract).unwrap_or_default(),
+            );
+        }
+        schema
+    }
+}
+
+impl fmt::Display for ContractDocument {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.name())
+    }
+}
+
+/// Requires every `contract.id` in a document to name this contract.  The
+/// version stays open so that a schema can describe compatible versions.
+fn pin_contract_id(schema: &mut Value) {
+    let name = <ContractIdentity as JsonSchema>::schema_name();
+    if let Some(Value::Object(properties)) =
+        schema.pointer_mut(&format!("/$defs/{name}/properties"))
+    {
+        properties.insert(
+            "id".to_string(),
+            serde_json::json!({ "const": GOVERNED_RUN_CONTRACT_ID }),
+        );
+    }
+}
+
+/// Writes every schema of this contract version below `root` and returns the
+/// written paths.
+pub fn write_schemas(root: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let contract = ContractIdentity::v1();
+    let mut written = Vec::new();
+    for document in ContractDocument::all() {
+        let path = root.join(document.published_path(&contract));
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut encoded = serde_json::to_string_pretty(&document.schema())?;
+        encoded.push('\n');
+        std::fs::write(&path, encoded)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Directory, relative to the crate root, holding the published schemas of
+/// [`GOVERNED_RUN_CONTRACT_VERSION`].
+pub fn published_dir() -> PathBuf {
+    Path::new("schemas")
+        .join(GOVERNED_RUN_CONTRACT_ID)
+        .join(GOVERNED_RUN_CONTRACT_VERSION)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ViolationKind {
+    Type { expected: Vec<String> },
+    MissingProperty { name: String },
+    UnexpectedProperty { name: String },
+    NotInEnum,
+    ConstMismatch { expected: Value },
+    NoVariantMatched,
+    MultipleVariantsMatched { count: usize },
+    BelowMinimum { minimum: Value },
+    AboveMaximum { maximum: Value },
+    TooShort { min_length: u64 },
+    TooLong { max_length: u64 },
+    TooFewItems { min_items: u64 },
+    TooManyItems { max_items: u64 },
+    Format { format: String },
+    Rejected,
+    UnresolvedReference { reference: String },
+}
+
+/// One reason an instance does not match a schema.  `pointer` is the JSON
+/// pointer of the offending value in the instance.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaViolation {
+    pub pointer: String,
+    pub kind: ViolationKind,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pointer = if self.pointer.is_empty() {
+            "/"
+        } else {
+            &self.pointer
+        };
+        write!(formatter, "{pointer}: {:?}", self.kind)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SchemaValidator {
+    document: ContractDocument,
+    schema: Value,
+}
+
+impl SchemaValidator {
+    /// Validator for the generated schema of `document`.
+    pub fn new(document: ContractDocument) -> Self {
+        Self {
+            document,
+            schema: document.schema(),
+        }
+    }
+
+    /// Validator for a schema loaded elsewhere, such as a published file.
+    pub fn with_schema(document: ContractDocument, schema: Value) -> Self {
+        Self { document, schema }
+    }
+
+    pub fn document(&self) -> ContractDocument {
+        self.document
+    }
+
+    pub fn schema(&self) -> &Value {
+        &self.schema
+    }
+
+    pub fn validate(&self, instance: &Value) -> Result<(), Vec<SchemaViolation>> {
+        let mut violations = Vec::new();
+        self.check(&self.schema, instance, "", &mut violations, 0);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    fn check(
+        &self,
+        schema: &Value,
+        instance: &Value,
+        pointer: &str,
+        violations: &mut Vec<SchemaViolation>,
+        depth: usize,
+    ) {
+        let mut violate = |kind| {
+            violations.push(SchemaViolation {
+                pointer: pointer.to_string(),
+                kind,
+            })
+        };
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return violate(ViolationKind::Rejected),
+            Value::Object(schema) => schema,
+            _ => return,
+        };
+
+        if let Some(Value::String(reference)) = schema.get("$ref") {
+            match self.resolve(reference) {
+                Some(target) if depth < 64 => {
+                    self.check(target, instance, pointer, violations, depth + 1)
+                }
+                _ => {
+                    violations.push(SchemaViolation {
+                        pointer: pointer.to_string(),
+                        kind: ViolationKind::UnresolvedReference {
+                            reference: reference.clone(),
+                        },
+                    });
+                }
+            }
+        }
+
+        let mut violate = |kind| {
+            violations.push(SchemaViolation {
+                pointer: pointer.to_string(),
+                kind,
+            })
+        };
+        if let Some(expected) = schema.get("type") {
+            let expected: Vec<String> = match expected {
+                Value::String(name) => vec![name.clone()],
+                Value::Array(names) => names
+                    .iter()
+                    .filter_map(|name| name.as_str().map(ToString::to_string))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            if !expected.is_empty() && !expected.iter().any(|name| has_type(instance, name)) {
+                return violate(ViolationKind::Type { expected });
+            }
+        }
+        if let Some(Value::Array(allowed)) = schema.get("enum")
+            && !allowed.contains(instance)
+        {
+            violate(ViolationKind::NotInEnum);
+        }
+        if let Some(expected) = schema.get("const")
+            && expected != instance
+        {
+            violate(ViolationKind::ConstMismatch {
+                expected: expected.clone(),
+            });
+        }
+        check_bounds(schema, instance, &mut violate);
+
+        for keyword in ["anyOf", "oneOf", "allOf"] {
+            let Some(Value::Array(branches)) = schema.get(keyword) else {
+                continue;
+            };
+            let results: Vec<Vec<SchemaViolation>> = branches
+                .iter()
+                .map(|branch| {
+                    let mut found = Vec::new();
+                    self.check(branch, instance, pointer, &mut found, depth + 1);
+                    found
+                })
+                .collect();
+            let matched = results.iter().filter(|found| found.is_empty()).count();
+            match keyword {
+                "allOf" => violations.extend(results.into_iter().flatten()),
+                "oneOf" if matched > 1 => violations.push(SchemaViolation {
+                    pointer: pointer.to_string(),
+                    kind: ViolationKind::MultipleVariantsMatched { count: matched },
+                }),
+                _ if matched == 0 => {
+                    // Report the branch that came closest so that the caller
+                    // sees, for example, the bad field of the intended variant.
+                    violations.push(SchemaViolation {
+                        pointer: pointer.to_string(),
+                        kind: ViolationKind::NoVariantMatched,
+                    });
+                    if let Some(closest) = results.into_iter().min_by_key(Vec::len) {
+                        violations.extend(closest);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        match instance {
+            Value::Object(object) => self.check_object(schema, object, pointer, violations, depth),
+            Value::Array(items) => {
+                if let Some(item_schema) = schema.get("items") {
+                    for (index, item) in items.iter().enumerate() {
+                        let pointer = format!("{pointer}/{index}");
+                        self.check(item_schema, item, &pointer, violations, depth + 1);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_object(
+        &self,
+        schema: &serde_json::Map<String, Value>,
+        object: &serde_json::Map<String, Value>,
+        pointer: &str,
+        violations: &mut Vec<SchemaViolation>,
+        depth: usize,
+    ) {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    violations.push(SchemaViolation {
+                        pointer: pointer.to_string(),
+                        kind: ViolationKind::MissingProperty {
+                            name: name.to_string(),
+                        },
+                    });
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, value) in object {
+            let child = format!("{pointer}/{}", escape_pointer(name));
+            match properties.and_then(|properties| properties.get(name)) {
+                Some(property) => self.check(property, value, &child, violations, depth + 1),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => violations.push(SchemaViolation {
+                        pointer: pointer.to_string(),
+                        kind: ViolationKind::UnexpectedProperty { name: name.clone() },
+                    }),
+                    Some(additional) => {
+                        self.check(additional, value, &child, violations, depth + 1)
+                    }
+                    None => {}
+                },
+            }
+        }
+    }
+
+    fn resolve(&self, reference: &str) -> Option<&Value> {
+        let pointer = reference.strip_prefix('#')?;
+        self.schema.pointer(pointer)
+    }
+}
+
+/// Validates `instance` against the generated schema of `document`.
+pub fn validate(document: ContractDocument, instance: &Value) -> Result<(), Vec<SchemaViolation>> {
+    SchemaValidator::new(document).validate(instance)
+}
+
+fn has_type(instance: &Value, name: &str) -> bool {
+    match name {
+        "null" => instance.is_null(),
+        "boolean" => instance.is_boolean(),
+        "object" => instance.is_object(),
+        "array" => instance.is_array(),
+        "string" => instance.is_string(),
+        "number" => instance.is_number(),
+        "integer" => {
+            instance.is_i64()
+                || instance.is_u64()
+                || instance
+                    .as_f64()
+                    .is_some_and(|number| number.fract() == 0.0)
+        }
+        _ => false,
+    }
+}
+
+fn check_bounds(
+    schema: &serde_json::Map<String, Value>,
+    instance: &Value,
+    violate: &mut impl FnMut(ViolationKind),
+) {
+    let limit = |keyword: &str| schema.get(keyword).and_then(Value::as_u64);
+    match instance {
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            if let Some(minimum) = schema.get("minimum")
+                && minimum.as_f64().is_some_and(|minimum| number < minimum)
+            {
+                violate(ViolationKind::BelowMinimum {
+                    minimum: minimum.clone(),
+                });
+            }
+            if let Some(maximum) = schema.get("maximum")
+                && maximum.as_f64().is_some_and(|maximum| number > maximum)
+            {
+                violate(ViolationKind::AboveMaximum {
+                    maximum: maximum.clone(),
+                });
+            }
+        }
+        Value::String(text) => {
+            let length = text.chars().count() as u64;
+            if let Some(min_length) = limit("minLength")
+                && length < min_length
+            {
+                violate(ViolationKind::TooShort { min_length });
+            }
+            if let Some(max_length) = limit("maxLength")
+                && length > max_length
+            {
+                violate(ViolationKind::TooLong { max_length });
+            }
+            if let Some(Value::String(format)) = schema.get("format")
+                && !matches_format(format, text)
+            {
+                violate(ViolationKind::Format {
+                    format: format.clone(),
+                });
+            }
+        }
+        Value::Array(items) => {
+            let count = items.len() as u64;
+            if let Some(min_items) = limit("minItems")
+                && count < min_items
+            {
+                violate(ViolationKind::TooFewItems { min_items });
+            }
+            if let Some(max_items) = limit("maxItems")
+                && count > max_items
+            {
+                violate(ViolationKind::TooManyItems { max_items });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Formats are annotations in 2020-12; only the ones serde enforces are
+/// asserted here.
+fn matches_format(format: &str, text: &str) -> bool {
+    match format {
+        "date-time" => chrono::DateTime::parse_from_rfc3339(text).is_ok(),
+        _ => true,
+    }
+}
+
+fn escape_pointer(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
@@ -0,0 +1,199 @@
+//! Contract versions, negotiation, and up-conversion of older documents.
+//!
+//! A document is compatible when it names [`GOVERNED_RUN_CONTRACT_ID`] and a
+//! version with the same major as a supported version and a minor no newer
+//! than it.  Minor releases only add optional fields, so the engine accepts
+//! older compatible requests and always produces events, snapshots, and
+//! outcomes in [`GOVERNED_RUN_CONTRACT_VERSION`].
+
+use super::schema::ContractDocument;
+use super::{
+    ContractError, ContractIdentity, GOVERNED_RUN_CONTRACT_ID, GOVERNED_RUN_CONTRACT_VERSION,
+    RunRequest, RunSnapshot, SUPPORTED_CONTRACT_VERSIONS,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ContractVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl ContractVersion {
+    pub fn parse(version: &str) -> Result<Self, ContractError> {
+        let invalid = || ContractError::InvalidVersion {
+            version: version.to_string(),
+        };
+        let mut parts = version.split('.').map(|part| {
+            if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            part.parse::<u64>().map_err(|_| invalid())
+        });
+        let (Some(major), Some(minor), Some(patch), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        Ok(Self {
+            major: major?,
+            minor: minor?,
+            patch: patch?,
+        })
+    }
+
+    /// Whether documents of `self` can be accepted by an implementation of
+    /// `supported`.
+    pub fn is_compatible_with(self, supported: Self) -> bool {
+        self.major == supported.major && self.minor <= supported.minor
+    }
+}
+
+impl fmt::Display for ContractVersion {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl ContractIdentity {
+    /// Checks that this build can accept the identity and returns its
+    /// version.
+    pub fn check_compatible(&self) -> Result<ContractVersion, ContractError> {
+        ContractSupport::current().check(self)
+    }
+
+    pub fn is_compatible(&self) -> bool {
+        self.check_compatible().is_ok()
+    }
+}
+
+/// What this build of Pincher advertises to hosts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractSupport {
+    pub id: String,
+    /// Version of every document the engine produces.
+    pub current: String,
+    /// Newest accepted version of each supported major.  Older minors and
+    /// any patch of these are accepted too.
+    pub supported: Vec<String>,
+}
+
+impl ContractSupport {
+    pub fn current() -> Self {
+        Self {
+            id: GOVERNED_RUN_CONTRACT_ID.to_string(),
+            current: GOVERNED_RUN_CONTRACT_VERSION.to_string(),
+            supported: supported_strings(),
+        }
+    }
+
+    /// Checks `contract` against this support's id and supported versions
+    /// and returns its version.
+    pub fn check(&self, contract: &ContractIdentity) -> Result<ContractVersion, ContractError> {
+        if contract.id != self.id {
+            return Err(ContractError::UnknownContract {
+                id: contract.id.clone(),
+            });
+        }
+        let version = ContractVersion::parse(&contract.version)?;
+        if self
+            .supported
+            .iter()
+            .filter_map(|supported| ContractVersion::parse(supported).ok())
+            .any(|supported| version.is_compatible_with(supported))
+        {
+            Ok(version)
+        } else {
+            Err(ContractError::UnsupportedVersion {
+                version: contract.version.clone(),
+                supported: self.supported.clone(),
+            })
+        }
+    }
+
+    pub fn accepts(&self, contract: &ContractIdentity) -> bool {
+        self.check(contract).is_ok()
+    }
+
+    /// Picks the newest of the host's `offered` versions that the engine
+    /// accepts.  The host writes requests in the returned version; everything
+    /// the engine returns is in [`ContractSupport::current`].
+    pub fn negotiate(
+        &self,
+        offered: &[ContractIdentity],
+    ) -> Result<ContractIdentity, ContractError> {
+        offered
+            .iter()
+            .filter_map(|contract| self.check(contract).ok().map(|version| (version, contract)))
+            .max_by_key(|(version, _)| *version)
+            .map(|(_, contract)| contract.clone())
+            .ok_or_else(|| ContractError::NoCommonVersion {
+                offered: offered
+                    .iter()
+                    .map(|contract| format!("{} {}", contract.id, contract.version))
+                    .collect(),
+                supported: self.supported.clone(),
+            })
+    }
+}
+
+impl RunRequest {
+    /// Restamps a compatible request with the current contract.  Incompatible
+    /// requests are returned unchanged so that validation can reject them.
+    pub(super) fn upgraded(mut self) -> Self {
+        if self.contract.is_compatible() {
+            self.contract = ContractIdentity::v1();
+        }
+        self
+    }
+}
+
+/// Up-converts a serialized request of any compatible version.
+pub fn upgrade_request(mut value: Value) -> Result<RunRequest, ContractError> {
+    upgrade_document(ContractDocument::RunRequest, &mut value)?;
+    decode(ContractDocument::RunRequest, value)
+}
+
+/// Up-converts a serialized snapshot of any compatible version, including the
+/// request it carries.
+pub fn upgrade_snapshot(mut value: Value) -> Result<RunSnapshot, ContractError> {
+    upgrade_document(ContractDocument::RunSnapshot, &mut value)?;
+    if let Some(request) = value.get_mut("request") {
+        upgrade_document(ContractDocument::RunRequest, request)?;
+    }
+    decode(ContractDocument::RunSnapshot, value)
+}
+
+fn upgrade_document(document: ContractDocument, value: &mut Value) -> Result<(), ContractError> {
+    let contract: ContractIdentity = value
+        .get("contract")
+        .cloned()
+        .ok_or_else(|| ContractError::Malformed {
+            document: document.name(),
+            reason: "missing contract identity".to_string(),
+        })
+        .and_then(|contract| decode(document, contract))?;
+    contract.check_compatible()?;
+    value["contract"] = serde_json::to_value(ContractIdentity::v1()).unwrap_or_default();
+    Ok(())
+}
+
+fn decode<T: serde::de::DeserializeOwned>(
+    document: ContractDocument,
+    value: Value,
+) -> Result<T, ContractError> {
+    serde_json::from_value(value).map_err(|error| ContractError::Malformed {
+        document: document.name(),
+        reason: error.to_string(),
+    })
+}
+
+fn supported_strings() -> Vec<String> {
+    SUPPORTED_CONTRACT_VERSIONS
+        .iter()
+        .map(ToString::to_string)
+        .collect()
+}
@@ -14,47 +14,87 @@
 //!
 //! Real provider adapters, tool and patch execution, transport, persistence,
 //! retries/recovery, and multi-agent operation are deferred from governed-run
-//! contract version 1.0.0. Deterministic fake ports in the integration tests
+//! contract version 1.1.0. Deterministic fake ports in the integration tests
 //! prove the boundary without credentials or a live provider.
 
+pub mod daemon;
 pub mod decapod;
 pub mod governed_run;
 
 pub use governed_run::{
-    AdvisoryEvidence, ApprovalEvidence, ApprovalInterlockRef, ApprovalStatus, BlockedReason,
-    ContextEvidence, ContextEvidenceRef, ContractError, ContractIdentity, CorrelationId,
-    CustodyBinding, CustodyEvidence, CustodyFailure, CustodyField, CustodyReceiptRef,
-    DecapodControlPlane, DecapodPortError, EventCustody, EventId, EventKind, EventSink,
-    EventSinkError, FailureCode, GOVERNED_RUN_CONTRACT_ID,
-    GOVERNED_RUN_CONTRACT_VERSION, GovernedInferenceRequest, GovernedRunEngine, IdempotencyKey,
-    InMemoryEventSink, IntentId, InterlockDecision, InvalidRequestReason, ProofEvidence,
-    ProofEvidenceRef, ProofFailure, ProviderError, ProviderProposal, ProviderProposalRef,
-    ProviderTurn, Remediation, RepositoryRef, RunError, RunEvent, RunFailure, RunId, RunOutcome,
-    RunRequest, RunSnapshot, RunState, SessionRef, StateTransition, TaskRef,
-    UnsupportedDecapodControlPlane, ValidationEvidence, ValidationEvidenceRef, ValidationFailure,
-    WorkUnitRef, WorkspaceRef,
+    AdvisoryAcknowledgement, AdvisoryEvidence, ApprovalEvidence, ApprovalInterlockRef,
+    ApprovalProgress, ApprovalQuorum, ApprovalStatus, BlockedReason, ContextEvidence,
+    ContextEvidenceRef, ContractError, ContractIdentity, CorrelationId, CustodyBinding,
+    CustodyEvidence, CustodyFailure, CustodyField, CustodyReceiptRef, DecapodControlPlane,
+    DecapodPortError, EventCustody, EventId, EventKind, EventSink, EventSinkError, FailureCode,
+    GOVERNED_RUN_CONTRACT_ID, GOVERNED_RUN_CONTRACT_VERSION, GovernancePolicy,
+    GovernedInferenceRequest, GovernedRunEngine, IdempotencyKey, InMemoryEventSink, IntentId,
+    InterlockDecision, InvalidRequestReason, ProjectRootRef, ProofEvidence, ProofEvidenceRef,
+    ProofFailure, ProviderError, ProviderProposal, ProviderProposalRef, ProviderTurn, Remediation,
+    RepositoryRef, RunCancellation, RunError, RunEvent, RunFailure, RunId, RunOutcome, RunRequest,
+    RunSnapshot, RunState, SUPPORTED_CONTRACT_VERSIONS, ScopeApproval, SessionRef, StateTransition,
+    TaskRef, UnsupportedDecapodControlPlane, UnsupportedProviderTurn, ValidationEvidence,
+    ValidationEvidenceRef, ValidationFailure, WorkUnitRef, WorkspaceRef,
+};
+pub use governed_run::chain::{ChainVerification, ChainViolation, EventChainLink};
+pub use governed_run::projection::{
+    EvidenceField, ProjectionDiscrepancy, ProjectionReport, RunProjection,
+};
+pub use governed_run::redaction::{CredentialPattern, RedactionPolicy, Redactor};
+pub use governed_run::scheduler::{QueuePosition, RunScheduler, WorkspaceLease};
+pub use governed_run::schema::{
+    ContractDocument, SchemaValidator, SchemaViolation, ViolationKind,
+};
+pub use governed_run::version::{
+    ContractSupport, ContractVersion, upgrade_request, upgrade_snapshot,
 };
 
 pub use decapod::{
     Decapod, DecapodError,
+    bootstrap::{
+        BootstrapError, BootstrapStep, CustodyBootstrap, CustodyPlane, CustodyRequest, DecapodCustody,
+    },
     broker::{Event, EventEmitter, EventSource, EventType},
     capabilities::{Capabilities, CapabilitiesManager, SchemaInfo},
     cli::{Advisory, Attestation, ContextCapsule, DecapodCli, Interlock, Receipt},
     commitment::{
-        CommitmentEntry, ProofSurface, ProofVerification, StateCommitment, StateCommitmentManager,
+        CommitmentEntry, EntryType, ProofSurface, ProofType, ProofVerification, StateCommitment,
+        StateCommitmentManager,
     },
     coordination::{
         Agent, AgentMessage, AgentStatus, AgentType, CoordinationManager, CoordinationPlan,
         Dependency, DependencyType, MessageType, SubAgentPlan,
     },
+    discovery::{
+        BinaryDiscovery, BinarySource, CompatibilityCheck, CompatibilityError, DecapodBinary,
+        DecapodInstall, DecapodVersion, DiscoveryError, VersionRange,
+    },
     docs::{DocsManager, IngestResult},
+    envelope::{DecodeMode, EnvelopeError, ParseError},
     governance::{
         AdvisoryPriority, ApprovalRequirement, GovernanceDecision, GovernanceEngine,
         GovernanceResponse,
     },
-    rpc::{RpcClient, RpcResponse},
-    session::{Session, SessionConfig, get_session_password},
+    lifecycle::{SessionError, SessionManager},
+    project::{ProjectRoot, ProjectRootError},
+    proof::{
+        AuditEvidence, EvidenceError, ExternalEvidence, ReviewEvidence, ReviewVerdict,
+        TestResultEvidence, ValidationReportEvidence,
+    },
+    queue::{ClaimedTask, TaskBoard, TaskPriority, TaskQueue},
+    reconcile::{
+        DecapodLedger, FailureReport, OutcomeReconciler, ReconcileError, ReconcileStep, WorkLedger,
+    },
+    rpc::{AgentInitData, RpcClient, RpcRequest, RpcResponse, StoreEntry},
+    secret::{EnvSecret, FdSecret, FileSecret, Secret, SecretError, SecretSource, StdinSecret},
+    session::{Session, SessionConfig, SessionExpired, get_session_password},
+    sim::{ApprovalOutcome, Scenario, SimError, SimState, Simulator},
+    stdio::{StdioStats, StdioTransport},
     todo::{Task, TaskStatus, TodoManager},
+    transport::{
+        DecapodTransport, Invocation, ProcessTransport, ScriptedTransport, TransportError,
+        TransportOutput,
+    },
     validate::{ValidationDetail, ValidationError, ValidationResult, Validator},
     workspace::{Workspace, WorkspaceManager, WorkspaceStatus, WorkspaceStatusResponse},
     workunit::{Approval, Patch, Proof, WorkUnit, WorkUnitManager, WorkUnitState, WorkUnitStatus},
@@ -1,15 +1,131 @@
+mod commands;
+
 use anyhow::Result;
-use pincher::Decapod;
+use commands::{Args, Failure};
+use pincher::daemon::RunService;
+use pincher::daemon::http::{HttpConfig, HttpServer};
+use pincher::{Decapod, UnsupportedDecapodControlPlane, UnsupportedProviderTurn};
+use std::process::ExitCode;
 use tracing_subscriber::{fmt, EnvFilter};
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     fmt()
         .with_env_filter(EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
         .init();
 
-    let decapod = Decapod::new()?;
-    decapod.run().await?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("run") => commands::run(&args[1..]),
+        Some("inspect") => commands::inspect(&args[1..]),
+        Some("events") => commands::events(&args[1..]),
+        Some("validate-request") => commands::validate_request(&args[1..]),
+        Some("serve") => serve(&args[1..]).await,
+        Some("help" | "--help" | "-h") => {
+            println!("{}", commands::USAGE_TEXT);
+            Ok(commands::SUCCESS)
+        }
+        Some("--version" | "-V") => {
+            println!("pincher {}", env!("CARGO_PKG_VERSION"));
+            Ok(commands::SUCCESS)
+        }
+        Some("decapod") => legacy().await,
+        Some(other) => Err(Failure::usage(format!("unknown command {other}"))),
+        None => Err(Failure::usage("missing command")),
+    };
+    match result {
+        Ok(code) => ExitCode::from(code),
+        Err(failure) => failure.report(),
+    }
+}
+
+/// `pincher decapod`: the original interactive Decapod loop.
+async fn legacy() -> Result<u8, Failure> {
+    let run = async {
+        let decapod = Decapod::connect().await?;
+        decapod.run().await?;
+        Ok::<_, anyhow::Error>(())
+    };
+    run.await
+        .map(|()| commands::SUCCESS)
+        .map_err(|error| Failure::error(format!("{error:#}")))
+}
+
+/// `pincher serve [--socket <path>] [--http <addr>] [--http-token-file <path>]
+/// [--max-runs <n>]`
+async fn serve(args: &[String]) -> Result<u8, Failure> {
+    let args = Args::parse(
+        args,
+        &["--socket", "--http", "--http-token-file", "--max-runs"],
+        &[],
+    )?;
+    if let Some(extra) = args.positional().first() {
+        return Err(Failure::usage(format!("unexpected argument {extra}")));
+    }
+    let socket = commands::socket_arg(&args)?;
+    let http = commands::http_config(&args)?;
+    let scheduler = commands::scheduler(&args)?;
 
+    // The concrete Decapod and provider adapters are not wired yet, so every
+    // submitted run fails closed at custody validation.
+    let service = RunService::scheduled(
+        UnsupportedDecapodControlPlane,
+        UnsupportedProviderTurn,
+        scheduler,
+    );
+    serve_until_shutdown(service, &socket, http)
+        .await
+        .map(|()| commands::SUCCESS)
+        .map_err(|error| Failure::error(format!("{error:#}")))
+}
+
+#[cfg(unix)]
+async fn serve_until_shutdown(
+    service: RunService<UnsupportedDecapodControlPlane, UnsupportedProviderTurn>,
+    socket: &std::path::Path,
+    http: Option<HttpConfig>,
+) -> Result<()> {
+    use pincher::daemon::jsonrpc::serve_unix;
+
+    let (stop, stopped) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        let mut terminate =
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                Ok(terminate) => terminate,
+                Err(error) => {
+                    tracing::warn!("cannot listen for SIGTERM: {error}");
+                    let _ = tokio::signal::ctrl_c().await;
+                    stop.send_replace(true);
+                    return;
+                }
+            };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+        stop.send_replace(true);
+    });
+    let shutdown = |mut stopped: tokio::sync::watch::Receiver<bool>| async move {
+        let _ = stopped.wait_for(|stopped| *stopped).await;
+    };
+
+    let unix = serve_unix(service.clone(), socket, shutdown(stopped.clone()));
+    match http {
+        Some(config) => {
+            let server = HttpServer::bind(config).await?;
+            tokio::try_join!(unix, server.serve(service, shutdown(stopped)))?;
+        }
+        None => unix.await?,
+    }
     Ok(())
 }
+
+#[cfg(not(unix))]
+async fn serve_until_shutdown(
+    _service: RunService<UnsupportedDecapodControlPlane, UnsupportedProviderTurn>,
+    _socket: &std::path::Path,
+    _http: Option<HttpConfig>,
+) -> Result<()> {
+    anyhow::bail!("pincher serve requires Unix domain sockets")
+}
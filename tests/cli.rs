@@ -0,0 +1,185 @@
+use pincher::{
+    CorrelationId, CustodyBinding, FailureCode, IdempotencyKey, IntentId, RepositoryRef, RunEvent,
+    RunId, RunRequest, RunSnapshot, RunState, SessionRef, TaskRef, WorkUnitRef, WorkspaceRef,
+};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn pincher(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_pincher"))
+        .args(args)
+        .output()
+        .expect("run pincher")
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn request() -> RunRequest {
+    RunRequest::v1(
+        RunId::new("run-1").expect("fixture reference"),
+        IntentId::new("intent-1").expect("fixture reference"),
+        CorrelationId::new("correlation-1").expect("fixture reference"),
+        IdempotencyKey::new("idempotency-1").expect("fixture reference"),
+        CustodyBinding::complete(
+            SessionRef::new("session-1").expect("fixture reference"),
+            TaskRef::new("task-1").expect("fixture reference"),
+            WorkUnitRef::new("work-unit-1").expect("fixture reference"),
+            RepositoryRef::new("repository-1").expect("fixture reference"),
+            WorkspaceRef::new("workspace-1").expect("fixture reference"),
+        ),
+    )
+}
+
+fn write_json(dir: &Path, name: &str, value: &impl serde::Serialize) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, serde_json::to_vec(value).expect("encode")).expect("write fixture");
+    path
+}
+
+fn path(path: &Path) -> &str {
+    path.to_str().expect("utf-8 path")
+}
+
+#[test]
+fn failure_codes_map_to_distinct_exit_statuses_above_the_reserved_range() {
+    let codes = [
+        FailureCode::InvalidRequest,
+        FailureCode::Custody,
+        FailureCode::Context,
+        FailureCode::Provider,
+        FailureCode::Validation,
+        FailureCode::Proof,
+        FailureCode::ControlPlane,
+        FailureCode::EventSink,
+        FailureCode::IllegalTransition,
+        FailureCode::Cancelled,
+    ];
+    let mut statuses: Vec<u8> = codes.iter().map(FailureCode::exit_code).collect();
+    assert!(statuses.iter().all(|status| *status >= 10));
+    statuses.sort_unstable();
+    statuses.dedup();
+    assert_eq!(statuses.len(), codes.len());
+}
+
+#[test]
+fn validate_request_accepts_a_contract_request_and_reports_violations_by_pointer() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let valid = write_json(dir.path(), "request.json", &request());
+    let output = pincher(&["validate-request", path(&valid)]);
+    assert_eq!(output.status.code(), Some(0), "{output:?}");
+
+    let mut invalid = serde_json::to_value(request()).expect("encode");
+    invalid["run_id"] = serde_json::json!("");
+    invalid
+        .as_object_mut()
+        .expect("request")
+        .remove("intent_id");
+    let invalid = write_json(dir.path(), "invalid.json", &invalid);
+    let output = pincher(&["validate-request", path(&invalid)]);
+    assert_eq!(
+        output.status.code(),
+        Some(FailureCode::InvalidRequest.exit_code().into())
+    );
+    assert!(stdout(&output).contains("/run_id"), "{output:?}");
+
+    let mut unsupported = request();
+    unsupported.contract.version = "2.0.0".to_string();
+    let unsupported = write_json(dir.path(), "unsupported.json", &unsupported);
+    let output = pincher(&["validate-request", path(&unsupported)]);
+    assert_eq!(
+        output.status.code(),
+        Some(FailureCode::InvalidRequest.exit_code().into())
+    );
+    assert!(
+        stdout(&output).contains("UnsupportedContract"),
+        "{output:?}"
+    );
+}
+
+#[test]
+fn run_writes_events_and_a_snapshot_and_exits_with_the_failure_code() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let request_path = write_json(dir.path(), "request.json", &request());
+    let log = dir.path().join("events.jsonl");
+    let snapshot_path = dir.path().join("snapshot.json");
+
+    // Without Decapod adapters the run fails closed at custody.
+    let output = pincher(&[
+        "run",
+        path(&request_path),
+        "--events",
+        path(&log),
+        "--output",
+        path(&snapshot_path),
+    ]);
+    let custody = FailureCode::Custody.exit_code();
+    assert_eq!(output.status.code(), Some(custody.into()), "{output:?}");
+
+    let snapshot: RunSnapshot =
+        serde_json::from_slice(&std::fs::read(&snapshot_path).expect("snapshot"))
+            .expect("decode snapshot");
+    assert_eq!(snapshot.state, RunState::Failed);
+    let events: Vec<RunEvent> = std::fs::read_to_string(&log)
+        .expect("event log")
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("decode event"))
+        .collect();
+    assert_eq!(events.len() as u64, snapshot.event_count);
+
+    let output = pincher(&["inspect", path(&snapshot_path)]);
+    assert_eq!(output.status.code(), Some(custody.into()));
+    let rendered = stdout(&output);
+    assert!(rendered.contains("state        failed"), "{rendered}");
+    assert!(rendered.contains("remediation"), "{rendered}");
+
+    let output = pincher(&["events", path(&log), "--state", "failed"]);
+    assert_eq!(output.status.code(), Some(0));
+    let lines: Vec<String> = stdout(&output).lines().map(String::from).collect();
+    assert_eq!(lines.len(), 1, "{lines:?}");
+    assert!(lines[0].contains("run.state.failed"));
+    assert!(lines[0].contains("failure=Custody"));
+
+    let output = pincher(&["events", path(&log), "--run", "other-run", "--json"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).is_empty());
+
+    let output = pincher(&["events", path(&log), "--verify"]);
+    assert_eq!(output.status.code(), Some(0), "{output:?}");
+    assert!(stdout(&output).starts_with("run-1: ok"));
+}
+
+#[test]
+fn events_verify_detects_a_tampered_log() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let request_path = write_json(dir.path(), "request.json", &request());
+    let log = dir.path().join("events.jsonl");
+    pincher(&["run", path(&request_path), "--events", path(&log)]);
+
+    let tampered: Vec<String> = std::fs::read_to_string(&log)
+        .expect("event log")
+        .lines()
+        .map(|line| {
+            let mut event: RunEvent = serde_json::from_str(line).expect("decode event");
+            event.source = "forged".to_string();
+            serde_json::to_string(&event).expect("encode event")
+        })
+        .collect();
+    std::fs::write(&log, tampered.join("\n")).expect("rewrite log");
+
+    let output = pincher(&["events", path(&log), "--verify"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+}
+
+#[test]
+fn usage_errors_exit_with_status_two() {
+    assert_eq!(pincher(&[]).status.code(), Some(2));
+    assert_eq!(pincher(&["frobnicate"]).status.code(), Some(2));
+    assert_eq!(pincher(&["run"]).status.code(), Some(2));
+    assert_eq!(
+        pincher(&["events", "log", "--bogus"]).status.code(),
+        Some(2)
+    );
+    assert_eq!(pincher(&["help"]).status.code(), Some(0));
+}
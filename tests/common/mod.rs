@@ -0,0 +1,181 @@
+//! Fixture shared by the daemon tests: a control plane that approves
+//! everything and a provider the test releases by hand.
+
+#![allow(dead_code)]
+
+use pincher::governed_run::*;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+pub trait Ref: Sized {
+    fn new(value: String) -> Result<Self, ContractError>;
+}
+
+macro_rules! refs {
+    ($($name:ident),* $(,)?) => {
+        $(impl Ref for $name {
+            fn new(value: String) -> Result<Self, ContractError> {
+                $name::new(value)
+            }
+        })*
+    };
+}
+
+refs!(
+    RunId,
+    IntentId,
+    SessionRef,
+    TaskRef,
+    WorkUnitRef,
+    RepositoryRef,
+    WorkspaceRef,
+    CustodyReceiptRef,
+    ContextEvidenceRef,
+    ValidationEvidenceRef,
+    ProofEvidenceRef,
+    ProviderProposalRef,
+    CorrelationId,
+    IdempotencyKey,
+);
+
+pub fn id<T: Ref>(value: &str) -> T {
+    T::new(value.to_string()).unwrap()
+}
+
+pub fn request(run: &str) -> RunRequest {
+    RunRequest::v1(
+        id(run),
+        id("intent-1"),
+        id("correlation-1"),
+        id(&format!("{run}-key")),
+        CustodyBinding::complete(
+            id("session-1"),
+            id("task-1"),
+            id("work-unit-1"),
+            id("repository-1"),
+            id("workspace-1"),
+        ),
+    )
+}
+
+#[derive(Clone)]
+pub struct FakeControl;
+
+impl DecapodControlPlane for FakeControl {
+    fn validate_custody(
+        &self,
+        binding: &CustodyBinding,
+    ) -> Result<CustodyEvidence, DecapodPortError> {
+        Ok(CustodyEvidence {
+            session: binding.session.clone().unwrap(),
+            task: binding.task.clone().unwrap(),
+            work_unit: binding.work_unit.clone().unwrap(),
+            repository: binding.repository.clone().unwrap(),
+            workspace: binding.workspace.clone().unwrap(),
+            receipt: id("custody-receipt-1"),
+            workspace_allowed: true,
+            project_root: binding.project_root.clone(),
+        })
+    }
+
+    fn resolve_context(
+        &self,
+        _custody: &CustodyEvidence,
+        _intent: &IntentId,
+    ) -> Result<ContextEvidence, DecapodPortError> {
+        Ok(ContextEvidence {
+            reference: id("context-1"),
+            resolved: true,
+        })
+    }
+
+    fn evaluate_interlocks(
+        &self,
+        _custody: &CustodyEvidence,
+        _context: &ContextEvidence,
+    ) -> Result<InterlockDecision, DecapodPortError> {
+        Ok(InterlockDecision::Allow { advisory: None })
+    }
+
+    fn approval_status(
+        &self,
+        _custody: &CustodyEvidence,
+        _context: &ContextEvidence,
+    ) -> Result<ApprovalStatus, DecapodPortError> {
+        Ok(ApprovalStatus::NotRequired)
+    }
+
+    fn validate(
+        &self,
+        _custody: &CustodyEvidence,
+        _context: &ContextEvidence,
+        _proposal: &ProviderProposal,
+    ) -> Result<ValidationEvidence, DecapodPortError> {
+        Ok(ValidationEvidence {
+            reference: id("validation-1"),
+            passed: true,
+        })
+    }
+
+    fn obtain_proof(
+        &self,
+        _custody: &CustodyEvidence,
+        _validation: &ValidationEvidence,
+    ) -> Result<ProofEvidence, DecapodPortError> {
+        Ok(ProofEvidence {
+            reference: id("proof-1"),
+            backed: true,
+        })
+    }
+}
+
+/// Provider that blocks inside `infer` until the test opens the gate.
+#[derive(Clone, Default)]
+pub struct GatedProvider {
+    gate: Arc<(Mutex<Gate>, Condvar)>,
+}
+
+#[derive(Default)]
+pub struct Gate {
+    open: bool,
+    entered: usize,
+}
+
+impl GatedProvider {
+    pub fn open() -> Self {
+        let provider = Self::default();
+        provider.release();
+        provider
+    }
+
+    pub fn release(&self) {
+        let (gate, changed) = &*self.gate;
+        gate.lock().unwrap().open = true;
+        changed.notify_all();
+    }
+
+    pub fn entered(&self) -> usize {
+        self.gate.0.lock().unwrap().entered
+    }
+
+    pub async fn wait_entered(&self) {
+        while self.entered() == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+}
+
+impl ProviderTurn for GatedProvider {
+    fn infer(&self, _request: GovernedInferenceRequest) -> Result<ProviderProposal, ProviderError> {
+        let (gate, changed) = &*self.gate;
+        let mut gate = gate.lock().unwrap();
+        gate.entered += 1;
+        while !gate.open {
+            gate = changed.wait(gate).unwrap();
+        }
+        Ok(ProviderProposal {
+            reference: id("proposal-1"),
+            output_digest: "sha256:proposal".to_string(),
+        })
+    }
+}
@@ -0,0 +1,377 @@
+use pincher::decapod::bootstrap::{BootstrapStep, CustodyBootstrap, CustodyPlane, CustodyRequest};
+use pincher::decapod::project::ProjectRoot;
+use pincher::decapod::session::Session;
+use pincher::decapod::todo::{Task, TaskStatus};
+use pincher::decapod::workspace::{Workspace, WorkspaceStatus};
+use pincher::decapod::workunit::{WorkUnit, WorkUnitState, WorkUnitStatus};
+use pincher::governed_run::*;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct State {
+    task: Option<Task>,
+    work_units: Vec<WorkUnit>,
+    calls: Vec<String>,
+    /// Operations that fail the next time they are called.
+    failing: Vec<&'static str>,
+    sessions: usize,
+    /// Directory Decapod runs in, when it runs in one.
+    root: Option<PathBuf>,
+}
+
+/// Scripted Decapod holding a single task.
+#[derive(Clone, Default)]
+struct FakePlane {
+    state: Arc<Mutex<State>>,
+}
+
+impl FakePlane {
+    fn with_task(task_id: &str) -> Self {
+        let plane = Self::default();
+        plane.state().task = Some(Task {
+            id: task_id.to_string(),
+            content: "fix the build".to_string(),
+            status: TaskStatus::Pending,
+            priority: None,
+            owner: None,
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            updated_at: "2026-01-01T00:00:00Z".to_string(),
+            claimed_at: None,
+            completed_at: None,
+            blocked_by: Some(Vec::new()),
+        });
+        plane
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn fail(&self, operation: &'static str) {
+        self.state().failing.push(operation);
+    }
+
+    fn calls(&self) -> Vec<String> {
+        std::mem::take(&mut self.state().calls)
+    }
+
+    fn call(&self, operation: &'static str, detail: &str) -> anyhow::Result<()> {
+        let mut state = self.state();
+        state
+            .calls
+            .push(format!("{operation} {detail}").trim_end().to_string());
+        if let Some(index) = state
+            .failing
+            .iter()
+            .position(|failing| *failing == operation)
+        {
+            state.failing.remove(index);
+            anyhow::bail!("decapod refused {operation}");
+        }
+        Ok(())
+    }
+
+    fn task_status(&self) -> (TaskStatus, Option<String>) {
+        let state = self.state();
+        let task = state.task.as_ref().unwrap();
+        (task.status.clone(), task.owner.clone())
+    }
+}
+
+fn session(id: usize) -> Session {
+    Session {
+        token: format!("token-{id}"),
+        session_id: format!("session-{id}"),
+        expires_at: None,
+        created_at: "2026-01-01T00:00:00Z".to_string(),
+    }
+}
+
+impl CustodyPlane for FakePlane {
+    async fn acquire_session(&self) -> anyhow::Result<Session> {
+        self.call("acquire", "")?;
+        let mut state = self.state();
+        state.sessions += 1;
+        Ok(session(state.sessions))
+    }
+
+    async fn task(&self, _: &Session, task_id: &str) -> anyhow::Result<Task> {
+        self.call("get", task_id)?;
+        Ok(self.state().task.clone().unwrap())
+    }
+
+    async fn claim_task(&self, session: &Session, task_id: &str) -> anyhow::Result<Task> {
+        self.call("claim", task_id)?;
+        let mut state = self.state();
+        let task = state.task.as_mut().unwrap();
+        task.status = TaskStatus::Claimed;
+        task.owner = Some(session.session_id.clone());
+        Ok(task.clone())
+    }
+
+    async fn release_task(&self, _: &Session, task_id: &str) -> anyhow::Result<Task> {
+        self.call("release", task_id)?;
+        let mut state = self.state();
+        let task = state.task.as_mut().unwrap();
+        task.status = TaskStatus::Pending;
+        task.owner = None;
+        Ok(task.clone())
+    }
+
+    async fn work_units(&self, _: &Session, task_id: &str) -> anyhow::Result<Vec<WorkUnit>> {
+        self.call("list", task_id)?;
+        Ok(self.state().work_units.clone())
+    }
+
+    async fn init_work_unit(
+        &self,
+        _: &Session,
+        task_id: &str,
+        intent_ref: &str,
+    ) -> anyhow::Result<WorkUnit> {
+        self.call("init", task_id)?;
+        let mut state = self.state();
+        let unit = WorkUnit {
+            id: format!("wu-{}", state.work_units.len() + 1),
+            task_id: task_id.to_string(),
+            intent_ref: intent_ref.to_string(),
+            status: WorkUnitStatus::Active,
+            state: WorkUnitState {
+                intent: intent_ref.to_string(),
+                plan: None,
+                patches: Vec::new(),
+                approvals: Vec::new(),
+            },
+            acceptance_criteria: Vec::new(),
+            constraints: Vec::new(),
+            proofs: Vec::new(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            updated_at: "2026-01-01T00:00:00Z".to_string(),
+        };
+        state.work_units.push(unit.clone());
+        Ok(unit)
+    }
+
+    async fn fail_work_unit(
+        &self,
+        _: &Session,
+        work_unit_id: &str,
+        _reason: &str,
+    ) -> anyhow::Result<WorkUnit> {
+        self.call("fail", work_unit_id)?;
+        let mut state = self.state();
+        let unit = state
+            .work_units
+            .iter_mut()
+            .find(|unit| unit.id == work_unit_id)
+            .unwrap();
+        unit.status = WorkUnitStatus::Failed;
+        Ok(unit.clone())
+    }
+
+    async fn ensure_workspace(&self, _: &Session, name: Option<&str>) -> anyhow::Result<Workspace> {
+        let name = name.unwrap_or("default");
+        self.call("ensure", name)?;
+        Ok(Workspace {
+            name: name.to_string(),
+            branch: format!("ws/{name}"),
+            path: format!("/tmp/{name}"),
+            status: WorkspaceStatus::Active,
+            created_at: None,
+        })
+    }
+
+    fn project_root(&self) -> anyhow::Result<Option<ProjectRoot>> {
+        let root = self.state().root.clone();
+        Ok(root.map(ProjectRoot::verify).transpose()?)
+    }
+}
+
+fn custody_request() -> CustodyRequest {
+    CustodyRequest::new(
+        "task-1",
+        IntentId::new("intent-1").unwrap(),
+        RepositoryRef::new("repository-1").unwrap(),
+    )
+    .unwrap()
+    .with_workspace("feature")
+}
+
+#[tokio::test]
+async fn bootstrap_runs_the_custody_steps_in_order_and_binds_their_ids() {
+    let plane = FakePlane::with_task("task-1");
+    let mut bootstrap = CustodyBootstrap::new(plane.clone());
+
+    let run = bootstrap.bootstrap(&custody_request()).await.unwrap();
+    assert_eq!(
+        plane.calls(),
+        [
+            "acquire",
+            "get task-1",
+            "claim task-1",
+            "list task-1",
+            "init task-1",
+            "ensure feature"
+        ]
+    );
+    assert_eq!(
+        run.custody,
+        CustodyBinding::complete(
+            SessionRef::new("session-1").unwrap(),
+            TaskRef::new("task-1").unwrap(),
+            WorkUnitRef::new("wu-1").unwrap(),
+            RepositoryRef::new("repository-1").unwrap(),
+            WorkspaceRef::new("feature").unwrap(),
+        )
+    );
+    assert_eq!(run.intent_id.as_str(), "intent-1");
+    assert_eq!(run.idempotency_key.as_str(), "custody:task-1:intent-1");
+    assert!(run.validate().is_ok());
+}
+
+#[tokio::test]
+async fn bootstrap_is_idempotent_within_and_across_bootstrappers() {
+    let plane = FakePlane::with_task("task-1");
+    let mut bootstrap = CustodyBootstrap::new(plane.clone());
+    let first = bootstrap.bootstrap(&custody_request()).await.unwrap();
+    plane.calls();
+
+    // The same key returns the same run without touching Decapod.
+    let again = bootstrap.bootstrap(&custody_request()).await.unwrap();
+    assert_eq!(again, first);
+    assert!(plane.calls().is_empty());
+
+    // A different key for the same task reuses the claim and the open work
+    // unit instead of claiming and initialising again.
+    let retry = custody_request().with_idempotency_key(IdempotencyKey::new("retry").unwrap());
+    let resumed = bootstrap.bootstrap(&retry).await.unwrap();
+    assert_eq!(
+        plane.calls(),
+        ["get task-1", "list task-1", "ensure feature"]
+    );
+    assert_eq!(resumed.custody, first.custody);
+    assert_ne!(resumed.run_id, first.run_id);
+
+    // A fresh bootstrapper holds another session, so the claim is taken over
+    // but the work unit is still reused.
+    let mut other = CustodyBootstrap::new(plane.clone());
+    let taken_over = other.bootstrap(&custody_request()).await.unwrap();
+    assert_eq!(
+        plane.calls(),
+        [
+            "acquire",
+            "get task-1",
+            "claim task-1",
+            "list task-1",
+            "ensure feature"
+        ]
+    );
+    assert_eq!(taken_over.custody.work_unit, first.custody.work_unit);
+    assert_eq!(plane.state().work_units.len(), 1);
+}
+
+#[tokio::test]
+async fn a_failed_workspace_step_abandons_the_work_unit_and_releases_the_task() {
+    let plane = FakePlane::with_task("task-1");
+    plane.fail("ensure");
+    let mut bootstrap = CustodyBootstrap::new(plane.clone());
+
+    let error = bootstrap.bootstrap(&custody_request()).await.unwrap_err();
+    assert_eq!(error.step, BootstrapStep::EnsureWorkspace);
+    assert!(error.rollback_failures.is_empty());
+    assert_eq!(
+        plane.calls()[4..],
+        [
+            "init task-1",
+            "ensure feature",
+            "fail wu-1",
+            "release task-1"
+        ]
+    );
+    assert_eq!(plane.task_status(), (TaskStatus::Pending, None));
+    assert_eq!(plane.state().work_units[0].status, WorkUnitStatus::Failed);
+
+    // Nothing was remembered, so the next attempt starts over.
+    let run = bootstrap.bootstrap(&custody_request()).await.unwrap();
+    assert_eq!(
+        run.custody.work_unit,
+        Some(WorkUnitRef::new("wu-2").unwrap())
+    );
+}
+
+#[tokio::test]
+async fn a_failed_build_leaves_the_ensured_workspace_in_place() {
+    let plane = FakePlane::with_task("task-1");
+    let mut bootstrap = CustodyBootstrap::new(plane.clone());
+
+    let error = bootstrap
+        .bootstrap(&custody_request().with_workspace(" "))
+        .await
+        .unwrap_err();
+    assert_eq!(error.step, BootstrapStep::BuildRequest);
+    assert!(error.rollback_failures.is_empty());
+    assert_eq!(
+        plane.calls()[4..],
+        ["init task-1", "ensure", "fail wu-1", "release task-1"]
+    );
+}
+
+#[tokio::test]
+async fn only_what_the_failed_attempt_acquired_is_undone() {
+    let plane = FakePlane::with_task("task-1");
+    let mut bootstrap = CustodyBootstrap::new(plane.clone());
+    plane.fail("init");
+    let error = bootstrap.bootstrap(&custody_request()).await.unwrap_err();
+    assert_eq!(error.step, BootstrapStep::InitWorkUnit);
+    assert_eq!(
+        plane.calls().last().map(String::as_str),
+        Some("release task-1")
+    );
+
+    // The claim is held from an earlier attempt, so it is not released.
+    plane.claim_task(&session(1), "task-1").await.unwrap();
+    plane.calls();
+    plane.fail("init");
+    let error = bootstrap.bootstrap(&custody_request()).await.unwrap_err();
+    assert_eq!(error.step, BootstrapStep::InitWorkUnit);
+    assert_eq!(plane.calls(), ["get task-1", "list task-1", "init task-1"]);
+    assert_eq!(plane.task_status().0, TaskStatus::Claimed);
+}
+
+#[tokio::test]
+async fn failed_undo_steps_are_reported_with_the_error() {
+    let plane = FakePlane::with_task("task-1");
+    let mut bootstrap = CustodyBootstrap::new(plane.clone());
+    plane.fail("ensure");
+    plane.fail("release");
+
+    let error = bootstrap.bootstrap(&custody_request()).await.unwrap_err();
+    assert_eq!(error.step, BootstrapStep::EnsureWorkspace);
+    assert!(error.to_string().contains("ensure the workspace"));
+    assert_eq!(error.rollback_failures.len(), 1);
+    assert!(error.rollback_failures[0].starts_with("task task-1"));
+    assert_eq!(plane.state().work_units[0].status, WorkUnitStatus::Failed);
+}
+
+#[tokio::test]
+async fn the_project_root_is_verified_first_and_bound_next_to_the_repository() {
+    let dir = tempfile::tempdir().unwrap();
+    let plane = FakePlane::with_task("task-1");
+    plane.state().root = Some(dir.path().to_path_buf());
+    let mut bootstrap = CustodyBootstrap::new(plane.clone());
+
+    let error = bootstrap.bootstrap(&custody_request()).await.unwrap_err();
+    assert_eq!(error.step, BootstrapStep::VerifyProjectRoot);
+    assert!(plane.calls().is_empty());
+    assert!(bootstrap.session().is_none());
+
+    std::fs::create_dir(dir.path().join(".decapod")).unwrap();
+    let run = bootstrap.bootstrap(&custody_request()).await.unwrap();
+    let root = ProjectRoot::verify(dir.path()).unwrap();
+    assert_eq!(run.custody.project_root, Some(root.to_ref().unwrap()));
+    assert_eq!(
+        run.custody.repository,
+        Some(RepositoryRef::new("repository-1").unwrap())
+    );
+}
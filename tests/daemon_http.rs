@@ -0,0 +1,230 @@
+mod common;
+
+use common::*;
+use pincher::daemon::RunService;
+use pincher::daemon::http::{HttpConfig, HttpServer};
+use pincher::governed_run::chain::verify_event_chain;
+use pincher::governed_run::*;
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+struct Reply {
+    status: u16,
+    head: String,
+    body: String,
+}
+
+impl Reply {
+    fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+
+    /// `(id, data)` of every `run-event` frame, and the data of the `end`
+    /// frame if the stream sent one.
+    fn sse(&self) -> (Vec<(u64, RunEvent)>, Option<Value>) {
+        let mut events = Vec::new();
+        let mut end = None;
+        for frame in self.body.split("\n\n").filter(|frame| !frame.is_empty()) {
+            let field = |name: &str| {
+                frame
+                    .lines()
+                    .find_map(|line| line.strip_prefix(&format!("{name}: ")))
+                    .map(str::to_string)
+            };
+            match field("event").as_deref() {
+                Some("run-event") => events.push((
+                    field("id").unwrap().parse().unwrap(),
+                    serde_json::from_str(&field("data").unwrap()).unwrap(),
+                )),
+                Some("end") => end = Some(serde_json::from_str(&field("data").unwrap()).unwrap()),
+                _ => {}
+            }
+        }
+        (events, end)
+    }
+}
+
+/// Sends one request on a fresh connection and reads until the server
+/// closes it.
+async fn send(addr: SocketAddr, method: &str, path: &str, headers: &[&str], body: &str) -> Reply {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let mut request =
+        format!("{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n");
+    for header in headers {
+        request.push_str(&format!("{header}\r\n"));
+    }
+    request.push_str(&format!("Content-Length: {}\r\n\r\n{body}", body.len()));
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    tokio::time::timeout(Duration::from_secs(5), stream.read_to_string(&mut response))
+        .await
+        .expect("server did not close the response")
+        .unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    Reply {
+        status: head[9..12].parse().unwrap(),
+        head: head.to_string(),
+        body: body.to_string(),
+    }
+}
+
+async fn get(addr: SocketAddr, path: &str, headers: &[&str]) -> Reply {
+    send(addr, "GET", path, headers, "").await
+}
+
+struct Server {
+    service: RunService<FakeControl, GatedProvider>,
+    addr: SocketAddr,
+    shutdown: tokio::sync::oneshot::Sender<()>,
+    served: tokio::task::JoinHandle<std::io::Result<()>>,
+}
+
+async fn start(provider: GatedProvider, token: Option<&str>) -> Server {
+    let mut config = HttpConfig::new("127.0.0.1:0".parse().unwrap());
+    if let Some(token) = token {
+        config = config.with_token(token);
+    }
+    let server = HttpServer::bind(config).await.unwrap();
+    let addr = server.local_addr().unwrap();
+    let service = RunService::new(FakeControl, provider);
+    let (shutdown, stop) = tokio::sync::oneshot::channel::<()>();
+    let served = tokio::spawn(server.serve(service.clone(), async {
+        let _ = stop.await;
+    }));
+    Server {
+        service,
+        addr,
+        shutdown,
+        served,
+    }
+}
+
+#[tokio::test]
+async fn runs_are_submitted_fetched_and_streamed_with_resume() {
+    let provider = GatedProvider::default();
+    let server = start(provider.clone(), None).await;
+    let addr = server.addr;
+
+    let body = serde_json::to_string(&request("run-1")).unwrap();
+    let submitted = send(addr, "POST", "/runs", &[], &body).await;
+    assert_eq!(submitted.status, 202, "{}", submitted.body);
+    assert!(submitted.head.contains("Location: /runs/run-1"));
+    assert_eq!(submitted.json()["run_id"], "run-1");
+
+    // While the provider is blocked the snapshot is folded from the events.
+    provider.wait_entered().await;
+    let running = get(addr, "/runs/run-1", &[]).await;
+    assert_eq!(running.status, 200);
+    let running: RunSnapshot = serde_json::from_str(&running.body).unwrap();
+    assert_eq!(running.state, RunState::Executing);
+    assert!(running.custody.is_some());
+
+    let stream = tokio::spawn(get(addr, "/runs/run-1/events", &[]));
+    provider.release();
+    let streamed = stream.await.unwrap();
+    assert_eq!(streamed.status, 200);
+    assert!(streamed.head.contains("Content-Type: text/event-stream"));
+    let (frames, end) = streamed.sse();
+    let events: Vec<RunEvent> = frames.iter().map(|(_, event)| event.clone()).collect();
+    assert!(frames.iter().all(|(id, event)| *id == event.sequence));
+    assert_eq!(events.last().unwrap().state, Some(RunState::Ready));
+    assert_eq!(end.unwrap()["last_sequence"], json!(events.len()));
+    verify_event_chain(&events, None).unwrap();
+
+    let resumed = get(addr, "/runs/run-1/events", &["Last-Event-ID: 3"]).await;
+    let (frames, _) = resumed.sse();
+    assert_eq!(frames.first().unwrap().0, 4);
+    assert_eq!(frames.len(), events.len() - 3);
+    let caught_up = get(
+        addr,
+        "/runs/run-1/events",
+        &[&format!("Last-Event-ID: {}", events.len())],
+    )
+    .await;
+    let (frames, end) = caught_up.sse();
+    assert!(frames.is_empty());
+    assert_eq!(end.unwrap()["last_sequence"], json!(events.len()));
+
+    let status = server.service.wait(&id("run-1")).await.unwrap();
+    let Some(RunOutcome::Ready(snapshot)) = status.outcome else {
+        panic!("run must be ready");
+    };
+    let fetched = get(addr, "/runs/run-1", &[]).await;
+    assert_eq!(fetched.json(), serde_json::to_value(&snapshot).unwrap());
+
+    let mut conflicting = request("run-1");
+    conflicting.idempotency_key = id("another-key");
+    let body = serde_json::to_string(&conflicting).unwrap();
+    assert_eq!(send(addr, "POST", "/runs", &[], &body).await.status, 409);
+    let malformed = send(addr, "POST", "/runs", &[], "{not json").await;
+    assert_eq!(malformed.status, 400);
+    assert_eq!(malformed.json()["error"]["code"], "InvalidRequest");
+    let missing = get(addr, "/runs/run-9", &[]).await;
+    assert_eq!(missing.status, 404);
+    assert_eq!(missing.json()["error"]["code"], "RunNotFound");
+    assert_eq!(get(addr, "/runs/run-9/events", &[]).await.status, 404);
+    assert_eq!(
+        get(addr, "/runs/run-1/events", &["Last-Event-ID: x"])
+            .await
+            .status,
+        400
+    );
+    assert_eq!(get(addr, "/runs", &[]).await.status, 405);
+    assert_eq!(get(addr, "/other", &[]).await.status, 404);
+
+    server.shutdown.send(()).unwrap();
+    server.served.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn a_configured_token_is_required_on_every_route() {
+    let server = start(GatedProvider::open(), Some("secret-token")).await;
+    let addr = server.addr;
+
+    let anonymous = get(addr, "/runs/run-1", &[]).await;
+    assert_eq!(anonymous.status, 401);
+    assert!(anonymous.head.contains("WWW-Authenticate: Bearer"));
+    let body = serde_json::to_string(&request("run-1")).unwrap();
+    let forged = send(
+        addr,
+        "POST",
+        "/runs",
+        &["Authorization: Bearer secret-tokem"],
+        &body,
+    )
+    .await;
+    assert_eq!(forged.status, 401);
+    assert!(server.service.get(&id("run-1")).is_err());
+
+    let authorized = ["Authorization: Bearer secret-token"];
+    assert_eq!(
+        send(addr, "POST", "/runs", &authorized, &body).await.status,
+        202
+    );
+    let streamed = get(addr, "/runs/run-1/events", &authorized).await;
+    assert!(streamed.sse().1.is_some());
+
+    server.shutdown.send(()).unwrap();
+    server.served.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn non_loopback_binds_require_a_token() {
+    let public = HttpConfig::new("0.0.0.0:0".parse().unwrap());
+    let refused = HttpServer::bind(public.clone()).await.err().unwrap();
+    assert_eq!(refused.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(
+        HttpServer::bind(public.clone().with_token(""))
+            .await
+            .is_err()
+    );
+    assert!(
+        HttpServer::bind(public.with_token("secret-token"))
+            .await
+            .is_ok()
+    );
+    assert!(HttpConfig::default().addr.ip().is_loopback());
+}
@@ -0,0 +1,328 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::*;
+use pincher::daemon::jsonrpc::serve_unix;
+use pincher::daemon::{RunService, ServiceError};
+use pincher::governed_run::chain::verify_event_chain;
+use pincher::governed_run::*;
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::UnixStream;
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+
+struct Client {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+    next_id: u64,
+    notifications: VecDeque<Value>,
+}
+
+impl Client {
+    async fn connect(path: &Path) -> Self {
+        let stream = UnixStream::connect(path).await.unwrap();
+        let (reader, writer) = stream.into_split();
+        Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            next_id: 0,
+            notifications: VecDeque::new(),
+        }
+    }
+
+    async fn send_line(&mut self, line: &str) {
+        self.writer.write_all(line.as_bytes()).await.unwrap();
+        self.writer.write_all(b"\n").await.unwrap();
+    }
+
+    async fn read(&mut self) -> Value {
+        let line = tokio::time::timeout(Duration::from_secs(5), self.lines.next_line())
+            .await
+            .expect("daemon did not answer")
+            .unwrap()
+            .expect("daemon closed the connection");
+        serde_json::from_str(&line).unwrap()
+    }
+
+    /// Sends a request and returns its response, keeping notifications that
+    /// arrive first.
+    async fn call(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        self.send_line(&message.to_string()).await;
+        loop {
+            let message = self.read().await;
+            if message["id"] == json!(id) {
+                return message;
+            }
+            self.notifications.push_back(message);
+        }
+    }
+
+    async fn notification(&mut self) -> Value {
+        match self.notifications.pop_front() {
+            Some(message) => message,
+            None => self.read().await,
+        }
+    }
+}
+
+struct Daemon {
+    service: RunService<FakeControl, GatedProvider>,
+    socket: PathBuf,
+    shutdown: tokio::sync::oneshot::Sender<()>,
+    served: tokio::task::JoinHandle<std::io::Result<()>>,
+    _dir: tempfile::TempDir,
+}
+
+async fn start(provider: GatedProvider) -> Daemon {
+    let dir = tempfile::tempdir().unwrap();
+    let socket = dir.path().join("pincher.sock");
+    let service = RunService::new(FakeControl, provider);
+    let (shutdown, stop) = tokio::sync::oneshot::channel::<()>();
+    let served = tokio::spawn({
+        let service = service.clone();
+        let socket = socket.clone();
+        async move {
+            serve_unix(service, &socket, async {
+                let _ = stop.await;
+            })
+            .await
+        }
+    });
+    while !socket.exists() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    Daemon {
+        service,
+        socket,
+        shutdown,
+        served,
+        _dir: dir,
+    }
+}
+
+#[tokio::test]
+async fn runs_are_submitted_streamed_and_handed_off_over_the_socket() {
+    let daemon = start(GatedProvider::open()).await;
+    let mode = std::fs::metadata(&daemon.socket).unwrap().permissions();
+    assert_eq!(
+        std::os::unix::fs::PermissionsExt::mode(&mode) & 0o777,
+        0o600
+    );
+    let siblings: Vec<_> = std::fs::read_dir(daemon.socket.parent().unwrap())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(siblings, ["pincher.sock"]);
+    let mut client = Client::connect(&daemon.socket).await;
+
+    let support = client.call("contract.support", Value::Null).await;
+    assert_eq!(support["result"]["current"], GOVERNED_RUN_CONTRACT_VERSION);
+
+    let submitted = client
+        .call("run.submit", json!({ "request": request("run-1") }))
+        .await;
+    assert_eq!(submitted["result"]["run_id"], "run-1");
+    let subscribed = client
+        .call("events.subscribe", json!({ "run_id": "run-1" }))
+        .await;
+    let subscription = subscribed["result"]["subscription"].clone();
+
+    let mut events = Vec::new();
+    loop {
+        let message = client.notification().await;
+        assert_eq!(message["params"]["subscription"], subscription);
+        if message["method"] == "events.closed" {
+            assert_eq!(message["params"]["last_sequence"], json!(events.len()));
+            break;
+        }
+        assert_eq!(message["method"], "events.event");
+        events
+            .push(serde_json::from_value::<RunEvent>(message["params"]["event"].clone()).unwrap());
+    }
+    assert_eq!(events.last().unwrap().state, Some(RunState::Ready));
+    verify_event_chain(&events, None).unwrap();
+
+    let status = daemon.service.wait(&id("run-1")).await.unwrap();
+    let fetched = client.call("run.get", json!({ "run_id": "run-1" })).await;
+    assert_eq!(fetched["result"], serde_json::to_value(&status).unwrap());
+    assert!(matches!(status.outcome, Some(RunOutcome::Ready(_))));
+
+    let again = client
+        .call("run.submit", json!({ "request": request("run-1") }))
+        .await;
+    assert_eq!(again["result"]["event_count"], json!(events.len()));
+    let mut conflicting = request("run-1");
+    conflicting.idempotency_key = id("another-key");
+    let conflict = client
+        .call("run.submit", json!({ "request": conflicting }))
+        .await;
+    assert_eq!(conflict["error"]["code"], -32002);
+
+    let handed_off = client
+        .call("run.handoff", json!({ "run_id": "run-1" }))
+        .await;
+    assert_eq!(handed_off["result"]["state"], "handed_off");
+    let repeated = client
+        .call("run.handoff", json!({ "run_id": "run-1" }))
+        .await;
+    assert_eq!(repeated["error"]["code"], -32005);
+    let all = daemon.service.events_after(&id("run-1"), 0).unwrap();
+    verify_event_chain(&all, None).unwrap();
+
+    let cancel = client
+        .call("run.cancel", json!({ "run_id": "run-1" }))
+        .await;
+    assert_eq!(cancel["error"]["code"], -32004);
+    let missing = client.call("run.get", json!({ "run_id": "run-9" })).await;
+    assert_eq!(missing["error"]["code"], -32001);
+    let unknown = client
+        .call("run.delete", json!({ "run_id": "run-1" }))
+        .await;
+    assert_eq!(unknown["error"]["code"], -32601);
+    let invalid = client.call("run.get", json!({ "run": "run-1" })).await;
+    assert_eq!(invalid["error"]["code"], -32602);
+    client.send_line("{not json").await;
+    assert_eq!(client.read().await["error"]["code"], -32700);
+    client
+        .send_line(r#"[{"jsonrpc":"2.0","id":"a","method":"run.get","params":{"run_id":"run-1"}},{"jsonrpc":"2.0","method":"run.get","params":{"run_id":"run-1"}}]"#)
+        .await;
+    let batch = client.read().await;
+    assert_eq!(batch.as_array().unwrap().len(), 1);
+    assert_eq!(batch[0]["id"], "a");
+
+    daemon.shutdown.send(()).unwrap();
+    daemon.served.await.unwrap().unwrap();
+    assert!(!daemon.socket.exists());
+}
+
+#[tokio::test]
+async fn subscriptions_to_a_rejected_run_are_closed() {
+    let daemon = start(GatedProvider::open()).await;
+    let mut client = Client::connect(&daemon.socket).await;
+    // Rejected before it starts, so the run never publishes an event.
+    let mut invalid = request("run-1");
+    invalid.contract.version = "2.0.0".to_string();
+    daemon.service.submit(invalid).unwrap();
+    let subscribed = client
+        .call("events.subscribe", json!({ "run_id": "run-1" }))
+        .await;
+
+    let closed = client.notification().await;
+    assert_eq!(closed["method"], "events.closed");
+    assert_eq!(
+        closed["params"]["subscription"],
+        subscribed["result"]["subscription"]
+    );
+    assert_eq!(closed["params"]["last_sequence"], 0);
+    let status = daemon.service.wait(&id("run-1")).await.unwrap();
+    assert_eq!(status.event_count, 0);
+    assert!(matches!(status.outcome, Some(RunOutcome::Failed(_))));
+
+    // A run that has already returned closes at once.
+    let resubscribed = client
+        .call("events.subscribe", json!({ "run_id": "run-1" }))
+        .await;
+    let closed = client.notification().await;
+    assert_eq!(closed["method"], "events.closed");
+    assert_eq!(
+        closed["params"]["subscription"],
+        resubscribed["result"]["subscription"]
+    );
+}
+
+#[tokio::test]
+async fn submissions_are_upgraded_like_http_and_finished_runs_are_evicted() {
+    let daemon = start(GatedProvider::open()).await;
+    let mut client = Client::connect(&daemon.socket).await;
+    let mut older = serde_json::to_value(request("run-1")).unwrap();
+    older["contract"]["version"] = json!("1.0.3");
+    let submitted = client.call("run.submit", json!({ "request": older })).await;
+    assert_eq!(submitted["result"]["run_id"], "run-1");
+    let status = daemon.service.wait(&id("run-1")).await.unwrap();
+    let Some(RunOutcome::Ready(snapshot)) = status.outcome else {
+        panic!("a compatible request must run");
+    };
+    assert_eq!(snapshot.request.contract, ContractIdentity::v1());
+
+    let mut newer = serde_json::to_value(request("run-2")).unwrap();
+    newer["contract"]["version"] = json!("2.0.0");
+    let rejected = client.call("run.submit", json!({ "request": newer })).await;
+    assert_eq!(rejected["error"]["code"], -32602);
+    assert_eq!(
+        daemon.service.get(&id("run-2")),
+        Err(ServiceError::RunNotFound {
+            run_id: "run-2".to_string()
+        })
+    );
+
+    let service = RunService::new(FakeControl, GatedProvider::open()).with_retention(1);
+    for run in ["run-1", "run-2"] {
+        service.submit(request(run)).unwrap();
+        service.wait(&id(run)).await.unwrap();
+    }
+    assert!(matches!(
+        service.get(&id("run-1")),
+        Err(ServiceError::RunNotFound { .. })
+    ));
+    assert!(service.get(&id("run-2")).unwrap().is_finished());
+}
+
+#[tokio::test]
+async fn cancellation_stops_a_run_at_its_next_checkpoint() {
+    let provider = GatedProvider::default();
+    let service = RunService::new(FakeControl, provider.clone());
+    service.submit(request("run-1")).unwrap();
+    provider.wait_entered().await;
+
+    let status = service.cancel(&id("run-1")).unwrap();
+    assert!(status.cancel_requested);
+    assert_eq!(status.state, RunState::Executing);
+    provider.release();
+
+    let status = service.wait(&id("run-1")).await.unwrap();
+    let Some(RunOutcome::Failed(snapshot)) = status.outcome else {
+        panic!("cancelled run must fail");
+    };
+    assert!(matches!(
+        snapshot.failure,
+        Some(RunFailure::Cancelled { .. })
+    ));
+    assert_eq!(snapshot.validation, None);
+    let events = service.events_after(&id("run-1"), 0).unwrap();
+    assert_eq!(events.last().unwrap().failure, Some(FailureCode::Cancelled));
+    assert_eq!(provider.entered(), 1);
+}
+
+#[tokio::test]
+async fn shutdown_drains_in_flight_runs_and_refuses_new_ones() {
+    let provider = GatedProvider::default();
+    let daemon = start(provider.clone()).await;
+    let mut client = Client::connect(&daemon.socket).await;
+    client
+        .call("run.submit", json!({ "request": request("run-1") }))
+        .await;
+    provider.wait_entered().await;
+
+    daemon.shutdown.send(()).unwrap();
+    while !daemon.service.is_draining() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    let refused = client
+        .call("run.submit", json!({ "request": request("run-2") }))
+        .await;
+    assert_eq!(refused["error"]["code"], -32006);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!daemon.served.is_finished());
+
+    provider.release();
+    daemon.served.await.unwrap().unwrap();
+    let status = daemon.service.get(&id("run-1")).unwrap();
+    assert!(matches!(status.outcome, Some(RunOutcome::Ready(_))));
+}
@@ -0,0 +1,199 @@
+use pincher::decapod::discovery::{
+    BinaryDiscovery, BinarySource, CompatibilityCheck, CompatibilityError, DecapodInstall,
+    DecapodVersion, DiscoveryError, PASSWORD_STDIN_FLAG, PROJECT_LOCAL_BINARY, SUPPORTED_VERSIONS,
+    VersionRange,
+};
+use pincher::decapod::secret::Secret;
+use pincher::decapod::session::Session;
+use pincher::decapod::sim::{Scenario, Simulator};
+use pincher::decapod::transport::DecapodTransport;
+use std::path::{Path, PathBuf};
+
+const SIM: &str = env!("CARGO_BIN_EXE_decapod-sim");
+
+#[cfg(unix)]
+fn executable(path: &Path) -> PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path.to_path_buf()
+}
+
+#[cfg(unix)]
+#[test]
+fn discovery_prefers_config_then_env_then_project_then_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let configured = executable(&dir.path().join("config/decapod"));
+    let from_env = executable(&dir.path().join("env/decapod"));
+    let root = dir.path().join("project");
+    let local = executable(&root.join(PROJECT_LOCAL_BINARY));
+    let on_path = executable(&dir.path().join("bin/decapod"));
+    let search_path =
+        std::env::join_paths([dir.path().join("empty"), dir.path().join("bin")]).unwrap();
+
+    let discovery = BinaryDiscovery::default().with_search_path(search_path);
+    let found = |discovery: &BinaryDiscovery| {
+        let binary = discovery.discover().unwrap();
+        (binary.path, binary.source)
+    };
+    assert_eq!(found(&discovery), (on_path.clone(), BinarySource::Path));
+
+    let discovery = discovery.with_project_root(&root);
+    assert_eq!(found(&discovery), (local, BinarySource::ProjectLocal));
+
+    let discovery = discovery.with_env_binary(Some(from_env.clone()));
+    assert_eq!(found(&discovery), (from_env, BinarySource::Environment));
+
+    let discovery = discovery.with_binary(&configured);
+    assert_eq!(found(&discovery), (configured, BinarySource::Config));
+
+    // A bare configured name is looked up on PATH.
+    let discovery = discovery.with_binary("decapod");
+    assert_eq!(found(&discovery), (on_path, BinarySource::Config));
+}
+
+#[test]
+fn named_binaries_that_do_not_exist_are_reported_not_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("nope/decapod");
+    let discovery = BinaryDiscovery::default()
+        .with_env_binary(Some(missing.clone()))
+        .with_search_path(std::env::join_paths([Path::new(SIM).parent().unwrap()]).unwrap());
+    assert_eq!(
+        discovery.discover().unwrap_err(),
+        DiscoveryError::Unusable {
+            origin: BinarySource::Environment,
+            path: missing,
+        }
+    );
+
+    let nowhere = BinaryDiscovery::default()
+        .with_project_root(dir.path())
+        .with_search_path(dir.path().as_os_str());
+    let DiscoveryError::NotFound { searched } = nowhere.discover().unwrap_err() else {
+        panic!("expected NotFound");
+    };
+    assert_eq!(searched.len(), 2);
+    assert!(searched[0].ends_with(PROJECT_LOCAL_BINARY));
+}
+
+#[test]
+fn versions_parse_and_compare_by_release() {
+    let parse = |text: &str| text.parse::<DecapodVersion>().unwrap();
+    assert_eq!(parse("0.4.2"), DecapodVersion::new(0, 4, 2));
+    assert_eq!(parse("v0.4.2-rc.1+build.7"), DecapodVersion::new(0, 4, 2));
+    assert_eq!(parse("1.3"), DecapodVersion::new(1, 3, 0));
+    assert!(matches!(
+        "latest".parse::<DecapodVersion>(),
+        Err(CompatibilityError::UnreadableVersion { .. })
+    ));
+
+    assert!(SUPPORTED_VERSIONS.contains(parse("0.1.0")));
+    assert!(!SUPPORTED_VERSIONS.contains(parse("1.0.0")));
+    assert_eq!(
+        VersionRange {
+            minimum: parse("0.2"),
+            below: parse("0.5"),
+        }
+        .to_string(),
+        ">=0.2.0, <0.5.0"
+    );
+}
+
+#[tokio::test]
+async fn the_password_is_piped_only_when_decapod_reports_the_flag() {
+    let install = connect(Scenario::new()).await.unwrap();
+    assert!(
+        install
+            .capabilities
+            .accepts_flag("session", "acquire", PASSWORD_STDIN_FLAG)
+    );
+    assert!(install.process().accepts_input());
+
+    let dir = tempfile::tempdir().unwrap();
+    Simulator::new(dir.path())
+        .set_scenario(
+            Scenario::new()
+                .with_password("hunter2")
+                .without_password_stdin(),
+        )
+        .unwrap();
+    let discovery = BinaryDiscovery::default()
+        .with_binary(SIM)
+        .with_project_root(dir.path());
+    let install = DecapodInstall::connect(&discovery, &CompatibilityCheck::new())
+        .await
+        .unwrap();
+    assert!(
+        !install
+            .capabilities
+            .accepts_flag("session", "acquire", PASSWORD_STDIN_FLAG)
+    );
+    assert!(!install.process().accepts_input());
+    Session::acquire_with(install.process(), &Secret::new("hunter2"))
+        .await
+        .unwrap();
+
+    // That Decapod rejects the flag outright.
+    let forced = install.process().clone().with_password_stdin(true);
+    assert!(
+        Session::acquire_with(&forced, &Secret::new("hunter2"))
+            .await
+            .is_err()
+    );
+}
+
+/// Connects to `decapod-sim` in a project running `scenario`.
+async fn connect(scenario: Scenario) -> Result<DecapodInstall, CompatibilityError> {
+    let dir = tempfile::tempdir().unwrap();
+    Simulator::new(dir.path()).set_scenario(scenario).unwrap();
+    let discovery = BinaryDiscovery::default()
+        .with_binary(SIM)
+        .with_project_root(dir.path());
+    DecapodInstall::connect(&discovery, &CompatibilityCheck::new()).await
+}
+
+#[tokio::test]
+async fn connect_checks_version_and_rpc_operations_before_anything_runs() {
+    let install = connect(Scenario::new()).await.unwrap();
+    assert_eq!(install.version, DecapodVersion::new(0, 1, 0));
+    assert_eq!(install.binary.source, BinarySource::Config);
+    assert!(install.process().project_root().is_some());
+
+    let too_new = connect(Scenario::new().with_version("1.2.0"))
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        too_new,
+        CompatibilityError::UnsupportedVersion { version, .. } if version == DecapodVersion::new(1, 2, 0)
+    ));
+
+    let old = connect(Scenario::new().with_rpc_operations(&["todo.add", "todo.claim"]))
+        .await
+        .unwrap_err();
+    let CompatibilityError::MissingOperations { missing, .. } = &old else {
+        panic!("expected MissingOperations, got {old}");
+    };
+    assert!(missing.contains(&"workunit.init".to_string()));
+    assert!(!missing.contains(&"todo.claim".to_string()));
+    assert!(old.to_string().contains("lacks required RPC operations"));
+
+    let relaxed = CompatibilityCheck::new().with_required_operations(&["todo.add"]);
+    let dir = tempfile::tempdir().unwrap();
+    Simulator::new(dir.path())
+        .set_scenario(Scenario::new().with_rpc_operations(&["todo.add"]))
+        .unwrap();
+    let discovery = BinaryDiscovery::default()
+        .with_binary(SIM)
+        .with_project_root(dir.path());
+    assert!(DecapodInstall::connect(&discovery, &relaxed).await.is_ok());
+
+    let missing = BinaryDiscovery::default().with_binary(dir.path().join("decapod"));
+    assert!(matches!(
+        DecapodInstall::connect(&missing, &relaxed)
+            .await
+            .unwrap_err(),
+        CompatibilityError::Discovery(DiscoveryError::Unusable { .. })
+    ));
+}
@@ -0,0 +1,167 @@
+use pincher::decapod::cli::DecapodResponse;
+use pincher::decapod::docs::DocsManager;
+use pincher::decapod::envelope::{
+    DecodeMode, EnvelopeError, ParseError, decode, decode_list, decode_response,
+};
+use pincher::decapod::todo::TodoManager;
+use pincher::decapod::transport::{ScriptedTransport, TransportOutput};
+use pincher::decapod::workspace::WorkspaceManager;
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Item {
+    id: String,
+}
+
+fn kind<T: std::fmt::Debug>(result: Result<T, EnvelopeError>) -> ParseError {
+    result.unwrap_err().kind
+}
+
+#[test]
+fn strict_mode_accepts_exactly_one_document_of_the_expected_shape() {
+    let item: Item = decode("todo get", "\n  {\"id\": \"a\"}\n", DecodeMode::Strict).unwrap();
+    assert_eq!(item.id, "a");
+
+    assert_eq!(
+        kind(decode::<Item>("todo get", " \n", DecodeMode::Strict)),
+        ParseError::Empty
+    );
+    assert!(matches!(
+        kind(decode::<Item>(
+            "todo get",
+            "claiming...\n{\"id\": \"a\"}",
+            DecodeMode::Strict
+        )),
+        ParseError::Syntax {
+            line: 1,
+            column: 1,
+            ..
+        }
+    ));
+    assert!(matches!(
+        kind(decode::<Item>("todo get", "{\"name\": \"a\"}", DecodeMode::Strict)),
+        ParseError::Shape { reason } if reason.contains("missing field `id`")
+    ));
+    assert_eq!(
+        kind(decode::<Item>(
+            "todo get",
+            "{\"id\": \"a\"}\n{\"id\": \"a\"}",
+            DecodeMode::Strict
+        )),
+        ParseError::Duplicate { count: 2 }
+    );
+    assert_eq!(
+        kind(decode::<Item>(
+            "todo get",
+            "{\"id\": \"a\"}{\"id\": \"b\"}",
+            DecodeMode::Strict
+        )),
+        ParseError::Conflicting { count: 2 }
+    );
+
+    let error = decode::<Item>("todo get", "", DecodeMode::Strict).unwrap_err();
+    assert_eq!(error.to_string(), "invalid todo get output: no output");
+}
+
+#[test]
+fn lenient_mode_skips_log_lines_but_not_disagreement() {
+    let noisy = "claiming task\n{\"id\": \"a\"}\n{\"id\": \"a\"}\ndone\n";
+    let item: Item = decode("todo claim", noisy, DecodeMode::Lenient).unwrap();
+    assert_eq!(item.id, "a");
+
+    let lines = "{\"id\": \"a\"}\nwarning: slow index\n{\"id\": \"b\"}\n";
+    let items: Vec<Item> = decode_list("todo list", lines, DecodeMode::Lenient).unwrap();
+    assert_eq!(items.len(), 2);
+    assert!(matches!(
+        kind(decode_list::<Item>("todo list", lines, DecodeMode::Strict)),
+        ParseError::Syntax { .. }
+    ));
+    assert_eq!(
+        kind(decode::<Item>("todo claim", lines, DecodeMode::Lenient)),
+        ParseError::Conflicting { count: 2 }
+    );
+
+    // Lenient mode never turns missing output into an empty result.
+    assert_eq!(
+        kind(decode_list::<Item>("todo list", "", DecodeMode::Lenient)),
+        ParseError::Empty
+    );
+    assert!(matches!(
+        kind(decode_list::<Item>(
+            "todo list",
+            "nothing to do",
+            DecodeMode::Lenient
+        )),
+        ParseError::Syntax { .. }
+    ));
+    assert!(matches!(
+        kind(decode::<Item>(
+            "todo claim",
+            "{\"name\": 1}",
+            DecodeMode::Lenient
+        )),
+        ParseError::Shape { .. }
+    ));
+}
+
+#[test]
+fn rpc_envelopes_must_agree_with_themselves() {
+    let decode =
+        |output: &str| decode_response::<Value>("rpc todo.add", output, DecodeMode::Strict);
+
+    let ok: DecapodResponse<Value> = decode(r#"{"success": true, "data": {"id": "t"}}"#).unwrap();
+    assert!(ok.success);
+    decode(r#"{"success": false, "error": "denied"}"#).unwrap();
+    decode(
+        r#"{"success": false, "interlock": {"policy": "p", "reason": "r", "blocking": true,
+            "required_approval": null}}"#,
+    )
+    .unwrap();
+
+    assert!(matches!(
+        kind(decode(r#"{"success": true, "error": "denied"}"#)),
+        ParseError::Inconsistent { .. }
+    ));
+    assert!(matches!(
+        kind(decode(r#"{"success": false}"#)),
+        ParseError::Inconsistent { .. }
+    ));
+}
+
+#[tokio::test]
+async fn managers_no_longer_invent_empty_results() {
+    let transport = ScriptedTransport::new()
+        .reply(&["docs", "search"], TransportOutput::ok("no index yet"))
+        .reply(&["workspace", "status"], TransportOutput::ok(""))
+        .reply(
+            &["todo", "list"],
+            TransportOutput::ok("{\"id\":\"a\"}\n{\"id\":\"b\"}\n"),
+        );
+
+    let search = DocsManager::new()
+        .with_transport(Arc::new(transport.clone()))
+        .search("custody", None, None, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        search.downcast_ref::<EnvelopeError>(),
+        Some(EnvelopeError { what, kind: ParseError::Syntax { .. } }) if what == "docs search"
+    ));
+
+    let status = WorkspaceManager::new()
+        .with_transport(Arc::new(transport.clone()))
+        .status()
+        .await
+        .unwrap_err();
+    assert_eq!(
+        status
+            .downcast_ref::<EnvelopeError>()
+            .map(|error| &error.kind),
+        Some(&ParseError::Empty)
+    );
+
+    let todos = TodoManager::new().with_transport(Arc::new(transport.clone()));
+    assert!(todos.list(None, None, None).await.is_err());
+}
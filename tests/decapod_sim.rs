@@ -0,0 +1,321 @@
+use pincher::decapod::bootstrap::{CustodyBootstrap, CustodyRequest, DecapodCustody};
+use pincher::decapod::capabilities::CapabilitiesManager;
+use pincher::decapod::cli::{Advisory, Interlock};
+use pincher::decapod::commitment::ProofType;
+use pincher::decapod::docs::DocsManager;
+use pincher::decapod::project::{DECAPOD_DIR, ProjectRoot};
+use pincher::decapod::proof::{ValidationReportEvidence, evidence_map};
+use pincher::decapod::rpc::RpcClient;
+use pincher::decapod::secret::Secret;
+use pincher::decapod::session::Session;
+use pincher::decapod::sim::{ApprovalOutcome, Scenario, Simulator};
+use pincher::decapod::stdio::StdioTransport;
+use pincher::decapod::todo::{TaskStatus, TodoManager};
+use pincher::decapod::transport::{DecapodTransport, Invocation, ProcessTransport};
+use pincher::decapod::validate::{ValidationError, Validator};
+use pincher::decapod::workspace::WorkspaceManager;
+use pincher::decapod::workunit::WorkUnitManager;
+use pincher::governed_run::{IntentId, RepositoryRef, TaskRef};
+use std::sync::Arc;
+use tempfile::TempDir;
+
+const SIM: &str = env!("CARGO_BIN_EXE_decapod-sim");
+
+/// A project directory and a transport running `decapod-sim` in it.
+fn project() -> (TempDir, Arc<dyn DecapodTransport>) {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join(DECAPOD_DIR)).unwrap();
+    let transport = ProcessTransport::new(SIM).with_project_root(dir.path());
+    (dir, Arc::new(transport))
+}
+
+fn script(dir: &TempDir, scenario: Scenario) {
+    Simulator::new(dir.path()).set_scenario(scenario).unwrap();
+}
+
+fn interlock(policy: &str, required_approval: Option<&str>) -> Interlock {
+    Interlock {
+        policy: policy.to_string(),
+        reason: format!("{policy} is in force"),
+        blocking: true,
+        required_approval: required_approval.map(String::from),
+    }
+}
+
+#[tokio::test]
+async fn custody_bootstraps_against_the_simulator() {
+    let (_dir, transport) = project();
+    let task = TodoManager::new()
+        .with_transport(transport.clone())
+        .add("fix the build", Some("high"), None)
+        .await
+        .unwrap();
+
+    let plane = DecapodCustody::new("hunter2").with_transport(transport.clone());
+    let mut bootstrap = CustodyBootstrap::new(plane);
+    let request = CustodyRequest::new(
+        &task.id,
+        IntentId::new("intent-1").unwrap(),
+        RepositoryRef::new("repository-1").unwrap(),
+    )
+    .unwrap()
+    .with_workspace("feature");
+    let run = bootstrap.bootstrap(&request).await.unwrap();
+    assert!(run.validate().is_ok());
+    assert_eq!(run.custody.task, Some(TaskRef::new(&task.id).unwrap()));
+    let root = ProjectRoot::verify(transport.project_root().unwrap()).unwrap();
+    assert_eq!(run.custody.project_root, Some(root.to_ref().unwrap()));
+
+    let session = bootstrap.session().unwrap();
+    assert!(session.validate_with(transport.as_ref()).await.unwrap());
+    let claimed = TodoManager::new()
+        .with_transport(transport.clone())
+        .get(&task.id)
+        .await
+        .unwrap();
+    assert_eq!(claimed.status, TaskStatus::Claimed);
+    assert_eq!(claimed.owner.as_deref(), Some(session.session_id()));
+
+    let status = WorkspaceManager::new()
+        .with_transport(transport)
+        .status()
+        .await
+        .unwrap();
+    assert_eq!(status.current.unwrap().name, "feature");
+}
+
+#[tokio::test]
+async fn managers_read_back_what_the_simulator_stored() {
+    let (dir, transport) = project();
+    std::fs::write(
+        dir.path().join("README.md"),
+        "# Pincher\n\nCustody first.\n",
+    )
+    .unwrap();
+
+    let task = RpcClient::new()
+        .with_transport(transport.clone())
+        .todo_add("write docs", None)
+        .await
+        .unwrap()
+        .into_data()
+        .unwrap();
+    let work_units = WorkUnitManager::new().with_transport(transport.clone());
+    let unit = work_units.init(&task.id, "intent-1").await.unwrap();
+    let evidence = evidence_map(&ValidationReportEvidence {
+        validation_ref: "validation-1".to_string(),
+        passed: Some(true),
+    })
+    .unwrap();
+    let proof = work_units
+        .record_proof(&unit.id, ProofType::Validation, "gates pass", evidence)
+        .await
+        .unwrap();
+    assert!(proof.passed);
+    assert_eq!(
+        work_units.list(Some(&task.id), None).await.unwrap().len(),
+        1
+    );
+
+    let docs = DocsManager::new().with_transport(transport.clone());
+    assert_eq!(docs.ingest().await.unwrap().documents_ingested, 1);
+    let found = docs.search("custody", None, None, None).await.unwrap();
+    assert_eq!(found.fragments[0].path, "README.md");
+    assert_eq!(
+        docs.list(None).await.unwrap()[0].title.as_deref(),
+        Some("Pincher")
+    );
+
+    let capabilities = CapabilitiesManager::new()
+        .with_transport(transport)
+        .discover_json()
+        .await
+        .unwrap();
+    assert!(
+        capabilities
+            .rpc_operations
+            .iter()
+            .any(|operation| operation.name == "todo.claim")
+    );
+}
+
+#[tokio::test]
+async fn scripted_interlocks_block_until_the_approval_they_name_is_granted() {
+    let (dir, transport) = project();
+    let todos = TodoManager::new().with_transport(transport.clone());
+    let task = todos.add("ship it", None, None).await.unwrap();
+    let unit = WorkUnitManager::new()
+        .with_transport(transport.clone())
+        .init(&task.id, "intent-1")
+        .await
+        .unwrap();
+    script(
+        &dir,
+        Scenario::new().with_interlock("todo.*", interlock("release-freeze", Some("maintainer"))),
+    );
+
+    let refused = todos.claim(&task.id).await.unwrap_err().to_string();
+    assert!(
+        refused.contains("blocked by policy release-freeze"),
+        "{refused}"
+    );
+    let rpc = RpcClient::new().with_transport(transport.clone());
+    let blocked = rpc.todo_claim(&task.id).await.unwrap();
+    assert!(blocked.is_blocked());
+    assert_eq!(blocked.get_blocking_policies()[0].policy, "release-freeze");
+
+    WorkUnitManager::new()
+        .with_transport(transport.clone())
+        .request_approval(&unit.id, vec!["maintainer"])
+        .await
+        .unwrap();
+    assert_eq!(
+        todos.claim(&task.id).await.unwrap().status,
+        TaskStatus::Claimed
+    );
+}
+
+#[tokio::test]
+async fn approval_outcomes_and_validation_failures_follow_the_scenario() {
+    let (dir, transport) = project();
+    let task = TodoManager::new()
+        .with_transport(transport.clone())
+        .add("ship it", None, None)
+        .await
+        .unwrap();
+    let work_units = WorkUnitManager::new().with_transport(transport.clone());
+    let unit = work_units.init(&task.id, "intent-1").await.unwrap();
+
+    script(
+        &dir,
+        Scenario::new().with_approval(ApprovalOutcome::Pending),
+    );
+    let pending = work_units
+        .request_approval(&unit.id, vec!["maintainer"])
+        .await
+        .unwrap();
+    assert!(pending.state.approvals.is_empty());
+
+    script(
+        &dir,
+        Scenario::new()
+            .with_approval(ApprovalOutcome::Denied)
+            .with_validation_failure(
+                Some("lint"),
+                ValidationError {
+                    check: "clippy".to_string(),
+                    message: "unused import".to_string(),
+                    remediation: None,
+                },
+            ),
+    );
+    let denied = work_units
+        .request_approval(&unit.id, vec!["maintainer"])
+        .await
+        .unwrap_err();
+    assert!(denied.to_string().contains("approval denied"), "{denied}");
+
+    let validator = Validator::new().with_transport(transport);
+    let lint = validator.run_gate("lint").await.unwrap();
+    assert!(!lint.passed);
+    assert_eq!(lint.errors[0].message, "unused import");
+    assert!(Validator::require_validation(&lint).is_err());
+    assert!(validator.run_gate("fmt").await.unwrap().passed);
+}
+
+#[tokio::test]
+async fn expired_sessions_are_refused_when_the_scenario_requires_one() {
+    let (dir, transport) = project();
+    script(
+        &dir,
+        Scenario::new().with_password("hunter2").requiring_session(),
+    );
+
+    assert!(
+        Session::acquire_with(transport.as_ref(), &Secret::new("wrong"))
+            .await
+            .is_err()
+    );
+    let session = Session::acquire_with(transport.as_ref(), &Secret::new("hunter2"))
+        .await
+        .unwrap();
+    let anonymous = TodoManager::new().with_transport(transport.clone());
+    let refused = anonymous.list(None, None, None).await.unwrap_err();
+    assert!(
+        refused.to_string().contains("session required"),
+        "{refused}"
+    );
+
+    let todos = TodoManager::new()
+        .with_transport(transport.clone())
+        .with_session(session.token());
+    assert!(todos.list(None, None, None).await.unwrap().is_empty());
+
+    let expired = transport
+        .invoke(Invocation::new(&["sim", "expire"]))
+        .await
+        .unwrap();
+    assert!(expired.success());
+    assert!(!session.validate_with(transport.as_ref()).await.unwrap());
+    let refused = todos.list(None, None, None).await.unwrap_err();
+    assert!(refused.to_string().contains("session expired"), "{refused}");
+}
+
+#[tokio::test]
+async fn the_stdio_server_shares_state_with_per_call_commands() {
+    let (dir, per_call) = project();
+    let stdio = Arc::new(
+        StdioTransport::new(ProcessTransport::new(SIM).with_project_root(dir.path()))
+            .with_pool_size(1)
+            .without_fallback(),
+    );
+
+    let task = TodoManager::new()
+        .with_transport(stdio.clone())
+        .add("pooled", None, None)
+        .await
+        .unwrap();
+    let fetched = TodoManager::new()
+        .with_transport(per_call)
+        .get(&task.id)
+        .await
+        .unwrap();
+    assert_eq!(fetched.content, "pooled");
+
+    let stats = stdio.stats();
+    assert_eq!((stats.spawned, stats.framed, stats.fallbacks), (1, 1, 0));
+    stdio.shutdown().await;
+}
+
+#[tokio::test]
+async fn the_simulator_answers_in_process_with_scripted_advisories() {
+    let dir = tempfile::tempdir().unwrap();
+    let simulator = Simulator::new(dir.path());
+    script(
+        &dir,
+        Scenario::new()
+            .with_advisory(
+                "workspace.ensure",
+                Advisory {
+                    message: "disk is nearly full".to_string(),
+                    suggestions: vec!["prune old workspaces".to_string()],
+                    priority: "critical".to_string(),
+                },
+            )
+            .with_interlock_times("todo.add", interlock("rate-limit", None), 1),
+    );
+    let rpc = RpcClient::new().with_transport(Arc::new(simulator.clone()));
+
+    let ensured = rpc.workspace_ensure().await.unwrap();
+    assert_eq!(ensured.advisory.unwrap().priority, "critical");
+    assert_eq!(ensured.data.unwrap().name, "default");
+
+    assert!(rpc.todo_add("first", None).await.unwrap().is_blocked());
+    let added = rpc
+        .todo_add("second", None)
+        .await
+        .unwrap()
+        .into_data()
+        .unwrap();
+    assert_eq!(simulator.load().unwrap().tasks[0].id, added.id);
+}
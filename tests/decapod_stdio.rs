@@ -0,0 +1,116 @@
+#![cfg(unix)]
+
+use pincher::decapod::stdio::StdioTransport;
+use pincher::decapod::transport::{
+    DecapodTransport, Invocation, ProcessTransport, TransportError, TransportOutput,
+};
+use std::time::Duration;
+
+const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/decapod-stdio.sh"
+);
+
+/// The fixture, run from this repository's own Decapod project.
+fn process() -> ProcessTransport {
+    ProcessTransport::new(FIXTURE).with_project_root(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn transport() -> StdioTransport {
+    StdioTransport::new(process().with_timeout(Duration::from_secs(5))).with_pool_size(1)
+}
+
+async fn call(transport: &StdioTransport, arg: &str) -> Result<TransportOutput, TransportError> {
+    transport.invoke(Invocation::new(&[arg, "--json"])).await
+}
+
+/// Splits fixture output into the answering process and the echoed argument.
+fn answer(output: &TransportOutput) -> (String, String) {
+    let (pid, arg) = output.stdout.split_once(' ').unwrap();
+    (pid.to_string(), arg.to_string())
+}
+
+#[tokio::test]
+async fn commands_share_one_connection_and_are_matched_by_request_id() {
+    let transport = transport();
+
+    let first = call(&transport, "todo").await.unwrap();
+    let second = call(&transport, "workspace").await.unwrap();
+    assert_eq!(answer(&first).1, "todo");
+    assert_eq!(answer(&second).1, "workspace");
+    assert_eq!(answer(&first).0, answer(&second).0);
+
+    // The fixture answers a held request after the next one.
+    let (held, next) = tokio::join!(call(&transport, "hold"), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        call(&transport, "validate").await
+    });
+    assert_eq!(answer(&held.unwrap()).1, "held");
+    assert_eq!(answer(&next.unwrap()).1, "validate");
+
+    let failed = call(&transport, "fail").await.unwrap();
+    assert_eq!(failed.status, Some(3));
+    assert_eq!(failed.stderr, "no such task");
+
+    let stats = transport.stats();
+    assert_eq!(stats.spawned, 1);
+    assert_eq!(stats.framed, 5);
+    assert_eq!(stats.fallbacks, 0);
+}
+
+#[tokio::test]
+async fn a_crashed_server_fails_its_request_and_is_replaced() {
+    let transport = transport();
+    let before = call(&transport, "todo").await.unwrap();
+
+    let crashed = call(&transport, "crash").await.unwrap_err();
+    assert!(
+        matches!(crashed, TransportError::Disconnected { ref command } if command == "crash --json")
+    );
+
+    let after = call(&transport, "todo").await.unwrap();
+    assert_ne!(answer(&before).0, answer(&after).0);
+    assert_eq!(transport.stats().spawned, 2);
+    assert_eq!(transport.stats().disconnects, 1);
+}
+
+#[tokio::test]
+async fn health_checks_replace_servers_that_died_while_idle() {
+    let transport = transport();
+    let before = call(&transport, "todo").await.unwrap();
+    let (pid, _) = answer(&before);
+
+    let killed = tokio::process::Command::new("kill")
+        .arg(&pid)
+        .status()
+        .await
+        .unwrap();
+    assert!(killed.success());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(transport.health_check().await, 1);
+    let after = call(&transport, "todo").await.unwrap();
+    assert_ne!(answer(&after).0, pid);
+    assert_eq!(transport.stats().spawned, 2);
+}
+
+#[tokio::test]
+async fn without_a_stdio_mode_commands_fall_back_to_a_process_per_call() {
+    let process = process().with_env("FIXTURE_MODE", "no-stdio");
+    let transport = StdioTransport::new(process.clone()).with_pool_size(1);
+
+    let first = call(&transport, "todo").await.unwrap();
+    let second = call(&transport, "todo").await.unwrap();
+    assert_eq!(answer(&first).1, "todo");
+    assert_ne!(answer(&first).0, answer(&second).0);
+    let stats = transport.stats();
+    assert_eq!((stats.spawned, stats.fallbacks), (0, 2));
+
+    let strict = StdioTransport::new(process)
+        .with_pool_size(1)
+        .without_fallback();
+    assert!(matches!(
+        call(&strict, "todo").await.unwrap_err(),
+        TransportError::Unavailable { .. }
+    ));
+}
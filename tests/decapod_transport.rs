@@ -0,0 +1,396 @@
+use pincher::decapod::capabilities::CapabilitiesManager;
+use pincher::decapod::commitment::ProofType;
+use pincher::decapod::docs::DocsManager;
+use pincher::decapod::envelope::{DecodeMode, EnvelopeError, ParseError};
+use pincher::decapod::proof::{TestResultEvidence, evidence_map};
+use pincher::decapod::rpc::RpcClient;
+use pincher::decapod::secret::Secret;
+use pincher::decapod::session::Session;
+use pincher::decapod::todo::{TaskStatus, TodoManager};
+use pincher::decapod::transport::{
+    DecapodTransport, Invocation, SESSION_ENV, ScriptedTransport, TransportError, TransportOutput,
+};
+use pincher::decapod::validate::Validator;
+use pincher::decapod::workspace::WorkspaceManager;
+use pincher::decapod::workunit::WorkUnitManager;
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+fn task(status: &str) -> Value {
+    json!({
+        "id": "task-1", "content": "fix the build", "status": status, "priority": null,
+        "owner": "agent-1", "created_at": "", "updated_at": "", "claimed_at": null,
+        "completed_at": null
+    })
+}
+
+fn session_of(invocation: &Invocation) -> Option<&str> {
+    invocation
+        .env
+        .iter()
+        .find(|(key, _)| key == SESSION_ENV)
+        .map(|(_, value)| value.expose())
+}
+
+#[tokio::test]
+async fn managers_send_their_commands_and_session_through_the_transport() {
+    let transport = ScriptedTransport::new()
+        .reply(&["todo", "claim"], TransportOutput::json(&task("claimed")))
+        .reply(
+            &["workspace", "ensure"],
+            TransportOutput::json(&json!({
+                "name": "fix-build", "branch": "agent/fix-build", "path": "/tmp/fix-build",
+                "status": "active", "created_at": null
+            })),
+        )
+        .reply(
+            &["docs", "search"],
+            TransportOutput::json(&json!({
+                "fragments": [{"path": "docs/a.md", "content": "x", "relevance_score": 0.5}],
+                "total_matches": 1, "query": "custody"
+            })),
+        )
+        .reply(
+            &["capabilities"],
+            TransportOutput::json(&json!({
+                "version": "0.9.0", "commands": [], "plugins": [],
+                "rpc_operations": [{"name": "todo.add", "description": null, "params": {}}]
+            })),
+        );
+    let shared: Arc<dyn DecapodTransport> = Arc::new(transport.clone());
+
+    let claimed = TodoManager::new()
+        .with_session("token-1")
+        .with_transport(shared.clone())
+        .claim("task-1")
+        .await
+        .unwrap();
+    assert_eq!(claimed.status, TaskStatus::Claimed);
+    let workspace = WorkspaceManager::new()
+        .with_transport(shared.clone())
+        .with_session("token-1")
+        .ensure(Some("fix-build"))
+        .await
+        .unwrap();
+    assert_eq!(workspace.branch, "agent/fix-build");
+    let found = DocsManager::new()
+        .with_transport(shared.clone())
+        .search("custody", None, None, Some("governance"))
+        .await
+        .unwrap();
+    assert_eq!(found.total_matches, 1);
+    let capabilities = CapabilitiesManager::new()
+        .with_transport(shared)
+        .discover_json()
+        .await
+        .unwrap();
+    assert_eq!(capabilities.rpc_operations[0].name, "todo.add");
+
+    assert_eq!(
+        transport.commands(),
+        [
+            "todo claim --id task-1",
+            "workspace ensure --name fix-build",
+            "docs search --query custody --tag governance",
+            "capabilities --format json",
+        ]
+    );
+    let sessions: Vec<_> = transport
+        .invocations()
+        .iter()
+        .map(|invocation| session_of(invocation).map(str::to_string))
+        .collect();
+    assert_eq!(
+        sessions,
+        [
+            Some("token-1".to_string()),
+            Some("token-1".to_string()),
+            None,
+            None
+        ]
+    );
+}
+
+#[tokio::test]
+async fn record_proof_round_trips_evidence_through_the_transport() {
+    let evidence = evidence_map(&TestResultEvidence {
+        passed: 12,
+        failed: 0,
+        skipped: 1,
+        command: Some("cargo test".to_string()),
+        report_ref: None,
+    })
+    .unwrap();
+    let work_unit = json!({
+        "id": "work-unit-1", "task_id": "task-1", "intent_ref": "intent-1", "status": "active",
+        "state": {"intent": "intent-1", "plan": null, "patches": [], "approvals": []},
+        "acceptance_criteria": [], "constraints": [],
+        "proofs": [{
+            "id": "proof-1", "proof_type": "test", "criteria": "ci", "evidence": evidence,
+            "passed": true, "verified_at": null
+        }],
+        "created_at": "", "updated_at": ""
+    });
+    let transport = ScriptedTransport::new()
+        .once(&["workunit", "proof"], TransportOutput::json(&work_unit))
+        .once(
+            &["workunit", "proof"],
+            TransportOutput::failure(2, "work unit is completed"),
+        );
+    let manager = WorkUnitManager::new()
+        .with_session("token-1")
+        .with_transport(Arc::new(transport.clone()));
+
+    let proof = manager
+        .record_proof("work-unit-1", ProofType::Test, "ci", evidence.clone())
+        .await
+        .unwrap();
+    assert_eq!(proof.evidence, evidence);
+    let sent = &transport.invocations()[0];
+    let flag = sent
+        .args
+        .iter()
+        .position(|arg| arg == "--evidence")
+        .unwrap();
+    let sent_evidence: Value = serde_json::from_str(&sent.args[flag + 1]).unwrap();
+    assert_eq!(sent_evidence, json!(evidence));
+
+    // A refused command surfaces Decapod's stderr.
+    let error = manager
+        .record_proof("work-unit-1", ProofType::Test, "ci", evidence)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "workunit command failed: work unit is completed"
+    );
+}
+
+#[tokio::test]
+async fn validator_keeps_a_failing_report_and_its_exit_status() {
+    let report = json!({
+        "passed": false, "gate": "default", "details": [],
+        "errors": [{"check": "fmt", "message": "unformatted", "remediation": null}],
+        "warnings": []
+    });
+    let transport = ScriptedTransport::new()
+        .reply(
+            &["validate", "--gate"],
+            TransportOutput {
+                status: Some(1),
+                stdout: "not json".to_string(),
+                stderr: String::new(),
+            },
+        )
+        .reply(
+            &["validate"],
+            TransportOutput {
+                status: Some(1),
+                stdout: report.to_string(),
+                stderr: String::new(),
+            },
+        );
+    let validator = Validator::new().with_transport(Arc::new(transport.clone()));
+
+    let result = validator.run().await.unwrap();
+    assert!(!result.passed);
+    assert_eq!(result.errors[0].message, "unformatted");
+    // A gate report that is not JSON is an error, unless the output is
+    // known to be legacy.
+    let error = validator.run_gate("release").await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<EnvelopeError>(),
+        Some(EnvelopeError {
+            kind: ParseError::Syntax { .. },
+            ..
+        })
+    ));
+    let legacy = Validator::new().with_transport(Arc::new(
+        transport.clone().with_decode_mode(DecodeMode::Lenient),
+    ));
+    let gated = legacy.run_gate("release").await.unwrap();
+    assert!(!gated.passed);
+    assert_eq!(gated.gate, "release");
+    assert_eq!(
+        transport.commands(),
+        [
+            "validate",
+            "validate --gate release",
+            "validate --gate release"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn sessions_and_rpc_calls_pass_credentials_in_the_environment() {
+    let transport = ScriptedTransport::new()
+        .reply(
+            &["session", "acquire"],
+            TransportOutput::ok(
+                "{\"token\":\"token-1\",\"session_id\":\"session-1\",\
+                 \"expires_at\":null,\"created_at\":\"2026-01-01T00:00:00Z\"}\n",
+            ),
+        )
+        .reply(
+            &["session", "validate"],
+            TransportOutput::failure(1, "expired"),
+        )
+        .reply(
+            &["rpc", "--op", "todo.list"],
+            TransportOutput::json(&json!({"id": "rpc-1", "success": true})),
+        );
+
+    let session = Session::acquire_with(&transport, &Secret::new("hunter2"))
+        .await
+        .unwrap();
+    assert_eq!(session.session_id(), "session-1");
+    assert!(!session.validate_with(&transport).await.unwrap());
+    let response = RpcClient::new()
+        .with_session(session.token())
+        .with_transport(Arc::new(transport.clone()))
+        .todo_list(Some("pending"))
+        .await
+        .unwrap();
+    assert!(response.success);
+
+    let invocations = transport.invocations();
+    assert_eq!(session_of(&invocations[0]), Some("hunter2"));
+    assert_eq!(session_of(&invocations[1]), Some("token-1"));
+    assert_eq!(session_of(&invocations[2]), Some("token-1"));
+    assert_eq!(
+        invocations[2].args,
+        [
+            "rpc",
+            "--op",
+            "todo.list",
+            "--params",
+            r#"{"status":"pending"}"#
+        ]
+    );
+}
+
+#[tokio::test]
+async fn unscripted_commands_fail_and_are_still_recorded() {
+    let transport =
+        ScriptedTransport::new().once(&["todo", "get"], TransportOutput::json(&task("pending")));
+    let todos = TodoManager::new().with_transport(Arc::new(transport.clone()));
+
+    todos.get("task-1").await.unwrap();
+    let error = todos.get("task-1").await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<TransportError>(),
+        Some(TransportError::Unscripted { command }) if command == "todo get --id task-1"
+    ));
+    assert_eq!(transport.commands().len(), 2);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn process_transport_owns_the_working_directory_environment_and_timeout() {
+    use pincher::decapod::transport::ProcessTransport;
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::Duration;
+
+    let root = tempfile::tempdir().unwrap();
+    let binary = root.path().join("decapod");
+    std::fs::write(
+        &binary,
+        "#!/bin/sh\n\
+         case \"$1\" in\n\
+           fail) echo 'no such task' >&2; exit 3 ;;\n\
+           slow) sleep 5 ;;\n\
+           *) printf '%s|%s|%s|%s' \"$(pwd)\" \"$PROJECT\" \"$DECAPOD_SESSION_PASSWORD\" \"$*\" ;;\n\
+         esac\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::fs::create_dir(root.path().join(".decapod")).unwrap();
+    let project = root.path().canonicalize().unwrap();
+
+    let transport = ProcessTransport::new(&binary)
+        .with_project_root(&project)
+        .with_env("PROJECT", "pincher")
+        .with_timeout(Duration::from_millis(300));
+
+    let output = transport
+        .invoke(Invocation::new(&["todo", "list"]).with_session(Some("token-1")))
+        .await
+        .unwrap();
+    assert!(output.success());
+    assert_eq!(
+        output.stdout,
+        format!("{}|pincher|token-1|todo list", project.display())
+    );
+
+    let failed = transport.invoke(Invocation::new(&["fail"])).await.unwrap();
+    assert_eq!(failed.status, Some(3));
+    assert_eq!(failed.stderr, "no such task\n");
+    assert_eq!(
+        failed.into_stdout("todo command").unwrap_err().to_string(),
+        "todo command failed: no such task\n"
+    );
+
+    let timed_out = transport
+        .invoke(Invocation::new(&["slow"]))
+        .await
+        .unwrap_err();
+    assert!(matches!(timed_out, TransportError::Timeout { ref command, .. } if command == "slow"));
+
+    let missing = ProcessTransport::new(root.path().join("missing"))
+        .with_project_root(&project)
+        .invoke(Invocation::new(&["todo", "list"]))
+        .await
+        .unwrap_err();
+    assert!(matches!(missing, TransportError::Spawn { .. }));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn commands_are_refused_without_a_verified_project_root() {
+    use pincher::decapod::project::{ProjectRoot, ProjectRootError};
+    use pincher::decapod::transport::ProcessTransport;
+
+    let root = tempfile::tempdir().unwrap();
+    std::fs::create_dir(root.path().join(".decapod")).unwrap();
+    let nested = root.path().join("src/decapod");
+    std::fs::create_dir_all(&nested).unwrap();
+    let run = |transport: ProcessTransport| async move {
+        transport
+            .invoke(Invocation::new(&["todo", "list"]))
+            .await
+            .unwrap_err()
+    };
+
+    let unset = run(ProcessTransport::new("/bin/echo")).await;
+    assert!(matches!(unset, TransportError::NoProjectRoot { .. }));
+
+    // A subdirectory is not the project root, even inside one.
+    let subdirectory = run(ProcessTransport::new("/bin/echo").with_project_root(&nested)).await;
+    assert!(matches!(
+        subdirectory,
+        TransportError::ProjectRoot(ProjectRootError::NotAProject { .. })
+    ));
+    let gone =
+        run(ProcessTransport::new("/bin/echo").with_project_root(root.path().join("gone"))).await;
+    assert!(matches!(
+        gone,
+        TransportError::ProjectRoot(ProjectRootError::Unreadable { .. })
+    ));
+
+    let discovered = ProjectRoot::discover_from(&nested).unwrap();
+    assert_eq!(discovered.path(), root.path().canonicalize().unwrap());
+    assert_eq!(
+        ProjectRoot::verify(nested.join("../..")).unwrap(),
+        discovered
+    );
+    let output = ProcessTransport::new("/bin/pwd")
+        .with_project_root(discovered.path())
+        .invoke(Invocation::new(&[] as &[&str]))
+        .await
+        .unwrap();
+    assert_eq!(output.stdout.trim_end(), discovered.to_string());
+    assert!(matches!(
+        ProjectRoot::discover_from(std::env::temp_dir().join("no-such-project")),
+        Err(ProjectRootError::NotFound { .. })
+    ));
+}
@@ -0,0 +1,37 @@
+#!/bin/sh
+# Stand-in for Decapod used by the stdio transport tests and benchmarks.
+#
+# `rpc --stdio` serves frames until stdin closes; anything else answers once.
+# Output is "<pid> <first argument>" so callers can tell processes apart.
+# A first argument of `crash` kills the server, `fail` exits 3, and `hold`
+# is answered only after the next request, out of order.
+# FIXTURE_MODE=no-stdio makes the server refuse to start.
+
+if [ "$1" = rpc ] && [ "$2" = --stdio ]; then
+  if [ "$FIXTURE_MODE" = no-stdio ]; then
+    echo "unknown flag --stdio" >&2
+    exit 2
+  fi
+  held=
+  while IFS= read -r line; do
+    id=${line#\{\"id\":}
+    id=${id%%,*}
+    arg=
+    case "$line" in
+      *'"args":["'*) arg=${line#*\"args\":\[\"}; arg=${arg%%\"*} ;;
+    esac
+    case "$arg" in
+      crash) exit 1 ;;
+      hold) held=$id; continue ;;
+      fail) printf '{"id":%s,"status":3,"stdout":"","stderr":"no such task"}\n' "$id"; continue ;;
+    esac
+    printf '{"id":%s,"status":0,"stdout":"%s %s","stderr":""}\n' "$id" "$$" "$arg"
+    if [ -n "$held" ]; then
+      printf '{"id":%s,"status":0,"stdout":"%s held","stderr":""}\n' "$held" "$$"
+      held=
+    fi
+  done
+  exit 0
+fi
+
+printf '%s %s' "$$" "$1"
@@ -0,0 +1,337 @@
+use pincher::decapod::cli;
+use pincher::decapod::governance::{
+    Advisory, AdvisoryPriority, Attestation, GovernanceDecision, GovernanceEngine,
+    GovernanceResponse, Interlock,
+};
+use pincher::decapod::rpc::RpcResponse;
+use pincher::governed_run::{
+    AdvisoryEvidence, ApprovalInterlockRef, ApprovalProgress, ApprovalQuorum, ApprovalStatus,
+    DecapodPortError, GovernancePolicy, InterlockDecision, ProofEvidence, ProofEvidenceRef,
+    Remediation, ValidationEvidence, ValidationEvidenceRef,
+};
+
+fn response() -> GovernanceResponse {
+    GovernanceResponse {
+        interlock: None,
+        advisories: Vec::new(),
+        attestation: None,
+        allowed_next_ops: vec!["workunit.claim".to_string()],
+        blocked_by: Vec::new(),
+    }
+}
+
+fn interlock(required_approval: Option<&str>) -> Interlock {
+    Interlock {
+        policy: "release-freeze".to_string(),
+        reason: "release freeze in effect".to_string(),
+        blocking: true,
+        required_approval: required_approval.map(str::to_string),
+        approver_scope: vec!["release".to_string(), "security".to_string()],
+        escalation_path: Some("vp-engineering".to_string()),
+    }
+}
+
+fn advisory(priority: AdvisoryPriority) -> Advisory {
+    Advisory {
+        message: "dependency is unmaintained".to_string(),
+        suggestions: vec!["pin the version".to_string()],
+        priority,
+        category: Some("supply-chain".to_string()),
+        related_policies: vec!["dependency-review".to_string()],
+    }
+}
+
+fn attestation(passed: bool, expiration: Option<&str>) -> Attestation {
+    Attestation {
+        proof_id: "proof-7".to_string(),
+        criteria: "tests pass".to_string(),
+        passed,
+        evidence: Default::default(),
+        verified_by: Some("ci".to_string()),
+        verified_at: None,
+        expiration: expiration.map(str::to_string),
+    }
+}
+
+fn freeze_policy(required_approval: Option<&str>) -> Option<GovernancePolicy> {
+    Some(GovernancePolicy {
+        name: "release-freeze".to_string(),
+        required_approval: required_approval.map(str::to_string),
+        approver_scope: vec!["release".to_string(), "security".to_string()],
+        escalation_path: Some("vp-engineering".to_string()),
+    })
+}
+
+fn advisory_ref(origin: &str, message: &str) -> ApprovalInterlockRef {
+    use sha2::{Digest, Sha256};
+    let digest = format!("{:x}", Sha256::digest(message.as_bytes()));
+    ApprovalInterlockRef::new(format!("advisory-{origin}-{}", &digest[..16])).unwrap()
+}
+
+fn interlock_ref() -> ApprovalInterlockRef {
+    ApprovalInterlockRef::new("release-freeze").unwrap()
+}
+
+#[test]
+fn proceed_allows_the_run_and_carries_the_attestation_as_evidence() {
+    let response = GovernanceResponse {
+        attestation: Some(attestation(true, None)),
+        ..response()
+    };
+    assert!(matches!(
+        GovernanceEngine::evaluate(&response),
+        GovernanceDecision::Proceed {
+            attestation: Some(_),
+            ..
+        }
+    ));
+    assert_eq!(
+        InterlockDecision::try_from(&response).unwrap(),
+        InterlockDecision::Allow { advisory: None }
+    );
+    assert_eq!(
+        ApprovalStatus::try_from(&response).unwrap(),
+        ApprovalStatus::NotRequired
+    );
+    assert_eq!(
+        ValidationEvidence::try_from(&response).unwrap(),
+        ValidationEvidence {
+            reference: ValidationEvidenceRef::new("proof-7").unwrap(),
+            passed: true,
+        }
+    );
+    assert_eq!(
+        ProofEvidence::try_from(&response).unwrap(),
+        ProofEvidence {
+            reference: ProofEvidenceRef::new("proof-7").unwrap(),
+            backed: true,
+        }
+    );
+
+    let expired = GovernanceResponse {
+        attestation: Some(attestation(true, Some("2000-01-01T00:00:00Z"))),
+        ..response.clone()
+    };
+    assert!(!ValidationEvidence::try_from(&expired).unwrap().passed);
+    assert!(!ProofEvidence::try_from(&expired).unwrap().backed);
+
+    let unattested = GovernanceResponse {
+        attestation: None,
+        ..response
+    };
+    assert!(matches!(
+        GovernanceEngine::evaluate(&unattested),
+        GovernanceDecision::Proceed {
+            attestation: None,
+            ..
+        }
+    ));
+    assert!(matches!(
+        ProofEvidence::try_from(&unattested).unwrap_err(),
+        DecapodPortError::Incomplete { .. }
+    ));
+}
+
+#[test]
+fn warnings_and_critical_advisories_keep_their_priority_and_policy() {
+    for priority in [AdvisoryPriority::Warning, AdvisoryPriority::Critical] {
+        let response = GovernanceResponse {
+            advisories: vec![advisory(AdvisoryPriority::Info), advisory(priority)],
+            ..response()
+        };
+        let decision = GovernanceEngine::evaluate(&response);
+        if priority == AdvisoryPriority::Warning {
+            assert!(
+                matches!(decision, GovernanceDecision::Warning { .. }),
+                "{decision:?}"
+            );
+        } else {
+            assert!(
+                matches!(decision, GovernanceDecision::CriticalAdvisory { .. }),
+                "{decision:?}"
+            );
+        }
+        assert_eq!(
+            InterlockDecision::try_from(&response).unwrap(),
+            InterlockDecision::Allow {
+                advisory: Some(AdvisoryEvidence {
+                    reference: Some(advisory_ref(
+                        "dependency-review",
+                        "dependency is unmaintained"
+                    )),
+                    priority,
+                }),
+            }
+        );
+        assert_eq!(
+            ApprovalStatus::try_from(&response).unwrap(),
+            ApprovalStatus::NotRequired
+        );
+    }
+}
+
+#[test]
+fn advisory_references_are_unique_per_message_and_stable() {
+    let reference = |advisory: Advisory| {
+        let response = GovernanceResponse {
+            advisories: vec![advisory],
+            ..response()
+        };
+        let InterlockDecision::Allow {
+            advisory: Some(evidence),
+        } = InterlockDecision::try_from(&response).unwrap()
+        else {
+            panic!("an advisory must allow the run with its evidence");
+        };
+        evidence.reference.unwrap()
+    };
+    let first = reference(advisory(AdvisoryPriority::Critical));
+    assert_eq!(first, reference(advisory(AdvisoryPriority::Critical)));
+    assert_ne!(
+        first,
+        ApprovalInterlockRef::new("dependency-review").unwrap()
+    );
+
+    let other = Advisory {
+        message: "dependency has a known vulnerability".to_string(),
+        ..advisory(AdvisoryPriority::Critical)
+    };
+    assert_eq!(
+        reference(other),
+        advisory_ref("dependency-review", "dependency has a known vulnerability")
+    );
+    assert_ne!(
+        first,
+        advisory_ref("dependency-review", "dependency has a known vulnerability")
+    );
+
+    let uncategorised = Advisory {
+        category: None,
+        related_policies: Vec::new(),
+        ..advisory(AdvisoryPriority::Critical)
+    };
+    let digest = reference(uncategorised);
+    assert!(digest.as_str().starts_with("advisory-"), "{digest:?}");
+    assert!(!digest.as_str().contains("supply-chain"), "{digest:?}");
+}
+
+#[test]
+fn blocking_interlocks_keep_policy_approvers_and_escalation() {
+    let response = GovernanceResponse {
+        interlock: Some(interlock(Some("human"))),
+        attestation: Some(attestation(true, None)),
+        ..response()
+    };
+    assert!(matches!(
+        GovernanceEngine::evaluate(&response),
+        GovernanceDecision::Blocked {
+            required_approval: Some(_),
+            ..
+        }
+    ));
+    let remediation = Remediation::new(
+        "obtain human approval from release, security under policy release-freeze, \
+         or escalate to vp-engineering",
+    );
+    assert_eq!(
+        InterlockDecision::try_from(&response).unwrap(),
+        InterlockDecision::Block {
+            reference: interlock_ref(),
+            remediation: remediation.clone(),
+            policy: freeze_policy(Some("human")),
+        }
+    );
+    assert_eq!(
+        ApprovalStatus::try_from(&response).unwrap(),
+        ApprovalStatus::Pending {
+            reference: interlock_ref(),
+            remediation,
+            policy: freeze_policy(Some("human")),
+            progress: Some(ApprovalProgress {
+                quorum: ApprovalQuorum {
+                    reference: interlock_ref(),
+                    scopes: vec!["release".to_string(), "security".to_string()],
+                    approvals_needed: 2,
+                },
+                scopes: Vec::new(),
+            }),
+        }
+    );
+    assert!(!ValidationEvidence::try_from(&response).unwrap().passed);
+
+    let unapprovable = GovernanceResponse {
+        interlock: Some(interlock(None)),
+        ..response
+    };
+    assert!(matches!(
+        GovernanceEngine::evaluate(&unapprovable),
+        GovernanceDecision::Blocked {
+            required_approval: None,
+            ..
+        }
+    ));
+    assert_eq!(
+        ApprovalStatus::try_from(&unapprovable).unwrap(),
+        ApprovalStatus::Denied {
+            reference: interlock_ref(),
+            remediation: Remediation::new(
+                "resolve policy release-freeze: release freeze in effect, \
+                 or escalate to vp-engineering"
+            ),
+            policy: freeze_policy(None),
+        }
+    );
+}
+
+#[test]
+fn rpc_responses_convert_through_their_governance_view() {
+    let blocking = cli::Interlock {
+        policy: "release-freeze".to_string(),
+        reason: "release freeze in effect".to_string(),
+        blocking: true,
+        required_approval: Some("human".to_string()),
+    };
+    let response: RpcResponse = RpcResponse {
+        success: false,
+        id: Some("rpc-1".to_string()),
+        receipt: None,
+        context_capsule: None,
+        allowed_next_ops: Vec::new(),
+        blocked_by: vec![blocking],
+        interlock: None,
+        advisory: Some(cli::Advisory {
+            message: "freeze ends friday".to_string(),
+            suggestions: Vec::new(),
+            priority: "CRITICAL".to_string(),
+        }),
+        attestation: Some(cli::Attestation {
+            proof_id: "proof-7".to_string(),
+            criteria: "tests pass".to_string(),
+            passed: true,
+            evidence: Default::default(),
+        }),
+        data: None,
+        error: None,
+    };
+    let governance = GovernanceResponse::from(&response);
+    assert!(governance.is_blocked());
+    assert!(governance.has_critical());
+
+    let InterlockDecision::Block { policy, .. } = InterlockDecision::try_from(&governance).unwrap()
+    else {
+        panic!("a blocked RPC response must block");
+    };
+    assert_eq!(
+        policy,
+        Some(GovernancePolicy {
+            required_approval: Some("human".to_string()),
+            ..GovernancePolicy::new("release-freeze")
+        })
+    );
+    assert!(matches!(
+        ApprovalStatus::try_from(&governance).unwrap(),
+        ApprovalStatus::Pending { .. }
+    ));
+    // The attestation of a failed response is not trusted.
+    assert!(!ProofEvidence::try_from(&governance).unwrap().backed);
+}
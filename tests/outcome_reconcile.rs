@@ -0,0 +1,402 @@
+use pincher::decapod::commitment::ProofType;
+use pincher::decapod::reconcile::{
+    DecapodLedger, FailureReport, OutcomeReconciler, ReconcileError, ReconcileStep, WorkLedger,
+};
+use pincher::decapod::todo::{Task, TaskStatus};
+use pincher::decapod::transport::{ScriptedTransport, TransportOutput};
+use pincher::decapod::workunit::{Proof, WorkUnit, WorkUnitStatus};
+use pincher::governed_run::*;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Records every call as `(operation, idempotency key, arguments)`.
+#[derive(Clone, Default)]
+struct FakeLedger {
+    calls: Arc<Mutex<Vec<(String, String, Value)>>>,
+    failing: Arc<Mutex<Vec<&'static str>>>,
+}
+
+impl FakeLedger {
+    fn call(
+        &self,
+        operation: &'static str,
+        key: &IdempotencyKey,
+        arguments: Value,
+    ) -> anyhow::Result<()> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push((operation.to_string(), key.to_string(), arguments));
+        let mut failing = self.failing.lock().unwrap();
+        if let Some(index) = failing.iter().position(|failing| *failing == operation) {
+            failing.remove(index);
+            anyhow::bail!("decapod refused {operation}");
+        }
+        Ok(())
+    }
+
+    fn operations(&self) -> Vec<String> {
+        self.calls
+            .lock()
+            .unwrap()
+            .drain(..)
+            .map(|(operation, _, _)| operation)
+            .collect()
+    }
+}
+
+fn work_unit(id: &str) -> WorkUnit {
+    serde_json::from_value(serde_json::json!({
+        "id": id, "task_id": "task-1", "intent_ref": "intent-1", "status": "active",
+        "state": {"intent": "intent-1", "plan": null, "patches": [], "approvals": []},
+        "acceptance_criteria": [], "constraints": [], "proofs": [],
+        "created_at": "2026-01-01T00:00:00Z", "updated_at": "2026-01-01T00:00:00Z"
+    }))
+    .unwrap()
+}
+
+impl WorkLedger for FakeLedger {
+    async fn record_proof(
+        &self,
+        key: &IdempotencyKey,
+        work_unit_id: &str,
+        proof_type: ProofType,
+        criteria: &str,
+        evidence: HashMap<String, Value>,
+    ) -> anyhow::Result<Proof> {
+        proof_type.validate_evidence(&evidence)?;
+        let arguments = serde_json::json!({
+            "work_unit": work_unit_id, "proof_type": proof_type,
+            "criteria": criteria, "evidence": evidence,
+        });
+        self.call("record_proof", key, arguments)?;
+        Ok(Proof {
+            id: "proof-record-1".to_string(),
+            proof_type: proof_type.to_string(),
+            criteria: criteria.to_string(),
+            evidence,
+            passed: true,
+            verified_at: None,
+        })
+    }
+
+    async fn complete_work_unit(
+        &self,
+        key: &IdempotencyKey,
+        work_unit_id: &str,
+    ) -> anyhow::Result<WorkUnit> {
+        self.call("complete_work_unit", key, work_unit_id.into())?;
+        Ok(work_unit(work_unit_id))
+    }
+
+    async fn complete_task(
+        &self,
+        key: &IdempotencyKey,
+        task_id: &str,
+        resolution: &str,
+    ) -> anyhow::Result<Task> {
+        self.call(
+            "complete_task",
+            key,
+            serde_json::json!([task_id, resolution]),
+        )?;
+        Ok(serde_json::from_value(serde_json::json!({
+            "id": task_id, "content": "", "status": "completed", "priority": null,
+            "owner": null, "created_at": "", "updated_at": "", "claimed_at": null,
+            "completed_at": null
+        }))
+        .unwrap())
+    }
+
+    async fn fail_work_unit(
+        &self,
+        key: &IdempotencyKey,
+        work_unit_id: &str,
+        report: &FailureReport,
+    ) -> anyhow::Result<WorkUnit> {
+        let arguments = serde_json::json!({"work_unit": work_unit_id, "report": report});
+        self.call("fail_work_unit", key, arguments)?;
+        Ok(work_unit(work_unit_id))
+    }
+
+    async fn block_work_unit(
+        &self,
+        key: &IdempotencyKey,
+        work_unit_id: &str,
+        interlock_ref: &str,
+    ) -> anyhow::Result<WorkUnit> {
+        self.call(
+            "block_work_unit",
+            key,
+            serde_json::json!([work_unit_id, interlock_ref]),
+        )?;
+        Ok(work_unit(work_unit_id))
+    }
+}
+
+fn request() -> RunRequest {
+    RunRequest::v1(
+        RunId::new("run-1").unwrap(),
+        IntentId::new("intent-1").unwrap(),
+        CorrelationId::new("correlation-1").unwrap(),
+        IdempotencyKey::new("idempotency-1").unwrap(),
+        CustodyBinding::complete(
+            SessionRef::new("session-1").unwrap(),
+            TaskRef::new("task-1").unwrap(),
+            WorkUnitRef::new("work-unit-1").unwrap(),
+            RepositoryRef::new("repository-1").unwrap(),
+            WorkspaceRef::new("workspace-1").unwrap(),
+        ),
+    )
+}
+
+/// A custody failure: the unsupported control plane rejects every binding.
+fn failed() -> RunOutcome {
+    GovernedRunEngine::new(
+        UnsupportedDecapodControlPlane,
+        UnsupportedProviderTurn,
+        InMemoryEventSink::default(),
+    )
+    .run(request())
+    .unwrap()
+}
+
+fn ready() -> RunOutcome {
+    let mut snapshot = failed().snapshot().clone();
+    snapshot.state = RunState::Ready;
+    snapshot.failure = None;
+    snapshot.validation = Some(ValidationEvidence {
+        reference: ValidationEvidenceRef::new("validation-1").unwrap(),
+        passed: true,
+    });
+    snapshot.proof = Some(ProofEvidence {
+        reference: ProofEvidenceRef::new("proof-1").unwrap(),
+        backed: true,
+    });
+    RunOutcome::Ready(snapshot)
+}
+
+fn blocked() -> RunOutcome {
+    let mut snapshot = failed().snapshot().clone();
+    snapshot.state = RunState::Blocked;
+    snapshot.failure = None;
+    snapshot.blocked = Some(BlockedReason::Interlock {
+        reference: ApprovalInterlockRef::new("interlock-7").unwrap(),
+        remediation: Remediation::new("ask an owner"),
+        policy: None,
+    });
+    RunOutcome::Blocked(snapshot)
+}
+
+#[tokio::test]
+async fn ready_runs_record_proof_then_complete_the_work_unit_and_task() {
+    let ledger = FakeLedger::default();
+    let mut reconciler = OutcomeReconciler::new(ledger.clone());
+
+    let performed = reconciler.reconcile(&ready()).await.unwrap();
+    assert_eq!(
+        performed,
+        [
+            ReconcileStep::RecordProof,
+            ReconcileStep::CompleteWorkUnit,
+            ReconcileStep::CompleteTask
+        ]
+    );
+    let calls = ledger.calls.lock().unwrap().clone();
+    assert!(calls.iter().all(|(_, key, _)| key == "idempotency-1"));
+    let (_, _, proof) = &calls[0];
+    assert_eq!(proof["work_unit"], "work-unit-1");
+    assert_eq!(proof["proof_type"], "validation");
+    assert_eq!(proof["evidence"]["proof_ref"], "proof-1");
+    assert_eq!(proof["evidence"]["validation_ref"], "validation-1");
+    assert_eq!(calls[2].2[0], "task-1");
+
+    // Reconciling again, including after a handoff, changes nothing.
+    ledger.operations();
+    let handed_off = ready().handoff().unwrap();
+    assert!(reconciler.reconcile(&handed_off).await.unwrap().is_empty());
+    assert!(ledger.operations().is_empty());
+}
+
+#[tokio::test]
+async fn a_partially_reconciled_run_resumes_at_the_failed_step() {
+    let ledger = FakeLedger::default();
+    ledger.failing.lock().unwrap().push("complete_task");
+    let mut reconciler = OutcomeReconciler::new(ledger.clone());
+
+    let error = reconciler.reconcile(&ready()).await.unwrap_err();
+    assert!(matches!(
+        error,
+        ReconcileError::Decapod {
+            step: ReconcileStep::CompleteTask,
+            ..
+        }
+    ));
+    let key = IdempotencyKey::new("idempotency-1").unwrap();
+    assert_eq!(reconciler.applied(&key).count(), 2);
+    ledger.operations();
+
+    assert_eq!(
+        reconciler.reconcile(&ready()).await.unwrap(),
+        [ReconcileStep::CompleteTask]
+    );
+    assert_eq!(ledger.operations(), ["complete_task"]);
+}
+
+#[tokio::test]
+async fn failed_runs_fail_the_work_unit_with_the_typed_failure_and_remediation() {
+    let ledger = FakeLedger::default();
+    let mut reconciler = OutcomeReconciler::new(ledger.clone());
+    let outcome = failed();
+
+    assert_eq!(
+        reconciler.reconcile(&outcome).await.unwrap(),
+        [ReconcileStep::FailWorkUnit]
+    );
+    let (_, key, arguments) = ledger.calls.lock().unwrap()[0].clone();
+    assert_eq!(key, "idempotency-1");
+    assert_eq!(arguments["work_unit"], "work-unit-1");
+    let report: FailureReport = serde_json::from_value(arguments["report"].clone()).unwrap();
+    let failure = outcome.snapshot().failure.clone().unwrap();
+    assert_eq!(report.code, FailureCode::Custody);
+    assert_eq!(report.remediation.as_ref(), failure.remediation());
+    assert!(report.remediation.is_some());
+    assert_eq!(report.failure, failure);
+    assert_eq!(report.idempotency_key.as_str(), "idempotency-1");
+}
+
+#[tokio::test]
+async fn blocked_runs_record_the_interlock_reference() {
+    let ledger = FakeLedger::default();
+    let mut reconciler = OutcomeReconciler::new(ledger.clone());
+
+    assert_eq!(
+        reconciler.reconcile(&blocked()).await.unwrap(),
+        [ReconcileStep::BlockWorkUnit]
+    );
+    let (operation, _, arguments) = ledger.calls.lock().unwrap()[0].clone();
+    assert_eq!(operation, "block_work_unit");
+    assert_eq!(arguments, serde_json::json!(["work-unit-1", "interlock-7"]));
+}
+
+#[tokio::test]
+async fn decapod_ledger_skips_transitions_decapod_already_shows() {
+    let key = IdempotencyKey::new("idempotency-1").unwrap();
+    let plan = "blocked by interlock interlock-7 [idempotency-1]";
+    let mut completed = serde_json::to_value(work_unit("work-unit-1")).unwrap();
+    completed["status"] = "completed".into();
+    let mut planned = serde_json::to_value(work_unit("work-unit-1")).unwrap();
+    planned["state"]["plan"] = plan.into();
+    let transport = ScriptedTransport::new()
+        .once(&["workunit", "get"], TransportOutput::json(&completed))
+        .once(
+            &["workunit", "get"],
+            TransportOutput::json(&work_unit("work-unit-1")),
+        )
+        .reply(&["workunit", "get"], TransportOutput::json(&planned))
+        .reply(&["workunit", "update"], TransportOutput::json(&planned));
+    let ledger = DecapodLedger::new("token").with_transport(Arc::new(transport.clone()));
+
+    let unit = ledger
+        .complete_work_unit(&key, "work-unit-1")
+        .await
+        .unwrap();
+    assert_eq!(unit.status, WorkUnitStatus::Completed);
+    for _ in 0..2 {
+        let unit = ledger
+            .block_work_unit(&key, "work-unit-1", "interlock-7")
+            .await
+            .unwrap();
+        assert_eq!(unit.state.plan.as_deref(), Some(plan));
+    }
+
+    let commands = transport.commands();
+    assert!(
+        !commands
+            .iter()
+            .any(|command| command.contains("workunit complete"))
+    );
+    let updates: Vec<_> = commands
+        .iter()
+        .filter(|command| command.contains("workunit update"))
+        .collect();
+    assert_eq!(updates.len(), 1);
+    assert!(updates[0].contains(&format!("--plan {plan}")));
+}
+
+#[tokio::test]
+async fn decapod_ledger_returns_proofs_and_tasks_decapod_already_records() {
+    let key = IdempotencyKey::new("idempotency-1").unwrap();
+    let evidence: HashMap<String, Value> = serde_json::from_value(serde_json::json!({
+        "validation_ref": "validation-1", "passed": true,
+    }))
+    .unwrap();
+    let mut recorded = evidence.clone();
+    recorded.insert("idempotency_key".to_string(), key.as_str().into());
+    let mut proven = serde_json::to_value(work_unit("work-unit-1")).unwrap();
+    proven["proofs"] = serde_json::json!([{
+        "id": "proof-1", "proof_type": "validation", "criteria": "run validated",
+        "evidence": recorded, "passed": true, "verified_at": null,
+    }]);
+    let task: Task = serde_json::from_value(serde_json::json!({
+        "id": "task-1", "content": "fix the build", "status": "completed",
+        "priority": null, "owner": "session-1", "blocked_by": [],
+        "created_at": "2026-01-01T00:00:00Z", "updated_at": "2026-01-01T00:00:00Z",
+        "claimed_at": null, "completed_at": "2026-01-02T00:00:00Z",
+    }))
+    .unwrap();
+    let transport = ScriptedTransport::new()
+        .reply(&["workunit", "get"], TransportOutput::json(&proven))
+        .reply(&["todo", "get"], TransportOutput::json(&task));
+    let ledger = DecapodLedger::new("token").with_transport(Arc::new(transport.clone()));
+
+    let proof = ledger
+        .record_proof(
+            &key,
+            "work-unit-1",
+            ProofType::Validation,
+            "run validated",
+            evidence,
+        )
+        .await
+        .unwrap();
+    assert_eq!(proof.id, "proof-1");
+    let completed = ledger
+        .complete_task(&key, "task-1", "run is ready")
+        .await
+        .unwrap();
+    assert_eq!(completed.status, TaskStatus::Completed);
+
+    assert!(transport.commands().iter().all(|command| {
+        !command.contains("workunit proof") && !command.contains("todo complete")
+    }));
+}
+
+#[tokio::test]
+async fn outcomes_missing_what_decapod_needs_are_rejected_without_calls() {
+    let ledger = FakeLedger::default();
+    let mut reconciler = OutcomeReconciler::new(ledger.clone());
+
+    let RunOutcome::Ready(mut snapshot) = ready() else {
+        unreachable!()
+    };
+    snapshot.proof = None;
+    let error = reconciler
+        .reconcile(&RunOutcome::Ready(snapshot))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, ReconcileError::Incomplete { .. }));
+
+    // A request rejected before custody has no work unit to fail.
+    let RunOutcome::Failed(mut snapshot) = failed() else {
+        unreachable!()
+    };
+    snapshot.request.custody.work_unit = None;
+    let performed = reconciler
+        .reconcile(&RunOutcome::Failed(snapshot))
+        .await
+        .unwrap();
+    assert!(performed.is_empty());
+    assert!(ledger.operations().is_empty());
+}
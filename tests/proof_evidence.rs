@@ -0,0 +1,187 @@
+use pincher::decapod::commitment::ProofType;
+use pincher::decapod::proof::{
+    AuditEvidence, EvidenceError, ExternalEvidence, ReviewEvidence, ReviewVerdict,
+    TestResultEvidence, ValidationReportEvidence, evidence_map,
+};
+use pincher::decapod::workunit::{Proof, WorkUnitManager};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+fn samples() -> Vec<(ProofType, HashMap<String, Value>)> {
+    vec![
+        (
+            ProofType::Validation,
+            evidence_map(&ValidationReportEvidence {
+                validation_ref: "validation-1".to_string(),
+                passed: Some(true),
+            })
+            .unwrap(),
+        ),
+        (
+            ProofType::Test,
+            evidence_map(&TestResultEvidence {
+                passed: 41,
+                failed: 0,
+                skipped: 2,
+                command: Some("cargo test --workspace".to_string()),
+                report_ref: None,
+            })
+            .unwrap(),
+        ),
+        (
+            ProofType::Review,
+            evidence_map(&ReviewEvidence {
+                reviewer: "maintainer".to_string(),
+                verdict: ReviewVerdict::Approved,
+                review_ref: Some("review-9".to_string()),
+            })
+            .unwrap(),
+        ),
+        (
+            ProofType::Audit,
+            evidence_map(&AuditEvidence {
+                audit_ref: "audit-3".to_string(),
+                auditor: None,
+                findings: Some(0),
+            })
+            .unwrap(),
+        ),
+        (
+            ProofType::External,
+            evidence_map(&ExternalEvidence {
+                source: "ci".to_string(),
+                reference: "build-77".to_string(),
+            })
+            .unwrap(),
+        ),
+    ]
+}
+
+fn proof(proof_type: ProofType, evidence: HashMap<String, Value>) -> Proof {
+    Proof {
+        id: "proof-1".to_string(),
+        proof_type: proof_type.to_string(),
+        criteria: "criteria".to_string(),
+        evidence,
+        passed: true,
+        verified_at: None,
+    }
+}
+
+#[test]
+fn every_proof_type_accepts_its_own_evidence_and_rejects_the_others() {
+    let samples = samples();
+    for (proof_type, evidence) in &samples {
+        assert_eq!(proof_type.to_string().parse::<ProofType>(), Ok(*proof_type));
+        assert_eq!(
+            proof_type.validate_evidence(evidence),
+            Ok(()),
+            "{proof_type}"
+        );
+
+        for (other, foreign) in &samples {
+            if other != proof_type {
+                assert!(
+                    matches!(
+                        proof_type.validate_evidence(foreign),
+                        Err(EvidenceError::Schema { .. })
+                    ),
+                    "{proof_type} accepted {other} evidence"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn evidence_round_trips_through_a_serialized_proof() {
+    for (proof_type, evidence) in samples() {
+        let mut evidence = evidence;
+        evidence.insert("note".to_string(), json!("kept as is"));
+        let recorded = proof(proof_type, evidence.clone());
+
+        let decoded: Proof =
+            serde_json::from_str(&serde_json::to_string(&recorded).unwrap()).unwrap();
+        assert_eq!(decoded.evidence, evidence);
+        assert_eq!(decoded.kind(), Some(proof_type));
+        assert_eq!(decoded.check_evidence(), Ok(()));
+    }
+
+    let test = &samples()[1];
+    let decoded: TestResultEvidence = proof(test.0, test.1.clone()).evidence_as().unwrap();
+    assert_eq!(decoded.passed, 41);
+    assert_eq!(decoded.command.as_deref(), Some("cargo test --workspace"));
+}
+
+#[test]
+fn incomplete_or_unknown_evidence_is_rejected() {
+    let mut review = samples()[2].1.clone();
+    review.insert("verdict".to_string(), json!("looks fine"));
+    assert!(matches!(
+        ProofType::Review.validate_evidence(&review),
+        Err(EvidenceError::Schema { .. })
+    ));
+
+    let blank = HashMap::from([("audit_ref".to_string(), json!("  "))]);
+    assert_eq!(
+        ProofType::Audit.validate_evidence(&blank),
+        Err(EvidenceError::Empty {
+            proof_type: ProofType::Audit,
+            field: "audit_ref"
+        })
+    );
+
+    // Decapod may report proofs without evidence or of types Pincher does not
+    // know; both decode but fail the check.
+    let legacy: Proof = serde_json::from_value(json!({
+        "id": "proof-2", "proof_type": "test", "criteria": "ci",
+        "passed": true, "verified_at": null
+    }))
+    .unwrap();
+    assert!(legacy.evidence.is_empty());
+    assert!(legacy.check_evidence().is_err());
+    let foreign = Proof {
+        proof_type: "benchmark".to_string(),
+        ..legacy
+    };
+    assert_eq!(foreign.kind(), None);
+    assert_eq!(
+        foreign.check_evidence(),
+        Err(EvidenceError::UnknownProofType("benchmark".to_string()))
+    );
+}
+
+#[test]
+fn evidence_schemas_name_the_required_material() {
+    let required = |proof_type: ProofType| {
+        let mut names: Vec<String> = proof_type.evidence_schema()["required"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|name| name.as_str().unwrap().to_string())
+            .collect();
+        names.sort();
+        names
+    };
+    assert_eq!(required(ProofType::Validation), ["validation_ref"]);
+    assert_eq!(required(ProofType::Test), ["failed", "passed"]);
+    assert_eq!(required(ProofType::Review), ["reviewer", "verdict"]);
+    assert_eq!(required(ProofType::Audit), ["audit_ref"]);
+    assert_eq!(required(ProofType::External), ["reference", "source"]);
+}
+
+#[tokio::test]
+async fn record_proof_rejects_invalid_evidence_before_calling_decapod() {
+    let manager = WorkUnitManager::new();
+    let error = manager
+        .record_proof("work-unit-1", ProofType::Test, "ci", HashMap::new())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<EvidenceError>(),
+        Some(EvidenceError::Schema {
+            proof_type: ProofType::Test,
+            ..
+        })
+    ));
+}
@@ -0,0 +1,182 @@
+use pincher::decapod::rpc::{RpcClient, RpcRequest, StoreEntry};
+use pincher::decapod::todo::TaskStatus;
+use pincher::decapod::transport::{ScriptedTransport, TransportOutput};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::sync::Arc;
+
+fn client(transport: &ScriptedTransport) -> RpcClient {
+    RpcClient::new()
+        .with_session("token-1")
+        .with_transport(Arc::new(transport.clone()))
+}
+
+fn reply(op: &str, envelope: Value) -> (Vec<String>, TransportOutput) {
+    (
+        vec!["rpc".to_string(), "--op".to_string(), op.to_string()],
+        TransportOutput::json(&envelope),
+    )
+}
+
+fn scripted(replies: Vec<(Vec<String>, TransportOutput)>) -> ScriptedTransport {
+    replies
+        .into_iter()
+        .fold(ScriptedTransport::new(), |transport, (prefix, output)| {
+            transport.reply(&prefix, output)
+        })
+}
+
+/// The `--params` a recorded invocation carried, if any.
+fn params(transport: &ScriptedTransport, index: usize) -> Option<Value> {
+    let args = transport.invocations()[index].args.clone();
+    let flag = args.iter().position(|arg| arg == "--params")?;
+    Some(serde_json::from_str(&args[flag + 1]).unwrap())
+}
+
+#[tokio::test]
+async fn helpers_return_the_typed_payload_decapod_sent() {
+    let transport = scripted(vec![
+        reply(
+            "todo.add",
+            json!({"success": true, "data": {
+                "id": "task-9", "content": "fix the build", "status": "pending",
+                "priority": "high", "owner": null, "created_at": "", "updated_at": "",
+                "claimed_at": null, "completed_at": null
+            }}),
+        ),
+        reply(
+            "workunit.init",
+            json!({"success": true, "data": {
+                "id": "work-unit-4", "task_id": "task-9", "intent_ref": "intent-1",
+                "status": "pending",
+                "state": {"intent": "intent-1", "plan": null, "patches": [], "approvals": []},
+                "acceptance_criteria": [], "constraints": [], "proofs": [],
+                "created_at": "", "updated_at": ""
+            }}),
+        ),
+        reply(
+            "store.query",
+            json!({"success": true, "data": [
+                {"entity_type": "note", "key": "a", "value": {"text": "first"}, "revision": 2},
+                {"entity_type": "note", "key": "b", "value": {"text": "second"}}
+            ]}),
+        ),
+    ]);
+    let rpc = client(&transport);
+
+    let task = rpc
+        .todo_add("fix the build", Some("high"))
+        .await
+        .unwrap()
+        .into_data()
+        .unwrap();
+    assert_eq!(task.id, "task-9");
+    assert_eq!(task.status, TaskStatus::Pending);
+    assert_eq!(
+        params(&transport, 0),
+        Some(json!({"content": "fix the build", "priority": "high"}))
+    );
+
+    let work_unit = rpc.workunit_init("task-9", "intent-1").await.unwrap();
+    assert_eq!(work_unit.data().unwrap().id, "work-unit-4");
+
+    let entries: Vec<StoreEntry> = rpc
+        .store_query("note", json!({"prefix": ""}))
+        .await
+        .unwrap()
+        .into_data()
+        .unwrap();
+    assert_eq!(entries[0].value["text"], "first");
+    assert_eq!(entries[0].revision, Some(2));
+    assert_eq!(entries[1].revision, None);
+}
+
+#[tokio::test]
+async fn operations_without_parameters_send_none() {
+    let transport = scripted(vec![
+        reply("validate.run", json!({"success": true})),
+        reply("todo.list", json!({"success": true, "data": []})),
+    ]);
+    let rpc = client(&transport);
+
+    let validation = rpc.validate_run().await.unwrap();
+    assert!(validation.data.is_none());
+    let tasks = rpc.todo_list(None).await.unwrap().into_data().unwrap();
+    assert!(tasks.is_empty());
+
+    assert_eq!(params(&transport, 0), None);
+    assert_eq!(params(&transport, 1), None);
+    assert_eq!(
+        transport.commands(),
+        ["rpc --op validate.run", "rpc --op todo.list"]
+    );
+}
+
+#[tokio::test]
+async fn into_data_refuses_failed_blocked_and_empty_responses() {
+    let transport = scripted(vec![
+        reply(
+            "todo.claim",
+            json!({"success": false, "error": "task is owned by agent-2"}),
+        ),
+        reply(
+            "workspace.ensure",
+            json!({"success": false, "interlock": {
+                "policy": "protected-branch", "reason": "main is protected",
+                "blocking": true, "required_approval": "maintainer"
+            }}),
+        ),
+        reply("workspace.status", json!({"success": true})),
+    ]);
+    let rpc = client(&transport);
+
+    let claimed = rpc.todo_claim("task-1").await.unwrap();
+    assert!(claimed.data.is_none());
+    assert_eq!(
+        claimed.into_data().unwrap_err().to_string(),
+        "RPC call failed: task is owned by agent-2"
+    );
+
+    let ensured = rpc.workspace_ensure().await.unwrap();
+    assert!(ensured.is_blocked());
+    assert_eq!(
+        ensured.into_data().unwrap_err().to_string(),
+        "RPC call blocked by protected-branch"
+    );
+
+    let status = rpc.workspace_status().await.unwrap();
+    assert_eq!(
+        status.into_data().unwrap_err().to_string(),
+        "RPC call returned no data"
+    );
+}
+
+#[derive(Serialize)]
+struct ProbeRequest {
+    depth: u8,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct ProbeData {
+    healthy: bool,
+}
+
+impl RpcRequest for ProbeRequest {
+    const OPERATION: &'static str = "health.probe";
+    type Data = ProbeData;
+}
+
+#[tokio::test]
+async fn hosts_can_send_their_own_typed_requests() {
+    let transport = scripted(vec![reply(
+        "health.probe",
+        json!({"success": true, "data": {"healthy": true}}),
+    )]);
+
+    let response = client(&transport)
+        .send(&ProbeRequest { depth: 2 })
+        .await
+        .unwrap();
+    assert_eq!(response.into_data().unwrap(), ProbeData { healthy: true });
+    assert_eq!(params(&transport, 0), Some(json!({"depth": 2})));
+}
@@ -0,0 +1,123 @@
+use pincher::decapod::secret::{
+    EnvSecret, FdSecret, FileSecret, Secret, SecretError, SecretSource,
+};
+use pincher::decapod::session::{Session, SessionConfig};
+use pincher::decapod::transport::{
+    Invocation, ProcessTransport, SESSION_ENV, ScriptedTransport, TransportOutput,
+};
+
+#[test]
+fn env_sources_read_their_variable_and_treat_empty_as_unset() {
+    let path = EnvSecret::new("PATH").read().unwrap().unwrap();
+    assert_eq!(path.expose(), std::env::var("PATH").unwrap());
+    assert_eq!(
+        EnvSecret::new("PINCHER_TEST_SECRET_THAT_IS_NEVER_SET")
+            .read()
+            .unwrap(),
+        None
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn file_sources_trim_the_line_ending_and_refuse_files_others_can_read() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("password");
+    std::fs::write(&path, "hunter2\n").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+    let secret = FileSecret::new(&path).read().unwrap().unwrap();
+    assert_eq!(secret.expose(), "hunter2");
+
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+    assert!(matches!(
+        FileSecret::new(&path).read().unwrap_err(),
+        SecretError::Exposed { path: exposed } if exposed == path
+    ));
+
+    std::fs::write(&path, "\n").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+    assert_eq!(FileSecret::new(&path).read().unwrap(), None);
+
+    assert!(matches!(
+        FileSecret::new(dir.path().join("missing"))
+            .read()
+            .unwrap_err(),
+        SecretError::Unreadable { .. }
+    ));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn fd_sources_read_an_inherited_descriptor() {
+    use std::os::fd::AsRawFd;
+
+    let mut file = tempfile::tempfile().unwrap();
+    std::io::Write::write_all(&mut file, b"hunter2\r\n").unwrap();
+    let fd = u32::try_from(file.as_raw_fd()).unwrap();
+    let secret = FdSecret::new(fd).read().unwrap().unwrap();
+    assert_eq!(secret.expose(), "hunter2");
+}
+
+#[test]
+fn secrets_never_print_their_value() {
+    let config = SessionConfig::new()
+        .with_password("hunter2")
+        .without_persistence();
+    assert_eq!(format!("{:?}", Secret::new("hunter2")), "Secret(..)");
+    assert!(!format!("{config:?}").contains("hunter2"));
+    assert!(!serde_json::to_string(&config).unwrap().contains("hunter2"));
+    let invocation = Invocation::new(&["session", "acquire"]).with_input(Secret::new("hunter2"));
+    assert!(!format!("{invocation:?}").contains("hunter2"));
+
+    let kept = config
+        .with_password_from(&EnvSecret::new("PINCHER_TEST_SECRET_THAT_IS_NEVER_SET"))
+        .unwrap();
+    assert_eq!(kept.password().map(Secret::expose), Some("hunter2"));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn the_password_reaches_decapod_on_stdin_and_not_in_its_environment() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let root = tempfile::tempdir().unwrap();
+    let binary = root.path().join("decapod");
+    std::fs::write(
+        &binary,
+        "#!/bin/sh\n\
+         read -r password\n\
+         printf '{\"token\":\"%s|%s|%s\",\"session_id\":\"s-1\",\"created_at\":\"now\"}' \
+           \"$password\" \"$DECAPOD_SESSION_PASSWORD\" \"$*\"\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+    std::fs::create_dir(root.path().join(".decapod")).unwrap();
+    let password = Secret::new("hunter2");
+    let transport = ProcessTransport::new(&binary).with_project_root(root.path());
+
+    // Until Decapod reports the flag, the password goes in the environment.
+    let session = Session::acquire_with(&transport, &password).await.unwrap();
+    assert_eq!(session.token, "|hunter2|session acquire");
+
+    let transport = transport.with_password_stdin(true);
+    let session = Session::acquire_with(&transport, &password).await.unwrap();
+    assert_eq!(session.token, "hunter2||session acquire --password-stdin");
+
+    // Transports without stdin still get the password, in the environment.
+    let scripted = ScriptedTransport::new().reply(
+        &["session", "acquire"],
+        TransportOutput::ok(r#"{"token":"t","session_id":"s-1","created_at":"now"}"#),
+    );
+    Session::acquire_with(&scripted, &password).await.unwrap();
+    let invocation = &scripted.invocations()[0];
+    assert_eq!(invocation.command(), "session acquire");
+    assert_eq!(invocation.input, None);
+    assert!(
+        invocation
+            .env
+            .contains(&(SESSION_ENV.to_string(), Secret::new("hunter2")))
+    );
+    assert!(!format!("{invocation:?}").contains("hunter2"));
+}
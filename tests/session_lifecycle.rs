@@ -0,0 +1,171 @@
+use pincher::decapod::bootstrap::{CustodyBootstrap, CustodyRequest, DecapodCustody};
+use pincher::decapod::lifecycle::{SessionError, SessionManager};
+use pincher::decapod::project::DECAPOD_DIR;
+use pincher::decapod::session::{SessionConfig, SessionExpired};
+use pincher::decapod::sim::{Scenario, Simulator};
+use pincher::decapod::transport::{DecapodTransport, Invocation, ProcessTransport};
+use pincher::governed_run::{
+    CustodyFailure, DecapodPortError, IntentId, RepositoryRef, SessionRef,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::TempDir;
+
+const SIM: &str = env!("CARGO_BIN_EXE_decapod-sim");
+
+/// A `decapod-sim` project running `scenario`, and where its session is
+/// persisted.
+fn project(scenario: Scenario) -> (TempDir, Arc<dyn DecapodTransport>, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join(DECAPOD_DIR)).unwrap();
+    Simulator::new(dir.path())
+        .set_scenario(scenario.with_password("hunter2"))
+        .unwrap();
+    let transport = ProcessTransport::new(SIM).with_project_root(dir.path());
+    let persisted = dir.path().join("state/session.json");
+    (dir, Arc::new(transport), persisted)
+}
+
+fn manager(transport: &Arc<dyn DecapodTransport>, persisted: &PathBuf) -> SessionManager {
+    let config = SessionConfig::new()
+        .with_password("hunter2")
+        .with_persist_path(persisted);
+    // Independent of DECAPOD_SESSION_TTL_SECS in the environment.
+    SessionManager::new(SessionConfig {
+        ttl_secs: None,
+        ..config
+    })
+    .with_transport(transport.clone())
+}
+
+fn sessions_issued(dir: &TempDir) -> usize {
+    Simulator::new(dir.path()).load().unwrap().sessions.len()
+}
+
+async fn expire_all(transport: &Arc<dyn DecapodTransport>) {
+    let expired = transport
+        .invoke(Invocation::new(&["sim", "expire"]))
+        .await
+        .unwrap();
+    assert!(expired.success());
+}
+
+#[cfg(unix)]
+fn mode(path: &PathBuf) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+#[tokio::test]
+async fn a_persisted_session_is_reused_while_decapod_still_honours_it() {
+    let (dir, transport, persisted) = project(Scenario::new());
+    let first = manager(&transport, &persisted).session().await.unwrap();
+    #[cfg(unix)]
+    assert_eq!(mode(&persisted), 0o600);
+
+    let restarted = manager(&transport, &persisted).session().await.unwrap();
+    assert_eq!(restarted.token, first.token);
+    assert_eq!(sessions_issued(&dir), 1);
+
+    expire_all(&transport).await;
+    let replaced = manager(&transport, &persisted).session().await.unwrap();
+    assert_ne!(replaced.token, first.token);
+    assert_eq!(sessions_issued(&dir), 2);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn a_session_file_others_can_read_is_not_trusted() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (dir, transport, persisted) = project(Scenario::new());
+    let first = manager(&transport, &persisted).session().await.unwrap();
+    std::fs::set_permissions(&persisted, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    let replaced = manager(&transport, &persisted).session().await.unwrap();
+    assert_ne!(replaced.token, first.token);
+    assert_eq!(sessions_issued(&dir), 2);
+    assert_eq!(mode(&persisted), 0o600);
+}
+
+#[tokio::test]
+async fn sessions_near_expiry_or_past_their_ttl_are_replaced() {
+    let (_dir, transport, persisted) = project(Scenario::new().with_session_ttl(30));
+    let sessions = manager(&transport, &persisted).with_refresh_margin(Duration::ZERO);
+    let first = sessions.session().await.unwrap();
+    assert_eq!(sessions.session().await.unwrap().token, first.token);
+
+    let margin = manager(&transport, &persisted).with_refresh_margin(Duration::from_secs(60));
+    assert!(margin.needs_refresh(&first));
+    let refreshed = margin.session().await.unwrap();
+    assert_ne!(refreshed.token, first.token);
+    assert_ne!(margin.session().await.unwrap().token, refreshed.token);
+
+    let (_dir, transport, persisted) = project(Scenario::new());
+    let capped = SessionManager::new(
+        SessionConfig::new()
+            .with_password("hunter2")
+            .with_persist_path(&persisted)
+            .with_ttl(30),
+    )
+    .with_transport(transport.clone())
+    .with_refresh_margin(Duration::from_secs(60));
+    let first = capped.session().await.unwrap();
+    assert!(first.expires().is_some() && !first.expires_within(Duration::from_secs(60)));
+    assert_ne!(capped.session().await.unwrap().token, first.token);
+}
+
+#[tokio::test]
+async fn managers_carry_the_current_token_and_report_expiry_as_typed_errors() {
+    let (_dir, transport, persisted) = project(Scenario::new().requiring_session());
+    let sessions = Arc::new(manager(&transport, &persisted));
+    let todos = sessions.todos().await.unwrap();
+    let task = todos.add("renew", None, None).await.unwrap();
+
+    let plane = DecapodCustody::new("unused")
+        .with_transport(transport.clone())
+        .with_sessions(sessions.clone());
+    let mut bootstrap = CustodyBootstrap::new(plane);
+    let request = CustodyRequest::new(
+        &task.id,
+        IntentId::new("intent-1").unwrap(),
+        RepositoryRef::new("repository-1").unwrap(),
+    )
+    .unwrap();
+    let run = bootstrap.bootstrap(&request).await.unwrap();
+    let session = sessions.session().await.unwrap();
+    assert_eq!(
+        run.custody.session,
+        Some(SessionRef::new(session.session_id()).unwrap())
+    );
+
+    expire_all(&transport).await;
+    let error = todos.get(&task.id).await.unwrap_err();
+    let expired = error
+        .downcast_ref::<SessionExpired>()
+        .unwrap_or_else(|| panic!("expected SessionExpired, got {error}"));
+    let session = run.custody.session.clone().unwrap();
+    assert_eq!(
+        expired.to_port_error(session.clone()),
+        DecapodPortError::CustodyRejected {
+            reason: CustodyFailure::SessionExpired { session },
+        }
+    );
+
+    sessions.refresh().await.unwrap();
+    let todos = sessions.todos().await.unwrap();
+    assert_eq!(todos.get(&task.id).await.unwrap().content, "renew");
+
+    sessions.forget().await.unwrap();
+    assert!(!persisted.exists());
+    let anonymous = SessionManager::new(SessionConfig {
+        password: None,
+        ..SessionConfig::new().without_persistence()
+    })
+    .with_transport(transport);
+    assert!(matches!(
+        anonymous.session().await.unwrap_err(),
+        SessionError::NoPassword
+    ));
+}
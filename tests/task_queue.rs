@@ -0,0 +1,302 @@
+use pincher::decapod::queue::{TaskBoard, TaskPriority, TaskQueue};
+use pincher::decapod::todo::{Task, TaskStatus};
+use pincher::governed_run::*;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+fn task(id: &str, priority: Option<&str>, created_at: &str, blocked_by: &[&str]) -> Task {
+    Task {
+        id: id.to_string(),
+        content: format!("work for {id}"),
+        status: TaskStatus::Pending,
+        priority: priority.map(str::to_string),
+        owner: None,
+        created_at: created_at.to_string(),
+        updated_at: created_at.to_string(),
+        claimed_at: None,
+        completed_at: None,
+        blocked_by: Some(blocked_by.iter().map(|id| id.to_string()).collect()),
+    }
+}
+
+/// In-memory todo list.  Claims of `contested` tasks fail as if another agent
+/// won the race.
+#[derive(Clone, Default)]
+struct FakeBoard {
+    tasks: Arc<Mutex<BTreeMap<String, Task>>>,
+    contested: Arc<Mutex<Vec<String>>>,
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl FakeBoard {
+    fn with(tasks: Vec<Task>) -> Self {
+        let board = Self::default();
+        for task in tasks {
+            board.tasks.lock().unwrap().insert(task.id.clone(), task);
+        }
+        board
+    }
+
+    fn set_status(&self, id: &str, status: TaskStatus) {
+        self.tasks.lock().unwrap().get_mut(id).unwrap().status = status;
+    }
+
+    fn status(&self, id: &str) -> TaskStatus {
+        self.tasks.lock().unwrap()[id].status.clone()
+    }
+
+    fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl TaskBoard for FakeBoard {
+    async fn pending(&self) -> anyhow::Result<Vec<Task>> {
+        Ok(self
+            .tasks
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|task| task.status == TaskStatus::Pending)
+            .cloned()
+            .collect())
+    }
+
+    async fn task(&self, task_id: &str) -> anyhow::Result<Task> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .get(task_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("no task {task_id}"))
+    }
+
+    async fn claim(&self, task_id: &str) -> anyhow::Result<Task> {
+        self.calls.lock().unwrap().push(format!("claim {task_id}"));
+        if self
+            .contested
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|id| id == task_id)
+        {
+            anyhow::bail!("task {task_id} is claimed by another agent");
+        }
+        let mut tasks = self.tasks.lock().unwrap();
+        let task = tasks.get_mut(task_id).unwrap();
+        task.status = TaskStatus::Claimed;
+        task.owner = Some("pincher".to_string());
+        Ok(task.clone())
+    }
+
+    async fn release(&self, task_id: &str) -> anyhow::Result<Task> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(format!("release {task_id}"));
+        let mut tasks = self.tasks.lock().unwrap();
+        let task = tasks.get_mut(task_id).unwrap();
+        task.status = TaskStatus::Pending;
+        task.owner = None;
+        Ok(task.clone())
+    }
+}
+
+fn build(task: &Task) -> Result<RunRequest, ContractError> {
+    Ok(RunRequest::v1(
+        RunId::new(format!("run-{}", task.id))?,
+        IntentId::new(format!("todo:{}", task.id))?,
+        CorrelationId::new(task.id.clone())?,
+        IdempotencyKey::new(format!("todo:{}", task.id))?,
+        CustodyBinding::complete(
+            SessionRef::new("session-1")?,
+            TaskRef::new(task.id.clone())?,
+            WorkUnitRef::new("work-unit-1")?,
+            RepositoryRef::new("repository-1")?,
+            WorkspaceRef::new("workspace-1")?,
+        ),
+    ))
+}
+
+async fn claim_next(queue: &mut TaskQueue<FakeBoard>) -> Option<String> {
+    queue
+        .poll(build)
+        .await
+        .unwrap()
+        .map(|claimed| claimed.task.id)
+}
+
+/// Outcome of the run for `task` ending in `state`.
+fn outcome(task: &str, state: RunState) -> RunOutcome {
+    let request = build(&self::task(task, None, "", &[])).unwrap();
+    let failed = GovernedRunEngine::new(
+        UnsupportedDecapodControlPlane,
+        UnsupportedProviderTurn,
+        InMemoryEventSink::default(),
+    )
+    .run(request)
+    .unwrap();
+    let mut snapshot = failed.snapshot().clone();
+    snapshot.state = state;
+    match state {
+        RunState::Ready => RunOutcome::Ready(snapshot),
+        RunState::Blocked => RunOutcome::Blocked(snapshot),
+        _ => RunOutcome::Failed(snapshot),
+    }
+}
+
+#[test]
+fn priorities_rank_named_levels_before_numbers_and_unknown_values_last() {
+    let rank = |priority| TaskPriority::parse(priority);
+    assert!(rank(Some("critical")) < rank(Some("high")));
+    assert!(rank(Some("high")) < rank(None));
+    assert_eq!(rank(None), rank(Some("Medium")));
+    assert!(rank(None) < rank(Some("low")));
+    assert_eq!(rank(Some("p1")), rank(Some("high")));
+    assert!(rank(Some("low")) < rank(Some("whenever")));
+}
+
+#[tokio::test]
+async fn tasks_are_claimed_by_priority_once_their_dependencies_complete() {
+    let board = FakeBoard::with(vec![
+        task("a", Some("low"), "2026-01-01T00:00:00Z", &[]),
+        task("b", Some("high"), "2026-01-01T00:00:00Z", &["c"]),
+        task("c", None, "2026-01-02T00:00:00Z", &[]),
+        task("d", None, "2026-01-01T00:00:00Z", &[]),
+        task("e", Some("critical"), "2026-01-03T00:00:00Z", &["done"]),
+        task("f", Some("critical"), "2026-01-01T00:00:00Z", &["unknown"]),
+        Task {
+            status: TaskStatus::Completed,
+            ..task("done", None, "2025-12-01T00:00:00Z", &[])
+        },
+    ]);
+    let mut queue = TaskQueue::new(board.clone());
+
+    // `f` depends on a task Decapod cannot resolve and is never claimed.
+    assert_eq!(claim_next(&mut queue).await.as_deref(), Some("e"));
+    assert_eq!(claim_next(&mut queue).await.as_deref(), Some("d"));
+    // `b` outranks `c` but waits for it.
+    assert_eq!(claim_next(&mut queue).await.as_deref(), Some("c"));
+    assert_eq!(claim_next(&mut queue).await.as_deref(), Some("a"));
+    assert_eq!(claim_next(&mut queue).await, None);
+
+    board.set_status("c", TaskStatus::Completed);
+    assert_eq!(claim_next(&mut queue).await.as_deref(), Some("b"));
+    assert_eq!(claim_next(&mut queue).await, None);
+    assert!(!board.calls().contains(&"claim f".to_string()));
+    assert_eq!(queue.in_flight().count(), 5);
+}
+
+#[tokio::test]
+async fn tasks_without_reported_dependencies_are_skipped() {
+    let unreported: Task = serde_json::from_value(serde_json::json!({
+        "id": "b",
+        "content": "work for b",
+        "status": "pending",
+        "priority": "critical",
+        "owner": null,
+        "created_at": "2026-01-01T00:00:00Z",
+        "updated_at": "2026-01-01T00:00:00Z",
+        "claimed_at": null,
+        "completed_at": null,
+    }))
+    .unwrap();
+    assert_eq!(unreported.blocked_by, None);
+    let board = FakeBoard::with(vec![
+        task("a", None, "2026-01-01T00:00:00Z", &[]),
+        unreported,
+    ]);
+    let mut queue = TaskQueue::new(board.clone());
+
+    let claimed = queue.poll(build).await.unwrap().unwrap();
+    assert_eq!(claimed.task.id, "a");
+    assert_eq!(board.calls(), vec!["claim a".to_string()]);
+    assert_eq!(board.status("b"), TaskStatus::Pending);
+
+    assert!(queue.poll(build).await.unwrap().is_none());
+    assert_eq!(board.calls(), vec!["claim a".to_string()]);
+}
+
+#[tokio::test]
+async fn contested_claims_are_skipped_and_the_next_task_is_claimed() {
+    let board = FakeBoard::with(vec![
+        task("a", Some("high"), "2026-01-01T00:00:00Z", &[]),
+        task("b", Some("low"), "2026-01-01T00:00:00Z", &[]),
+    ]);
+    board.contested.lock().unwrap().push("a".to_string());
+    let mut queue = TaskQueue::new(board.clone());
+
+    let claimed = queue.poll(build).await.unwrap().unwrap();
+    assert_eq!(claimed.task.id, "b");
+    assert_eq!(claimed.task.status, TaskStatus::Claimed);
+    assert_eq!(
+        claimed.request.custody.task,
+        Some(TaskRef::new("b").unwrap())
+    );
+    assert_eq!(board.calls(), ["claim a", "claim b"]);
+}
+
+#[tokio::test]
+async fn blocked_and_failed_runs_release_their_task_and_ready_runs_keep_it() {
+    let board = FakeBoard::with(vec![
+        task("a", Some("critical"), "2026-01-01T00:00:00Z", &[]),
+        task("b", Some("high"), "2026-01-01T00:00:00Z", &[]),
+        task("c", Some("low"), "2026-01-01T00:00:00Z", &[]),
+    ]);
+    let mut queue = TaskQueue::new(board.clone());
+    for _ in 0..3 {
+        claim_next(&mut queue).await.unwrap();
+    }
+
+    let released = queue
+        .settle(&outcome("a", RunState::Blocked))
+        .await
+        .unwrap();
+    assert_eq!(released.unwrap().status, TaskStatus::Pending);
+    assert!(
+        queue
+            .settle(&outcome("b", RunState::Failed))
+            .await
+            .unwrap()
+            .is_some()
+    );
+    assert!(
+        queue
+            .settle(&outcome("c", RunState::Ready))
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(board.status("a"), TaskStatus::Pending);
+    assert_eq!(board.status("b"), TaskStatus::Pending);
+    assert_eq!(board.status("c"), TaskStatus::Claimed);
+    assert_eq!(queue.in_flight().count(), 0);
+
+    // Runs the queue did not start are ignored.
+    assert!(
+        queue
+            .settle(&outcome("c", RunState::Failed))
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(board.status("c"), TaskStatus::Claimed);
+
+    // A released task is claimed again on the next poll.
+    assert_eq!(claim_next(&mut queue).await.as_deref(), Some("a"));
+}
+
+#[tokio::test]
+async fn a_task_whose_run_cannot_be_built_is_released() {
+    let board = FakeBoard::with(vec![task("a", None, "2026-01-01T00:00:00Z", &[])]);
+    let mut queue = TaskQueue::new(board.clone());
+
+    let error = queue
+        .poll(|_: &Task| SessionRef::new(" ").map(|_| unreachable!()))
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("task a"));
+    assert_eq!(board.calls(), ["claim a", "release a"]);
+    assert_eq!(board.status("a"), TaskStatus::Pending);
+    assert_eq!(queue.in_flight().count(), 0);
+}